@@ -0,0 +1,66 @@
+//! Derive macros for the Kiba key-value store.
+//!
+//! These macros are re-exported by the `kiba` crate when its `derive`
+//! feature is enabled, and should be used through it.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+/// Derive `kiba::KibaHash` for a struct with named fields, storing each field
+/// as a field of a hash. Field types must implement `ToString` and `FromStr`.
+#[proc_macro_derive(KibaHash)]
+pub fn derive_kiba_hash(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_kiba_hash(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_kiba_hash(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "KibaHash can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "KibaHash can only be derived for structs",
+            ))
+        }
+    };
+    let idents: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let names: Vec<String> = idents.iter().map(|ident| ident.to_string()).collect();
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::kiba::record::KibaHash for #name #ty_generics #where_clause {
+            const FIELDS: &'static [&'static str] = &[#(#names),*];
+
+            fn to_hash(&self) -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+                ::std::vec![
+                    #((#names.to_string(), ::std::string::ToString::to_string(&self.#idents))),*
+                ]
+            }
+
+            fn from_hash(
+                fields: &::std::collections::HashMap<::std::string::String, ::std::string::String>,
+            ) -> ::std::result::Result<Self, ::std::string::String> {
+                ::std::result::Result::Ok(Self {
+                    #(#idents: ::kiba::record::field(fields, #names)?),*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,34 @@
+use kiba::KibaHash;
+use std::collections::HashMap;
+
+#[derive(Debug, KibaHash, PartialEq)]
+struct Session {
+    user: String,
+    visits: u64,
+    admin: bool,
+}
+
+#[test]
+fn test_derive_kiba_hash() {
+    assert_eq!(Session::FIELDS, &["user", "visits", "admin"]);
+
+    let session = Session {
+        user: "shoyo".to_string(),
+        visits: 3,
+        admin: false,
+    };
+    let hash = session.to_hash();
+    assert_eq!(
+        hash,
+        vec![
+            ("user".to_string(), "shoyo".to_string()),
+            ("visits".to_string(), "3".to_string()),
+            ("admin".to_string(), "false".to_string()),
+        ]
+    );
+
+    let mut fields: HashMap<String, String> = hash.into_iter().collect();
+    assert_eq!(Session::from_hash(&fields), Ok(session));
+    fields.insert("visits".to_string(), "many".to_string());
+    assert!(Session::from_hash(&fields).is_err());
+}
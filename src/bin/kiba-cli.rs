@@ -1,30 +1,313 @@
+use kiba::client::ConnectionInfo;
+use kiba::snapshot::{from_hex, Decoder};
+use std::fs::File;
 use std::io::prelude::*;
+use std::io::BufReader;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::net::TcpStream;
 use tokio::prelude::*;
+use tokio::time::{delay_for, timeout};
+
+const USAGE: &str = "Usage: kiba-cli [<url>] [-f <script> [--abort-on-error]]
+       kiba-cli [<url>] [-r <count>] [-i <seconds>] <command> [<args>...]
+       kiba-cli [<url>] --dump <file>
+
+The URL is of the form kiba://host[:port][/namespace][?timeout=seconds] or
+host:port, and defaults to the value of KIBA_URL or 127.0.0.1:6464.";
+
+/// Environment variable holding the URL of the server to connect to by default
+const URL_VAR: &str = "KIBA_URL";
+
+/// Settings passed as command-line arguments
+struct Options {
+    url: String,
+    script: Option<String>,
+    abort_on_error: bool,
+
+    /// File to write a snapshot of the namespace to
+    dump: Option<String>,
+    command: Vec<String>,
+
+    /// Number of times to execute the command, or `None` to repeat indefinitely
+    repeat: Option<u64>,
+    interval: Option<Duration>,
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        url: std::env::var(URL_VAR).unwrap_or_else(|_| "127.0.0.1:6464".to_string()),
+        script: None,
+        abort_on_error: false,
+        dump: None,
+        command: Vec::new(),
+        repeat: Some(1),
+        interval: None,
+    };
+    let mut repeat = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        // Arguments following the command are passed to the server as is
+        if !options.command.is_empty() {
+            options.command.push(arg);
+            continue;
+        }
+        match arg.as_str() {
+            "-f" => match args.next() {
+                Some(path) => options.script = Some(path),
+                None => exit_with_usage("Expected a script file after \"-f\""),
+            },
+            "--abort-on-error" => options.abort_on_error = true,
+            "--dump" => match args.next() {
+                Some(path) => options.dump = Some(path),
+                None => exit_with_usage("Expected a file after \"--dump\""),
+            },
+            "-r" => match args.next().map(|count| count.parse::<i64>()) {
+                Some(Ok(-1)) => repeat = Some(None),
+                Some(Ok(count)) if count > 0 => repeat = Some(Some(count as u64)),
+                _ => exit_with_usage(
+                    "Count must be a positive integer, or -1 to repeat indefinitely",
+                ),
+            },
+            "-i" => match args.next().map(|secs| secs.parse::<f64>()) {
+                Some(Ok(secs)) if secs >= 0.0 && secs.is_finite() => {
+                    options.interval = Some(Duration::from_secs_f64(secs))
+                }
+                _ => exit_with_usage("Interval must be a non-negative number of seconds"),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => {
+                exit_with_usage(&format!("Unrecognized option \"{}\"", arg));
+            }
+            // Commands never contain a colon, unlike addresses
+            _ if arg.contains(':') => options.url = arg,
+            _ => options.command.push(arg),
+        }
+    }
+    if options.abort_on_error && options.script.is_none() {
+        exit_with_usage("\"--abort-on-error\" requires a script file");
+    }
+    if options.script.is_some() && !options.command.is_empty() {
+        exit_with_usage("A script file and a command cannot both be given");
+    }
+    if options.dump.is_some() && (options.script.is_some() || !options.command.is_empty()) {
+        exit_with_usage("\"--dump\" cannot be given with a script file or a command");
+    }
+    if (repeat.is_some() || options.interval.is_some()) && options.command.is_empty() {
+        exit_with_usage("\"-r\" and \"-i\" require a command");
+    }
+    options.repeat = match (repeat, options.interval) {
+        (Some(repeat), _) => repeat,
+        // Watch the command until interrupted if only an interval is given
+        (None, Some(_)) => None,
+        (None, None) => Some(1),
+    };
+    options
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+/// Connect to the server at a URL and select the URL's namespace.
+async fn connect(url: &str) -> io::Result<TcpStream> {
+    let info = match ConnectionInfo::parse(url) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("** Invalid URL \"{}\": {}", url, e);
+            std::process::exit(2);
+        }
+    };
+    let mut stream = match info.timeout {
+        Some(duration) => match timeout(duration, TcpStream::connect(&info.addr)).await {
+            Ok(stream) => stream?,
+            Err(_) => return Err(io::ErrorKind::TimedOut.into()),
+        },
+        None => TcpStream::connect(&info.addr).await?,
+    };
+    if let Some(namespace) = &info.namespace {
+        let reply = send(&mut stream, &format!("NAMESPACE {}", quote(namespace))).await?;
+        if reply.starts_with("(error)") {
+            eprintln!("** Could not select namespace \"{}\": {}", namespace, reply);
+            std::process::exit(1);
+        }
+    }
+    Ok(stream)
+}
+
+/// Send a command to the server and return its reply, without the terminator.
+async fn send(stream: &mut TcpStream, command: &str) -> io::Result<String> {
+    // Send the command in a single write, since the server treats the data
+    // received so far as a complete inline request
+    stream
+        .write_all(format!("{}\n", command).as_bytes())
+        .await?;
+
+    // Read until the reply is terminated with CRLF
+    let mut reply = Vec::new();
+    let mut rbuf = [0; 512];
+    while !reply.ends_with(b"\r\n") {
+        let n = stream.read(&mut rbuf[..]).await?;
+        if n == 0 {
+            println!("** Connection closed by server");
+            std::process::exit(1);
+        }
+        reply.extend_from_slice(&rbuf[..n]);
+    }
+    reply.truncate(reply.len() - 2);
+    Ok(String::from_utf8_lossy(&reply).to_string())
+}
+
+fn is_quit(command: &str) -> bool {
+    command.trim().eq_ignore_ascii_case("QUIT")
+}
+
+/// Execute the commands in a script file, one per line, skipping blank lines
+/// and lines starting with `#`. Return whether every command succeeded.
+async fn run_script(stream: &mut TcpStream, path: &str, abort_on_error: bool) -> io::Result<bool> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("** Could not open script file \"{}\": {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    let (mut succeeded, mut failed) = (0, 0);
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let command = line.trim();
+        if command.is_empty() || command.starts_with('#') {
+            continue;
+        }
+        let reply = send(stream, command).await?;
+        if reply.starts_with("(error)") {
+            eprintln!("{}:{}: {}", path, i + 1, reply);
+            failed += 1;
+            if abort_on_error {
+                eprintln!("** Aborted at line {}", i + 1);
+                break;
+            }
+        } else {
+            println!("{}", reply);
+            succeeded += 1;
+        }
+        if is_quit(command) {
+            break;
+        }
+    }
+    println!("** {} succeeded, {} failed", succeeded, failed);
+    Ok(failed == 0)
+}
+
+/// Write a snapshot of the selected namespace to a file. The server takes
+/// the snapshot while no writes are executing, so it is consistent.
+async fn dump(stream: &mut TcpStream, path: &str) -> io::Result<bool> {
+    let reply = send(stream, "SNAPSHOT").await?;
+    if reply.starts_with("(error)") {
+        eprintln!("** Could not take a snapshot: {}", reply);
+        return Ok(false);
+    }
+    let snapshot = from_hex(reply.trim_matches('"'))?;
+    Decoder::new(&mut &snapshot[..]).header()?;
+    if let Err(e) = std::fs::write(path, &snapshot) {
+        eprintln!("** Could not write snapshot to \"{}\": {}", path, e);
+        return Ok(false);
+    }
+    println!("** Wrote {} bytes to {}", snapshot.len(), path);
+    Ok(true)
+}
+
+/// Quote an argument if the server would otherwise split it.
+fn quote(arg: &str) -> String {
+    match arg.is_empty() || arg.contains(char::is_whitespace) {
+        true => format!("\"{}\"", arg),
+        false => arg.to_string(),
+    }
+}
+
+/// Execute a command the given number of times, waiting for the interval
+/// between executions. Results are prefixed with the time they were received
+/// if the command is executed more than once.
+async fn run_command(
+    stream: &mut TcpStream,
+    command: &[String],
+    repeat: Option<u64>,
+    interval: Option<Duration>,
+) -> io::Result<bool> {
+    let command: Vec<String> = command.iter().map(|arg| quote(arg)).collect();
+    let command = command.join(" ");
+    let timestamps = repeat != Some(1);
+    let mut ok = true;
+    let mut count = 0;
+    while repeat.is_none_or(|repeat| count < repeat) {
+        if count > 0 {
+            if let Some(interval) = interval {
+                delay_for(interval).await;
+            }
+        }
+        let reply = send(stream, &command).await?;
+        ok &= !reply.starts_with("(error)");
+        match timestamps {
+            true => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                println!("[{}.{:03}] {}", now.as_secs(), now.subsec_millis(), reply);
+            }
+            false => println!("{}", reply),
+        }
+        count += 1;
+    }
+    Ok(ok)
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("");
+    let options = parse_options();
+    let url = &options.url;
+
+    if let Some(path) = &options.script {
+        let mut stream = connect(url).await?;
+        let ok = run_script(&mut stream, path, options.abort_on_error).await?;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+    if let Some(path) = &options.dump {
+        let mut stream = connect(url).await?;
+        let ok = dump(&mut stream, path).await?;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+    if !options.command.is_empty() {
+        let mut stream = connect(url).await?;
+        let ok = run_command(
+            &mut stream,
+            &options.command,
+            options.repeat,
+            options.interval,
+        )
+        .await?;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    println!();
     println!(" ___  __    ___  ________  ________                 ________  ___       ___     ");
     println!("|\\  \\|\\  \\ |\\  \\|\\   __  \\|\\   __  \\               |\\   ____\\|\\  \\     |\\  \\    ");
     println!("\\ \\  \\/  /|\\ \\  \\ \\  \\|\\ /\\ \\  \\|\\  \\  ____________\\ \\  \\___|\\ \\  \\    \\ \\  \\   ");
     println!(" \\ \\   ___  \\ \\  \\ \\   __  \\ \\   __  \\|\\____________\\ \\  \\    \\ \\  \\    \\ \\  \\  ");
     println!("  \\ \\  \\\\ \\  \\ \\  \\ \\  \\|\\  \\ \\  \\ \\  \\|____________|\\ \\  \\____\\ \\  \\____\\ \\  \\ ");
     println!("   \\ \\__\\\\ \\__\\ \\__\\ \\_______\\ \\__\\ \\__\\              \\ \\_______\\ \\_______\\ \\__\\");
-    println!("    \\|__| \\|__|\\|__|\\|_______|\\|__|\\|__|               \\|_______|\\|_______|\\|__|");
-    println!("");
-    println!("");
+    println!(
+        "    \\|__| \\|__|\\|__|\\|_______|\\|__|\\|__|               \\|_______|\\|_______|\\|__|"
+    );
+    println!();
+    println!();
     println!("Kiba CLI 0.1 (unstable)");
     println!("===========================");
 
-    let argv: Vec<String> = std::env::args().collect();
-    let url = match argv.len() {
-        1 => "127.0.0.1:6464",
-        _ => &argv[1],
-    };
     println!("** Attempting to connect to: {}", url);
 
-    let mut stream = TcpStream::connect(url).await?;
+    let mut stream = connect(url).await?;
     println!(
         "** Successfully established outbound TCP connection with: {}",
         url
@@ -38,14 +321,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .read_line(&mut wbuf)
             .expect("Failed to read input");
 
-        stream.write_all(wbuf.as_bytes()).await?;
-
-        // let mut rbuf = [0; 512 * (1 << 20)];
-        let mut rbuf = [0; 512];
-        stream.read(&mut rbuf[..]).await?;
-
-        println!("{}\n", String::from_utf8_lossy(&rbuf));
-        if wbuf.trim_matches(|c: char| c.is_whitespace()).to_uppercase() == "QUIT" {
+        let reply = send(&mut stream, wbuf.trim_end_matches('\n')).await?;
+        println!("{}\n", reply);
+        if is_quit(&wbuf) {
             println!("** Goodbye!");
             std::process::exit(0);
         }
@@ -0,0 +1,295 @@
+use kiba::client::{Client, Pipeline, Reply};
+use kiba::import::{parse_record, Format};
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::time::Instant;
+
+const USAGE: &str = "Usage: kiba-import [<url>] --format <csv|tsv> [--key-column <n>]
+                   [--value-column <n> | --hash] [--header] [--batch <rows>] <file>
+
+Each row is stored at the key in its key column. By default, the value in
+the value column is stored as a string. With --hash, each row is stored as
+a hash whose fields are named by the header row. Columns are numbered from
+0, and the file is read from stdin if it is \"-\".";
+
+/// Environment variable holding the URL of the server to connect to by default
+const URL_VAR: &str = "KIBA_URL";
+
+/// Number of rows sent in each batch if no batch size is given
+const DEFAULT_BATCH: usize = 1000;
+
+/// Maximum number of failed rows to report individually
+const MAX_REPORTED_ERRORS: u64 = 10;
+
+/// Settings passed as command-line arguments
+struct Options {
+    url: String,
+    path: String,
+    format: Format,
+    key_column: usize,
+
+    /// Column stored as a string, or `None` to store rows as hashes
+    value_column: Option<usize>,
+
+    /// Whether the first row names the columns rather than holding a record
+    header: bool,
+    batch: usize,
+}
+
+fn parse_options() -> Options {
+    let mut url = std::env::var(URL_VAR).unwrap_or_else(|_| "127.0.0.1:6464".to_string());
+    let mut positional = Vec::new();
+    let mut format = None;
+    let mut key_column = 0;
+    let mut value_column = None;
+    let mut hash = false;
+    let mut header = false;
+    let mut batch = DEFAULT_BATCH;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(|name| Format::parse(&name)) {
+                Some(Ok(f)) => format = Some(f),
+                Some(Err(e)) => exit_with_usage(&e),
+                None => exit_with_usage("Expected a format after \"--format\""),
+            },
+            "--key-column" => match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) => key_column = n,
+                _ => exit_with_usage("Key column must be a non-negative integer"),
+            },
+            "--value-column" => match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) => value_column = Some(n),
+                _ => exit_with_usage("Value column must be a non-negative integer"),
+            },
+            "--hash" => hash = true,
+            "--header" => header = true,
+            "--batch" => match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => batch = n,
+                _ => exit_with_usage("Batch size must be a positive integer"),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                exit_with_usage(&format!("Unrecognized option \"{}\"", arg));
+            }
+            _ => positional.push(arg),
+        }
+    }
+    // The file is given last, following the URL if there is one
+    let path = match positional.len() {
+        1 => positional.remove(0),
+        2 => {
+            url = positional.remove(0);
+            positional.remove(0)
+        }
+        0 => exit_with_usage("Expected a file to import"),
+        _ => exit_with_usage(&format!("Unexpected argument \"{}\"", positional[2])),
+    };
+    let format = match format {
+        Some(format) => format,
+        None => exit_with_usage("Expected a format"),
+    };
+    if hash && value_column.is_some() {
+        exit_with_usage("\"--hash\" and \"--value-column\" cannot both be given");
+    }
+    let value_column = match hash {
+        true => None,
+        // The value is the column following the key by default
+        false => Some(value_column.unwrap_or(key_column + 1)),
+    };
+    Options {
+        url,
+        path,
+        format,
+        key_column,
+        value_column,
+        // Hash fields are named by the header, so it is always present
+        header: header || hash,
+        batch,
+    }
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+/// Queue the commands that store a row, returning how many were queued.
+fn queue_row(
+    pipeline: &mut Pipeline,
+    options: &Options,
+    columns: &[String],
+    row: &[String],
+) -> Result<usize, String> {
+    let field = |i: usize| match row.get(i) {
+        Some(field) => Ok(field.as_str()),
+        None => Err(format!(
+            "Expected at least {} columns, got {}",
+            i + 1,
+            row.len()
+        )),
+    };
+    let key = field(options.key_column)?;
+    match options.value_column {
+        Some(i) => {
+            pipeline.cmd(&["SET", key, field(i)?]);
+            Ok(1)
+        }
+        None => {
+            if row.len() != columns.len() {
+                return Err(format!(
+                    "Expected {} columns, got {}",
+                    columns.len(),
+                    row.len()
+                ));
+            }
+            let mut queued = 0;
+            for (i, (name, val)) in columns.iter().zip(row.iter()).enumerate() {
+                if i != options.key_column {
+                    pipeline.cmd(&["HSET", key, name, val]);
+                    queued += 1;
+                }
+            }
+            Ok(queued)
+        }
+    }
+}
+
+/// Counts of the rows processed so far
+#[derive(Default)]
+struct Progress {
+    imported: u64,
+    failed: u64,
+
+    /// Whether the progress line is displayed and must be ended before
+    /// printing anything else
+    displayed: bool,
+}
+
+impl Progress {
+    fn fail(&mut self, line: usize, error: &str) {
+        self.failed += 1;
+        if self.failed <= MAX_REPORTED_ERRORS {
+            if self.displayed {
+                eprintln!();
+                self.displayed = false;
+            }
+            eprintln!("line {}: {}", line, error);
+        }
+    }
+
+    fn display(&mut self) {
+        eprint!("\r** Imported {} rows", self.imported);
+        self.displayed = true;
+    }
+}
+
+/// Send a batch of rows, given as the line and number of commands of each
+/// row, and count the rows whose commands all succeeded.
+async fn send_batch(
+    client: &mut Client,
+    pipeline: &mut Pipeline,
+    rows: &mut Vec<(usize, usize)>,
+    progress: &mut Progress,
+) -> io::Result<()> {
+    if pipeline.is_empty() {
+        rows.clear();
+        return Ok(());
+    }
+    let replies = pipeline.query(client).await?;
+    let mut replies = replies.into_iter();
+    for (line, commands) in rows.drain(..) {
+        let errors: Vec<String> = replies
+            .by_ref()
+            .take(commands)
+            .filter_map(|reply| match reply {
+                Reply::Error(e) => Some(e),
+                _ => None,
+            })
+            .collect();
+        match errors.first() {
+            Some(e) => progress.fail(line, e),
+            None => progress.imported += 1,
+        }
+    }
+    *pipeline = Pipeline::new();
+    progress.display();
+    Ok(())
+}
+
+async fn import(
+    client: &mut Client,
+    options: &Options,
+    input: impl BufRead,
+) -> io::Result<Progress> {
+    let mut progress = Progress::default();
+    let mut columns = Vec::new();
+    let mut pipeline = Pipeline::new();
+    let mut rows = Vec::with_capacity(options.batch);
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let record = match parse_record(&line, options.format) {
+            Ok(record) => record,
+            Err(e) => {
+                progress.fail(i + 1, &e);
+                continue;
+            }
+        };
+        if options.header && columns.is_empty() {
+            columns = record;
+            if options.key_column >= columns.len() {
+                eprintln!("** The header has no column {}", options.key_column);
+                std::process::exit(1);
+            }
+            continue;
+        }
+        match queue_row(&mut pipeline, options, &columns, &record) {
+            Ok(commands) => rows.push((i + 1, commands)),
+            Err(e) => progress.fail(i + 1, &e),
+        }
+        if rows.len() >= options.batch {
+            send_batch(client, &mut pipeline, &mut rows, &mut progress).await?;
+        }
+    }
+    send_batch(client, &mut pipeline, &mut rows, &mut progress).await?;
+    Ok(progress)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let options = parse_options();
+    let input: Box<dyn BufRead> = match options.path.as_str() {
+        "-" => Box::new(BufReader::new(io::stdin())),
+        path => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("** Could not open \"{}\": {}", path, e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let mut client = match Client::open(&options.url).await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("** Could not connect to {}: {}", options.url, e);
+            std::process::exit(1);
+        }
+    };
+
+    let start = Instant::now();
+    let progress = import(&mut client, &options, input).await?;
+    eprintln!(
+        "\r** Imported {} rows, {} failed, in {:.2}s",
+        progress.imported,
+        progress.failed,
+        start.elapsed().as_secs_f64()
+    );
+    std::process::exit(if progress.failed == 0 { 0 } else { 1 });
+}
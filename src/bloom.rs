@@ -0,0 +1,262 @@
+use crate::snapshot::{invalid_data, Decoder, Encoder};
+use fnv::FnvHasher;
+use std::hash::Hasher;
+use std::io;
+
+/// Error rate used when a filter is implicitly created by an add operation
+pub const DEFAULT_ERROR_RATE: f64 = 0.01;
+
+/// Capacity used when a filter is implicitly created by an add operation
+pub const DEFAULT_CAPACITY: u64 = 100;
+
+/// Factor by which the capacity of each successive sub-filter grows
+const GROWTH_FACTOR: u64 = 2;
+
+/// Factor by which the error rate of each successive sub-filter is tightened
+const TIGHTENING_RATIO: f64 = 0.5;
+
+/// Maximum number of bytes a filter may reserve when it is created
+pub const MAX_RESERVED_BYTES: u64 = 256 * 1024 * 1024;
+
+/// A fixed-size bloom filter holding up to `capacity` items at its target
+/// error rate.
+#[derive(Debug)]
+struct BloomFilter {
+    bits: Vec<u64>,
+    nbits: u64,
+    nhashes: u64,
+    capacity: u64,
+    count: u64,
+}
+
+impl BloomFilter {
+    fn new(error_rate: f64, capacity: u64) -> Self {
+        let nbits = Self::nbits(error_rate, capacity);
+        let nhashes = (-error_rate.log2()).ceil().max(1.0) as u64;
+        Self {
+            bits: vec![0; nbits.div_ceil(64) as usize],
+            nbits,
+            nhashes,
+            capacity,
+            count: 0,
+        }
+    }
+
+    /// Return the number of bits needed to hold `capacity` items at the
+    /// error rate.
+    fn nbits(error_rate: f64, capacity: u64) -> u64 {
+        let ln2 = std::f64::consts::LN_2;
+        let nbits = (-(capacity as f64) * error_rate.ln() / (ln2 * ln2)).ceil() as u64;
+        nbits.max(64)
+    }
+
+    fn is_full(&self) -> bool {
+        self.count >= self.capacity
+    }
+
+    /// Return the bit positions for an item using double hashing.
+    fn positions(&self, hashes: (u64, u64)) -> impl Iterator<Item = u64> + '_ {
+        let (h1, h2) = hashes;
+        (0..self.nhashes).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.nbits)
+    }
+
+    fn contains(&self, hashes: (u64, u64)) -> bool {
+        self.positions(hashes)
+            .all(|pos| self.bits[(pos / 64) as usize] & (1 << (pos % 64)) != 0)
+    }
+
+    fn insert(&mut self, hashes: (u64, u64)) {
+        let positions: Vec<u64> = self.positions(hashes).collect();
+        for pos in positions {
+            self.bits[(pos / 64) as usize] |= 1 << (pos % 64);
+        }
+        self.count += 1;
+    }
+}
+
+/// A bloom filter that grows as items are added by chaining sub-filters of
+/// increasing capacity and decreasing error rate, so that the compounded
+/// false positive rate stays close to the rate it was created with.
+#[derive(Debug)]
+pub struct ScalableBloomFilter {
+    filters: Vec<BloomFilter>,
+    error_rate: f64,
+}
+
+impl ScalableBloomFilter {
+    pub fn new(error_rate: f64, capacity: u64) -> Self {
+        Self {
+            filters: vec![BloomFilter::new(error_rate * TIGHTENING_RATIO, capacity)],
+            error_rate,
+        }
+    }
+
+    /// Return the number of bytes a filter created with the error rate and
+    /// capacity would reserve up front.
+    pub fn reserved_bytes(error_rate: f64, capacity: u64) -> u64 {
+        BloomFilter::nbits(error_rate * TIGHTENING_RATIO, capacity)
+            .div_ceil(64)
+            .saturating_mul(8)
+    }
+
+    /// Add an item to the filter.
+    /// Return true if the item was newly added, and false if the item may
+    /// have already existed.
+    pub fn add(&mut self, item: &str) -> bool {
+        let hashes = hash_item(item);
+        if self.filters.iter().any(|f| f.contains(hashes)) {
+            return false;
+        }
+        if self.filters.last().unwrap().is_full() {
+            let n = self.filters.len() as i32;
+            let capacity = self
+                .filters
+                .last()
+                .unwrap()
+                .capacity
+                .saturating_mul(GROWTH_FACTOR);
+            let error_rate = self.error_rate * TIGHTENING_RATIO.powi(n + 1);
+            self.filters.push(BloomFilter::new(error_rate, capacity));
+        }
+        self.filters.last_mut().unwrap().insert(hashes);
+        true
+    }
+
+    /// Return if the item may exist in the filter.
+    /// False positives are possible, but false negatives are not.
+    pub fn contains(&self, item: &str) -> bool {
+        let hashes = hash_item(item);
+        self.filters.iter().any(|f| f.contains(hashes))
+    }
+
+    /// Return the number of items added to the filter.
+    pub fn len(&self) -> u64 {
+        self.filters.iter().map(|f| f.count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the approximate number of bytes used by the filter.
+    pub fn memory_usage(&self) -> u64 {
+        self.filters.iter().map(|f| f.bits.len() as u64 * 8).sum()
+    }
+
+    /// Write the filter in the snapshot encoding.
+    pub fn encode(&self, enc: &mut Encoder) -> io::Result<()> {
+        enc.f64(self.error_rate)?;
+        enc.u64(self.filters.len() as u64)?;
+        for filter in self.filters.iter() {
+            enc.u64(filter.nbits)?;
+            enc.u64(filter.nhashes)?;
+            enc.u64(filter.capacity)?;
+            enc.u64(filter.count)?;
+            for word in filter.bits.iter() {
+                enc.u64(*word)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a filter written by `encode`.
+    pub fn decode(dec: &mut Decoder) -> io::Result<Self> {
+        let error_rate = dec.f64()?;
+        let nfilters = dec.u64()?;
+        let mut filters = Vec::new();
+        for _ in 0..nfilters {
+            let nbits = dec.u64()?;
+            let nhashes = dec.u64()?;
+            let capacity = dec.u64()?;
+            let count = dec.u64()?;
+            if nbits == 0 {
+                return Err(invalid_data("Bloom filter has no bits"));
+            }
+            let mut bits = Vec::new();
+            for _ in 0..nbits.div_ceil(64) {
+                bits.push(dec.u64()?);
+            }
+            filters.push(BloomFilter {
+                bits,
+                nbits,
+                nhashes,
+                capacity,
+                count,
+            });
+        }
+        if filters.is_empty() {
+            return Err(invalid_data("Bloom filter has no sub-filters"));
+        }
+        Ok(Self {
+            filters,
+            error_rate,
+        })
+    }
+}
+
+/// Return two hashes of an item for double hashing. Filters are persisted in
+/// snapshots, so the hashes must not change between builds or platforms.
+fn hash_item(item: &str) -> (u64, u64) {
+    let mut hasher = FnvHasher::default();
+    hasher.write(item.as_bytes());
+    let h1 = hasher.finish();
+    hasher.write(&0x9e37_79b9_7f4a_7c15_u64.to_le_bytes());
+    let h2 = hasher.finish() | 1;
+    (h1, h2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_add_contains() {
+        let mut filter = ScalableBloomFilter::new(0.01, 100);
+        assert!(filter.is_empty());
+        assert!(filter.add("foo"));
+        assert!(!filter.add("foo"));
+        assert!(filter.contains("foo"));
+        assert!(!filter.contains("bar"));
+        assert_eq!(filter.len(), 1);
+    }
+
+    #[test]
+    fn test_bloom_scaling() {
+        let mut filter = ScalableBloomFilter::new(0.01, 10);
+        for i in 0..1000 {
+            filter.add(&format!("item{}", i));
+        }
+        assert!(filter.filters.len() > 1);
+
+        // No false negatives
+        for i in 0..1000 {
+            assert!(filter.contains(&format!("item{}", i)));
+        }
+
+        // False positive rate stays near the requested error rate
+        let false_positives = (0..1000)
+            .filter(|i| filter.contains(&format!("other{}", i)))
+            .count();
+        assert!(false_positives < 30);
+    }
+
+    #[test]
+    fn test_bloom_reserved_bytes() {
+        assert_eq!(ScalableBloomFilter::reserved_bytes(0.01, 1), 8);
+        assert_eq!(
+            ScalableBloomFilter::reserved_bytes(0.02, 1000),
+            ScalableBloomFilter::new(0.02, 1000).memory_usage()
+        );
+        assert!(ScalableBloomFilter::reserved_bytes(0.01, u64::MAX) > MAX_RESERVED_BYTES);
+        assert!(
+            ScalableBloomFilter::reserved_bytes(f64::MIN_POSITIVE, 1 << 40) > MAX_RESERVED_BYTES
+        );
+    }
+    #[test]
+    fn test_bloom_hash_item() {
+        // 64-bit FNV-1a, which filters restored from snapshots depend on
+        assert_eq!(hash_item("").0, 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash_item("foo").0, 0xdcb2_7518_fed9_d577);
+        assert_eq!(hash_item("foo").1 & 1, 1);
+    }
+}
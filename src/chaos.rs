@@ -0,0 +1,101 @@
+use crate::rng::Rng;
+use std::time::Duration;
+
+/// A change to the fault-injection settings requested with DEBUG CHAOS
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChaosAction {
+    /// Report the current settings
+    Status,
+    /// Delay every response by a number of milliseconds
+    Latency(u64),
+    /// Drop responses with a probability
+    Drop(f64),
+    /// Disconnect clients with a probability instead of responding
+    Disconnect(f64),
+    /// Disable all faults
+    Off,
+}
+
+/// A fault injected in place of a normal response
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+    /// Send no response, leaving the client waiting
+    Drop,
+    /// Close the connection without responding
+    Disconnect,
+}
+
+/// Fault-injection settings shared by all client connections.
+///
+/// Chaos mode lets client libraries and operators test their failure
+/// handling against a real server. It is only available if enabled in
+/// the config.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Chaos {
+    latency: u64,
+    drop_rate: f64,
+    disconnect_rate: f64,
+}
+
+impl Chaos {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an action and return a description of the resulting settings.
+    pub fn apply(&mut self, action: ChaosAction) -> Vec<String> {
+        match action {
+            ChaosAction::Status => {}
+            ChaosAction::Latency(ms) => self.latency = ms,
+            ChaosAction::Drop(p) => self.drop_rate = p,
+            ChaosAction::Disconnect(p) => self.disconnect_rate = p,
+            ChaosAction::Off => *self = Self::new(),
+        }
+        vec![
+            format!("latency {}", self.latency),
+            format!("drop {}", self.drop_rate),
+            format!("disconnect {}", self.disconnect_rate),
+        ]
+    }
+
+    /// Return the artificial delay applied before each response.
+    pub fn latency(&self) -> Duration {
+        Duration::from_millis(self.latency)
+    }
+
+    /// Return the fault to inject in place of the next response, if any.
+    pub fn next_fault(&self, rng: &mut Rng) -> Option<Fault> {
+        if rng.next_f64() < self.disconnect_rate {
+            return Some(Fault::Disconnect);
+        }
+        if rng.next_f64() < self.drop_rate {
+            return Some(Fault::Drop);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chaos() {
+        let mut chaos = Chaos::new();
+        let mut rng = Rng::new(0);
+        assert_eq!(chaos.next_fault(&mut rng), None);
+        assert_eq!(
+            chaos.apply(ChaosAction::Latency(50)),
+            vec!["latency 50", "drop 0", "disconnect 0"]
+        );
+        assert_eq!(chaos.latency(), Duration::from_millis(50));
+
+        chaos.apply(ChaosAction::Drop(1.0));
+        assert_eq!(chaos.next_fault(&mut rng), Some(Fault::Drop));
+        chaos.apply(ChaosAction::Disconnect(1.0));
+        assert_eq!(chaos.next_fault(&mut rng), Some(Fault::Disconnect));
+
+        chaos.apply(ChaosAction::Off);
+        assert_eq!(chaos, Chaos::new());
+    }
+}
@@ -0,0 +1,769 @@
+//! Asynchronous client for a Kiba server.
+//!
+//! Commands are sent in the multibulk format, so arguments may contain any
+//! characters. Connections switch the server to RESP replies with
+//! `FORMAT resp`, which are decoded into `Reply` values that can be converted
+//! to Rust types with `FromReply`.
+//!
+//! ```no_run
+//! use kiba::client::{Client, Pipeline};
+//!
+//! # async fn example() -> std::io::Result<()> {
+//! let mut client = Client::connect("127.0.0.1:6464").await?;
+//! client.query::<()>(&["SET", "counter", "1"]).await?;
+//! let counter: i64 = client.query(&["INCR", "counter"]).await?;
+//!
+//! let replies = Pipeline::new()
+//!     .cmd(&["INCR", "counter"])
+//!     .cmd(&["GET", "counter"])
+//!     .query(&mut client)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+use crate::protocol::{BULK_PREFIX, MULTIBULK_PREFIX};
+use crate::record::KibaHash;
+use crate::server::TERMINATOR;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::prelude::*;
+use tokio::time::timeout;
+
+/// Capacity of the buffer for reading replies
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// Port that servers listen on by default
+pub const DEFAULT_PORT: u16 = 6464;
+
+/// Settings for connecting to a server, parsed from a URL
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectionInfo {
+    /// Address of the server, of the form "host:port"
+    pub addr: String,
+
+    /// Namespace to select after connecting
+    pub namespace: Option<String>,
+
+    /// Time to wait for the connection to be established
+    pub timeout: Option<Duration>,
+}
+
+impl ConnectionInfo {
+    /// Parse a URL of the form `kiba://host[:port][/namespace][?timeout=seconds]`,
+    /// or an address of the form `host:port`.
+    ///
+    /// The `kibas://` (TLS) and `kiba+unix://` (Unix socket) schemes and
+    /// passwords are recognized but rejected, since the server supports
+    /// neither of these transports nor authentication.
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let rest = match url.split_once("://") {
+            Some(("kiba", rest)) => rest,
+            Some(("kibas", _)) => return Err("TLS connections are not supported".to_string()),
+            Some(("kiba+unix", _)) => {
+                return Err("Unix socket connections are not supported".to_string())
+            }
+            Some((scheme, _)) => return Err(format!("Unsupported URL scheme \"{}\"", scheme)),
+            None => {
+                return Ok(Self {
+                    addr: url.to_string(),
+                    namespace: None,
+                    timeout: None,
+                })
+            }
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (authority, path) = match rest.split_once('/') {
+            Some((authority, path)) => (authority, path),
+            None => (rest, ""),
+        };
+        if authority.contains('@') {
+            return Err("Passwords are not supported".to_string());
+        }
+        // The colons of a bracketed IPv6 address do not separate a port
+        let port = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.ends_with(']') => Some((host, port)),
+            _ => None,
+        };
+        let addr = match port {
+            Some((host, port)) if !host.is_empty() => match port.parse::<u16>() {
+                Ok(_) => authority.to_string(),
+                Err(_) => return Err(format!("Invalid port \"{}\"", port)),
+            },
+            None if !authority.is_empty() => format!("{}:{}", authority, DEFAULT_PORT),
+            _ => return Err(format!("Missing host in URL \"{}\"", url)),
+        };
+        let namespace = match path {
+            "" => None,
+            path => Some(path.to_string()),
+        };
+        let mut timeout = None;
+        for param in query.unwrap_or("").split('&').filter(|p| !p.is_empty()) {
+            match param.split_once('=') {
+                Some(("timeout", secs)) => match secs.parse::<f64>() {
+                    Ok(secs) if secs > 0.0 && secs.is_finite() => {
+                        timeout = Some(Duration::from_secs_f64(secs))
+                    }
+                    _ => return Err(format!("Invalid timeout \"{}\"", secs)),
+                },
+                _ => return Err(format!("Unrecognized URL parameter \"{}\"", param)),
+            }
+        }
+        Ok(Self {
+            addr,
+            namespace,
+            timeout,
+        })
+    }
+}
+
+/// A reply from the server
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply {
+    /// A status such as `OK` or `PONG`
+    Status(String),
+    Str(String),
+    Integer(i64),
+    Array(Vec<Reply>),
+    Nil,
+    Error(String),
+}
+
+impl Reply {
+    /// Decode the first reply in a buffer of RESP replies.
+    /// Return the reply and the number of bytes it occupies, or `None` if the
+    /// buffer does not yet hold a complete reply.
+    pub fn decode(buf: &[u8]) -> Result<Option<(Self, usize)>, String> {
+        let end = match buf.windows(2).position(|w| w == TERMINATOR) {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let line = String::from_utf8_lossy(&buf[1..end]).into_owned();
+        let next = end + TERMINATOR.len();
+        match buf[0] {
+            b'+' => Ok(Some((Reply::Status(line), next))),
+            // Errors without a code of their own are sent with the generic ERR
+            b'-' => {
+                let error = line.strip_prefix("ERR ").unwrap_or(&line).to_string();
+                Ok(Some((Reply::Error(error), next)))
+            }
+            b':' => match line.parse() {
+                Ok(int) => Ok(Some((Reply::Integer(int), next))),
+                Err(_) => Err(format!("Invalid integer reply \"{}\"", line)),
+            },
+            BULK_PREFIX => match decode_length(&line)? {
+                None => Ok(Some((Reply::Nil, next))),
+                Some(len) => {
+                    let end = next.saturating_add(len);
+                    if buf.len() < end.saturating_add(TERMINATOR.len()) {
+                        return Ok(None);
+                    }
+                    let s = String::from_utf8_lossy(&buf[next..end]).into_owned();
+                    Ok(Some((Reply::Str(s), end + TERMINATOR.len())))
+                }
+            },
+            MULTIBULK_PREFIX => match decode_length(&line)? {
+                None => Ok(Some((Reply::Nil, next))),
+                Some(len) => {
+                    let mut elements = Vec::new();
+                    let mut pos = next;
+                    for _ in 0..len {
+                        match Reply::decode(&buf[pos..])? {
+                            Some((element, n)) => {
+                                elements.push(element);
+                                pos += n;
+                            }
+                            None => return Ok(None),
+                        }
+                    }
+                    Ok(Some((Reply::Array(elements), pos)))
+                }
+            },
+            b => Err(format!(
+                "Unexpected reply type '{}'",
+                (b as char).escape_default()
+            )),
+        }
+    }
+}
+
+/// Decode the length of a bulk string or array, which is `None` for nil.
+fn decode_length(line: &str) -> Result<Option<usize>, String> {
+    match line.parse::<i64>() {
+        Ok(-1) => Ok(None),
+        Ok(len) if len >= 0 => Ok(Some(len as usize)),
+        _ => Err(format!("Invalid length \"{}\"", line)),
+    }
+}
+
+/// Conversion from a reply into a Rust type
+pub trait FromReply: Sized {
+    fn from_reply(reply: Reply) -> Result<Self, String>;
+}
+
+fn unexpected(reply: Reply, expected: &str) -> String {
+    match reply {
+        Reply::Error(e) => e,
+        reply => format!("Expected {}, got {:?}", expected, reply),
+    }
+}
+
+impl FromReply for Reply {
+    fn from_reply(reply: Reply) -> Result<Self, String> {
+        Ok(reply)
+    }
+}
+
+impl FromReply for () {
+    fn from_reply(reply: Reply) -> Result<Self, String> {
+        match reply {
+            Reply::Error(e) => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromReply for i64 {
+    fn from_reply(reply: Reply) -> Result<Self, String> {
+        match reply {
+            Reply::Integer(int) => Ok(int),
+            Reply::Str(ref s) | Reply::Status(ref s) if s.parse::<i64>().is_ok() => {
+                Ok(s.parse().unwrap())
+            }
+            reply => Err(unexpected(reply, "an integer")),
+        }
+    }
+}
+
+impl FromReply for bool {
+    fn from_reply(reply: Reply) -> Result<Self, String> {
+        i64::from_reply(reply).map(|int| int != 0)
+    }
+}
+
+impl FromReply for String {
+    fn from_reply(reply: Reply) -> Result<Self, String> {
+        match reply {
+            Reply::Str(s) | Reply::Status(s) => Ok(s),
+            Reply::Integer(int) => Ok(int.to_string()),
+            reply => Err(unexpected(reply, "a string")),
+        }
+    }
+}
+
+impl<T: FromReply> FromReply for Option<T> {
+    fn from_reply(reply: Reply) -> Result<Self, String> {
+        match reply {
+            Reply::Nil => Ok(None),
+            reply => T::from_reply(reply).map(Some),
+        }
+    }
+}
+
+impl<T: FromReply> FromReply for Vec<T> {
+    fn from_reply(reply: Reply) -> Result<Self, String> {
+        match reply {
+            Reply::Array(elements) => elements.into_iter().map(T::from_reply).collect(),
+            reply => Err(unexpected(reply, "an array")),
+        }
+    }
+}
+
+/// Encode a command in the multibulk format.
+fn encode(args: &[&str], buf: &mut Vec<u8>) {
+    buf.push(MULTIBULK_PREFIX);
+    buf.extend_from_slice(args.len().to_string().as_bytes());
+    buf.extend_from_slice(TERMINATOR);
+    for arg in args {
+        buf.push(BULK_PREFIX);
+        buf.extend_from_slice(arg.len().to_string().as_bytes());
+        buf.extend_from_slice(TERMINATOR);
+        buf.extend_from_slice(arg.as_bytes());
+        buf.extend_from_slice(TERMINATOR);
+    }
+}
+
+/// A connection to a server
+pub struct Client {
+    stream: TcpStream,
+
+    /// Data received after the last complete reply
+    buf: Vec<u8>,
+}
+
+impl Client {
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let mut client = Self {
+            stream: TcpStream::connect(addr).await?,
+            buf: Vec::with_capacity(READ_BUFFER_SIZE),
+        };
+        // Unlike the human-readable format, RESP delimits values containing
+        // line breaks
+        client.query::<()>(&["FORMAT", "resp"]).await?;
+        Ok(client)
+    }
+
+    /// Connect to a server at a URL accepted by `ConnectionInfo::parse`, and
+    /// select the URL's namespace.
+    pub async fn open(url: &str) -> io::Result<Self> {
+        let info = ConnectionInfo::parse(url)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut client = match info.timeout {
+            Some(duration) => match timeout(duration, Self::connect(info.addr.as_str())).await {
+                Ok(client) => client?,
+                Err(_) => return Err(io::ErrorKind::TimedOut.into()),
+            },
+            None => Self::connect(info.addr.as_str()).await?,
+        };
+        if let Some(namespace) = &info.namespace {
+            client.query::<()>(&["NAMESPACE", namespace]).await?;
+        }
+        Ok(client)
+    }
+
+    /// Execute a command and convert its reply.
+    /// Errors replied by the server are returned as `io::ErrorKind::Other`.
+    pub async fn query<T: FromReply>(&mut self, args: &[&str]) -> io::Result<T> {
+        let mut buf = Vec::new();
+        encode(args, &mut buf);
+        self.stream.write_all(&buf).await?;
+        let reply = self.read_reply().await?;
+        T::from_reply(reply).map_err(io::Error::other)
+    }
+
+    /// Store a value in the fields of a hash.
+    pub async fn write_hash<T: KibaHash>(&mut self, key: &str, value: &T) -> io::Result<()> {
+        let mut pipe = Pipeline::new();
+        for (field, val) in value.to_hash() {
+            pipe.cmd(&["HSET", key, &field, &val]);
+        }
+        for reply in pipe.query(self).await? {
+            <()>::from_reply(reply).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    /// Read a value from the fields of a hash.
+    /// Return `None` if none of the value's fields are set.
+    pub async fn read_hash<T: KibaHash>(&mut self, key: &str) -> io::Result<Option<T>> {
+        let mut pipe = Pipeline::new();
+        for field in T::FIELDS {
+            pipe.cmd(&["HGET", key, field]);
+        }
+        let mut fields = HashMap::new();
+        for (field, reply) in T::FIELDS.iter().zip(pipe.query(self).await?) {
+            if let Some(val) = Option::<String>::from_reply(reply).map_err(io::Error::other)? {
+                fields.insert(field.to_string(), val);
+            }
+        }
+        match fields.is_empty() {
+            true => Ok(None),
+            false => T::from_hash(&fields)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    /// Read the next reply from the server.
+    async fn read_reply(&mut self) -> io::Result<Reply> {
+        let mut chunk = [0; READ_BUFFER_SIZE];
+        loop {
+            let decoded = Reply::decode(&self.buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if let Some((reply, len)) = decoded {
+                self.buf.drain(..len);
+                return Ok(reply);
+            }
+            let n = self.stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+/// An integer counter stored in a key.
+///
+/// Unlike in Redis, incrementing a key that is not set is an error, so the
+/// counter must be initialized with `set` before it is changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Counter {
+    key: String,
+}
+
+impl Counter {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+        }
+    }
+
+    /// Return the value of the counter, which is 0 if the key is not set.
+    pub async fn get(&self, client: &mut Client) -> io::Result<i64> {
+        let val: Option<i64> = client.query(&["GET", &self.key]).await?;
+        Ok(val.unwrap_or(0))
+    }
+
+    /// Set the value of the counter.
+    pub async fn set(&self, client: &mut Client, val: i64) -> io::Result<()> {
+        client.query(&["SET", &self.key, &val.to_string()]).await
+    }
+
+    /// Increment the counter and return its new value.
+    pub async fn incr(&self, client: &mut Client) -> io::Result<i64> {
+        client.query(&["INCR", &self.key]).await
+    }
+
+    /// Decrement the counter and return its new value.
+    pub async fn decr(&self, client: &mut Client) -> io::Result<i64> {
+        client.query(&["DECR", &self.key]).await
+    }
+
+    /// Add an amount, which may be negative, to the counter and return its new value.
+    pub async fn add(&self, client: &mut Client, amount: i64) -> io::Result<i64> {
+        match amount < 0 {
+            true => {
+                let amount = amount.unsigned_abs().to_string();
+                client.query(&["DECRBY", &self.key, &amount]).await
+            }
+            false => {
+                client
+                    .query(&["INCRBY", &self.key, &amount.to_string()])
+                    .await
+            }
+        }
+    }
+}
+
+/// A first-in, first-out queue stored in a list.
+///
+/// Items are stored as strings with `ToString` and read with `FromStr`.
+/// Since each item is pushed and popped by a single command, several
+/// clients can use the same queue, and each item is popped by one client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Queue<T> {
+    key: String,
+    items: PhantomData<T>,
+}
+
+impl<T: ToString + FromStr> Queue<T> {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            items: PhantomData,
+        }
+    }
+
+    /// Add an item to the back of the queue and return the length of the queue.
+    pub async fn push(&self, client: &mut Client, item: &T) -> io::Result<i64> {
+        client.query(&["LPUSH", &self.key, &item.to_string()]).await
+    }
+
+    /// Remove and return the item at the front of the queue, if any.
+    ///
+    /// If the item cannot be read with `FromStr`, it has still been removed
+    /// from the queue, so it is returned as an `InvalidItem` inside an error
+    /// of kind `InvalidData` rather than being lost.
+    pub async fn pop(&self, client: &mut Client) -> io::Result<Option<T>> {
+        let item: Option<String> = client.query(&["RPOP", &self.key]).await?;
+        match item {
+            Some(item) => match item.parse() {
+                Ok(item) => Ok(Some(item)),
+                Err(_) => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    InvalidItem {
+                        key: self.key.clone(),
+                        item,
+                    },
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+/// An item popped from a queue that could not be read with `FromStr`.
+///
+/// The raw item can be recovered from the error returned by `Queue::pop`
+/// with `io::Error::into_inner` and `downcast`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidItem {
+    /// Key of the queue
+    pub key: String,
+    /// Item as it was stored
+    pub item: String,
+}
+
+impl fmt::Display for InvalidItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid item in queue \"{}\": \"{}\"",
+            self.key, self.item
+        )
+    }
+}
+
+impl Error for InvalidItem {}
+
+/// Commands queued to be sent to a server in a single write.
+///
+/// Replies are returned in the order that commands were queued. Unlike a
+/// transaction, other clients' commands may execute between the commands of
+/// a pipeline.
+#[derive(Clone, Debug, Default)]
+pub struct Pipeline {
+    buf: Vec<u8>,
+    len: usize,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a command.
+    pub fn cmd(&mut self, args: &[&str]) -> &mut Self {
+        encode(args, &mut self.buf);
+        self.len += 1;
+        self
+    }
+
+    /// Return the number of queued commands.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Send the queued commands and return their replies in order.
+    /// Errors replied by the server are returned as `Reply::Error`.
+    pub async fn query(&self, client: &mut Client) -> io::Result<Vec<Reply>> {
+        client.stream.write_all(&self.buf).await?;
+        let mut replies = Vec::with_capacity(self.len);
+        for _ in 0..self.len {
+            replies.push(client.read_reply().await?);
+        }
+        Ok(replies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::server::Server;
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    impl KibaHash for Point {
+        const FIELDS: &'static [&'static str] = &["x", "y"];
+
+        fn to_hash(&self) -> Vec<(String, String)> {
+            vec![
+                ("x".to_string(), self.x.to_string()),
+                ("y".to_string(), self.y.to_string()),
+            ]
+        }
+
+        fn from_hash(fields: &HashMap<String, String>) -> Result<Self, String> {
+            Ok(Self {
+                x: crate::record::field(fields, "x")?,
+                y: crate::record::field(fields, "y")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_reply_decode() {
+        let decode = |buf: &[u8]| Reply::decode(buf).unwrap().unwrap().0;
+        assert_eq!(decode(b"+OK\r\n"), Reply::Status("OK".to_string()));
+        assert_eq!(decode(b"$3\r\nbar\r\n"), Reply::Str("bar".to_string()));
+        assert_eq!(decode(b":-3\r\n"), Reply::Integer(-3));
+        assert_eq!(decode(b"$-1\r\n"), Reply::Nil);
+        assert_eq!(decode(b"-ERR Oops\r\n"), Reply::Error("Oops".to_string()));
+        assert_eq!(
+            decode(b"-BUSY Try again\r\n"),
+            Reply::Error("BUSY Try again".to_string())
+        );
+        assert_eq!(decode(b"*0\r\n"), Reply::Array(vec![]));
+        let scan = b"*2\r\n$1\r\n0\r\n*2\r\n$3\r\nfoo\r\n$4\r\na\r\nb\r\n:1\r\n";
+        assert_eq!(
+            Reply::decode(scan),
+            Ok(Some((
+                Reply::Array(vec![
+                    Reply::Str("0".to_string()),
+                    Reply::Array(vec![
+                        Reply::Str("foo".to_string()),
+                        Reply::Str("a\r\nb".to_string())
+                    ])
+                ]),
+                scan.len() - 4
+            )))
+        );
+
+        // Incomplete replies wait for more input
+        for i in 0..scan.len() - 4 {
+            assert_eq!(Reply::decode(&scan[..i]), Ok(None));
+        }
+        assert!(Reply::decode(b"?\r\n").is_err());
+        assert!(Reply::decode(b"$x\r\n").is_err());
+
+        assert_eq!(i64::from_reply(Reply::Integer(3)), Ok(3));
+        assert_eq!(i64::from_reply(Reply::Str("3".to_string())), Ok(3));
+        assert!(i64::from_reply(Reply::Nil).is_err());
+        assert_eq!(Option::<String>::from_reply(Reply::Nil), Ok(None));
+        assert_eq!(
+            <()>::from_reply(Reply::Error("Oops".to_string())),
+            Err("Oops".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connection_info() {
+        let info = ConnectionInfo::parse("kiba://localhost:7000/sessions?timeout=1.5").unwrap();
+        assert_eq!(info.addr, "localhost:7000");
+        assert_eq!(info.namespace.as_deref(), Some("sessions"));
+        assert_eq!(info.timeout, Some(Duration::from_millis(1500)));
+
+        let info = ConnectionInfo::parse("kiba://10.0.0.1").unwrap();
+        assert_eq!(info.addr, "10.0.0.1:6464");
+        assert_eq!(info.namespace, None);
+        assert_eq!(info.timeout, None);
+        assert_eq!(
+            ConnectionInfo::parse("kiba://[::1]").unwrap().addr,
+            "[::1]:6464"
+        );
+        assert_eq!(
+            ConnectionInfo::parse("127.0.0.1:6464").unwrap().addr,
+            "127.0.0.1:6464"
+        );
+
+        for url in [
+            "kibas://localhost",
+            "kiba+unix:///tmp/kiba.sock",
+            "kiba://:secret@localhost",
+            "http://localhost",
+            "kiba://",
+            "kiba://localhost:port",
+            "kiba://localhost?timeout=0",
+            "kiba://localhost?retries=3",
+        ]
+        .iter()
+        {
+            assert!(ConnectionInfo::parse(url).is_err(), "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_pipeline() {
+        let config = Config::builder().bind("127.0.0.1:0").build().unwrap();
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        let mut client = Client::connect(addr).await.unwrap();
+        client
+            .query::<()>(&["SET", "greeting", "hello world"])
+            .await
+            .unwrap();
+        let greeting: Option<String> = client.query(&["GET", "greeting"]).await.unwrap();
+        assert_eq!(greeting.as_deref(), Some("hello world"));
+        assert!(client.query::<i64>(&["INCR", "greeting"]).await.is_err());
+
+        // Values containing line breaks or the text of other replies are
+        // read back intact, including from a pipeline
+        let values = ["a\r\nb", "x\n1) y\n2) z", "(nil)", "(integer) 5"];
+        for (i, val) in values.iter().enumerate() {
+            let key = format!("tricky{}", i);
+            client.query::<()>(&["SET", &key, val]).await.unwrap();
+            let read: Option<String> = client.query(&["GET", &key]).await.unwrap();
+            assert_eq!(read.as_deref(), Some(*val));
+        }
+        let mut pipe = Pipeline::new();
+        pipe.cmd(&["GET", "tricky0"])
+            .cmd(&["GET", "tricky1"])
+            .cmd(&["GET", "missing"]);
+        assert_eq!(
+            pipe.query(&mut client).await.unwrap(),
+            vec![
+                Reply::Str(values[0].to_string()),
+                Reply::Str(values[1].to_string()),
+                Reply::Nil
+            ]
+        );
+
+        let mut pipe = Pipeline::new();
+        pipe.cmd(&["SET", "counter", "1"])
+            .cmd(&["INCR", "counter"])
+            .cmd(&["INCRBY"])
+            .cmd(&["GET", "counter"]);
+        assert_eq!(pipe.len(), 4);
+        let replies = pipe.query(&mut client).await.unwrap();
+        assert_eq!(replies.len(), 4);
+        assert_eq!(replies[0], Reply::Status("OK".to_string()));
+        assert_eq!(replies[1], Reply::Integer(2));
+        assert!(matches!(replies[2], Reply::Error(_)));
+        assert_eq!(replies[3], Reply::Str("2".to_string()));
+
+        // Values are stored in and read from the fields of a hash
+        let point = Point { x: 3, y: -4 };
+        client.write_hash("point", &point).await.unwrap();
+        assert_eq!(client.read_hash("point").await.unwrap(), Some(point));
+        assert_eq!(client.read_hash::<Point>("missing").await.unwrap(), None);
+
+        // Counters and queues wrap commands on a single key
+        let visits = Counter::new("visits");
+        assert_eq!(visits.get(&mut client).await.unwrap(), 0);
+        assert!(visits.incr(&mut client).await.is_err());
+        visits.set(&mut client, 0).await.unwrap();
+        assert_eq!(visits.incr(&mut client).await.unwrap(), 1);
+        assert_eq!(visits.add(&mut client, 10).await.unwrap(), 11);
+        assert_eq!(visits.add(&mut client, -4).await.unwrap(), 7);
+        assert_eq!(visits.decr(&mut client).await.unwrap(), 6);
+        assert_eq!(visits.get(&mut client).await.unwrap(), 6);
+
+        let jobs: Queue<u32> = Queue::new("jobs");
+        assert_eq!(jobs.push(&mut client, &1).await.unwrap(), 1);
+        assert_eq!(jobs.push(&mut client, &2).await.unwrap(), 2);
+        assert_eq!(jobs.pop(&mut client).await.unwrap(), Some(1));
+        assert_eq!(jobs.pop(&mut client).await.unwrap(), Some(2));
+        assert_eq!(jobs.pop(&mut client).await.unwrap(), None);
+        client.query::<()>(&["LPUSH", "jobs", "x"]).await.unwrap();
+        let e = jobs.pop(&mut client).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        let invalid = e.into_inner().unwrap().downcast::<InvalidItem>().unwrap();
+        assert_eq!(invalid.item, "x");
+
+        // Opening a URL selects its namespace
+        let url = format!("kiba://{}/other?timeout=5", addr);
+        let mut other = Client::open(&url).await.unwrap();
+        let counter: Option<String> = other.query(&["GET", "counter"]).await.unwrap();
+        assert_eq!(counter, None);
+
+        handle.shutdown();
+        drop(client);
+        drop(other);
+        running.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+//! Blocking client for a Kiba server.
+//!
+//! Wraps the asynchronous `Client` with a single-threaded runtime that each
+//! call blocks on, so that programs without an async runtime of their own can
+//! connect to a server. Enabled with the `blocking` feature.
+//!
+//! ```no_run
+//! use kiba::client::blocking::Client;
+//! use kiba::client::Pipeline;
+//!
+//! # fn example() -> std::io::Result<()> {
+//! let mut client = Client::connect("127.0.0.1:6464")?;
+//! client.query::<()>(&["SET", "counter", "1"])?;
+//! let counter: i64 = client.query(&["INCR", "counter"])?;
+//!
+//! let mut pipe = Pipeline::new();
+//! pipe.cmd(&["INCR", "counter"]).cmd(&["GET", "counter"]);
+//! let replies = client.pipeline(&pipe)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Calls must not be made from within an async runtime, since blocking on
+//! the client's runtime there panics.
+
+use super::{FromReply, Pipeline, Reply};
+use crate::record::KibaHash;
+use std::io;
+use tokio::net::ToSocketAddrs;
+use tokio::runtime::{Builder, Runtime};
+
+/// A connection to a server whose calls block until the server replies
+pub struct Client {
+    // Declared before the runtime so that the connection is closed before
+    // the runtime that it is registered with is dropped
+    inner: super::Client,
+    rt: Runtime,
+}
+
+impl Client {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let mut rt = build_runtime()?;
+        let inner = rt.block_on(super::Client::connect(addr))?;
+        Ok(Self { inner, rt })
+    }
+
+    /// Connect to a server at a URL accepted by `ConnectionInfo::parse`, and
+    /// select the URL's namespace.
+    pub fn open(url: &str) -> io::Result<Self> {
+        let mut rt = build_runtime()?;
+        let inner = rt.block_on(super::Client::open(url))?;
+        Ok(Self { inner, rt })
+    }
+
+    /// Execute a command and convert its reply.
+    /// Errors replied by the server are returned as `io::ErrorKind::Other`.
+    pub fn query<T: FromReply>(&mut self, args: &[&str]) -> io::Result<T> {
+        let inner = &mut self.inner;
+        self.rt.block_on(inner.query(args))
+    }
+
+    /// Send the queued commands of a pipeline and return their replies in order.
+    /// Errors replied by the server are returned as `Reply::Error`.
+    pub fn pipeline(&mut self, pipe: &Pipeline) -> io::Result<Vec<Reply>> {
+        let inner = &mut self.inner;
+        self.rt.block_on(pipe.query(inner))
+    }
+
+    /// Store a value in the fields of a hash.
+    pub fn write_hash<T: KibaHash>(&mut self, key: &str, value: &T) -> io::Result<()> {
+        let inner = &mut self.inner;
+        self.rt.block_on(inner.write_hash(key, value))
+    }
+
+    /// Read a value from the fields of a hash.
+    /// Return `None` if none of the value's fields are set.
+    pub fn read_hash<T: KibaHash>(&mut self, key: &str) -> io::Result<Option<T>> {
+        let inner = &mut self.inner;
+        self.rt.block_on(inner.read_hash(key))
+    }
+}
+
+/// Build the runtime that a client's calls block on. Only the connection's
+/// I/O runs on it, so it doesn't need worker threads.
+fn build_runtime() -> io::Result<Runtime> {
+    Builder::new().basic_scheduler().enable_all().build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::server::Server;
+
+    #[test]
+    fn test_blocking_client() {
+        // The server runs on worker threads while the test thread blocks
+        let mut rt = Runtime::new().unwrap();
+        let config = Config::builder().bind("127.0.0.1:0").build().unwrap();
+        let server = rt.block_on(Server::bind(config)).unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = rt.spawn(server.run());
+
+        let mut client = Client::connect(addr).unwrap();
+        client.query::<()>(&["SET", "counter", "1"]).unwrap();
+        assert_eq!(client.query::<i64>(&["INCR", "counter"]).unwrap(), 2);
+        assert!(client.query::<i64>(&["INCRBY"]).is_err());
+
+        let mut pipe = Pipeline::new();
+        pipe.cmd(&["INCR", "counter"]).cmd(&["GET", "counter"]);
+        assert_eq!(
+            client.pipeline(&pipe).unwrap(),
+            vec![Reply::Integer(3), Reply::Str("3".to_string())]
+        );
+
+        let mut other = Client::open(&format!("kiba://{}/other", addr)).unwrap();
+        let counter: Option<String> = other.query(&["GET", "counter"]).unwrap();
+        assert_eq!(counter, None);
+
+        handle.shutdown();
+        drop(client);
+        drop(other);
+        rt.block_on(running).unwrap().unwrap();
+    }
+}
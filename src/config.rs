@@ -1,16 +1,105 @@
+use crate::glob::glob_match;
+use crate::webhook::Webhook;
 use log::*;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 
-#[derive(Clone)]
+/// Hashing algorithm used for the store's hashmaps
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashFunction {
+    /// SipHash 1-3 with random keys, which resists HashDoS attacks
+    SipHash,
+    /// Fowler-Noll-Vo, which is faster for short keys but not DoS-resistant
+    Fnv,
+}
+
+/// Scheduler of the runtime that the server runs on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuntimeFlavor {
+    /// A pool of worker threads, which tasks are spread across
+    MultiThread,
+    /// A single thread, which runs every task
+    CurrentThread,
+}
+
+/// Settings for a server instance
+#[derive(Clone, Debug, PartialEq)]
 pub struct Config {
     pub bind: String,
+    pub http_bind: Option<String>,
+
+    /// Snapshot restored into the default namespace before the server is
+    /// marked ready
+    pub warmup: Option<String>,
+    pub reuse_port: bool,
+    pub drain_timeout: u64,
+    pub runtime: RuntimeFlavor,
+    pub worker_threads: usize,
+    pub proxy_protocol: bool,
+    pub chaos: bool,
+
+    /// Whether clients may generate synthetic datasets with DEBUG QUICKFILL
+    pub quickfill: bool,
+
+    /// Number of recent requests of each client kept for DEBUG JOURNAL, or 0
+    /// to keep none
+    pub journal_size: usize,
     pub cbound: usize,
+
+    /// Number of queued requests beyond which requests from low-priority
+    /// clients are rejected, or 0 to never reject them
+    pub shed_backlog: usize,
+    pub concurrent_reads: bool,
+    pub ordered_keys: bool,
+
+    /// Key prefixes whose keyspace hits and misses are counted for STATS PREFIX
+    pub stats_prefixes: Vec<String>,
+
+    /// Time to live in seconds given to keys matching a glob-style pattern
+    /// when they are created without one. The first matching rule applies.
+    pub default_ttls: Vec<(String, u64)>,
+
+    /// Percentage by which times to live of keys are randomly lengthened or
+    /// shortened when they are set
+    pub ttl_jitter: u64,
+
+    /// Milliseconds between purges of expired keys
+    pub purge_interval: u64,
+
+    /// Maximum number of expired keys removed from each namespace per purge,
+    /// or 0 to only remove expired keys when they are accessed
+    pub purge_limit: u64,
+    pub hasher: HashFunction,
+    pub namespace_max_keys: u64,
+    pub namespace_max_memory: u64,
+
+    /// Bytes of spare capacity beyond which a namespace is compacted after
+    /// expired keys are purged, or 0 to only compact with COMPACT
+    pub auto_compact: u64,
+    pub max_key_size: usize,
+    pub max_value_size: usize,
+    pub max_container_elements: u64,
+    pub max_request_args: usize,
+    pub max_request_size: usize,
+    pub webhook_url: Option<String>,
+    pub webhook_events: Vec<String>,
+
+    /// Webhook parsed from `webhook_url` and `webhook_events` when the config
+    /// is built
+    pub webhook: Option<Webhook>,
 }
 
-fn parse_kv(path: &str) -> HashMap<String, String> {
+/// Options that can be given on several lines, each with two values
+const REPEATED_OPTIONS: &[&str] = &["default-ttl"];
+
+/// Name and values of a line giving a repeated option
+type RepeatedOption = (String, String, String);
+
+/// Parse the options of a config file. Options in `REPEATED_OPTIONS` are
+/// returned separately, with the values of each line in order.
+fn parse_kv(path: &str) -> (HashMap<String, String>, Vec<RepeatedOption>) {
     if !path.ends_with("kiba.conf") {
         warn!("Was the correct path specified?");
         warn!("The config file should be named \"kiba.conf\"");
@@ -28,13 +117,18 @@ fn parse_kv(path: &str) -> HashMap<String, String> {
     };
 
     let mut kv = HashMap::new();
+    let mut repeated = Vec::new();
     for (i, line) in lines.enumerate() {
         let text = line.unwrap();
         if text.starts_with('#') {
             continue;
         }
         let tup: Vec<&str> = text.split_whitespace().collect();
-        if tup.len() == 0 {
+        if tup.is_empty() {
+            continue;
+        }
+        if REPEATED_OPTIONS.contains(&tup[0]) && tup.len() == 3 {
+            repeated.push((tup[0].to_string(), tup[1].to_string(), tup[2].to_string()));
             continue;
         }
         if tup.len() != 2 {
@@ -43,36 +137,496 @@ fn parse_kv(path: &str) -> HashMap<String, String> {
         }
         kv.insert(tup[0].to_string(), tup[1].to_string());
     }
-    kv
+    (kv, repeated)
 }
 
-pub fn parse_config(path: Option<&str>) -> Config {
-    let default: Config = Config {
-        bind: "127.0.0.1:6464".to_string(),
-        cbound: 128,
-    };
+fn parse_bool(name: &str, val: &str) -> bool {
+    match val {
+        "yes" => true,
+        "no" => false,
+        _ => {
+            error!(
+                "`{}` must be either \"yes\" or \"no\", found \"{}\"",
+                name, val
+            );
+            std::process::exit(1);
+        }
+    }
+}
 
-    match path {
-        Some(p) => {
-            let kv = parse_kv(p);
-            let mut config = default.clone();
-            if let Some(bind) = kv.get("bind") {
-                config.bind = bind.to_string();
-            }
-            if let Some(cbound) = kv.get("cbound") {
-                match cbound.parse::<usize>() {
-                    Ok(cb) => config.cbound = cb,
-                    Err(_) => {
-                        error!(
-                            "Channel size `cbound` must be a valid integer, found \"{}\"",
-                            cbound
-                        );
-                        std::process::exit(1);
-                    }
-                }
+fn parse_hasher(val: &str) -> HashFunction {
+    match val {
+        "default" => HashFunction::SipHash,
+        "fnv" => HashFunction::Fnv,
+        _ => {
+            error!(
+                "`hasher` must be either \"default\" or \"fnv\", found \"{}\"",
+                val
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_runtime(val: &str) -> RuntimeFlavor {
+    match val {
+        "multi-thread" => RuntimeFlavor::MultiThread,
+        "current-thread" => RuntimeFlavor::CurrentThread,
+        _ => {
+            error!(
+                "`runtime` must be either \"multi-thread\" or \"current-thread\", found \"{}\"",
+                val
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_int<T: std::str::FromStr>(name: &str, desc: &str, val: &str) -> T {
+    match val.parse::<T>() {
+        Ok(int) => int,
+        Err(_) => {
+            error!(
+                "{} `{}` must be a valid integer, found \"{}\"",
+                desc, name, val
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn parse_config(path: Option<&str>) -> Config {
+    let mut builder = ConfigBuilder::new();
+    if let Some(p) = path {
+        let (kv, repeated) = parse_kv(p);
+        if let Some(bind) = kv.get("bind") {
+            builder = builder.bind(bind);
+        }
+        if let Some(bind) = kv.get("http-bind") {
+            builder = builder.http_bind(bind);
+        }
+        if let Some(path) = kv.get("warmup") {
+            builder = builder.warmup(path);
+        }
+        if let Some(reuse) = kv.get("reuse-port") {
+            builder = builder.reuse_port(parse_bool("reuse-port", reuse));
+        }
+        if let Some(timeout) = kv.get("drain-timeout") {
+            builder = builder.drain_timeout(parse_int("drain-timeout", "Drain timeout", timeout));
+        }
+        if let Some(runtime) = kv.get("runtime") {
+            builder = builder.runtime(parse_runtime(runtime));
+        }
+        if let Some(threads) = kv.get("worker-threads") {
+            builder = builder.worker_threads(parse_int("worker-threads", "Thread count", threads));
+        }
+        if let Some(proxy) = kv.get("proxy-protocol") {
+            builder = builder.proxy_protocol(parse_bool("proxy-protocol", proxy));
+        }
+        if let Some(chaos) = kv.get("chaos") {
+            builder = builder.chaos(parse_bool("chaos", chaos));
+        }
+        if let Some(quickfill) = kv.get("quickfill") {
+            builder = builder.quickfill(parse_bool("quickfill", quickfill));
+        }
+        if let Some(size) = kv.get("journal-size") {
+            builder = builder.journal_size(parse_int("journal-size", "Journal size", size));
+        }
+        if let Some(cbound) = kv.get("cbound") {
+            builder = builder.cbound(parse_int("cbound", "Channel size", cbound));
+        }
+        if let Some(backlog) = kv.get("shed-backlog") {
+            builder = builder.shed_backlog(parse_int("shed-backlog", "Backlog limit", backlog));
+        }
+        if let Some(concurrent) = kv.get("concurrent-reads") {
+            builder = builder.concurrent_reads(parse_bool("concurrent-reads", concurrent));
+        }
+        if let Some(ordered) = kv.get("ordered-keys") {
+            builder = builder.ordered_keys(parse_bool("ordered-keys", ordered));
+        }
+        if let Some(prefixes) = kv.get("stats-prefixes") {
+            builder = builder.stats_prefixes(prefixes.split(',').map(|p| p.to_string()).collect());
+        }
+        for (name, pattern, ttl) in repeated.iter() {
+            if name == "default-ttl" {
+                builder = builder.default_ttl(pattern, parse_int(name, "Default TTL", ttl));
             }
-            config
         }
-        None => default,
+        if let Some(jitter) = kv.get("ttl-jitter") {
+            builder = builder.ttl_jitter(parse_int("ttl-jitter", "TTL jitter", jitter));
+        }
+        if let Some(interval) = kv.get("purge-interval") {
+            builder =
+                builder.purge_interval(parse_int("purge-interval", "Purge interval", interval));
+        }
+        if let Some(limit) = kv.get("purge-limit") {
+            builder = builder.purge_limit(parse_int("purge-limit", "Purge limit", limit));
+        }
+        if let Some(hasher) = kv.get("hasher") {
+            builder = builder.hasher(parse_hasher(hasher));
+        }
+        if let Some(max) = kv.get("namespace-max-keys") {
+            builder = builder.namespace_max_keys(parse_int("namespace-max-keys", "Key quota", max));
+        }
+        if let Some(max) = kv.get("namespace-max-memory") {
+            builder = builder.namespace_max_memory(parse_int(
+                "namespace-max-memory",
+                "Memory quota",
+                max,
+            ));
+        }
+        if let Some(spare) = kv.get("auto-compact") {
+            builder =
+                builder.auto_compact(parse_int("auto-compact", "Compaction threshold", spare));
+        }
+        if let Some(max) = kv.get("max-key-size") {
+            builder = builder.max_key_size(parse_int("max-key-size", "Key size limit", max));
+        }
+        if let Some(max) = kv.get("max-value-size") {
+            builder = builder.max_value_size(parse_int("max-value-size", "Value size limit", max));
+        }
+        if let Some(max) = kv.get("max-container-elements") {
+            builder = builder.max_container_elements(parse_int(
+                "max-container-elements",
+                "Container size limit",
+                max,
+            ));
+        }
+        if let Some(max) = kv.get("max-request-args") {
+            builder = builder.max_request_args(parse_int(
+                "max-request-args",
+                "Request argument limit",
+                max,
+            ));
+        }
+        if let Some(max) = kv.get("max-request-size") {
+            builder =
+                builder.max_request_size(parse_int("max-request-size", "Request size limit", max));
+        }
+        if let Some(url) = kv.get("webhook-url") {
+            builder = builder.webhook_url(url);
+        }
+        if let Some(events) = kv.get("webhook-events") {
+            builder = builder.webhook_events(events.split(',').map(|e| e.to_string()).collect());
+        }
+    }
+    match builder.build() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind: "127.0.0.1:6464".to_string(),
+            http_bind: None,
+            warmup: None,
+            reuse_port: false,
+            drain_timeout: 30,
+            runtime: RuntimeFlavor::MultiThread,
+            worker_threads: 0,
+            proxy_protocol: false,
+            chaos: false,
+            quickfill: false,
+            journal_size: 0,
+            cbound: 128,
+            shed_backlog: 0,
+            concurrent_reads: false,
+            ordered_keys: false,
+            stats_prefixes: vec![],
+            default_ttls: vec![],
+            ttl_jitter: 0,
+            purge_interval: 100,
+            purge_limit: 20,
+            hasher: HashFunction::SipHash,
+            namespace_max_keys: 0,
+            namespace_max_memory: 0,
+            auto_compact: 0,
+            max_key_size: 0,
+            max_value_size: 0,
+            max_container_elements: 0,
+            max_request_args: 1024 * 1024,
+            max_request_size: 64 * 1024 * 1024,
+            webhook_url: None,
+            webhook_events: vec!["*".to_string()],
+            webhook: None,
+        }
+    }
+}
+
+impl Config {
+    /// Return a builder starting from the default settings.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// Return the default time to live in seconds of a key, if any.
+    pub fn default_ttl(&self, key: &str) -> Option<u64> {
+        self.default_ttls
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, key))
+            .map(|(_, secs)| *secs)
+    }
+}
+
+/// Builds a validated `Config` programmatically, starting from the default
+/// settings. Each setting corresponds to an option in kiba.conf.
+#[derive(Clone, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind(mut self, bind: &str) -> Self {
+        self.config.bind = bind.to_string();
+        self
+    }
+
+    pub fn http_bind(mut self, bind: &str) -> Self {
+        self.config.http_bind = Some(bind.to_string());
+        self
+    }
+
+    pub fn warmup(mut self, path: &str) -> Self {
+        self.config.warmup = Some(path.to_string());
+        self
+    }
+
+    pub fn reuse_port(mut self, reuse: bool) -> Self {
+        self.config.reuse_port = reuse;
+        self
+    }
+
+    pub fn drain_timeout(mut self, secs: u64) -> Self {
+        self.config.drain_timeout = secs;
+        self
+    }
+
+    pub fn runtime(mut self, runtime: RuntimeFlavor) -> Self {
+        self.config.runtime = runtime;
+        self
+    }
+
+    pub fn worker_threads(mut self, threads: usize) -> Self {
+        self.config.worker_threads = threads;
+        self
+    }
+
+    pub fn proxy_protocol(mut self, proxy: bool) -> Self {
+        self.config.proxy_protocol = proxy;
+        self
+    }
+
+    pub fn chaos(mut self, chaos: bool) -> Self {
+        self.config.chaos = chaos;
+        self
+    }
+
+    pub fn quickfill(mut self, quickfill: bool) -> Self {
+        self.config.quickfill = quickfill;
+        self
+    }
+
+    pub fn journal_size(mut self, size: usize) -> Self {
+        self.config.journal_size = size;
+        self
+    }
+
+    pub fn cbound(mut self, cbound: usize) -> Self {
+        self.config.cbound = cbound;
+        self
+    }
+
+    pub fn shed_backlog(mut self, backlog: usize) -> Self {
+        self.config.shed_backlog = backlog;
+        self
+    }
+
+    pub fn concurrent_reads(mut self, concurrent: bool) -> Self {
+        self.config.concurrent_reads = concurrent;
+        self
+    }
+
+    pub fn ordered_keys(mut self, ordered: bool) -> Self {
+        self.config.ordered_keys = ordered;
+        self
+    }
+
+    pub fn stats_prefixes(mut self, prefixes: Vec<String>) -> Self {
+        self.config.stats_prefixes = prefixes;
+        self
+    }
+
+    /// Add a rule giving keys that match the pattern a time to live.
+    pub fn default_ttl(mut self, pattern: &str, secs: u64) -> Self {
+        self.config.default_ttls.push((pattern.to_string(), secs));
+        self
+    }
+
+    pub fn ttl_jitter(mut self, percent: u64) -> Self {
+        self.config.ttl_jitter = percent;
+        self
+    }
+
+    pub fn purge_interval(mut self, millis: u64) -> Self {
+        self.config.purge_interval = millis;
+        self
+    }
+
+    pub fn purge_limit(mut self, limit: u64) -> Self {
+        self.config.purge_limit = limit;
+        self
+    }
+
+    pub fn hasher(mut self, hasher: HashFunction) -> Self {
+        self.config.hasher = hasher;
+        self
+    }
+
+    pub fn namespace_max_keys(mut self, max: u64) -> Self {
+        self.config.namespace_max_keys = max;
+        self
+    }
+
+    pub fn namespace_max_memory(mut self, max: u64) -> Self {
+        self.config.namespace_max_memory = max;
+        self
+    }
+
+    pub fn auto_compact(mut self, spare: u64) -> Self {
+        self.config.auto_compact = spare;
+        self
+    }
+
+    pub fn max_key_size(mut self, max: usize) -> Self {
+        self.config.max_key_size = max;
+        self
+    }
+
+    pub fn max_value_size(mut self, max: usize) -> Self {
+        self.config.max_value_size = max;
+        self
+    }
+
+    pub fn max_container_elements(mut self, max: u64) -> Self {
+        self.config.max_container_elements = max;
+        self
+    }
+
+    pub fn max_request_args(mut self, max: usize) -> Self {
+        self.config.max_request_args = max;
+        self
+    }
+
+    pub fn max_request_size(mut self, max: usize) -> Self {
+        self.config.max_request_size = max;
+        self
+    }
+
+    pub fn webhook_url(mut self, url: &str) -> Self {
+        self.config.webhook_url = Some(url.to_string());
+        self
+    }
+
+    pub fn webhook_events(mut self, events: Vec<String>) -> Self {
+        self.config.webhook_events = events;
+        self
+    }
+
+    /// Validate the settings and return the config.
+    pub fn build(self) -> Result<Config, String> {
+        let mut config = self.config;
+        check_addr("bind", &config.bind)?;
+        if let Some(bind) = &config.http_bind {
+            check_addr("http-bind", bind)?;
+        }
+        if config.worker_threads > 0 && config.runtime == RuntimeFlavor::CurrentThread {
+            return Err(
+                "`worker-threads` cannot be set with the current-thread runtime".to_string(),
+            );
+        }
+        if config.default_ttls.iter().any(|(_, secs)| *secs == 0) {
+            return Err("Default TTL `default-ttl` must be a positive integer".to_string());
+        }
+        if config.ttl_jitter > 100 {
+            return Err("TTL jitter `ttl-jitter` must be a percentage from 0 to 100".to_string());
+        }
+        if config.purge_interval == 0 {
+            return Err("Purge interval `purge-interval` must be a positive integer".to_string());
+        }
+        if config.cbound == 0 {
+            return Err("Channel size `cbound` must be a positive integer".to_string());
+        }
+        config.webhook = match &config.webhook_url {
+            Some(url) => Some(Webhook::new(url, config.webhook_events.clone())?),
+            None => None,
+        };
+        Ok(config)
+    }
+}
+
+/// Check that an address has the form "host:port".
+fn check_addr(name: &str, addr: &str) -> Result<(), String> {
+    match addr.rfind(':') {
+        Some(i) if i > 0 && addr[i + 1..].parse::<u16>().is_ok() => Ok(()),
+        _ => Err(format!(
+            "`{}` must be an address of the form \"host:port\", found \"{}\"",
+            name, addr
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_builder() {
+        let config = Config::builder()
+            .bind("0.0.0.0:7000")
+            .max_key_size(64)
+            .max_request_args(0)
+            .build()
+            .unwrap();
+        assert_eq!(config.bind, "0.0.0.0:7000");
+        assert_eq!(config.max_key_size, 64);
+        assert_eq!(config.max_request_args, 0);
+        assert_eq!(config.max_request_size, Config::default().max_request_size);
+        assert_eq!(config.cbound, Config::default().cbound);
+
+        assert!(Config::builder().bind("localhost").build().is_err());
+        assert!(Config::builder().bind(":6464").build().is_err());
+        assert!(Config::builder().http_bind("[::1]:x").build().is_err());
+        assert!(Config::builder().cbound(0).build().is_err());
+        assert!(Config::builder().default_ttl("sess:*", 0).build().is_err());
+        assert!(Config::builder().ttl_jitter(101).build().is_err());
+        assert!(Config::builder().purge_interval(0).build().is_err());
+        assert!(Config::builder()
+            .runtime(RuntimeFlavor::CurrentThread)
+            .worker_threads(2)
+            .build()
+            .is_err());
+        assert!(Config::builder()
+            .webhook_url("https://example.com")
+            .build()
+            .is_err());
+        let config = Config::builder()
+            .webhook_url("http://127.0.0.1:8080/kiba")
+            .webhook_events(vec!["set".to_string()])
+            .build()
+            .unwrap();
+        assert_eq!(
+            config.webhook,
+            Some(Webhook::new("http://127.0.0.1:8080/kiba", vec!["set".to_string()]).unwrap())
+        );
+        assert_eq!(Config::builder().build().unwrap().webhook, None);
     }
 }
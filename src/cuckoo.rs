@@ -0,0 +1,239 @@
+use crate::snapshot::{invalid_data, Decoder, Encoder};
+use fnv::FnvHasher;
+use std::hash::Hasher;
+use std::io;
+
+/// Number of buckets in each sub-filter. Must be a power of two.
+pub const DEFAULT_BUCKETS: usize = 1024;
+
+/// Number of fingerprints held by a single bucket
+const BUCKET_SIZE: usize = 4;
+
+/// Maximum number of relocations attempted before adding a sub-filter
+const MAX_KICKS: usize = 500;
+
+/// Marks an empty slot. Fingerprints are never zero.
+const EMPTY: u16 = 0;
+
+type Bucket = [u16; BUCKET_SIZE];
+
+/// A cuckoo filter supporting approximate membership tests with deletion.
+///
+/// Items are stored as 16-bit fingerprints in one of two candidate buckets.
+/// When both buckets of an item are full, resident fingerprints are relocated
+/// to their alternate buckets. If relocation fails, a new sub-filter of the
+/// same dimensions is chained so that insertions never fail.
+#[derive(Debug)]
+pub struct CuckooFilter {
+    tables: Vec<Vec<Bucket>>,
+    nbuckets: usize,
+    count: u64,
+}
+
+impl CuckooFilter {
+    pub fn new(nbuckets: usize) -> Self {
+        let nbuckets = nbuckets.next_power_of_two();
+        Self {
+            tables: vec![vec![[EMPTY; BUCKET_SIZE]; nbuckets]],
+            nbuckets,
+            count: 0,
+        }
+    }
+
+    /// Add an item to the filter.
+    /// Duplicates are stored independently, so an item added twice must be
+    /// deleted twice.
+    pub fn add(&mut self, item: &str) {
+        let (fp, i1, i2) = self.locate(item);
+        self.count += 1;
+        for table in self.tables.iter_mut() {
+            if insert_into_bucket(&mut table[i1], fp) || insert_into_bucket(&mut table[i2], fp) {
+                return;
+            }
+        }
+
+        // Relocate fingerprints within the newest sub-filter
+        let mask = self.nbuckets - 1;
+        let table = self.tables.last_mut().unwrap();
+        let mut victim = fp;
+        let mut idx = i1;
+        for kick in 0..MAX_KICKS {
+            let slot = kick % BUCKET_SIZE;
+            std::mem::swap(&mut table[idx][slot], &mut victim);
+            idx = alt_index(idx, victim, mask);
+            if insert_into_bucket(&mut table[idx], victim) {
+                return;
+            }
+        }
+
+        // The victim's bucket indices are unchanged in a table of equal size
+        let mut table = vec![[EMPTY; BUCKET_SIZE]; self.nbuckets];
+        table[idx][0] = victim;
+        self.tables.push(table);
+    }
+
+    /// Return if the item may exist in the filter.
+    pub fn contains(&self, item: &str) -> bool {
+        let (fp, i1, i2) = self.locate(item);
+        self.tables
+            .iter()
+            .any(|table| table[i1].contains(&fp) || table[i2].contains(&fp))
+    }
+
+    /// Remove one occurrence of an item from the filter.
+    /// Return true if the item was found and removed.
+    pub fn delete(&mut self, item: &str) -> bool {
+        let (fp, i1, i2) = self.locate(item);
+        for table in self.tables.iter_mut().rev() {
+            if remove_from_bucket(&mut table[i1], fp) || remove_from_bucket(&mut table[i2], fp) {
+                self.count -= 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Return the number of items stored in the filter.
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Return the approximate number of bytes used by the filter.
+    pub fn memory_usage(&self) -> u64 {
+        (self.tables.len() * self.nbuckets * std::mem::size_of::<Bucket>()) as u64
+    }
+
+    /// Write the filter in the snapshot encoding.
+    pub fn encode(&self, enc: &mut Encoder) -> io::Result<()> {
+        enc.u64(self.nbuckets as u64)?;
+        enc.u64(self.count)?;
+        enc.u64(self.tables.len() as u64)?;
+        for table in self.tables.iter() {
+            for bucket in table.iter() {
+                for fp in bucket.iter() {
+                    enc.u64(*fp as u64)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a filter written by `encode`.
+    pub fn decode(dec: &mut Decoder) -> io::Result<Self> {
+        let nbuckets = dec.u64()? as usize;
+        let count = dec.u64()?;
+        let ntables = dec.u64()?;
+        if !nbuckets.is_power_of_two() || ntables == 0 {
+            return Err(invalid_data("Cuckoo filter has invalid dimensions"));
+        }
+        let mut tables = Vec::new();
+        for _ in 0..ntables {
+            let mut table = Vec::new();
+            for _ in 0..nbuckets {
+                let mut bucket = [EMPTY; BUCKET_SIZE];
+                for fp in bucket.iter_mut() {
+                    *fp = dec.u64()? as u16;
+                }
+                table.push(bucket);
+            }
+            tables.push(table);
+        }
+        Ok(Self {
+            tables,
+            nbuckets,
+            count,
+        })
+    }
+
+    /// Return the fingerprint and both candidate bucket indices of an item.
+    /// Filters are persisted in snapshots, so the hash must not change between
+    /// builds or platforms.
+    fn locate(&self, item: &str) -> (u16, usize, usize) {
+        let mut hasher = FnvHasher::default();
+        hasher.write(item.as_bytes());
+        let hash = hasher.finish();
+        let fp = match (hash >> 48) as u16 {
+            EMPTY => 1,
+            fp => fp,
+        };
+        let mask = self.nbuckets - 1;
+        let i1 = hash as usize & mask;
+        (fp, i1, alt_index(i1, fp, mask))
+    }
+}
+
+fn alt_index(idx: usize, fp: u16, mask: usize) -> usize {
+    (idx ^ (fp as usize).wrapping_mul(0x5bd1_e995)) & mask
+}
+
+fn insert_into_bucket(bucket: &mut Bucket, fp: u16) -> bool {
+    match bucket.iter_mut().find(|slot| **slot == EMPTY) {
+        Some(slot) => {
+            *slot = fp;
+            true
+        }
+        None => false,
+    }
+}
+
+fn remove_from_bucket(bucket: &mut Bucket, fp: u16) -> bool {
+    match bucket.iter_mut().find(|slot| **slot == fp) {
+        Some(slot) => {
+            *slot = EMPTY;
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cuckoo_add_contains_delete() {
+        let mut filter = CuckooFilter::new(DEFAULT_BUCKETS);
+        assert!(filter.is_empty());
+        filter.add("foo");
+        filter.add("foo");
+        assert!(filter.contains("foo"));
+        assert!(!filter.contains("bar"));
+        assert_eq!(filter.len(), 2);
+
+        // Duplicates must be deleted individually
+        assert!(filter.delete("foo"));
+        assert!(filter.contains("foo"));
+        assert!(filter.delete("foo"));
+        assert!(!filter.contains("foo"));
+        assert!(!filter.delete("foo"));
+    }
+
+    #[test]
+    fn test_cuckoo_overflow() {
+        let mut filter = CuckooFilter::new(8);
+        for i in 0..1000 {
+            filter.add(&format!("item{}", i));
+        }
+        assert!(filter.tables.len() > 1);
+
+        // No false negatives
+        for i in 0..1000 {
+            assert!(filter.contains(&format!("item{}", i)));
+        }
+        for i in 0..1000 {
+            assert!(filter.delete(&format!("item{}", i)));
+        }
+        assert!(filter.is_empty());
+    }
+    #[test]
+    fn test_cuckoo_locate() {
+        // 64-bit FNV-1a, which filters restored from snapshots depend on
+        let (fp, i1, _) = CuckooFilter::new(1024).locate("foo");
+        assert_eq!(fp, 0xdcb2);
+        assert_eq!(i1, 0xd577 & 1023);
+    }
+}
@@ -1,15 +1,176 @@
-use crate::store::Store;
+use crate::chaos::ChaosAction;
+use crate::config::Config;
+use crate::fill::Fill;
+use crate::glob::glob_match;
+use crate::index::Filter;
+use crate::lcs::{lcs, LcsInput};
+use crate::lexer::{lookup_command, Command, Flag, COMMANDS};
+use crate::protocol::{Reply, ReplyFormat};
+use crate::rng::Rng;
+use crate::snapshot::to_hex;
+use crate::store::{ExpiringStore, Store};
+use crate::timeseries::Aggregation;
+use crate::vector::Metric;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Priority of a client's requests while the executor is overloaded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Priority {
+    Normal,
+    /// Requests are rejected while the executor's backlog exceeds `shed-backlog`
+    Low,
+}
+
+/// Condition under which SET writes its value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SetCondition {
+    /// Only write the value if the key does not exist (NX)
+    Absent,
+    /// Only write the value if the key exists (XX)
+    Present,
+}
+
+/// Expiry of a key written by SET
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SetExpiry {
+    /// Discard any existing expiry
+    Discard,
+    /// Keep the existing expiry (KEEPTTL)
+    Keep,
+    /// Expire after a number of milliseconds (EX or PX)
+    After(u64),
+}
 
 #[derive(Debug, PartialEq)]
 pub enum Request {
     Ping,
+    CommandInfo {
+        names: Vec<String>,
+    },
+    Namespace {
+        name: String,
+    },
+    Priority {
+        priority: Priority,
+    },
+    Format {
+        format: ReplyFormat,
+    },
+    DebugChaos {
+        action: ChaosAction,
+    },
+    DebugQuickfill {
+        fill: Fill,
+    },
+    DebugJournal {
+        client: u64,
+    },
+    Info {
+        section: Option<String>,
+    },
+    StatsPrefix {
+        prefix: String,
+    },
+    Snapshot,
+    Compact,
+    Del {
+        keys: Vec<String>,
+    },
+    Exists {
+        keys: Vec<String>,
+    },
+    Type {
+        key: String,
+    },
+    Rename {
+        key: String,
+        newkey: String,
+    },
+    RenameNx {
+        key: String,
+        newkey: String,
+    },
+    Keys {
+        pattern: String,
+    },
+    RandomKey,
+    DbSize,
+    FlushDb,
+    FlushAll,
+    KeyRange {
+        start: String,
+        end: String,
+        limit: Option<u64>,
+    },
+    Scan {
+        cursor: u64,
+        pattern: Option<String>,
+        kind: Option<String>,
+        count: u64,
+    },
+    Expire {
+        key: String,
+        seconds: u64,
+    },
+    PExpire {
+        key: String,
+        millis: u64,
+    },
+    ExpireAt {
+        key: String,
+        timestamp: u64,
+    },
+    PExpireAt {
+        key: String,
+        timestamp: u64,
+    },
+    Ttl {
+        key: String,
+    },
+    PTtl {
+        key: String,
+    },
+    ExpireTime {
+        key: String,
+    },
+    PExpireTime {
+        key: String,
+    },
+    Persist {
+        key: String,
+    },
+    Version {
+        key: String,
+    },
+    /// Wait for a key to be written, for up to a timeout in seconds
+    WaitKey {
+        key: String,
+        timeout: Option<u64>,
+    },
     Get {
         key: String,
     },
+    GetDel {
+        key: String,
+    },
     Set {
         key: String,
         val: String,
     },
+    SetIfVersion {
+        key: String,
+        val: String,
+        version: u64,
+    },
+    SetWith {
+        key: String,
+        val: String,
+        condition: Option<SetCondition>,
+        expiry: SetExpiry,
+    },
     Incr {
         key: String,
     },
@@ -24,6 +185,34 @@ pub enum Request {
         key: String,
         delta: i64,
     },
+    IncrByFloat {
+        key: String,
+        delta: f64,
+    },
+    Append {
+        key: String,
+        val: String,
+    },
+    GetRange {
+        key: String,
+        start: i64,
+        end: i64,
+    },
+    SetRange {
+        key: String,
+        offset: u64,
+        val: String,
+    },
+    StrLen {
+        key: String,
+    },
+    StrAlgoLcs {
+        input: LcsInput,
+        len: bool,
+        idx: bool,
+        min_match_len: usize,
+        with_match_len: bool,
+    },
     LPush {
         key: String,
         val: String,
@@ -62,10 +251,99 @@ pub enum Request {
         field: String,
         val: String,
     },
+    HIncrByFloat {
+        key: String,
+        field: String,
+        delta: f64,
+    },
     HDel {
         key: String,
         field: String,
     },
+    HExpire {
+        key: String,
+        field: String,
+        seconds: u64,
+    },
+    HPExpire {
+        key: String,
+        field: String,
+        millis: u64,
+    },
+    HTtl {
+        key: String,
+        field: String,
+    },
+    HPersist {
+        key: String,
+        field: String,
+    },
+    BFReserve {
+        key: String,
+        error_rate: f64,
+        capacity: u64,
+    },
+    BFAdd {
+        key: String,
+        item: String,
+    },
+    BFMAdd {
+        key: String,
+        items: Vec<String>,
+    },
+    BFExists {
+        key: String,
+        item: String,
+    },
+    CFAdd {
+        key: String,
+        item: String,
+    },
+    CFExists {
+        key: String,
+        item: String,
+    },
+    CFDel {
+        key: String,
+        item: String,
+    },
+    TSCreate {
+        key: String,
+        retention: u64,
+    },
+    TSAdd {
+        key: String,
+        timestamp: Option<i64>,
+        value: f64,
+    },
+    TSRange {
+        key: String,
+        from: i64,
+        to: i64,
+        aggregation: Option<(Aggregation, u64)>,
+    },
+    VAdd {
+        key: String,
+        element: String,
+        vector: Vec<f64>,
+    },
+    VSim {
+        key: String,
+        query: Vec<f64>,
+        metric: Metric,
+        count: usize,
+    },
+    FTCreate {
+        index: String,
+        prefix: String,
+        fields: Vec<String>,
+    },
+    FTSearch {
+        index: String,
+        filters: Vec<Filter>,
+        offset: usize,
+        count: usize,
+    },
     NoOp,
     Quit,
     Invalid {
@@ -73,178 +351,3098 @@ pub enum Request {
     },
 }
 
+impl Request {
+    /// Return the key in the keyspace that this request operates on, if any.
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            Request::Expire { key, .. }
+            | Request::PExpire { key, .. }
+            | Request::ExpireAt { key, .. }
+            | Request::PExpireAt { key, .. }
+            | Request::Ttl { key }
+            | Request::PTtl { key }
+            | Request::ExpireTime { key }
+            | Request::PExpireTime { key }
+            | Request::Persist { key }
+            | Request::Version { key }
+            | Request::WaitKey { key, .. }
+            | Request::Type { key }
+            | Request::Rename { key, .. }
+            | Request::RenameNx { key, .. }
+            | Request::Get { key }
+            | Request::GetDel { key }
+            | Request::Set { key, .. }
+            | Request::SetIfVersion { key, .. }
+            | Request::SetWith { key, .. }
+            | Request::Incr { key }
+            | Request::Decr { key }
+            | Request::IncrBy { key, .. }
+            | Request::DecrBy { key, .. }
+            | Request::IncrByFloat { key, .. }
+            | Request::Append { key, .. }
+            | Request::GetRange { key, .. }
+            | Request::SetRange { key, .. }
+            | Request::StrLen { key }
+            | Request::LPush { key, .. }
+            | Request::RPush { key, .. }
+            | Request::LPop { key }
+            | Request::RPop { key }
+            | Request::SAdd { key, .. }
+            | Request::SRem { key, .. }
+            | Request::SIsMember { key, .. }
+            | Request::SMembers { key }
+            | Request::HGet { key, .. }
+            | Request::HSet { key, .. }
+            | Request::HIncrByFloat { key, .. }
+            | Request::HDel { key, .. }
+            | Request::HExpire { key, .. }
+            | Request::HPExpire { key, .. }
+            | Request::HTtl { key, .. }
+            | Request::HPersist { key, .. }
+            | Request::BFReserve { key, .. }
+            | Request::BFAdd { key, .. }
+            | Request::BFMAdd { key, .. }
+            | Request::BFExists { key, .. }
+            | Request::CFAdd { key, .. }
+            | Request::CFExists { key, .. }
+            | Request::CFDel { key, .. }
+            | Request::TSCreate { key, .. }
+            | Request::TSAdd { key, .. }
+            | Request::TSRange { key, .. }
+            | Request::VAdd { key, .. }
+            | Request::VSim { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Return every key in the keyspace that this request operates on.
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            Request::Del { keys } | Request::Exists { keys } => {
+                keys.iter().map(|key| key.as_str()).collect()
+            }
+            Request::Rename { key, newkey } | Request::RenameNx { key, newkey } => {
+                vec![key, newkey]
+            }
+            _ => self.key().into_iter().collect(),
+        }
+    }
+
+    /// Return the hash field that this request operates on, if any.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Request::HGet { field, .. }
+            | Request::HSet { field, .. }
+            | Request::HIncrByFloat { field, .. }
+            | Request::HDel { field, .. }
+            | Request::HExpire { field, .. }
+            | Request::HPExpire { field, .. }
+            | Request::HTtl { field, .. }
+            | Request::HPersist { field, .. } => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Return the values (including fields and members) that this request
+    /// would write to the store.
+    fn values(&self) -> Vec<&str> {
+        match self {
+            Request::Set { val, .. }
+            | Request::SetIfVersion { val, .. }
+            | Request::SetWith { val, .. }
+            | Request::Append { val, .. }
+            | Request::SetRange { val, .. }
+            | Request::LPush { val, .. }
+            | Request::RPush { val, .. }
+            | Request::SAdd { val, .. }
+            | Request::BFAdd { item: val, .. }
+            | Request::CFAdd { item: val, .. }
+            | Request::VAdd { element: val, .. } => vec![val],
+            Request::HSet { field, val, .. } => vec![field, val],
+            Request::BFMAdd { items, .. } => items.iter().map(|i| i.as_str()).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Return the number of elements that this request would add to the
+    /// container stored at its key.
+    fn additions(&self, store: &impl Store) -> u64 {
+        match self {
+            Request::SAdd { key, val } => match store.sismember(key.to_string(), val.to_string()) {
+                Ok(true) => 0,
+                _ => 1,
+            },
+            Request::HSet { key, field, .. } => {
+                match store.hget(key.to_string(), field.to_string()) {
+                    Ok(Some(_)) => 0,
+                    _ => 1,
+                }
+            }
+            Request::BFMAdd { items, .. } => items.len() as u64,
+            Request::LPush { .. }
+            | Request::RPush { .. }
+            | Request::BFAdd { .. }
+            | Request::CFAdd { .. }
+            | Request::TSAdd { .. }
+            | Request::VAdd { .. } => 1,
+            _ => 0,
+        }
+    }
+
+    /// Return true if the request is a command that never modifies the store.
+    pub fn is_read_only(&self) -> bool {
+        match self.command() {
+            Some(cmd) => cmd.has_flag(Flag::ReadOnly),
+            None => false,
+        }
+    }
+
+    /// Return the command table entry for this request, if it is a command.
+    pub fn command(&self) -> Option<&'static Command> {
+        let name = match self {
+            Request::Ping => "PING",
+            Request::CommandInfo { .. } => "COMMAND",
+            Request::Namespace { .. } => "NAMESPACE",
+            Request::Priority { .. } => "PRIORITY",
+            Request::Format { .. } => "FORMAT",
+            Request::DebugChaos { .. }
+            | Request::DebugQuickfill { .. }
+            | Request::DebugJournal { .. } => "DEBUG",
+            Request::Info { .. } => "INFO",
+            Request::StatsPrefix { .. } => "STATS",
+            Request::Snapshot => "SNAPSHOT",
+            Request::Compact => "COMPACT",
+            Request::Del { .. } => "DEL",
+            Request::Exists { .. } => "EXISTS",
+            Request::Type { .. } => "TYPE",
+            Request::Rename { .. } => "RENAME",
+            Request::RenameNx { .. } => "RENAMENX",
+            Request::Keys { .. } => "KEYS",
+            Request::RandomKey => "RANDOMKEY",
+            Request::DbSize => "DBSIZE",
+            Request::FlushDb => "FLUSHDB",
+            Request::FlushAll => "FLUSHALL",
+            Request::KeyRange { .. } => "KEYRANGE",
+            Request::Scan { .. } => "SCAN",
+            Request::Expire { .. } => "EXPIRE",
+            Request::PExpire { .. } => "PEXPIRE",
+            Request::ExpireAt { .. } => "EXPIREAT",
+            Request::PExpireAt { .. } => "PEXPIREAT",
+            Request::Ttl { .. } => "TTL",
+            Request::PTtl { .. } => "PTTL",
+            Request::ExpireTime { .. } => "EXPIRETIME",
+            Request::PExpireTime { .. } => "PEXPIRETIME",
+            Request::Persist { .. } => "PERSIST",
+            Request::Version { .. } => "VERSION",
+            Request::WaitKey { .. } => "WAITKEY",
+            Request::Get { .. } => "GET",
+            Request::GetDel { .. } => "GETDEL",
+            Request::Set { .. } | Request::SetIfVersion { .. } | Request::SetWith { .. } => "SET",
+            Request::Incr { .. } => "INCR",
+            Request::Decr { .. } => "DECR",
+            Request::IncrBy { .. } => "INCRBY",
+            Request::DecrBy { .. } => "DECRBY",
+            Request::IncrByFloat { .. } => "INCRBYFLOAT",
+            Request::Append { .. } => "APPEND",
+            Request::GetRange { .. } => "GETRANGE",
+            Request::SetRange { .. } => "SETRANGE",
+            Request::StrLen { .. } => "STRLEN",
+            Request::StrAlgoLcs { .. } => "STRALGO",
+            Request::LPush { .. } => "LPUSH",
+            Request::RPush { .. } => "RPUSH",
+            Request::LPop { .. } => "LPOP",
+            Request::RPop { .. } => "RPOP",
+            Request::SAdd { .. } => "SADD",
+            Request::SRem { .. } => "SREM",
+            Request::SIsMember { .. } => "SISMEMBER",
+            Request::SMembers { .. } => "SMEMBERS",
+            Request::HGet { .. } => "HGET",
+            Request::HSet { .. } => "HSET",
+            Request::HIncrByFloat { .. } => "HINCRBYFLOAT",
+            Request::HDel { .. } => "HDEL",
+            Request::HExpire { .. } => "HEXPIRE",
+            Request::HPExpire { .. } => "HPEXPIRE",
+            Request::HTtl { .. } => "HTTL",
+            Request::HPersist { .. } => "HPERSIST",
+            Request::BFReserve { .. } => "BF.RESERVE",
+            Request::BFAdd { .. } => "BF.ADD",
+            Request::BFMAdd { .. } => "BF.MADD",
+            Request::BFExists { .. } => "BF.EXISTS",
+            Request::CFAdd { .. } => "CF.ADD",
+            Request::CFExists { .. } => "CF.EXISTS",
+            Request::CFDel { .. } => "CF.DEL",
+            Request::TSCreate { .. } => "TS.CREATE",
+            Request::TSAdd { .. } => "TS.ADD",
+            Request::TSRange { .. } => "TS.RANGE",
+            Request::VAdd { .. } => "VADD",
+            Request::VSim { .. } => "VSIM",
+            Request::FTCreate { .. } => "FT.CREATE",
+            Request::FTSearch { .. } => "FT.SEARCH",
+            Request::Quit => "QUIT",
+            Request::NoOp | Request::Invalid { .. } => return None,
+        };
+        lookup_command(name)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Response {
-    pub body: String,
+    pub body: Reply,
 }
 
 // Response body formats
 
-pub fn f_pong() -> String {
-    "PONG".to_string()
+pub fn f_pong() -> Reply {
+    Reply::Status("PONG".to_string())
 }
 
-pub fn f_ok() -> String {
-    "OK".to_string()
+pub fn f_ok() -> Reply {
+    Reply::Status("OK".to_string())
 }
 
-pub fn f_nil() -> String {
-    "(nil)".to_string()
+pub fn f_nil() -> Reply {
+    Reply::Nil
 }
 
-pub fn f_noop() -> String {
-    '\u{0}'.to_string()
+pub fn f_noop() -> Reply {
+    Reply::Status('\u{0}'.to_string())
 }
 
-pub fn f_quit() -> String {
-    "Goodbye!".to_string()
+pub fn f_quit() -> Reply {
+    Reply::Status("Goodbye!".to_string())
 }
 
-pub fn f_empty() -> String {
-    "(empty list or set)".to_string()
+pub fn f_empty() -> Reply {
+    Reply::Array(vec![])
 }
 
-pub fn f_int(int: i64) -> String {
-    format!("(integer) {}", int)
+pub fn f_int(int: i64) -> Reply {
+    Reply::Integer(int)
 }
 
-pub fn f_uint(uint: u64) -> String {
-    format!("(integer) {}", uint)
+pub fn f_uint(uint: u64) -> Reply {
+    Reply::Integer(uint.min(i64::MAX as u64) as i64)
 }
 
-pub fn f_str(s: String) -> String {
-    format!("\"{}\"", s)
+pub fn f_str(s: String) -> Reply {
+    Reply::Str(s)
 }
 
-pub fn f_vec(v: Vec<String>) -> String {
-    let mut res = String::new();
-    let mut iter = v.iter().enumerate();
-    if let Some((idx, item)) = iter.next() {
-        res.push_str(&format!("{}) {}", idx + 1, item));
-    }
-    while let Some((idx, item)) = iter.next() {
-        res.push_str(&format!("\n{}) {}", idx + 1, item));
+pub fn f_bare(s: String) -> Reply {
+    Reply::Bare(s)
+}
+
+pub fn f_vec(v: Vec<Reply>) -> Reply {
+    Reply::Array(v)
+}
+
+pub fn f_sample(timestamp: i64, value: f64) -> Reply {
+    Reply::Bare(format!("{} {}", timestamp, value))
+}
+
+pub fn f_scored(member: String, score: f64) -> Reply {
+    Reply::Bare(format!("{} {}", member, score))
+}
+
+pub fn f_command(cmd: &Command) -> Reply {
+    let flags: Vec<&str> = cmd.flags.iter().map(|f| f.name()).collect();
+    match flags.len() {
+        0 => Reply::Bare(cmd.name.to_lowercase()),
+        _ => Reply::Bare(format!(
+            "{} ({})",
+            cmd.name.to_lowercase(),
+            flags.join(", ")
+        )),
     }
-    res
 }
 
-pub fn f_err(e: String) -> String {
-    format!("(error) {}", e)
+pub fn f_scan(cursor: u64, keys: Vec<String>) -> Reply {
+    let keys = keys.into_iter().map(f_bare).collect();
+    Reply::Array(vec![Reply::Str(cursor.to_string()), Reply::Array(keys)])
 }
 
-pub async fn execute(req: Request, store: &mut impl Store) -> Response {
-    match req {
-        Request::Ping => Response { body: f_pong() },
-        Request::Get { key } => match store.get(key).unwrap() {
-            Some(val) => Response { body: f_str(val) },
-            None => Response { body: f_nil() },
-        },
-        Request::Set { key, val } => {
-            let _ = store.set(key, val);
-            Response { body: f_ok() }
-        }
-        Request::Incr { key } => match store.incr(key) {
-            Ok(val) => Response { body: f_int(val) },
-            Err(e) => Response {
-                body: f_err(e.message),
-            },
-        },
-        Request::Decr { key } => match store.decr(key) {
-            Ok(val) => Response { body: f_int(val) },
-            Err(e) => Response {
-                body: f_err(e.message),
-            },
-        },
-        Request::IncrBy { key, delta } => match store.incrby(key, delta) {
-            Ok(val) => Response { body: f_int(val) },
-            Err(e) => Response {
-                body: f_err(e.message),
-            },
-        },
-        Request::DecrBy { key, delta } => match store.decrby(key, delta) {
-            Ok(val) => Response { body: f_int(val) },
-            Err(e) => Response {
-                body: f_err(e.message),
-            },
-        },
-        Request::LPush { key, val } => {
-            let len = store.lpush(key, val).unwrap();
-            Response { body: f_uint(len) }
-        }
-        Request::RPush { key, val } => {
-            let len = store.rpush(key, val).unwrap();
-            Response { body: f_uint(len) }
-        }
-        Request::LPop { key } => match store.lpop(key).unwrap() {
-            Some(val) => Response { body: f_str(val) },
-            None => Response { body: f_nil() },
-        },
-        Request::RPop { key } => match store.rpop(key).unwrap() {
-            Some(val) => Response { body: f_str(val) },
-            None => Response { body: f_nil() },
-        },
-        Request::SAdd { key, val } => {
-            let len = store.sadd(key, val).unwrap();
-            Response { body: f_uint(len) }
+pub fn f_info(lines: Vec<String>) -> Reply {
+    Reply::Bare(lines.join("\n"))
+}
+
+pub fn f_err(e: String) -> Reply {
+    Reply::Error(e)
+}
+
+pub(crate) fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Check a write request against the key and value size limits in the config.
+/// Return an error response if the request must be rejected.
+/// A limit of 0 is unlimited.
+pub fn check_limits(req: &Request, store: &impl Store, config: &Config) -> Option<Response> {
+    match req.command() {
+        Some(cmd) if cmd.has_flag(Flag::Write) => {}
+        _ => return None,
+    }
+    let key = req.key()?;
+    if config.max_key_size > 0 && req.keys().iter().any(|k| k.len() > config.max_key_size) {
+        return Some(Response {
+            body: f_err(format!(
+                "Key exceeds maximum size of {} bytes",
+                config.max_key_size
+            )),
+        });
+    }
+    // Appending grows the string already stored at the key, and writing a
+    // range may grow it past its end
+    let size = |v: &str| match req {
+        Request::Append { .. } => store.strlen(key.to_string()).unwrap() as usize + v.len(),
+        Request::SetRange { offset, .. } if !v.is_empty() => {
+            let len = store.strlen(key.to_string()).unwrap() as usize;
+            len.max((*offset as usize).saturating_add(v.len()))
         }
-        Request::SRem { key, val } => {
-            let len = store.srem(key, val).unwrap();
-            Response { body: f_uint(len) }
+        _ => v.len(),
+    };
+    if config.max_value_size > 0 && req.values().iter().any(|v| size(v) > config.max_value_size) {
+        return Some(Response {
+            body: f_err(format!(
+                "Value exceeds maximum size of {} bytes",
+                config.max_value_size
+            )),
+        });
+    }
+    if config.max_container_elements > 0 {
+        let additions = req.additions(store);
+        if additions > 0
+            && store.cardinality(key.to_string()).unwrap() + additions
+                > config.max_container_elements
+        {
+            return Some(Response {
+                body: f_err(format!(
+                    "Container exceeds maximum of {} elements",
+                    config.max_container_elements
+                )),
+            });
         }
-        Request::SIsMember { key, val } => match store.sismember(key, val).unwrap() {
-            true => Response { body: f_uint(1) },
-            false => Response { body: f_uint(0) },
-        },
-        Request::SMembers { key } => {
-            let members = store.smembers(key).unwrap();
-            match members.len() {
-                0 => Response { body: f_empty() },
-                _ => Response {
-                    body: f_vec(members),
-                },
+    }
+    None
+}
+
+/// Check a request against the per-namespace quotas in the config.
+/// Return an error response if the request must be rejected.
+///
+/// Commands flagged `denyoom` are rejected while the store's memory usage is
+/// at or above `namespace_max_memory`, and are rejected if they would create
+/// a new key while the store holds `namespace_max_keys` keys.
+/// A quota of 0 is unlimited.
+pub fn check_quota(req: &Request, store: &impl Store, config: &Config) -> Option<Response> {
+    match req.command() {
+        Some(cmd) if cmd.has_flag(Flag::DenyOom) => {}
+        _ => return None,
+    }
+    if config.namespace_max_memory > 0
+        && store.memory_usage().unwrap() >= config.namespace_max_memory
+    {
+        return Some(Response {
+            body: f_err("Namespace memory quota exceeded".to_string()),
+        });
+    }
+    if config.namespace_max_keys > 0 {
+        if let Some(key) = req.key() {
+            if !store.exists(key.to_string()).unwrap()
+                && store.dbsize().unwrap() >= config.namespace_max_keys
+            {
+                return Some(Response {
+                    body: f_err("Namespace key quota exceeded".to_string()),
+                });
             }
         }
-        Request::HGet { key, field } => match store.hget(key, field).unwrap() {
-            Some(val) => Response { body: f_str(val) },
-            None => Response { body: f_nil() },
-        },
-        Request::HSet { key, field, val } => match store.hset(key, field, val).unwrap() {
-            Some(_) => Response { body: f_uint(0) },
-            None => Response { body: f_uint(1) },
-        },
-        Request::HDel { key, field } => {
-            let del = store.hdel(key, field).unwrap();
-            Response { body: f_uint(del) }
-        }
-        Request::NoOp => Response { body: f_noop() },
-        Request::Quit => Response { body: f_quit() },
-        Request::Invalid { error } => Response { body: f_err(error) },
     }
+    None
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::store::{StdStore, Store};
+/// Namespace that clients operate on until they select another
+pub const DEFAULT_NAMESPACE: &str = "default";
 
-    #[tokio::test]
-    async fn test_execute_misc() {
-        let mut store: StdStore = Store::new();
+/// Number of keys examined by SCAN if no count is given
+pub const DEFAULT_SCAN_COUNT: u64 = 10;
 
-        // PING
-        assert_eq!(
-            execute(Request::Ping, &mut store).await,
-            Response {
-                body: "PONG".to_string()
-            }
-        );
-    }
+/// Maximum number of keys sampled by RANDOMKEY while looking for one that
+/// has not expired
+const RANDOM_KEY_ATTEMPTS: u64 = 16;
 
-    #[tokio::test]
-    async fn test_execute_strings() {
-        let mut store: StdStore = Store::new();
+/// Version of keys that have not been written since the server started
+const INITIAL_VERSION: u64 = 1;
+
+/// Executes requests against a separate store for each namespace, enforcing
+/// the limits and quotas in the config.
+pub struct Executor<S: ExpiringStore> {
+    stores: HashMap<String, S>,
+    config: Config,
+    stats: Stats,
+
+    /// Keys of each namespace in lexicographic order, if ordered keys are enabled
+    ordered: HashMap<String, BTreeSet<String>>,
+
+    /// Version of each key of each namespace that was written since startup
+    versions: HashMap<String, HashMap<String, u64>>,
+
+    /// Namespace and key of each key removed because it expired since they
+    /// were last taken, if expired keys are tracked
+    expired: Option<Vec<(String, String)>>,
+
+    /// Hits and misses of each prefix in `stats-prefixes`, for each namespace.
+    /// Behind a mutex so that reads under a shared lock can count them.
+    prefix_stats: Mutex<HashMap<String, HashMap<String, PrefixStats>>>,
+
+    /// Version assigned to the most recent write
+    clock: u64,
+
+    /// Generator for the jitter of times to live and for random choices of
+    /// requests. Behind a mutex so that reads under a shared lock can use it.
+    rng: Mutex<Rng>,
+
+    /// Virtual time in milliseconds since the Unix epoch, if set by a
+    /// simulation, which is used instead of the system clock
+    time: Option<i64>,
+}
+
+/// Counters reported under `INFO stats`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of keys removed because their time to live ran out
+    pub expired_keys: u64,
+
+    /// Number of hash fields removed because their time to live ran out
+    pub expired_fields: u64,
+
+    /// Number of keys removed to free memory
+    pub evicted_keys: u64,
+
+    /// Number of requests waiting to be executed
+    pub queue_depth: u64,
+
+    /// Number of requests rejected because the backlog was too long
+    pub shed_requests: u64,
+}
+
+/// Keyspace hits and misses of reads of keys starting with a prefix
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PrefixStats {
+    /// Number of keys read that existed
+    pub hits: u64,
+
+    /// Number of keys read that did not exist
+    pub misses: u64,
+}
+
+impl<S: ExpiringStore> Executor<S> {
+    pub fn new(config: Config) -> Self {
+        let mut stores = HashMap::new();
+        stores.insert(DEFAULT_NAMESPACE.to_string(), S::new());
+        Self {
+            stores,
+            config,
+            stats: Stats::default(),
+            ordered: HashMap::new(),
+            versions: HashMap::new(),
+            expired: None,
+            prefix_stats: Mutex::new(HashMap::new()),
+            clock: INITIAL_VERSION,
+            rng: Mutex::new(Rng::from_time()),
+            time: None,
+        }
+    }
+
+    /// Create an executor that makes random choices from a seed and whose
+    /// time stands still until it is changed with `set_time`, so that its
+    /// behavior can be replayed exactly.
+    pub fn with_clock(config: Config, seed: u64, time: i64) -> Self {
+        Self {
+            rng: Mutex::new(Rng::new(seed)),
+            time: Some(time),
+            ..Self::new(config)
+        }
+    }
+
+    /// Set the virtual time in milliseconds since the Unix epoch.
+    pub fn set_time(&mut self, time: i64) {
+        self.time = Some(time);
+    }
+
+    /// Return the time in milliseconds since the Unix epoch.
+    fn now(&self) -> i64 {
+        self.time.unwrap_or_else(unix_millis)
+    }
+
+    /// Replace the contents of a namespace with a store, such as one loaded
+    /// from a snapshot. Return the number of keys restored.
+    pub fn restore(&mut self, namespace: &str, store: S) -> u64 {
+        let keys: BTreeSet<String> = store.keys().cloned().collect();
+        self.versions.remove(namespace);
+        if self.config.ordered_keys {
+            self.ordered.insert(namespace.to_string(), keys.clone());
+        }
+        self.stores.insert(namespace.to_string(), store);
+        keys.len() as u64
+    }
+
+    /// Remove up to `purge-limit` expired keys from each namespace.
+    /// Return the number of keys removed.
+    pub fn purge_expired(&mut self) -> u64 {
+        let now = self.now();
+        let mut purged = 0;
+        for (namespace, store) in self.stores.iter_mut() {
+            let expired = store.purge_expired(now, self.config.purge_limit).unwrap();
+            if let Some(keys) = self.ordered.get_mut(namespace) {
+                for key in expired.iter() {
+                    keys.remove(key);
+                }
+            }
+            if let Some(versions) = self.versions.get_mut(namespace) {
+                for key in expired.iter() {
+                    versions.remove(key);
+                }
+            }
+            purged += expired.len() as u64;
+            if let Some(tracked) = self.expired.as_mut() {
+                tracked.extend(expired.into_iter().map(|key| (namespace.clone(), key)));
+            }
+            self.stats.expired_fields += store
+                .purge_expired_fields(now, self.config.purge_limit)
+                .unwrap();
+        }
+        self.stats.expired_keys += purged;
+        purged
+    }
+
+    /// Keep the keys that expire from now on until they are taken with
+    /// `take_expired`, such as to report them to a webhook.
+    pub fn track_expired(&mut self) {
+        self.expired.get_or_insert_with(Vec::new);
+    }
+
+    /// Return the namespace and key of each key that expired since the last
+    /// call, if expired keys are tracked.
+    pub fn take_expired(&mut self) -> Vec<(String, String)> {
+        match self.expired.as_mut() {
+            Some(expired) => std::mem::take(expired),
+            None => vec![],
+        }
+    }
+
+    /// Compact each namespace holding more spare capacity than `auto-compact`.
+    /// Return the approximate number of bytes freed.
+    pub fn auto_compact(&mut self) -> u64 {
+        if self.config.auto_compact == 0 {
+            return 0;
+        }
+        let mut freed = 0;
+        for store in self.stores.values_mut() {
+            if store.spare_capacity().unwrap() > self.config.auto_compact {
+                freed += store.compact().unwrap();
+            }
+        }
+        freed
+    }
+
+    /// Return the number of writes that changed a namespace.
+    pub fn changes(&self, namespace: &str) -> u64 {
+        match self.stores.get(namespace) {
+            Some(store) => store.changes().unwrap(),
+            None => 0,
+        }
+    }
+
+    /// Record the state of the queue of requests waiting for the executor.
+    pub fn record_queue(&mut self, depth: u64, shed: u64) {
+        self.stats.queue_depth = depth;
+        self.stats.shed_requests = shed;
+    }
+
+    /// Return keys between start and end inclusive in lexicographic order.
+    fn keyrange(&self, namespace: &str, start: &str, end: &str, limit: Option<u64>) -> Response {
+        if !self.config.ordered_keys {
+            return Response {
+                body: f_err("Ordered keys are disabled".to_string()),
+            };
+        }
+        if start > end {
+            return Response { body: f_empty() };
+        }
+        let keys: Vec<String> = match self.ordered.get(namespace) {
+            Some(keys) => keys
+                .range::<str, _>((Bound::Included(start), Bound::Included(end)))
+                .take(limit.unwrap_or(u64::MAX) as usize)
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        match keys.len() {
+            0 => Response { body: f_empty() },
+            _ => Response {
+                body: f_vec(keys.into_iter().map(f_bare).collect()),
+            },
+        }
+    }
+
+    /// Return the version of a key, which changes whenever the key is written.
+    /// Return 0 if the key does not exist.
+    fn version(&self, namespace: &str, key: &str) -> u64 {
+        let exists = match self.stores.get(namespace) {
+            Some(store) => {
+                !is_expired(store, key, self.now()) && store.exists(key.to_string()).unwrap()
+            }
+            None => false,
+        };
+        if !exists {
+            return 0;
+        }
+        self.versions
+            .get(namespace)
+            .and_then(|versions| versions.get(key))
+            .copied()
+            .unwrap_or(INITIAL_VERSION)
+    }
+
+    /// Create a synthetic dataset in a namespace, if enabled by the config.
+    /// Keys stop being created once a namespace quota is reached.
+    fn quickfill(&mut self, namespace: &str, fill: &Fill) -> Response {
+        if !self.config.quickfill {
+            return Response {
+                body: f_err("Quickfill is disabled".to_string()),
+            };
+        }
+        let config = &self.config;
+        if config.max_value_size > 0 && fill.max_value_size() > config.max_value_size as u64 {
+            return Response {
+                body: f_err(format!(
+                    "Value exceeds maximum size of {} bytes",
+                    config.max_value_size
+                )),
+            };
+        }
+        if config.max_container_elements > 0
+            && fill.has_containers()
+            && fill.elements > config.max_container_elements
+        {
+            return Response {
+                body: f_err(format!(
+                    "Container exceeds maximum of {} elements",
+                    config.max_container_elements
+                )),
+            };
+        }
+        let now = self.now();
+        let store = self
+            .stores
+            .entry(namespace.to_string())
+            .or_insert_with(S::new);
+        let created = fill.apply(store, now, |store| {
+            (config.namespace_max_keys > 0 && store.dbsize().unwrap() >= config.namespace_max_keys)
+                || (config.namespace_max_memory > 0
+                    && store.memory_usage().unwrap() >= config.namespace_max_memory)
+        });
+        for key in created.iter() {
+            if apply_default_ttl(store, &self.config, key, now) {
+                let rng = self.rng.get_mut().unwrap();
+                jitter_expiry(store, rng, self.config.ttl_jitter, key, now);
+            }
+        }
+        let versions = self.versions.entry(namespace.to_string()).or_default();
+        for key in created.iter() {
+            self.clock += 1;
+            versions.insert(key.clone(), self.clock);
+        }
+        if self.config.ordered_keys {
+            let keys = self.ordered.entry(namespace.to_string()).or_default();
+            keys.extend(created.iter().cloned());
+        }
+        Response {
+            body: f_uint(created.len() as u64),
+        }
+    }
+
+    /// Remove every key of a namespace, or of every namespace if none is given,
+    /// along with their versions and ordered keys.
+    fn flush(&mut self, namespace: Option<&str>) -> Response {
+        for (name, store) in self.stores.iter_mut() {
+            if namespace.is_none_or(|namespace| namespace == name) {
+                store.flushdb().unwrap();
+            }
+        }
+        match namespace {
+            Some(namespace) => {
+                self.versions.remove(namespace);
+                self.ordered.remove(namespace);
+            }
+            None => {
+                self.versions.clear();
+                self.ordered.clear();
+            }
+        }
+        Response { body: f_ok() }
+    }
+
+    /// Return information about the keyspace and server statistics.
+    /// If a section is given, only that section is included.
+    pub fn info(&self, section: Option<&str>) -> Response {
+        let mut lines = Vec::new();
+        if section.is_none() || section == Some("keyspace") {
+            lines.push("# Keyspace".to_string());
+            let mut names: Vec<&String> = self.stores.keys().collect();
+            names.sort();
+            for name in names {
+                let store = &self.stores[name];
+                let keys = store.dbsize().unwrap();
+                if keys == 0 {
+                    continue;
+                }
+                let mut fields = vec![format!("keys={}", keys)];
+                for (kind, count) in store.type_counts().unwrap() {
+                    if count > 0 {
+                        fields.push(format!("{}={}", kind, count));
+                    }
+                }
+                lines.push(format!("{}:{}", name, fields.join(",")));
+            }
+        }
+        if section.is_none() || section == Some("stats") {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push("# Stats".to_string());
+            lines.push(format!("namespaces:{}", self.stores.len()));
+            lines.push(format!("expired_keys:{}", self.stats.expired_keys));
+            lines.push(format!("expired_fields:{}", self.stats.expired_fields));
+            lines.push(format!("evicted_keys:{}", self.stats.evicted_keys));
+            lines.push(format!("queue_depth:{}", self.stats.queue_depth));
+            lines.push(format!("shed_requests:{}", self.stats.shed_requests));
+        }
+        Response {
+            body: f_info(lines),
+        }
+    }
+
+    /// Return the number of keys of a namespace starting with a prefix and
+    /// their approximate memory usage. Hits and misses are included if the
+    /// prefix is one of `stats-prefixes`.
+    fn stats_prefix(&self, namespace: &str, prefix: &str) -> Response {
+        let (mut keys, mut memory) = (0, 0);
+        let now = self.now();
+        if let Some(store) = self.stores.get(namespace) {
+            // Keys holding values of several types are counted once
+            let matching: BTreeSet<&String> = store
+                .keys()
+                .filter(|key| key.starts_with(prefix) && !is_expired(store, key, now))
+                .collect();
+            for key in matching {
+                keys += 1;
+                memory += store.key_memory_usage(key.clone()).unwrap();
+            }
+        }
+        let mut lines = vec![format!("keys:{}", keys), format!("memory:{}", memory)];
+        if self.config.stats_prefixes.iter().any(|p| p == prefix) {
+            let stats = self
+                .prefix_stats
+                .lock()
+                .unwrap()
+                .get(namespace)
+                .and_then(|prefixes| prefixes.get(prefix))
+                .copied()
+                .unwrap_or_default();
+            lines.push(format!("hits:{}", stats.hits));
+            lines.push(format!("misses:{}", stats.misses));
+        }
+        Response {
+            body: f_info(lines),
+        }
+    }
+
+    /// Execute a request in a namespace, creating its store if it does not exist.
+    pub async fn run(&mut self, req: Request, namespace: &str) -> Response {
+        let req = match req {
+            Request::Info { section } => return self.info(section.as_deref()),
+            Request::StatsPrefix { prefix } => return self.stats_prefix(namespace, &prefix),
+            Request::KeyRange { start, end, limit } => {
+                return self.keyrange(namespace, &start, &end, limit)
+            }
+            Request::Version { key } => {
+                return Response {
+                    body: f_uint(self.version(namespace, &key)),
+                }
+            }
+            Request::DebugQuickfill { fill } => return self.quickfill(namespace, &fill),
+            Request::FlushDb => return self.flush(Some(namespace)),
+            Request::FlushAll => return self.flush(None),
+            Request::SetIfVersion { key, val, version } => {
+                if self.version(namespace, &key) != version {
+                    return Response {
+                        body: f_err("Version mismatch".to_string()),
+                    };
+                }
+                Request::Set { key, val }
+            }
+            req => req,
+        };
+        // Capture the keys of writes so their versions and the ordered
+        // keyspace can be updated
+        let written: Vec<String> = match req.command() {
+            Some(cmd) if cmd.has_flag(Flag::Write) => {
+                req.keys().iter().map(|key| key.to_string()).collect()
+            }
+            _ => vec![],
+        };
+        // Reply of requests that set an expiry when they succeed. Absolute
+        // expiry times are kept as given, without jitter.
+        let expiring = match &req {
+            Request::Expire { .. } | Request::PExpire { .. } => Some(f_uint(1)),
+            Request::SetWith {
+                expiry: SetExpiry::After(_),
+                ..
+            } => Some(f_ok()),
+            _ => None,
+        };
+        let config = &self.config;
+        let now = self.now();
+        let store = self
+            .stores
+            .entry(namespace.to_string())
+            .or_insert_with(S::new);
+        // Expired keys are removed when accessed, in addition to being purged
+        for key in req.keys() {
+            if is_expired(store, key, now) {
+                store.del(key.to_string()).unwrap();
+                self.stats.expired_keys += 1;
+                if let Some(tracked) = self.expired.as_mut() {
+                    tracked.push((namespace.to_string(), key.to_string()));
+                }
+                if let Some(keys) = self.ordered.get_mut(namespace) {
+                    keys.remove(key);
+                }
+                if let Some(versions) = self.versions.get_mut(namespace) {
+                    versions.remove(key);
+                }
+            }
+        }
+        if let Some(key) = req.key() {
+            if let Some(field) = req.field() {
+                if is_field_expired(store, key, field, now) {
+                    store.hdel(key.to_string(), field.to_string()).unwrap();
+                    self.stats.expired_fields += 1;
+                }
+            }
+        }
+        count_hits(&self.prefix_stats, config, namespace, &req, store, now);
+        let existed: Vec<bool> = written
+            .iter()
+            .map(|key| store.exists(key.clone()).unwrap())
+            .collect();
+        let rejected =
+            check_limits(&req, store, config).or_else(|| check_quota(&req, store, config));
+        let changes = store.changes().unwrap();
+        let resp = match rejected {
+            Some(resp) => resp,
+            None => execute_at(req, store, now, self.rng.get_mut().unwrap()).await,
+        };
+        let changed = store.changes().unwrap() != changes;
+        for (key, existed) in written.into_iter().zip(existed) {
+            let exists = store.exists(key.clone()).unwrap();
+            let ttl_set = (exists && !existed && apply_default_ttl(store, config, &key, now))
+                || expiring.as_ref() == Some(&resp.body);
+            if ttl_set {
+                let rng = self.rng.get_mut().unwrap();
+                jitter_expiry(store, rng, config.ttl_jitter, &key, now);
+            }
+            // Writes that failed or changed nothing keep the key's version
+            if changed {
+                let versions = self.versions.entry(namespace.to_string()).or_default();
+                match exists {
+                    true => {
+                        self.clock += 1;
+                        versions.insert(key.clone(), self.clock);
+                    }
+                    false => {
+                        versions.remove(&key);
+                    }
+                }
+            }
+            if config.ordered_keys {
+                let keys = self.ordered.entry(namespace.to_string()).or_default();
+                match exists {
+                    true => keys.insert(key),
+                    false => keys.remove(&key),
+                };
+            }
+        }
+        resp
+    }
+
+    /// Execute a read-only request in a namespace without modifying the
+    /// executor, so that reads can run concurrently under a shared lock.
+    pub async fn run_read(&self, req: Request, namespace: &str) -> Response {
+        match &req {
+            Request::Info { section } => return self.info(section.as_deref()),
+            Request::StatsPrefix { prefix } => return self.stats_prefix(namespace, prefix),
+            Request::KeyRange { start, end, limit } => {
+                return self.keyrange(namespace, start, end, *limit)
+            }
+            Request::Version { key } => {
+                return Response {
+                    body: f_uint(self.version(namespace, key)),
+                }
+            }
+            _ => {}
+        }
+        let now = self.now();
+        let (prefix_stats, config) = (&self.prefix_stats, &self.config);
+        match self.stores.get(namespace) {
+            Some(store) => count_hits(prefix_stats, config, namespace, &req, store, now),
+            None => count_hits(prefix_stats, config, namespace, &req, &S::new(), now),
+        }
+        // Reads draw from a generator split off the executor's, so that its
+        // lock is not held while the request runs
+        let mut rng = Rng::new(self.rng.lock().unwrap().next_u64());
+        match self.stores.get(namespace) {
+            // Expired keys and fields can't be removed under a shared lock, so
+            // they are read as if the store were empty
+            Some(store)
+                if !req.key().is_some_and(|key| {
+                    is_expired(store, key, now)
+                        || req
+                            .field()
+                            .is_some_and(|field| is_field_expired(store, key, field, now))
+                }) =>
+            {
+                execute_read_at(req, store, now, &mut rng).await
+            }
+            _ => execute_read_at(req, &S::new(), now, &mut rng).await,
+        }
+    }
+}
+
+/// Count a hit or miss for each key of a read-only request that starts with
+/// a prefix in `stats-prefixes`.
+fn count_hits(
+    prefix_stats: &Mutex<HashMap<String, HashMap<String, PrefixStats>>>,
+    config: &Config,
+    namespace: &str,
+    req: &Request,
+    store: &impl ExpiringStore,
+    now: i64,
+) {
+    if config.stats_prefixes.is_empty() || !req.is_read_only() {
+        return;
+    }
+    let mut prefix_stats = prefix_stats.lock().unwrap();
+    for key in req.keys() {
+        let hit = !is_expired(store, key, now) && store.exists(key.to_string()).unwrap();
+        for prefix in config.stats_prefixes.iter() {
+            if !key.starts_with(prefix.as_str()) {
+                continue;
+            }
+            let stats = prefix_stats
+                .entry(namespace.to_string())
+                .or_default()
+                .entry(prefix.clone())
+                .or_default();
+            match hit {
+                true => stats.hits += 1,
+                false => stats.misses += 1,
+            }
+        }
+    }
+}
+
+/// Give a newly created key the default time to live of its pattern, unless
+/// it was created with one. Return if the time to live was set.
+fn apply_default_ttl(store: &mut impl ExpiringStore, config: &Config, key: &str, now: i64) -> bool {
+    match config.default_ttl(key) {
+        Some(secs) if store.get_expiry(key.to_string()).unwrap().is_none() => {
+            let millis = secs.saturating_mul(1000).min(i64::MAX as u64) as i64;
+            let at = now.saturating_add(millis);
+            store.set_expiry(key.to_string(), Some(at)).unwrap()
+        }
+        _ => false,
+    }
+}
+
+/// Lengthen or shorten the time to live of a key by a random amount of up to
+/// a percentage of it.
+fn jitter_expiry(store: &mut impl ExpiringStore, rng: &mut Rng, percent: u64, key: &str, now: i64) {
+    if percent == 0 {
+        return;
+    }
+    if let Some(at) = store.get_expiry(key.to_string()).unwrap() {
+        let ttl = at.saturating_sub(now).max(0) as u64;
+        let ttl = jitter(rng, percent, ttl);
+        let at = now.saturating_add(ttl.min(i64::MAX as u64) as i64);
+        store.set_expiry(key.to_string(), Some(at)).unwrap();
+    }
+}
+
+/// Return a duration spread uniformly within a percentage of itself.
+fn jitter(rng: &mut Rng, percent: u64, millis: u64) -> u64 {
+    let spread = (millis as u128 * percent as u128 / 100) as u64;
+    (millis - spread).saturating_add(rng.below(spread.saturating_mul(2).saturating_add(1)))
+}
+
+/// Return if the key has an expiry that has passed by a time.
+fn is_expired(store: &impl ExpiringStore, key: &str, now: i64) -> bool {
+    match store.get_expiry(key.to_string()).unwrap() {
+        Some(at) => at <= now,
+        None => false,
+    }
+}
+
+/// Return if the field of a hash has an expiry that has passed by a time.
+fn is_field_expired(store: &impl ExpiringStore, key: &str, field: &str, now: i64) -> bool {
+    match store
+        .get_field_expiry(key.to_string(), field.to_string())
+        .unwrap()
+    {
+        Some(at) => at <= now,
+        None => false,
+    }
+}
+
+pub async fn execute(req: Request, store: &mut impl ExpiringStore) -> Response {
+    execute_at(req, store, unix_millis(), &mut Rng::from_time()).await
+}
+
+/// Execute a request at a time in milliseconds since the Unix epoch, making
+/// random choices with a generator.
+pub async fn execute_at(
+    req: Request,
+    store: &mut impl ExpiringStore,
+    now: i64,
+    rng: &mut Rng,
+) -> Response {
+    match req {
+        Request::Expire { key, seconds } => {
+            let millis = seconds.saturating_mul(1000).min(i64::MAX as u64);
+            let at = now.saturating_add(millis as i64);
+            match store.set_expiry(key, Some(at)).unwrap() {
+                true => Response { body: f_uint(1) },
+                false => Response { body: f_uint(0) },
+            }
+        }
+        Request::PExpire { key, millis } => {
+            let at = now.saturating_add(millis.min(i64::MAX as u64) as i64);
+            match store.set_expiry(key, Some(at)).unwrap() {
+                true => Response { body: f_uint(1) },
+                false => Response { body: f_uint(0) },
+            }
+        }
+        Request::ExpireAt { key, timestamp } => {
+            let at = timestamp.saturating_mul(1000).min(i64::MAX as u64) as i64;
+            match store.set_expiry(key, Some(at)).unwrap() {
+                true => Response { body: f_uint(1) },
+                false => Response { body: f_uint(0) },
+            }
+        }
+        Request::PExpireAt { key, timestamp } => {
+            let at = timestamp.min(i64::MAX as u64) as i64;
+            match store.set_expiry(key, Some(at)).unwrap() {
+                true => Response { body: f_uint(1) },
+                false => Response { body: f_uint(0) },
+            }
+        }
+        Request::Del { keys } => {
+            let mut deleted = 0;
+            for key in keys {
+                if store.del(key).unwrap() {
+                    deleted += 1;
+                }
+            }
+            Response {
+                body: f_uint(deleted),
+            }
+        }
+        Request::FlushDb | Request::FlushAll => {
+            store.flushdb().unwrap();
+            Response { body: f_ok() }
+        }
+        Request::Compact => Response {
+            body: f_uint(store.compact().unwrap()),
+        },
+        Request::Rename { key, newkey } => match store.rename(key, newkey) {
+            Ok(()) => Response { body: f_ok() },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::RenameNx { key, newkey } => match store.renamenx(key, newkey) {
+            Ok(renamed) => Response {
+                body: f_uint(renamed as u64),
+            },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::Persist { key } => {
+            let had_expiry = store.get_expiry(key.clone()).unwrap().is_some();
+            match had_expiry && store.set_expiry(key, None).unwrap() {
+                true => Response { body: f_uint(1) },
+                false => Response { body: f_uint(0) },
+            }
+        }
+        // Connection settings are applied by the connection
+        Request::Namespace { .. } | Request::Priority { .. } | Request::Format { .. } => {
+            Response { body: f_ok() }
+        }
+        // Chaos requests are handled by the connection if chaos mode is enabled
+        Request::DebugChaos { .. } => Response {
+            body: f_err("Chaos mode is disabled".to_string()),
+        },
+        // Journals are kept by the server if enabled
+        Request::DebugJournal { .. } => Response {
+            body: f_err("Journals are disabled".to_string()),
+        },
+        // Quickfill requests are handled by the executor if enabled
+        Request::DebugQuickfill { .. } => Response {
+            body: f_err("Quickfill is disabled".to_string()),
+        },
+        Request::Set { key, val } => {
+            let _ = store.set(key, val);
+            Response { body: f_ok() }
+        }
+        Request::SetWith {
+            key,
+            val,
+            condition,
+            expiry,
+        } => {
+            let exists = store.exists(key.clone()).unwrap();
+            if condition.is_some_and(|condition| exists != (condition == SetCondition::Present)) {
+                return Response { body: f_nil() };
+            }
+            let at = match expiry {
+                SetExpiry::Discard => None,
+                SetExpiry::Keep => store.get_expiry(key.clone()).unwrap(),
+                SetExpiry::After(millis) => {
+                    Some(now.saturating_add(millis.min(i64::MAX as u64) as i64))
+                }
+            };
+            let _ = store.set(key.clone(), val);
+            if at.is_some() {
+                store.set_expiry(key, at).unwrap();
+            }
+            Response { body: f_ok() }
+        }
+        Request::GetDel { key } => match store.getdel(key).unwrap() {
+            Some(val) => Response { body: f_str(val) },
+            None => Response { body: f_nil() },
+        },
+        Request::Incr { key } => match store.incr(key) {
+            Ok(val) => Response { body: f_int(val) },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::Decr { key } => match store.decr(key) {
+            Ok(val) => Response { body: f_int(val) },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::IncrBy { key, delta } => match store.incrby(key, delta) {
+            Ok(val) => Response { body: f_int(val) },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::DecrBy { key, delta } => match store.decrby(key, delta) {
+            Ok(val) => Response { body: f_int(val) },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::IncrByFloat { key, delta } => match store.incrbyfloat(key, delta) {
+            Ok(val) => Response {
+                body: f_str(val.to_string()),
+            },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::Append { key, val } => {
+            let len = store.append(key, val).unwrap();
+            Response { body: f_uint(len) }
+        }
+        Request::SetRange { key, offset, val } => match store.setrange(key, offset, val) {
+            Ok(len) => Response { body: f_uint(len) },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::LPush { key, val } => {
+            let len = store.lpush(key, val).unwrap();
+            Response { body: f_uint(len) }
+        }
+        Request::RPush { key, val } => {
+            let len = store.rpush(key, val).unwrap();
+            Response { body: f_uint(len) }
+        }
+        Request::LPop { key } => match store.lpop(key).unwrap() {
+            Some(val) => Response { body: f_str(val) },
+            None => Response { body: f_nil() },
+        },
+        Request::RPop { key } => match store.rpop(key).unwrap() {
+            Some(val) => Response { body: f_str(val) },
+            None => Response { body: f_nil() },
+        },
+        Request::SAdd { key, val } => {
+            let len = store.sadd(key, val).unwrap();
+            Response { body: f_uint(len) }
+        }
+        Request::SRem { key, val } => {
+            let len = store.srem(key, val).unwrap();
+            Response { body: f_uint(len) }
+        }
+        Request::HSet { key, field, val } => match store.hset(key, field, val).unwrap() {
+            Some(_) => Response { body: f_uint(0) },
+            None => Response { body: f_uint(1) },
+        },
+        Request::HIncrByFloat { key, field, delta } => {
+            match store.hincrbyfloat(key, field, delta) {
+                Ok(val) => Response {
+                    body: f_str(val.to_string()),
+                },
+                Err(e) => Response {
+                    body: f_err(e.message),
+                },
+            }
+        }
+        Request::HDel { key, field } => {
+            let del = store.hdel(key, field).unwrap();
+            Response { body: f_uint(del) }
+        }
+        Request::HExpire {
+            key,
+            field,
+            seconds,
+        } => {
+            let millis = seconds.saturating_mul(1000).min(i64::MAX as u64);
+            let at = now.saturating_add(millis as i64);
+            match store.set_field_expiry(key, field, Some(at)).unwrap() {
+                true => Response { body: f_uint(1) },
+                false => Response { body: f_uint(0) },
+            }
+        }
+        Request::HPExpire { key, field, millis } => {
+            let at = now.saturating_add(millis.min(i64::MAX as u64) as i64);
+            match store.set_field_expiry(key, field, Some(at)).unwrap() {
+                true => Response { body: f_uint(1) },
+                false => Response { body: f_uint(0) },
+            }
+        }
+        Request::HPersist { key, field } => {
+            let had_expiry = store
+                .get_field_expiry(key.clone(), field.clone())
+                .unwrap()
+                .is_some();
+            match had_expiry && store.set_field_expiry(key, field, None).unwrap() {
+                true => Response { body: f_uint(1) },
+                false => Response { body: f_uint(0) },
+            }
+        }
+        Request::BFReserve {
+            key,
+            error_rate,
+            capacity,
+        } => match store.bf_reserve(key, error_rate, capacity) {
+            Ok(_) => Response { body: f_ok() },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::BFAdd { key, item } => match store.bf_add(key, item).unwrap() {
+            true => Response { body: f_uint(1) },
+            false => Response { body: f_uint(0) },
+        },
+        Request::BFMAdd { key, items } => {
+            let added = store.bf_madd(key, items).unwrap();
+            Response {
+                body: f_vec(added.into_iter().map(|a| f_uint(a as u64)).collect()),
+            }
+        }
+        Request::CFAdd { key, item } => {
+            store.cf_add(key, item).unwrap();
+            Response { body: f_uint(1) }
+        }
+        Request::CFDel { key, item } => match store.cf_del(key, item).unwrap() {
+            true => Response { body: f_uint(1) },
+            false => Response { body: f_uint(0) },
+        },
+        Request::TSCreate { key, retention } => match store.ts_create(key, retention) {
+            Ok(_) => Response { body: f_ok() },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::TSAdd {
+            key,
+            timestamp,
+            value,
+        } => {
+            let timestamp = timestamp.unwrap_or(now);
+            match store.ts_add(key, timestamp, value) {
+                Ok(ts) => Response { body: f_int(ts) },
+                Err(e) => Response {
+                    body: f_err(e.message),
+                },
+            }
+        }
+        Request::VAdd {
+            key,
+            element,
+            vector,
+        } => match store.vadd(key, element, vector) {
+            Ok(true) => Response { body: f_uint(1) },
+            Ok(false) => Response { body: f_uint(0) },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::FTCreate {
+            index,
+            prefix,
+            fields,
+        } => match store.ft_create(index, prefix, fields) {
+            Ok(_) => Response { body: f_ok() },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::NoOp => Response { body: f_noop() },
+        Request::Quit => Response { body: f_quit() },
+        Request::Invalid { error } => Response { body: f_err(error) },
+        // Read-only requests
+        req => execute_read_at(req, store, now, rng).await,
+    }
+}
+
+/// Execute a read-only request, which never modifies the store.
+/// Other requests are rejected with an error.
+pub async fn execute_read(req: Request, store: &impl ExpiringStore) -> Response {
+    execute_read_at(req, store, unix_millis(), &mut Rng::from_time()).await
+}
+
+/// Execute a read-only request at a time in milliseconds since the Unix
+/// epoch, making random choices with a generator.
+pub async fn execute_read_at(
+    req: Request,
+    store: &impl ExpiringStore,
+    now: i64,
+    rng: &mut Rng,
+) -> Response {
+    match req {
+        // Keys are counted each time they are given. Expired keys may not have
+        // been removed yet if the request is executed under a shared lock.
+        Request::Exists { keys } => {
+            let count = keys
+                .into_iter()
+                .filter(|key| !is_expired(store, key, now) && store.exists(key.clone()).unwrap())
+                .count();
+            Response {
+                body: f_uint(count as u64),
+            }
+        }
+        Request::Ttl { key } => {
+            if !store.exists(key.clone()).unwrap() {
+                return Response { body: f_int(-2) };
+            }
+            match store.get_expiry(key).unwrap() {
+                // Round up, so that a key reported with a TTL of 0 has expired
+                Some(at) => Response {
+                    body: f_int((at - now + 999).max(0) / 1000),
+                },
+                None => Response { body: f_int(-1) },
+            }
+        }
+        Request::PTtl { key } => {
+            if !store.exists(key.clone()).unwrap() {
+                return Response { body: f_int(-2) };
+            }
+            match store.get_expiry(key).unwrap() {
+                Some(at) => Response {
+                    body: f_int((at - now).max(0)),
+                },
+                None => Response { body: f_int(-1) },
+            }
+        }
+        Request::ExpireTime { key } => {
+            if !store.exists(key.clone()).unwrap() {
+                return Response { body: f_int(-2) };
+            }
+            match store.get_expiry(key).unwrap() {
+                Some(at) => Response {
+                    body: f_int(at / 1000),
+                },
+                None => Response { body: f_int(-1) },
+            }
+        }
+        Request::PExpireTime { key } => {
+            if !store.exists(key.clone()).unwrap() {
+                return Response { body: f_int(-2) };
+            }
+            match store.get_expiry(key).unwrap() {
+                Some(at) => Response { body: f_int(at) },
+                None => Response { body: f_int(-1) },
+            }
+        }
+        Request::Snapshot => {
+            let mut buf = Vec::new();
+            store.serialize(&mut buf).unwrap();
+            Response {
+                body: f_str(to_hex(&buf)),
+            }
+        }
+        // Server information, ordered keys and versions are maintained by the executor
+        Request::Info { .. } | Request::StatsPrefix { .. } => Response {
+            body: f_err("Server information is unavailable".to_string()),
+        },
+        Request::KeyRange { .. } => Response {
+            body: f_err("Ordered keys are disabled".to_string()),
+        },
+        Request::Version { .. } | Request::SetIfVersion { .. } => Response {
+            body: f_err("Versions are unavailable".to_string()),
+        },
+        Request::Type { key } => match store.key_type(key).unwrap() {
+            Some(kind) => Response {
+                body: Reply::Status(kind.to_string()),
+            },
+            None => Response {
+                body: Reply::Status("none".to_string()),
+            },
+        },
+        // Keys holding values of several types are listed once
+        Request::Keys { pattern } => {
+            let mut keys: Vec<String> = store
+                .keys()
+                .filter(|key| glob_match(&pattern, key) && !is_expired(store, key, now))
+                .cloned()
+                .collect();
+            keys.sort();
+            keys.dedup();
+            match keys.len() {
+                0 => Response { body: f_empty() },
+                _ => Response {
+                    body: f_vec(keys.into_iter().map(f_bare).collect()),
+                },
+            }
+        }
+        // Keys that have expired but have not been removed yet are skipped, so
+        // nil may be returned if most keys have expired
+        Request::RandomKey => {
+            for _ in 0..RANDOM_KEY_ATTEMPTS {
+                match store.random_key(rng).unwrap() {
+                    Some(key) if is_expired(store, &key, now) => continue,
+                    Some(key) => return Response { body: f_str(key) },
+                    None => break,
+                }
+            }
+            Response { body: f_nil() }
+        }
+        Request::DbSize => Response {
+            body: f_uint(store.dbsize().unwrap()),
+        },
+        Request::Scan {
+            cursor,
+            pattern,
+            kind,
+            count,
+        } => {
+            let (next, keys) = store
+                .scan(cursor, pattern.as_deref(), kind.as_deref(), count)
+                .unwrap();
+            Response {
+                body: f_scan(next, keys),
+            }
+        }
+        Request::Ping => Response { body: f_pong() },
+        Request::CommandInfo { names } => {
+            let info = match names.len() {
+                0 => COMMANDS.iter().map(f_command).collect(),
+                _ => names
+                    .iter()
+                    .map(|name| match lookup_command(name) {
+                        Some(cmd) => f_command(cmd),
+                        None => f_nil(),
+                    })
+                    .collect(),
+            };
+            Response { body: f_vec(info) }
+        }
+        Request::Get { key } => match store.get(key).unwrap() {
+            Some(val) => Response { body: f_str(val) },
+            None => Response { body: f_nil() },
+        },
+        Request::GetRange { key, start, end } => {
+            let val = store.getrange(key, start, end).unwrap();
+            Response { body: f_str(val) }
+        }
+        Request::StrLen { key } => {
+            let len = store.strlen(key).unwrap();
+            Response { body: f_uint(len) }
+        }
+        Request::StrAlgoLcs {
+            input,
+            len,
+            idx,
+            min_match_len,
+            with_match_len,
+        } => {
+            let (a, b) = match input {
+                LcsInput::Strings(a, b) => (a, b),
+                LcsInput::Keys(ka, kb) => (
+                    store.get(ka).unwrap().unwrap_or_default(),
+                    store.get(kb).unwrap().unwrap_or_default(),
+                ),
+            };
+            let result = lcs(&a, &b);
+            let total = result.sequence.chars().count() as u64;
+            if len {
+                return Response {
+                    body: f_uint(total),
+                };
+            }
+            if !idx {
+                return Response {
+                    body: f_str(result.sequence),
+                };
+            }
+            let mut lines: Vec<String> = result
+                .matches
+                .into_iter()
+                .filter(|m| m.len >= min_match_len)
+                .map(|m| {
+                    let range = format!("{}-{} {}-{}", m.a.0, m.a.1, m.b.0, m.b.1);
+                    match with_match_len {
+                        true => format!("{} (length {})", range, m.len),
+                        false => range,
+                    }
+                })
+                .collect();
+            lines.push(format!("len {}", total));
+            Response {
+                body: f_vec(lines.into_iter().map(f_bare).collect()),
+            }
+        }
+        Request::SIsMember { key, val } => match store.sismember(key, val).unwrap() {
+            true => Response { body: f_uint(1) },
+            false => Response { body: f_uint(0) },
+        },
+        Request::SMembers { key } => {
+            let members = store.smembers(key).unwrap();
+            match members.len() {
+                0 => Response { body: f_empty() },
+                _ => Response {
+                    body: f_vec(members.into_iter().map(f_bare).collect()),
+                },
+            }
+        }
+        Request::HTtl { key, field } => {
+            if store.hget(key.clone(), field.clone()).unwrap().is_none() {
+                return Response { body: f_int(-2) };
+            }
+            match store.get_field_expiry(key, field).unwrap() {
+                Some(at) => Response {
+                    body: f_int((at - now + 999).max(0) / 1000),
+                },
+                None => Response { body: f_int(-1) },
+            }
+        }
+        Request::HGet { key, field } => match store.hget(key, field).unwrap() {
+            Some(val) => Response { body: f_str(val) },
+            None => Response { body: f_nil() },
+        },
+        Request::BFExists { key, item } => match store.bf_exists(key, item).unwrap() {
+            true => Response { body: f_uint(1) },
+            false => Response { body: f_uint(0) },
+        },
+        Request::CFExists { key, item } => match store.cf_exists(key, item).unwrap() {
+            true => Response { body: f_uint(1) },
+            false => Response { body: f_uint(0) },
+        },
+        Request::TSRange {
+            key,
+            from,
+            to,
+            aggregation,
+        } => match store.ts_range(key, from, to, aggregation) {
+            Ok(samples) => match samples.len() {
+                0 => Response { body: f_empty() },
+                _ => Response {
+                    body: f_vec(samples.into_iter().map(|(t, v)| f_sample(t, v)).collect()),
+                },
+            },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::VSim {
+            key,
+            query,
+            metric,
+            count,
+        } => match store.vsim(key, query, metric, count) {
+            Ok(matches) => match matches.len() {
+                0 => Response { body: f_empty() },
+                _ => Response {
+                    body: f_vec(matches.into_iter().map(|(m, s)| f_scored(m, s)).collect()),
+                },
+            },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        Request::FTSearch {
+            index,
+            filters,
+            offset,
+            count,
+        } => match store.ft_search(index, filters, offset, count) {
+            Ok(keys) => match keys.len() {
+                0 => Response { body: f_empty() },
+                _ => Response {
+                    body: f_vec(keys.into_iter().map(f_bare).collect()),
+                },
+            },
+            Err(e) => Response {
+                body: f_err(e.message),
+            },
+        },
+        // Waiting requires the changes observed by a server
+        Request::WaitKey { .. } => Response {
+            body: f_err("WAITKEY is only available through a server".to_string()),
+        },
+        _ => Response {
+            body: f_err("Command is not read-only".to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use crate::fill::FillType;
+    use crate::snapshot::from_hex;
+    use crate::store::{StdStore, Store};
+
+    #[tokio::test]
+    async fn test_execute_misc() {
+        let mut store: StdStore = Store::new();
+
+        // PING
+        assert_eq!(execute(Request::Ping, &mut store).await.body, "PONG");
+
+        // COMMAND
+        assert_eq!(
+            execute(
+                Request::CommandInfo {
+                    names: vec!["get".to_string(), "LPUSH".to_string(), "foo".to_string()]
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "1) get (readonly)\n2) lpush (write, denyoom)\n3) (nil)"
+        );
+        let all = execute(Request::CommandInfo { names: vec![] }, &mut store).await;
+        assert_eq!(all.body.to_string().lines().count(), COMMANDS.len());
+
+        // NAMESPACE
+        assert_eq!(
+            execute(
+                Request::Namespace {
+                    name: "app1".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "OK"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_limits() {
+        let mut store: StdStore = Store::new();
+        let mut config = parse_config(None);
+        config.max_key_size = 3;
+        config.max_value_size = 5;
+        config.max_container_elements = 2;
+        let sadd = |key: &str, val: &str| Request::SAdd {
+            key: key.to_string(),
+            val: val.to_string(),
+        };
+        assert_eq!(check_limits(&sadd("foo", "a"), &store, &config), None);
+        assert_eq!(
+            check_limits(&sadd("food", "a"), &store, &config),
+            Some(Response {
+                body: f_err("Key exceeds maximum size of 3 bytes".to_string())
+            })
+        );
+        assert_eq!(
+            check_limits(&sadd("foo", "abcdef"), &store, &config),
+            Some(Response {
+                body: f_err("Value exceeds maximum size of 5 bytes".to_string())
+            })
+        );
+
+        // Containers may not grow beyond the limit, but existing members can be re-added
+        execute(sadd("foo", "a"), &mut store).await;
+        execute(sadd("foo", "b"), &mut store).await;
+        assert_eq!(check_limits(&sadd("foo", "a"), &store, &config), None);
+        assert_eq!(
+            check_limits(&sadd("foo", "c"), &store, &config),
+            Some(Response {
+                body: f_err("Container exceeds maximum of 2 elements".to_string())
+            })
+        );
+
+        // Appends may not grow a string beyond the limit
+        let append = |val: &str| Request::Append {
+            key: "bar".to_string(),
+            val: val.to_string(),
+        };
+        execute(append("abc"), &mut store).await;
+        assert_eq!(check_limits(&append("de"), &store, &config), None);
+        assert_eq!(
+            check_limits(&append("def"), &store, &config),
+            Some(Response {
+                body: f_err("Value exceeds maximum size of 5 bytes".to_string())
+            })
+        );
+        let setrange = |offset, val: &str| Request::SetRange {
+            key: "bar".to_string(),
+            offset,
+            val: val.to_string(),
+        };
+        assert_eq!(check_limits(&setrange(0, "abcde"), &store, &config), None);
+        assert_eq!(
+            check_limits(&setrange(4, "de"), &store, &config),
+            Some(Response {
+                body: f_err("Value exceeds maximum size of 5 bytes".to_string())
+            })
+        );
+
+        // Reads are not limited
+        let get = Request::Get {
+            key: "food".to_string(),
+        };
+        assert_eq!(check_limits(&get, &store, &config), None);
+    }
+
+    #[tokio::test]
+    async fn test_check_quota() {
+        let mut store: StdStore = Store::new();
+        let mut config = parse_config(None);
+        let set = |key: &str| Request::Set {
+            key: key.to_string(),
+            val: "bar".to_string(),
+        };
+        assert_eq!(check_quota(&set("foo"), &store, &config), None);
+
+        // Key quota rejects writes to new keys only
+        config.namespace_max_keys = 1;
+        execute(set("foo"), &mut store).await;
+        assert_eq!(check_quota(&set("foo"), &store, &config), None);
+        assert_eq!(
+            check_quota(&set("baz"), &store, &config),
+            Some(Response {
+                body: f_err("Namespace key quota exceeded".to_string())
+            })
+        );
+
+        // Memory quota rejects writes that may grow memory, but not removals
+        config.namespace_max_memory = 1;
+        assert_eq!(
+            check_quota(&set("foo"), &store, &config),
+            Some(Response {
+                body: f_err("Namespace memory quota exceeded".to_string())
+            })
+        );
+        let pop = Request::LPop {
+            key: "foo".to_string(),
+        };
+        assert_eq!(check_quota(&pop, &store, &config), None);
+        let get = Request::Get {
+            key: "foo".to_string(),
+        };
+        assert_eq!(check_quota(&get, &store, &config), None);
+    }
+
+    #[tokio::test]
+    async fn test_execute_read() {
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let get = || Request::Get {
+            key: "foo".to_string(),
+        };
+        let set = || Request::Set {
+            key: "foo".to_string(),
+            val: "bar".to_string(),
+        };
+        assert!(get().is_read_only());
+        assert!(!set().is_read_only());
+        assert!(!Request::NoOp.is_read_only());
+
+        executor.run(set(), DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            executor.run_read(get(), DEFAULT_NAMESPACE).await.body,
+            "\"bar\""
+        );
+        // Reads from a namespace that doesn't exist yet see an empty keyspace
+        assert_eq!(executor.run_read(get(), "other").await.body, "(nil)");
+        assert_eq!(
+            executor.run_read(set(), DEFAULT_NAMESPACE).await.body,
+            "(error) Command is not read-only"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_snapshot() {
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let set = Request::Set {
+            key: "foo".to_string(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        let body = executor
+            .run_read(Request::Snapshot, DEFAULT_NAMESPACE)
+            .await
+            .body;
+        let bytes = from_hex(body.to_string().trim_matches('"')).unwrap();
+        let copy = <StdStore>::deserialize(&mut &bytes[..]).unwrap();
+        assert_eq!(
+            copy.get("foo".to_string()).unwrap(),
+            Some("bar".to_string())
+        );
+
+        // Each namespace is snapshotted separately
+        let body = executor.run(Request::Snapshot, "other").await.body;
+        let bytes = from_hex(body.to_string().trim_matches('"')).unwrap();
+        let copy = <StdStore>::deserialize(&mut &bytes[..]).unwrap();
+        assert_eq!(copy.dbsize().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_execute_stats_prefix() {
+        let config = Config::builder()
+            .stats_prefixes(vec!["user:".to_string()])
+            .build()
+            .unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        for key in &["user:1", "user:2", "sess:1"] {
+            let set = Request::Set {
+                key: key.to_string(),
+                val: "bar".to_string(),
+            };
+            executor.run(set, DEFAULT_NAMESPACE).await;
+        }
+        let get = |key: &str| Request::Get {
+            key: key.to_string(),
+        };
+        executor.run(get("user:1"), DEFAULT_NAMESPACE).await;
+        executor.run(get("user:3"), DEFAULT_NAMESPACE).await;
+        executor.run_read(get("user:2"), DEFAULT_NAMESPACE).await;
+        executor.run(get("sess:1"), DEFAULT_NAMESPACE).await;
+        // Writes are not counted
+        let set = Request::Set {
+            key: "user:4".to_string(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, "other").await;
+
+        let stats = |prefix: &str| Request::StatsPrefix {
+            prefix: prefix.to_string(),
+        };
+        assert_eq!(
+            executor.run(stats("user:"), DEFAULT_NAMESPACE).await.body,
+            "keys:2\nmemory:18\nhits:2\nmisses:1"
+        );
+        assert_eq!(
+            executor.run_read(stats("user:"), "other").await.body,
+            "keys:1\nmemory:9\nhits:0\nmisses:0"
+        );
+        // Hits and misses are only counted for configured prefixes
+        assert_eq!(
+            executor.run(stats("sess:"), DEFAULT_NAMESPACE).await.body,
+            "keys:1\nmemory:9"
+        );
+        assert_eq!(
+            executor.run(stats("dne:"), DEFAULT_NAMESPACE).await.body,
+            "keys:0\nmemory:0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_info() {
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let set = |key: &str| Request::Set {
+            key: key.to_string(),
+            val: "bar".to_string(),
+        };
+        executor.run(set("foo"), DEFAULT_NAMESPACE).await;
+        executor.run(set("baz"), DEFAULT_NAMESPACE).await;
+        executor
+            .run(
+                Request::LPush {
+                    key: "list".to_string(),
+                    val: "item".to_string(),
+                },
+                "app1",
+            )
+            .await;
+        executor.run(Request::Ping, "empty").await;
+
+        assert_eq!(
+            executor
+                .run(
+                    Request::Info {
+                        section: Some("keyspace".to_string())
+                    },
+                    DEFAULT_NAMESPACE
+                )
+                .await
+                .body,
+            "# Keyspace\napp1:keys=1,list=1\ndefault:keys=2,string=2"
+        );
+        assert_eq!(
+            executor
+                .run_read(Request::Info { section: None }, DEFAULT_NAMESPACE)
+                .await,
+            Response {
+                body: f_bare(
+                    "# Keyspace\napp1:keys=1,list=1\ndefault:keys=2,string=2\n\n\
+                       # Stats\nnamespaces:3\nexpired_keys:0\nexpired_fields:0\nevicted_keys:0\n\
+                       queue_depth:0\nshed_requests:0"
+                        .to_string()
+                )
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_keyrange() {
+        let range = |start: &str, end: &str, limit| Request::KeyRange {
+            start: start.to_string(),
+            end: end.to_string(),
+            limit,
+        };
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        assert_eq!(
+            executor
+                .run(range("a", "z", None), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "(error) Ordered keys are disabled"
+        );
+
+        let config = Config::builder()
+            .ordered_keys(true)
+            .max_key_size(9)
+            .build()
+            .unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        for key in &[
+            "user:1500",
+            "user:1000",
+            "user:2001",
+            "order:1",
+            "user:2000",
+        ] {
+            let req = Request::SAdd {
+                key: key.to_string(),
+                val: "item".to_string(),
+            };
+            executor.run(req, DEFAULT_NAMESPACE).await;
+        }
+        assert_eq!(
+            executor
+                .run(range("user:1000", "user:2000", None), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "1) user:1000\n2) user:1500\n3) user:2000"
+        );
+        assert_eq!(
+            executor
+                .run_read(range("user:1000", "user:2000", Some(1)), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "1) user:1000"
+        );
+
+        // Rejected writes do not add keys
+        let set = Request::Set {
+            key: "user:10000".to_string(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            executor
+                .run(range("user:1000", "user:2000", Some(10)), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "1) user:1000\n2) user:1500\n3) user:2000"
+        );
+        assert_eq!(
+            executor.run(range("z", "a", None), DEFAULT_NAMESPACE).await,
+            Response { body: f_empty() }
+        );
+        assert_eq!(
+            executor.run(range("a", "z", None), "other").await,
+            Response { body: f_empty() }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_scan() {
+        let mut store: StdStore = Store::new();
+        let scan = |cursor, pattern: &str| Request::Scan {
+            cursor,
+            pattern: Some(pattern.to_string()),
+            kind: None,
+            count: 10,
+        };
+        assert_eq!(
+            execute(scan(0, "*"), &mut store).await.body,
+            "1) \"0\"\n2) (empty list or set)"
+        );
+        let _ = store.set("foo".to_string(), "bar".to_string());
+        let _ = store.sadd("food".to_string(), "item".to_string());
+        let _ = store.sadd("baz".to_string(), "item".to_string());
+        let resp = execute(scan(0, "foo*"), &mut store).await;
+        assert!(
+            resp.body == "1) \"0\"\n2) 1) foo\n   2) food"
+                || resp.body == "1) \"0\"\n2) 1) food\n   2) foo"
+        );
+        let sets = Request::Scan {
+            cursor: 0,
+            pattern: Some("foo*".to_string()),
+            kind: Some("set".to_string()),
+            count: 10,
+        };
+        assert_eq!(execute(sets, &mut store).await.body, "1) \"0\"\n2) 1) food");
+    }
+
+    #[tokio::test]
+    async fn test_execute_keys() {
+        let mut store: StdStore = Store::new();
+        let keys = |pattern: &str| Request::Keys {
+            pattern: pattern.to_string(),
+        };
+        assert_eq!(
+            execute(keys("*"), &mut store).await.body,
+            "(empty list or set)"
+        );
+        let _ = store.set("user:10".to_string(), "shoyo".to_string());
+        let _ = store.rpush("user:10".to_string(), "item".to_string());
+        let _ = store.set("user:2".to_string(), "inokuchi".to_string());
+        let _ = store.sadd("users".to_string(), "user:2".to_string());
+        let _ = store.set("user:3".to_string(), "expired".to_string());
+        let _ = store.set_expiry("user:3".to_string(), Some(0));
+        assert_eq!(
+            execute(keys("user:*"), &mut store).await.body,
+            "1) user:10\n2) user:2"
+        );
+        assert_eq!(execute(keys("user?"), &mut store).await.body, "1) users");
+        assert_eq!(
+            execute(keys("user:[0-2]"), &mut store).await.body,
+            "1) user:2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_set_options() {
+        let mut store: StdStore = Store::new();
+        let set = |val: &str, condition, expiry| Request::SetWith {
+            key: "foo".to_string(),
+            val: val.to_string(),
+            condition,
+            expiry,
+        };
+        let ttl = |store: &StdStore| store.get_expiry("foo".to_string()).unwrap();
+
+        // XX requires the key to exist, and NX requires it not to
+        let present = Some(SetCondition::Present);
+        let absent = Some(SetCondition::Absent);
+        assert_eq!(
+            execute(set("a", present, SetExpiry::Discard), &mut store)
+                .await
+                .body,
+            f_nil()
+        );
+        assert_eq!(
+            execute(set("b", absent, SetExpiry::After(60000)), &mut store)
+                .await
+                .body,
+            f_ok()
+        );
+        let at = ttl(&store).unwrap();
+        assert!(at > unix_millis() + 59000);
+        assert_eq!(
+            execute(set("c", absent, SetExpiry::Discard), &mut store)
+                .await
+                .body,
+            f_nil()
+        );
+        assert_eq!(store.get("foo".to_string()).unwrap().unwrap(), "b");
+
+        // KEEPTTL keeps the expiry, which is otherwise discarded
+        execute(set("d", present, SetExpiry::Keep), &mut store).await;
+        assert_eq!(store.get("foo".to_string()).unwrap().unwrap(), "d");
+        assert_eq!(ttl(&store), Some(at));
+        execute(set("e", None, SetExpiry::Discard), &mut store).await;
+        assert_eq!(ttl(&store), None);
+    }
+
+    #[tokio::test]
+    async fn test_execute_type() {
+        let mut store: StdStore = Store::new();
+        let kind = |key: &str| Request::Type {
+            key: key.to_string(),
+        };
+        let _ = store.set("foo".to_string(), "bar".to_string());
+        let _ = store.hset("baz".to_string(), "field".to_string(), "val".to_string());
+        assert_eq!(execute(kind("foo"), &mut store).await.body, "string");
+        assert_eq!(execute(kind("baz"), &mut store).await.body, "hash");
+        assert_eq!(execute(kind("dne"), &mut store).await.body, "none");
+    }
+
+    #[tokio::test]
+    async fn test_execute_flush() {
+        let config = Config::builder().ordered_keys(true).build().unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        let set = |key: &str| Request::Set {
+            key: key.to_string(),
+            val: "val".to_string(),
+        };
+        executor.run(set("foo"), DEFAULT_NAMESPACE).await;
+        executor.run(set("bar"), "other").await;
+        executor.run(set("baz"), "third").await;
+
+        assert_eq!(executor.run(Request::FlushDb, "other").await.body, "OK");
+        assert_eq!(
+            executor.run(Request::DbSize, DEFAULT_NAMESPACE).await.body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            executor.run(Request::DbSize, "other").await.body,
+            "(integer) 0"
+        );
+        let version = Request::Version {
+            key: "bar".to_string(),
+        };
+        assert_eq!(executor.run(version, "other").await.body, "(integer) 0");
 
-        // SET AND GET
+        assert_eq!(
+            executor
+                .run(Request::FlushAll, DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "OK"
+        );
+        for namespace in &[DEFAULT_NAMESPACE, "third"] {
+            assert_eq!(
+                executor.run(Request::DbSize, namespace).await.body,
+                "(integer) 0"
+            );
+        }
+        let range = Request::KeyRange {
+            start: "a".to_string(),
+            end: "z".to_string(),
+            limit: None,
+        };
+        assert_eq!(
+            executor.run(range, "third").await.body,
+            "(empty list or set)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_compact() {
+        let config = Config::builder().auto_compact(1024).build().unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        let set = |i: u64| Request::Set {
+            key: i.to_string(),
+            val: "val".to_string(),
+        };
+        for i in 0..1000 {
+            executor.run(set(i), DEFAULT_NAMESPACE).await;
+            executor.run(set(i), "other").await;
+        }
+        executor.run(Request::FlushDb, DEFAULT_NAMESPACE).await;
+        executor.run(Request::FlushDb, "other").await;
+
+        let compact = executor.run(Request::Compact, DEFAULT_NAMESPACE).await;
+        assert_ne!(compact.body, "(integer) 0");
+        assert_eq!(
+            executor.run(Request::Compact, DEFAULT_NAMESPACE).await.body,
+            "(integer) 0"
+        );
+        // Namespaces beyond the threshold are compacted automatically
+        assert!(executor.auto_compact() > 0);
+        assert_eq!(executor.auto_compact(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_execute_randomkey_dbsize() {
+        let mut store: StdStore = Store::new();
+        assert_eq!(execute(Request::RandomKey, &mut store).await.body, f_nil());
+        assert_eq!(execute(Request::DbSize, &mut store).await.body, f_uint(0));
+
+        let _ = store.set("foo".to_string(), "bar".to_string());
+        let _ = store.set("baz".to_string(), "expired".to_string());
+        let _ = store.set_expiry("baz".to_string(), Some(0));
+        assert_eq!(execute(Request::DbSize, &mut store).await.body, f_uint(2));
+        for _ in 0..10 {
+            assert_eq!(
+                execute(Request::RandomKey, &mut store).await.body,
+                "\"foo\""
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_del() {
+        let config = Config::builder().ordered_keys(true).build().unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        for key in &["foo", "bar", "baz"] {
+            let set = Request::Set {
+                key: key.to_string(),
+                val: "val".to_string(),
+            };
+            executor.run(set, DEFAULT_NAMESPACE).await;
+        }
+        let lpush = Request::LPush {
+            key: "foo".to_string(),
+            val: "item".to_string(),
+        };
+        executor.run(lpush, DEFAULT_NAMESPACE).await;
+        let expire = Request::Expire {
+            key: "baz".to_string(),
+            seconds: 0,
+        };
+        executor.run(expire, DEFAULT_NAMESPACE).await;
+
+        // Expired and missing keys are not counted
+        let del = Request::Del {
+            keys: vec!["foo".to_string(), "baz".to_string(), "dne".to_string()],
+        };
+        assert_eq!(
+            executor.run(del, DEFAULT_NAMESPACE).await.body,
+            "(integer) 1"
+        );
+        let version = |key: &str| Request::Version {
+            key: key.to_string(),
+        };
+        assert_eq!(
+            executor.run(version("foo"), DEFAULT_NAMESPACE).await.body,
+            "(integer) 0"
+        );
+        let range = Request::KeyRange {
+            start: "a".to_string(),
+            end: "z".to_string(),
+            limit: None,
+        };
+        assert_eq!(executor.run(range, DEFAULT_NAMESPACE).await.body, "1) bar");
+    }
+
+    #[tokio::test]
+    async fn test_execute_rename() {
+        let config = Config::builder().ordered_keys(true).build().unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        for key in &["foo", "bar"] {
+            let set = Request::Set {
+                key: key.to_string(),
+                val: key.to_string(),
+            };
+            executor.run(set, DEFAULT_NAMESPACE).await;
+        }
+        let rename = |key: &str, newkey: &str| Request::Rename {
+            key: key.to_string(),
+            newkey: newkey.to_string(),
+        };
+        let renamenx = |key: &str, newkey: &str| Request::RenameNx {
+            key: key.to_string(),
+            newkey: newkey.to_string(),
+        };
+        assert_eq!(
+            executor
+                .run(renamenx("foo", "bar"), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "(integer) 0"
+        );
+        assert_eq!(
+            executor
+                .run(rename("foo", "bar"), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "OK"
+        );
+        assert_eq!(
+            executor
+                .run(rename("foo", "baz"), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "(error) Specified key does not exist"
+        );
+        assert_eq!(
+            executor
+                .run(renamenx("bar", "baz"), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "(integer) 1"
+        );
+        let get = Request::Get {
+            key: "baz".to_string(),
+        };
+        assert_eq!(executor.run(get, DEFAULT_NAMESPACE).await.body, "\"foo\"");
+        let range = Request::KeyRange {
+            start: "a".to_string(),
+            end: "z".to_string(),
+            limit: None,
+        };
+        assert_eq!(executor.run(range, DEFAULT_NAMESPACE).await.body, "1) baz");
+    }
+
+    #[tokio::test]
+    async fn test_execute_exists() {
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let set = Request::Set {
+            key: "foo".to_string(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        let sadd = Request::SAdd {
+            key: "baz".to_string(),
+            val: "item".to_string(),
+        };
+        executor.run(sadd, DEFAULT_NAMESPACE).await;
+        let exists = |keys: &[&str]| Request::Exists {
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        };
+        assert_eq!(
+            executor
+                .run(exists(&["foo", "baz", "dne", "foo"]), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "(integer) 3"
+        );
+
+        // Expired keys don't exist, even before they are removed
+        let expire = Request::Expire {
+            key: "foo".to_string(),
+            seconds: 0,
+        };
+        executor.run(expire, DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            executor
+                .run_read(exists(&["foo", "baz"]), DEFAULT_NAMESPACE)
+                .await
+                .body,
+            "(integer) 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_default_ttl() {
+        let config = Config::builder()
+            .default_ttl("sess:*", 3600)
+            .default_ttl("forever:*", u64::MAX)
+            .default_ttl("*", 60)
+            .build()
+            .unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        let ttl = |key: &str| Request::Ttl {
+            key: key.to_string(),
+        };
+        let hset = |key: &str| Request::HSet {
+            key: key.to_string(),
+            field: "user".to_string(),
+            val: "shoyo".to_string(),
+        };
+        executor.run(hset("sess:1"), DEFAULT_NAMESPACE).await;
+        executor.run(hset("cart:1"), DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            executor.run(ttl("sess:1"), DEFAULT_NAMESPACE).await.body,
+            "(integer) 3600"
+        );
+        assert_eq!(
+            executor.run(ttl("cart:1"), DEFAULT_NAMESPACE).await.body,
+            "(integer) 60"
+        );
+
+        // Writes to existing keys keep their time to live
+        let persist = Request::Persist {
+            key: "sess:1".to_string(),
+        };
+        executor.run(persist, DEFAULT_NAMESPACE).await;
+        executor.run(hset("sess:1"), DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            executor.run(ttl("sess:1"), DEFAULT_NAMESPACE).await.body,
+            "(integer) -1"
+        );
+
+        // Times to live too large to represent are clamped rather than wrapped
+        executor.run(hset("forever:1"), DEFAULT_NAMESPACE).await;
+        match executor.run(ttl("forever:1"), DEFAULT_NAMESPACE).await.body {
+            Reply::Integer(secs) => assert!(secs > 0),
+            body => panic!("Unexpected reply {}", body),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_ttl_jitter() {
+        let config = Config::builder()
+            .default_ttl("sess:*", 1000)
+            .ttl_jitter(10)
+            .build()
+            .unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        let mut ttls = Vec::new();
+        for i in 0..50 {
+            let key = format!("sess:{}", i);
+            let set = Request::Set {
+                key: key.clone(),
+                val: "bar".to_string(),
+            };
+            executor.run(set, DEFAULT_NAMESPACE).await;
+            let resp = executor.run(Request::Ttl { key }, DEFAULT_NAMESPACE).await;
+            let ttl: u64 = resp
+                .body
+                .to_string()
+                .trim_start_matches("(integer) ")
+                .parse()
+                .unwrap();
+            assert!((900..=1100).contains(&ttl));
+            ttls.push(ttl);
+        }
+        ttls.dedup();
+        assert!(ttls.len() > 1);
+
+        let expire = Request::Expire {
+            key: "foo".to_string(),
+            seconds: 1000,
+        };
+        let set = Request::Set {
+            key: "foo".to_string(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        executor.run(expire, DEFAULT_NAMESPACE).await;
+        let ttl = Request::Ttl {
+            key: "foo".to_string(),
+        };
+        let resp = executor.run(ttl, DEFAULT_NAMESPACE).await;
+        let ttl: u64 = resp
+            .body
+            .to_string()
+            .trim_start_matches("(integer) ")
+            .parse()
+            .unwrap();
+        assert!((900..=1100).contains(&ttl));
+    }
+
+    #[tokio::test]
+    async fn test_execute_expiry() {
+        let key = || "foo".to_string();
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let body = |resp: Response| resp.body;
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Ttl { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(integer) -2"
+        );
+        let set = Request::Set {
+            key: key(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Ttl { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(integer) -1"
+        );
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Persist { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(integer) 0"
+        );
+
+        let expire = |seconds| Request::Expire {
+            key: key(),
+            seconds,
+        };
+        assert_eq!(
+            body(executor.run(expire(100), DEFAULT_NAMESPACE).await),
+            "(integer) 1"
+        );
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Ttl { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(integer) 100"
+        );
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Persist { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(integer) 1"
+        );
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::PTtl { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(integer) -1"
+        );
+        let pexpire = Request::PExpire {
+            key: key(),
+            millis: 60_000,
+        };
+        assert_eq!(
+            body(executor.run(pexpire, DEFAULT_NAMESPACE).await),
+            "(integer) 1"
+        );
+        let resp = executor
+            .run(Request::PTtl { key: key() }, DEFAULT_NAMESPACE)
+            .await;
+        let pttl: i64 = resp
+            .body
+            .to_string()
+            .trim_start_matches("(integer) ")
+            .parse()
+            .unwrap();
+        assert!((59_000..=60_000).contains(&pttl));
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Ttl { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(integer) 60"
+        );
+
+        // Times to live too large to represent keep the key practically forever
+        assert_eq!(
+            body(executor.run(expire(u64::MAX), DEFAULT_NAMESPACE).await),
+            "(integer) 1"
+        );
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Get { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "\"bar\""
+        );
+        let pexpire = Request::PExpire {
+            key: key(),
+            millis: i64::MAX as u64 + 1,
+        };
+        assert_eq!(
+            body(executor.run(pexpire, DEFAULT_NAMESPACE).await),
+            "(integer) 1"
+        );
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Get { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "\"bar\""
+        );
+
+        // Expired keys are not visible to reads, and are removed when accessed
+        executor.run(expire(0), DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            body(
+                executor
+                    .run_read(Request::Get { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(nil)"
+        );
+        assert_eq!(
+            body(
+                executor
+                    .run(Request::Get { key: key() }, DEFAULT_NAMESPACE)
+                    .await
+            ),
+            "(nil)"
+        );
+        assert_eq!(
+            body(executor.run(expire(100), DEFAULT_NAMESPACE).await),
+            "(integer) 0"
+        );
+        assert_eq!(executor.stats.expired_keys, 1);
+
+        // Keys that are never accessed are purged
+        let limit = executor.config.purge_limit;
+        for i in 0..(limit + 1) {
+            let set = Request::Set {
+                key: i.to_string(),
+                val: "bar".to_string(),
+            };
+            executor.run(set, DEFAULT_NAMESPACE).await;
+            let expire = Request::Expire {
+                key: i.to_string(),
+                seconds: 0,
+            };
+            executor.run(expire, DEFAULT_NAMESPACE).await;
+        }
+        assert_eq!(executor.purge_expired(), limit);
+        assert_eq!(executor.purge_expired(), 1);
+        assert_eq!(executor.stats.expired_keys, limit + 2);
+
+        // Without a purge limit, expired keys are only removed when accessed
+        executor.config.purge_limit = 0;
+        let set = Request::Set {
+            key: key(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            body(executor.run(expire(0), DEFAULT_NAMESPACE).await),
+            "(integer) 1"
+        );
+        assert_eq!(executor.purge_expired(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_execute_take_expired() {
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let expire = |key: &str, namespace: &str| {
+            let set = Request::Set {
+                key: key.to_string(),
+                val: "bar".to_string(),
+            };
+            let expire = Request::Expire {
+                key: key.to_string(),
+                seconds: 0,
+            };
+            (set, expire, namespace.to_string())
+        };
+        let (set, exp, namespace) = expire("foo", DEFAULT_NAMESPACE);
+        executor.run(set, &namespace).await;
+        executor.run(exp, &namespace).await;
+        executor.purge_expired();
+        // Expired keys are only kept once tracked
+        assert!(executor.take_expired().is_empty());
+
+        executor.track_expired();
+        for (key, namespace) in &[("foo", DEFAULT_NAMESPACE), ("bar", "other")] {
+            let (set, exp, namespace) = expire(key, namespace);
+            executor.run(set, &namespace).await;
+            executor.run(exp, &namespace).await;
+        }
+        let get = Request::Get {
+            key: "foo".to_string(),
+        };
+        executor.run(get, DEFAULT_NAMESPACE).await;
+        executor.purge_expired();
+        assert_eq!(
+            executor.take_expired(),
+            vec![
+                (DEFAULT_NAMESPACE.to_string(), "foo".to_string()),
+                ("other".to_string(), "bar".to_string())
+            ]
+        );
+        assert!(executor.take_expired().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_expire_at() {
+        async fn run(executor: &mut Executor<StdStore>, req: Request) -> String {
+            executor.run(req, DEFAULT_NAMESPACE).await.body.to_string()
+        }
+        // Absolute expiry times are not jittered
+        let config = Config::builder().ttl_jitter(50).build().unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        let key = || "foo".to_string();
+        let expireat = |timestamp| Request::ExpireAt {
+            key: key(),
+            timestamp,
+        };
+        assert_eq!(
+            run(&mut executor, expireat(4102444800)).await,
+            "(integer) 0"
+        );
+        assert_eq!(
+            run(&mut executor, Request::ExpireTime { key: key() }).await,
+            "(integer) -2"
+        );
+        let set = Request::Set {
+            key: key(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            run(&mut executor, Request::ExpireTime { key: key() }).await,
+            "(integer) -1"
+        );
+        assert_eq!(
+            run(&mut executor, expireat(4102444800)).await,
+            "(integer) 1"
+        );
+        assert_eq!(
+            run(&mut executor, Request::ExpireTime { key: key() }).await,
+            "(integer) 4102444800"
+        );
+        assert_eq!(
+            run(&mut executor, Request::PExpireTime { key: key() }).await,
+            "(integer) 4102444800000"
+        );
+
+        // Times in the past expire the key
+        let pexpireat = Request::PExpireAt {
+            key: key(),
+            timestamp: 1,
+        };
+        assert_eq!(run(&mut executor, pexpireat).await, "(integer) 1");
+        assert_eq!(
+            run(&mut executor, Request::Get { key: key() }).await,
+            "(nil)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_field_expiry() {
+        async fn run(executor: &mut Executor<StdStore>, req: Request) -> String {
+            executor.run(req, DEFAULT_NAMESPACE).await.body.to_string()
+        }
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let s = |s: &str| s.to_string();
+        let httl = || Request::HTtl {
+            key: s("session"),
+            field: s("token"),
+        };
+        let hget = || Request::HGet {
+            key: s("session"),
+            field: s("token"),
+        };
+        let hexpire = |seconds| Request::HExpire {
+            key: s("session"),
+            field: s("token"),
+            seconds,
+        };
+        let hpersist = || Request::HPersist {
+            key: s("session"),
+            field: s("token"),
+        };
+        assert_eq!(run(&mut executor, httl()).await, "(integer) -2");
+        assert_eq!(run(&mut executor, hexpire(100)).await, "(integer) 0");
+        for (field, val) in [("token", "abc"), ("user", "shoyo")].iter() {
+            let hset = Request::HSet {
+                key: s("session"),
+                field: s(field),
+                val: s(val),
+            };
+            executor.run(hset, DEFAULT_NAMESPACE).await;
+        }
+        assert_eq!(run(&mut executor, httl()).await, "(integer) -1");
+        assert_eq!(run(&mut executor, hpersist()).await, "(integer) 0");
+        assert_eq!(run(&mut executor, hexpire(100)).await, "(integer) 1");
+        assert_eq!(run(&mut executor, httl()).await, "(integer) 100");
+        assert_eq!(run(&mut executor, hpersist()).await, "(integer) 1");
+        assert_eq!(run(&mut executor, httl()).await, "(integer) -1");
+        let hpexpire = Request::HPExpire {
+            key: s("session"),
+            field: s("token"),
+            millis: 1500,
+        };
+        assert_eq!(run(&mut executor, hpexpire).await, "(integer) 1");
+        assert_eq!(run(&mut executor, httl()).await, "(integer) 2");
+
+        // Times to live too large to represent keep the field practically forever
+        assert_eq!(run(&mut executor, hexpire(u64::MAX)).await, "(integer) 1");
+        assert_eq!(run(&mut executor, hget()).await, "\"abc\"");
+        let hpexpire = Request::HPExpire {
+            key: s("session"),
+            field: s("token"),
+            millis: u64::MAX,
+        };
+        assert_eq!(run(&mut executor, hpexpire).await, "(integer) 1");
+        assert_eq!(run(&mut executor, hget()).await, "\"abc\"");
+
+        // Expired fields are not visible to reads, and are removed when accessed,
+        // leaving the other fields of the hash
+        assert_eq!(run(&mut executor, hexpire(0)).await, "(integer) 1");
+        assert_eq!(
+            executor.run_read(hget(), DEFAULT_NAMESPACE).await.body,
+            "(nil)"
+        );
+        assert_eq!(executor.stats.expired_fields, 0);
+        assert_eq!(run(&mut executor, hget()).await, "(nil)");
+        assert_eq!(executor.stats.expired_fields, 1);
+        let user = Request::HGet {
+            key: s("session"),
+            field: s("user"),
+        };
+        assert_eq!(run(&mut executor, user).await, "\"shoyo\"");
+
+        // Fields that are never accessed are purged
+        let expire_user = Request::HExpire {
+            key: s("session"),
+            field: s("user"),
+            seconds: 0,
+        };
+        executor.run(expire_user, DEFAULT_NAMESPACE).await;
+        executor.purge_expired();
+        assert_eq!(executor.stats.expired_fields, 2);
+        assert!(executor
+            .info(Some("stats"))
+            .body
+            .to_string()
+            .contains("expired_fields:2"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_versions() {
+        async fn run(executor: &mut Executor<StdStore>, req: Request) -> String {
+            executor.run(req, DEFAULT_NAMESPACE).await.body.to_string()
+        }
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let s = |s: &str| s.to_string();
+        let version = || Request::Version { key: s("foo") };
+        let set = |val: &str, version| Request::SetIfVersion {
+            key: s("foo"),
+            val: s(val),
+            version,
+        };
+        assert_eq!(run(&mut executor, version()).await, "(integer) 0");
+        assert_eq!(
+            run(&mut executor, set("bar", 1)).await,
+            "(error) Version mismatch"
+        );
+        assert_eq!(run(&mut executor, set("bar", 0)).await, "OK");
+        let first = run(&mut executor, version()).await;
+        assert_ne!(first, "(integer) 0");
+        assert_eq!(
+            executor.run_read(version(), DEFAULT_NAMESPACE).await.body,
+            first
+        );
+
+        // Any write to the key changes its version
+        let incr = Request::Incr { key: s("foo") };
+        assert!(run(&mut executor, incr).await.starts_with("(error)"));
+        assert_eq!(run(&mut executor, version()).await, first);
+        let plain = Request::Set {
+            key: s("foo"),
+            val: s("baz"),
+        };
+        assert_eq!(run(&mut executor, plain).await, "OK");
+        let second = run(&mut executor, version()).await;
+        assert_ne!(second, first);
+        let stale: u64 = first["(integer) ".len()..].parse().unwrap();
+        assert_eq!(
+            run(&mut executor, set("qux", stale)).await,
+            "(error) Version mismatch"
+        );
+        let current: u64 = second["(integer) ".len()..].parse().unwrap();
+        assert_eq!(run(&mut executor, set("qux", current)).await, "OK");
+        let get = Request::Get { key: s("foo") };
+        assert_eq!(run(&mut executor, get).await, "\"qux\"");
+
+        // Writes that change nothing keep the version
+        let third = run(&mut executor, version()).await;
+        let persist = Request::Persist { key: s("foo") };
+        assert_eq!(run(&mut executor, persist).await, "(integer) 0");
+        let nx = Request::SetWith {
+            key: s("foo"),
+            val: s("quux"),
+            condition: Some(SetCondition::Absent),
+            expiry: SetExpiry::Discard,
+        };
+        assert_eq!(run(&mut executor, nx).await, "(nil)");
+        assert_eq!(run(&mut executor, version()).await, third);
+        let sadd = Request::SAdd {
+            key: s("tags"),
+            val: s("red"),
+        };
+        run(&mut executor, sadd).await;
+        let tags = Request::Version { key: s("tags") };
+        let before = run(&mut executor, tags).await;
+        let srem = Request::SRem {
+            key: s("tags"),
+            val: s("blue"),
+        };
+        run(&mut executor, srem).await;
+        let tags = Request::Version { key: s("tags") };
+        assert_eq!(run(&mut executor, tags).await, before);
+
+        // Expired keys no longer have a version
+        let expire = Request::Expire {
+            key: s("foo"),
+            seconds: 0,
+        };
+        run(&mut executor, expire).await;
+        assert_eq!(run(&mut executor, version()).await, "(integer) 0");
+
+        // Versions are not tracked outside of an executor
+        let mut store: StdStore = StdStore::new();
+        assert_eq!(
+            execute(version(), &mut store).await.body,
+            "(error) Versions are unavailable"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_quickfill() {
+        let config = Config::builder()
+            .ordered_keys(true)
+            .quickfill(true)
+            .build()
+            .unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        let quickfill = |count| Request::DebugQuickfill {
+            fill: Fill {
+                count,
+                types: vec![(FillType::List, 1), (FillType::Set, 1)],
+                ..Fill::default()
+            },
+        };
+        let set = Request::Set {
+            key: "key:0".to_string(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        assert_eq!(
+            executor.run(quickfill(10), DEFAULT_NAMESPACE).await.body,
+            "(integer) 9"
+        );
+        assert_eq!(
+            executor.run(quickfill(20), DEFAULT_NAMESPACE).await.body,
+            "(integer) 10"
+        );
+        assert!(executor
+            .info(Some("keyspace"))
+            .body
+            .to_string()
+            .contains("default:keys=20,"));
+
+        // Created keys are tracked like other writes
+        let range = Request::KeyRange {
+            start: "key:1".to_string(),
+            end: "key:10".to_string(),
+            limit: None,
+        };
+        assert_eq!(
+            executor.run(range, DEFAULT_NAMESPACE).await.body,
+            "1) key:1\n2) key:10"
+        );
+        let version = Request::Version {
+            key: "key:5".to_string(),
+        };
+        assert_ne!(
+            executor.run(version, DEFAULT_NAMESPACE).await.body,
+            "(integer) 1"
+        );
+
+        // Quickfill is disabled unless enabled by the config
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        assert_eq!(
+            executor.run(quickfill(10), DEFAULT_NAMESPACE).await.body,
+            "(error) Quickfill is disabled"
+        );
+
+        // Generated keys are subject to the quotas and limits
+        let config = Config::builder()
+            .quickfill(true)
+            .namespace_max_keys(15)
+            .max_value_size(64)
+            .max_container_elements(8)
+            .build()
+            .unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        assert_eq!(
+            executor.run(quickfill(20), DEFAULT_NAMESPACE).await.body,
+            "(integer) 15"
+        );
+        let oversized = |size, elements| Request::DebugQuickfill {
+            fill: Fill {
+                count: 10,
+                prefix: "big:".to_string(),
+                types: vec![(FillType::Hash, 1)],
+                size,
+                elements,
+                ..Fill::default()
+            },
+        };
+        assert_eq!(
+            executor.run(oversized((16, 128), 4), "other").await.body,
+            "(error) Value exceeds maximum size of 64 bytes"
+        );
+        assert_eq!(
+            executor.run(oversized((16, 16), 16), "other").await.body,
+            "(error) Container exceeds maximum of 8 elements"
+        );
+        let config = Config::builder()
+            .quickfill(true)
+            .namespace_max_memory(500)
+            .build()
+            .unwrap();
+        let mut executor: Executor<StdStore> = Executor::new(config);
+        executor
+            .run(oversized((16, 16), 4), DEFAULT_NAMESPACE)
+            .await;
+        let usage = executor.stores[DEFAULT_NAMESPACE].memory_usage().unwrap();
+        assert!((500..600).contains(&usage));
+    }
+
+    #[tokio::test]
+    async fn test_execute_getdel() {
+        let mut executor: Executor<StdStore> = Executor::new(parse_config(None));
+        let set = Request::Set {
+            key: "foo".to_string(),
+            val: "bar".to_string(),
+        };
+        executor.run(set, DEFAULT_NAMESPACE).await;
+        let getdel = || Request::GetDel {
+            key: "foo".to_string(),
+        };
+        assert_eq!(
+            executor.run(getdel(), DEFAULT_NAMESPACE).await.body,
+            "\"bar\""
+        );
+        assert_eq!(
+            executor.run(getdel(), DEFAULT_NAMESPACE).await.body,
+            "(nil)"
+        );
+        let exists = Request::Exists {
+            keys: vec!["foo".to_string()],
+        };
+        assert_eq!(
+            executor.run(exists, DEFAULT_NAMESPACE).await.body,
+            "(integer) 0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_strings() {
+        let mut store: StdStore = Store::new();
+
+        // SET AND GET
         assert_eq!(
             execute(
                 Request::Set {
@@ -253,10 +3451,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "OK".to_string()
-            }
+            .await
+            .body,
+            "OK"
         );
         assert_eq!(
             execute(
@@ -265,10 +3462,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "\"bar\"".to_string()
-            }
+            .await
+            .body,
+            "\"bar\""
         );
         assert_eq!(
             execute(
@@ -277,10 +3473,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(nil)".to_string()
-            }
+            .await
+            .body,
+            "(nil)"
         );
 
         // INCR, DECR, INCRBY, DECRBY
@@ -293,72 +3488,200 @@ mod tests {
             )
             .await,
             Response {
-                body: "(error) Value stored at key cannot be represented as a 64-bit integer"
-                    .to_string()
+                body: f_err(
+                    "Value stored at key cannot be represented as a 64-bit integer".to_string()
+                )
             }
         );
         assert_eq!(
             execute(
-                Request::Incr {
-                    key: "baz".to_string()
+                Request::Incr {
+                    key: "baz".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(error) Specified key does not exist"
+        );
+        let _ = store.set("cnt".to_string(), 1.to_string());
+        assert_eq!(
+            execute(
+                Request::Incr {
+                    key: "cnt".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 2"
+        );
+        assert_eq!(
+            execute(
+                Request::Decr {
+                    key: "cnt".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::IncrBy {
+                    key: "cnt".to_string(),
+                    delta: 10
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 11"
+        );
+        assert_eq!(
+            execute(
+                Request::DecrBy {
+                    key: "cnt".to_string(),
+                    delta: 20
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) -9"
+        );
+
+        // INCRBYFLOAT, HINCRBYFLOAT
+        let _ = store.set("float".to_string(), "1.25".to_string());
+        let incrbyfloat = Request::IncrByFloat {
+            key: "float".to_string(),
+            delta: 0.5,
+        };
+        assert_eq!(execute(incrbyfloat, &mut store).await.body, "\"1.75\"");
+        let _ = store.hset("item".to_string(), "price".to_string(), "abc".to_string());
+        let hincrbyfloat = Request::HIncrByFloat {
+            key: "item".to_string(),
+            field: "price".to_string(),
+            delta: 1.0,
+        };
+        assert_eq!(
+            execute(hincrbyfloat, &mut store).await.body,
+            "(error) Value cannot be represented as a finite number"
+        );
+
+        // APPEND, GETRANGE, STRLEN
+        let append = |val: &str| Request::Append {
+            key: "greeting".to_string(),
+            val: val.to_string(),
+        };
+        assert_eq!(
+            execute(append("Hello"), &mut store).await.body,
+            "(integer) 5"
+        );
+        assert_eq!(
+            execute(append(" World"), &mut store).await.body,
+            "(integer) 11"
+        );
+        let getrange = Request::GetRange {
+            key: "greeting".to_string(),
+            start: -5,
+            end: -1,
+        };
+        assert_eq!(execute(getrange, &mut store).await.body, "\"World\"");
+        let setrange = Request::SetRange {
+            key: "greeting".to_string(),
+            offset: 6,
+            val: "Kiba!".to_string(),
+        };
+        assert_eq!(execute(setrange, &mut store).await.body, "(integer) 11");
+        assert_eq!(
+            execute(
+                Request::Get {
+                    key: "greeting".to_string()
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(error) Specified key does not exist".to_string()
-            }
+            .await
+            .body,
+            "\"Hello Kiba!\""
         );
-        let _ = store.set("cnt".to_string(), 1.to_string());
+        let strlen = |key: &str| Request::StrLen {
+            key: key.to_string(),
+        };
+        assert_eq!(
+            execute(strlen("greeting"), &mut store).await.body,
+            "(integer) 11"
+        );
+        assert_eq!(execute(strlen("dne"), &mut store).await.body, "(integer) 0");
+    }
+
+    #[tokio::test]
+    async fn test_execute_stralgo() {
+        let mut store: StdStore = Store::new();
+        let _ = store.set("a".to_string(), "ohmytext".to_string());
+        let _ = store.set("b".to_string(), "mynewtext".to_string());
+
+        // STRALGO LCS
         assert_eq!(
             execute(
-                Request::Incr {
-                    key: "cnt".to_string()
+                Request::StrAlgoLcs {
+                    input: LcsInput::Keys("a".to_string(), "b".to_string()),
+                    len: false,
+                    idx: false,
+                    min_match_len: 0,
+                    with_match_len: false,
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 2".to_string()
-            }
+            .await
+            .body,
+            "\"mytext\""
         );
         assert_eq!(
             execute(
-                Request::Decr {
-                    key: "cnt".to_string()
+                Request::StrAlgoLcs {
+                    input: LcsInput::Keys("a".to_string(), "missing".to_string()),
+                    len: true,
+                    idx: false,
+                    min_match_len: 0,
+                    with_match_len: false,
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 1".to_string()
-            }
+            .await
+            .body,
+            "(integer) 0"
         );
         assert_eq!(
             execute(
-                Request::IncrBy {
-                    key: "cnt".to_string(),
-                    delta: 10
+                Request::StrAlgoLcs {
+                    input: LcsInput::Strings("ohmytext".to_string(), "mynewtext".to_string()),
+                    len: false,
+                    idx: true,
+                    min_match_len: 0,
+                    with_match_len: false,
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 11".to_string()
-            }
+            .await
+            .body,
+            "1) 4-7 5-8\n2) 2-3 0-1\n3) len 6"
         );
         assert_eq!(
             execute(
-                Request::DecrBy {
-                    key: "cnt".to_string(),
-                    delta: 20
+                Request::StrAlgoLcs {
+                    input: LcsInput::Strings("ohmytext".to_string(), "mynewtext".to_string()),
+                    len: false,
+                    idx: true,
+                    min_match_len: 4,
+                    with_match_len: true,
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) -9".to_string()
-            }
+            .await
+            .body,
+            "1) 4-7 5-8 (length 4)\n2) len 6"
         );
     }
 
@@ -375,10 +3698,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 1".to_string()
-            }
+            .await
+            .body,
+            "(integer) 1"
         );
         assert_eq!(
             execute(
@@ -388,10 +3710,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 2".to_string()
-            }
+            .await
+            .body,
+            "(integer) 2"
         );
         assert_eq!(
             execute(
@@ -400,10 +3721,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "\"b\"".to_string()
-            }
+            .await
+            .body,
+            "\"b\""
         );
         assert_eq!(
             execute(
@@ -412,10 +3732,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "\"a\"".to_string()
-            }
+            .await
+            .body,
+            "\"a\""
         );
         assert_eq!(
             execute(
@@ -424,14 +3743,14 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(nil)".to_string()
-            }
+            .await
+            .body,
+            "(nil)"
         );
     }
 
     #[tokio::test]
+    #[allow(clippy::cmp_owned)]
     async fn test_execute_sets() {
         let mut store: StdStore = Store::new();
 
@@ -444,10 +3763,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 0".to_string()
-            }
+            .await
+            .body,
+            "(integer) 0"
         );
         assert_eq!(
             execute(
@@ -457,10 +3775,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 1".to_string()
-            }
+            .await
+            .body,
+            "(integer) 1"
         );
         assert_eq!(
             execute(
@@ -470,10 +3787,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 2".to_string()
-            }
+            .await
+            .body,
+            "(integer) 2"
         );
         assert_eq!(
             execute(
@@ -483,10 +3799,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 1".to_string()
-            }
+            .await
+            .body,
+            "(integer) 1"
         );
         assert_eq!(
             execute(
@@ -496,10 +3811,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 0".to_string()
-            }
+            .await
+            .body,
+            "(integer) 0"
         );
         let response = execute(
             Request::SMembers {
@@ -528,10 +3842,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(nil)".to_string()
-            }
+            .await
+            .body,
+            "(nil)"
         );
         assert_eq!(
             execute(
@@ -542,10 +3855,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 1".to_string()
-            }
+            .await
+            .body,
+            "(integer) 1"
         );
         assert_eq!(
             execute(
@@ -556,10 +3868,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 0".to_string()
-            }
+            .await
+            .body,
+            "(integer) 0"
         );
         assert_eq!(
             execute(
@@ -569,10 +3880,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "\"John Smith\"".to_string()
-            }
+            .await
+            .body,
+            "\"John Smith\""
         );
         assert_eq!(
             execute(
@@ -582,10 +3892,9 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 0".to_string()
-            }
+            .await
+            .body,
+            "(integer) 0"
         );
         assert_eq!(
             execute(
@@ -595,10 +3904,380 @@ mod tests {
                 },
                 &mut store
             )
-            .await,
-            Response {
-                body: "(integer) 1".to_string()
-            }
+            .await
+            .body,
+            "(integer) 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_blooms() {
+        let mut store: StdStore = Store::new();
+
+        // Bloom filter operations
+        assert_eq!(
+            execute(
+                Request::BFReserve {
+                    key: "seen".to_string(),
+                    error_rate: 0.01,
+                    capacity: 1000,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "OK"
+        );
+        assert_eq!(
+            execute(
+                Request::BFReserve {
+                    key: "seen".to_string(),
+                    error_rate: 0.01,
+                    capacity: 1000,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(error) Specified key already exists"
+        );
+        assert_eq!(
+            execute(
+                Request::BFAdd {
+                    key: "seen".to_string(),
+                    item: "a".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::BFMAdd {
+                    key: "seen".to_string(),
+                    items: vec!["a".to_string(), "b".to_string()]
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "1) (integer) 0\n2) (integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::BFExists {
+                    key: "seen".to_string(),
+                    item: "b".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::BFExists {
+                    key: "unseen".to_string(),
+                    item: "b".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_cuckoos() {
+        let mut store: StdStore = Store::new();
+
+        // Cuckoo filter operations
+        assert_eq!(
+            execute(
+                Request::CFAdd {
+                    key: "seen".to_string(),
+                    item: "a".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::CFExists {
+                    key: "seen".to_string(),
+                    item: "a".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::CFDel {
+                    key: "seen".to_string(),
+                    item: "a".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::CFDel {
+                    key: "seen".to_string(),
+                    item: "a".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 0"
+        );
+        assert_eq!(
+            execute(
+                Request::CFExists {
+                    key: "seen".to_string(),
+                    item: "a".to_string()
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_timeseries() {
+        let mut store: StdStore = Store::new();
+
+        // Time series operations
+        assert_eq!(
+            execute(
+                Request::TSCreate {
+                    key: "temp".to_string(),
+                    retention: 0,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "OK"
+        );
+        for (ts, val) in [(1000, 20.0), (1500, 22.0), (2000, 21.5)].iter() {
+            assert_eq!(
+                execute(
+                    Request::TSAdd {
+                        key: "temp".to_string(),
+                        timestamp: Some(*ts),
+                        value: *val,
+                    },
+                    &mut store
+                )
+                .await,
+                Response { body: f_int(*ts) }
+            );
+        }
+        assert_eq!(
+            execute(
+                Request::TSRange {
+                    key: "temp".to_string(),
+                    from: i64::MIN,
+                    to: i64::MAX,
+                    aggregation: None,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "1) 1000 20\n2) 1500 22\n3) 2000 21.5"
+        );
+        assert_eq!(
+            execute(
+                Request::TSRange {
+                    key: "temp".to_string(),
+                    from: 0,
+                    to: 5000,
+                    aggregation: Some((Aggregation::Max, 1000)),
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "1) 1000 22\n2) 2000 21.5"
+        );
+        assert_eq!(
+            execute(
+                Request::TSRange {
+                    key: "humidity".to_string(),
+                    from: 0,
+                    to: 5000,
+                    aggregation: None,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(error) Specified key does not exist"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_vectors() {
+        let mut store: StdStore = Store::new();
+
+        // Vector operations
+        assert_eq!(
+            execute(
+                Request::VAdd {
+                    key: "points".to_string(),
+                    element: "a".to_string(),
+                    vector: vec![0.0, 0.0],
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::VAdd {
+                    key: "points".to_string(),
+                    element: "b".to_string(),
+                    vector: vec![3.0, 4.0],
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(integer) 1"
+        );
+        assert_eq!(
+            execute(
+                Request::VAdd {
+                    key: "points".to_string(),
+                    element: "c".to_string(),
+                    vector: vec![1.0],
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(error) Vector dimension mismatch. Expected 2, got 1"
+        );
+        assert_eq!(
+            execute(
+                Request::VSim {
+                    key: "points".to_string(),
+                    query: vec![3.0, 0.0],
+                    metric: Metric::Euclidean,
+                    count: 10,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "1) a 3\n2) b 4"
+        );
+        assert_eq!(
+            execute(
+                Request::VSim {
+                    key: "nowhere".to_string(),
+                    query: vec![3.0, 0.0],
+                    metric: Metric::Cosine,
+                    count: 10,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(empty list or set)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_search() {
+        let mut store: StdStore = Store::new();
+        let _ = store.hset("user:1".to_string(), "age".to_string(), "30".to_string());
+        let _ = store.hset("user:2".to_string(), "age".to_string(), "42".to_string());
+
+        // Search operations
+        assert_eq!(
+            execute(
+                Request::FTCreate {
+                    index: "users".to_string(),
+                    prefix: "user:".to_string(),
+                    fields: vec!["age".to_string()],
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "OK"
+        );
+        assert_eq!(
+            execute(
+                Request::FTSearch {
+                    index: "users".to_string(),
+                    filters: vec![Filter::Range {
+                        field: "age".to_string(),
+                        min: 0.0,
+                        max: 100.0
+                    }],
+                    offset: 1,
+                    count: 10,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "1) user:2"
+        );
+        assert_eq!(
+            execute(
+                Request::FTSearch {
+                    index: "users".to_string(),
+                    filters: vec![Filter::Eq {
+                        field: "age".to_string(),
+                        value: "99".to_string()
+                    }],
+                    offset: 0,
+                    count: 10,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(empty list or set)"
+        );
+        assert_eq!(
+            execute(
+                Request::FTSearch {
+                    index: "items".to_string(),
+                    filters: vec![Filter::Eq {
+                        field: "age".to_string(),
+                        value: "99".to_string()
+                    }],
+                    offset: 0,
+                    count: 10,
+                },
+                &mut store
+            )
+            .await
+            .body,
+            "(error) Specified index does not exist"
         );
     }
 }
@@ -0,0 +1,261 @@
+use crate::rng::Rng;
+use crate::store::ExpiringStore;
+
+/// Maximum number of keys created by a single DEBUG QUICKFILL
+pub const MAX_COUNT: u64 = 10_000_000;
+
+/// Maximum size in bytes of the generated strings and elements
+pub const MAX_SIZE: u64 = 1024 * 1024;
+
+/// Maximum number of elements in each generated list, set or hash
+pub const MAX_ELEMENTS: u64 = 100_000;
+
+/// Maximum number of bytes of values a single DEBUG QUICKFILL may generate
+pub const MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Type of the values created by DEBUG QUICKFILL
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FillType {
+    String,
+    List,
+    Set,
+    Hash,
+}
+
+impl FillType {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "string" => Some(FillType::String),
+            "list" => Some(FillType::List),
+            "set" => Some(FillType::Set),
+            "hash" => Some(FillType::Hash),
+            _ => None,
+        }
+    }
+}
+
+/// A synthetic dataset requested with DEBUG QUICKFILL, used to test the
+/// server's capacity without writing custom load scripts.
+///
+/// Datasets are generated from a seeded generator, so the same parameters
+/// always produce the same keys and values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fill {
+    /// Number of keys, named by the prefix followed by their index
+    pub count: u64,
+    pub prefix: String,
+
+    /// Types of the created values, with their relative weights
+    pub types: Vec<(FillType, u64)>,
+
+    /// Minimum and maximum size in bytes of strings and of the elements of
+    /// lists and hashes. Set members are also prefixed with their index.
+    pub size: (u64, u64),
+
+    /// Number of elements in each list, set or hash
+    pub elements: u64,
+
+    /// Minimum and maximum time to live in seconds, if keys expire
+    pub ttl: Option<(u64, u64)>,
+
+    /// Probability that a key is given a time to live
+    pub expiring: f64,
+
+    pub seed: u64,
+}
+
+impl Default for Fill {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            prefix: "key:".to_string(),
+            types: vec![(FillType::String, 1)],
+            size: (16, 16),
+            elements: 4,
+            ttl: None,
+            expiring: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+impl Fill {
+    /// Return if the dataset includes lists, sets or hashes.
+    pub fn has_containers(&self) -> bool {
+        self.types.iter().any(|(kind, _)| *kind != FillType::String)
+    }
+
+    /// Return the size in bytes of the largest string or element the dataset
+    /// may contain.
+    pub fn max_value_size(&self) -> u64 {
+        let (_, max) = self.size;
+        match self.types.iter().any(|(kind, _)| *kind == FillType::Set) {
+            // Set members are prefixed with their index
+            true => max + format!("{}:", self.elements - 1).len() as u64,
+            false => max,
+        }
+    }
+
+    /// Return the largest number of bytes of values the dataset may contain.
+    pub fn max_bytes(&self) -> u64 {
+        let per_key = match self.has_containers() {
+            true => self.max_value_size().saturating_mul(self.elements),
+            false => self.max_value_size(),
+        };
+        per_key.saturating_mul(self.count)
+    }
+
+    /// Create the keys of the dataset that do not already exist, stopping
+    /// early once `full` returns true for the store. Expiry times are relative
+    /// to `now` in milliseconds since the Unix epoch.
+    /// Return the keys that were created.
+    pub fn apply<S: ExpiringStore>(
+        &self,
+        store: &mut S,
+        now: i64,
+        full: impl Fn(&S) -> bool,
+    ) -> Vec<String> {
+        let mut seeds = Rng::new(self.seed);
+        let mut created = Vec::new();
+        for i in 0..self.count {
+            if full(store) {
+                break;
+            }
+            // Each key has its own generator, so that its value does not
+            // depend on which other keys already existed
+            let mut rng = Rng::new(seeds.next_u64());
+            let key = format!("{}{}", self.prefix, i);
+            if store.exists(key.clone()).unwrap() {
+                continue;
+            }
+            match self.pick_type(&mut rng) {
+                FillType::String => {
+                    store.set(key.clone(), self.value(&mut rng)).unwrap();
+                }
+                FillType::List => {
+                    for _ in 0..self.elements {
+                        store.rpush(key.clone(), self.value(&mut rng)).unwrap();
+                    }
+                }
+                FillType::Set => {
+                    for j in 0..self.elements {
+                        let member = format!("{}:{}", j, self.value(&mut rng));
+                        store.sadd(key.clone(), member).unwrap();
+                    }
+                }
+                FillType::Hash => {
+                    for j in 0..self.elements {
+                        let field = format!("field:{}", j);
+                        store
+                            .hset(key.clone(), field, self.value(&mut rng))
+                            .unwrap();
+                    }
+                }
+            }
+            if let Some((min, max)) = self.ttl {
+                if rng.next_f64() < self.expiring {
+                    let secs = min + rng.below(max - min + 1);
+                    let millis = secs.saturating_mul(1000).min(i64::MAX as u64);
+                    let at = now.saturating_add(millis as i64);
+                    store.set_expiry(key.clone(), Some(at)).unwrap();
+                }
+            }
+            created.push(key);
+        }
+        created
+    }
+
+    /// Pick a type at random according to the weights of the types.
+    fn pick_type(&self, rng: &mut Rng) -> FillType {
+        let total: u64 = self.types.iter().map(|(_, weight)| weight).sum();
+        let mut pick = rng.below(total);
+        for (kind, weight) in self.types.iter() {
+            if pick < *weight {
+                return *kind;
+            }
+            pick -= weight;
+        }
+        FillType::String
+    }
+
+    /// Generate a value of lowercase letters with a size in the size range.
+    fn value(&self, rng: &mut Rng) -> String {
+        let (min, max) = self.size;
+        let len = min + rng.below(max - min + 1);
+        (0..len)
+            .map(|_| (b'a' + rng.below(26) as u8) as char)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::unix_millis;
+    use crate::store::{StdStore, Store};
+
+    #[test]
+    fn test_fill() {
+        let fill = Fill {
+            count: 100,
+            types: vec![(FillType::String, 1), (FillType::Hash, 1)],
+            size: (8, 32),
+            ttl: Some((60, 120)),
+            expiring: 0.5,
+            ..Fill::default()
+        };
+        let mut store: StdStore = StdStore::new();
+        store.set("key:0".to_string(), "taken".to_string()).unwrap();
+        let created = fill.apply(&mut store, unix_millis(), |_| false);
+        assert_eq!(created.len(), 99);
+        assert_eq!(store.dbsize().unwrap(), 100);
+        assert_eq!(store.get("key:0".to_string()).unwrap().unwrap(), "taken");
+
+        let counts = store.type_counts().unwrap();
+        let count = |kind| counts.iter().find(|(k, _)| *k == kind).unwrap().1;
+        assert!(count("string") > 25 && count("hash") > 25);
+        for key in created.iter() {
+            if let Some(val) = store.get(key.clone()).unwrap() {
+                assert!(val.len() >= 8 && val.len() <= 32);
+            }
+        }
+        let expiring = created
+            .iter()
+            .filter(|key| store.get_expiry(key.to_string()).unwrap().is_some())
+            .count();
+        assert!(expiring > 25 && expiring < 75);
+
+        // The same parameters produce the same dataset
+        let mut copy: StdStore = StdStore::new();
+        fill.apply(&mut copy, unix_millis(), |_| false);
+        assert_eq!(
+            store.get("key:1".to_string()).unwrap(),
+            copy.get("key:1".to_string()).unwrap()
+        );
+
+        // Keys stop being created once the store is full
+        let mut store: StdStore = StdStore::new();
+        let created = fill.apply(&mut store, unix_millis(), |store| {
+            store.dbsize().unwrap() >= 10
+        });
+        assert_eq!(created.len(), 10);
+    }
+
+    #[test]
+    fn test_fill_sizes() {
+        let fill = Fill {
+            count: 1000,
+            types: vec![(FillType::String, 1), (FillType::Set, 1)],
+            size: (8, 32),
+            elements: 100,
+            ..Fill::default()
+        };
+        assert_eq!(fill.max_value_size(), 35);
+        assert_eq!(fill.max_bytes(), 3_500_000);
+        let fill = Fill {
+            count: u64::MAX,
+            ..Fill::default()
+        };
+        assert_eq!(fill.max_bytes(), u64::MAX);
+    }
+}
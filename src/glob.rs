@@ -0,0 +1,108 @@
+/// Return if a string matches a glob-style pattern.
+///
+/// Supported syntax:
+/// - `*` matches any sequence of characters, including none
+/// - `?` matches any single character
+/// - `[abc]` matches one of the listed characters, `[a-z]` a range of
+///   characters, and `[^abc]` any character not listed
+/// - `\` escapes the following character
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+    matches(&p, &s)
+}
+
+fn matches(p: &[char], s: &[char]) -> bool {
+    let (mut pi, mut si) = (0, 0);
+    // Position of the last `*` in the pattern and the string position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while si < s.len() {
+        let step = match p.get(pi) {
+            Some('*') => {
+                star = Some((pi, si));
+                pi += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match_class(&p[pi..], s[si]),
+            Some('\\') if pi + 1 < p.len() => match p[pi + 1] == s[si] {
+                true => Some(2),
+                false => None,
+            },
+            Some(&c) if c == s[si] => Some(1),
+            _ => None,
+        };
+        match (step, star) {
+            (Some(len), _) => {
+                pi += len;
+                si += 1;
+            }
+            // Backtrack, letting the last `*` consume one more character
+            (None, Some((spi, ssi))) => {
+                pi = spi + 1;
+                si = ssi + 1;
+                star = Some((spi, ssi + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Match a character against a class at the start of the pattern.
+/// Return the length of the class in the pattern if the character matches.
+fn match_class(p: &[char], c: char) -> Option<usize> {
+    let mut i = 1;
+    let negate = p.get(i) == Some(&'^');
+    if negate {
+        i += 1;
+    }
+    let mut found = false;
+    while i < p.len() && p[i] != ']' {
+        if p[i] == '\\' && i + 1 < p.len() {
+            i += 1;
+        }
+        if i + 2 < p.len() && p[i + 1] == '-' && p[i + 2] != ']' {
+            let (lo, hi) = match p[i] <= p[i + 2] {
+                true => (p[i], p[i + 2]),
+                false => (p[i + 2], p[i]),
+            };
+            found |= lo <= c && c <= hi;
+            i += 3;
+        } else {
+            found |= p[i] == c;
+            i += 1;
+        }
+    }
+    // An unterminated class matches nothing
+    if i >= p.len() || found == negate {
+        return None;
+    }
+    Some(i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("user:*", "user:1000"));
+        assert!(!glob_match("user:*", "order:1"));
+        assert!(glob_match("*:1*0", "user:1000"));
+        assert!(glob_match("h?llo", "hello"));
+        assert!(!glob_match("h?llo", "hllo"));
+        assert!(glob_match("h[ae]llo", "hallo"));
+        assert!(!glob_match("h[ae]llo", "hillo"));
+        assert!(glob_match("h[^e]llo", "hallo"));
+        assert!(!glob_match("h[^e]llo", "hello"));
+        assert!(glob_match("h[a-c]llo", "hbllo"));
+        assert!(!glob_match("h[a-c]llo", "hdllo"));
+        assert!(glob_match("h\\*llo", "h*llo"));
+        assert!(!glob_match("h\\*llo", "hello"));
+        assert!(!glob_match("h[ello", "hello"));
+        assert!(glob_match("キー*", "キー1"));
+    }
+}
@@ -0,0 +1,91 @@
+use log::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::prelude::*;
+
+/// Status code and body of an HTTP response
+type Reply = (u16, &'static str);
+
+/// Return the reply to an HTTP request line (e.g. "GET /healthz HTTP/1.1").
+///
+/// `/healthz` reports liveness and succeeds as long as the server responds.
+/// `/readyz` succeeds only once the server is accepting client connections.
+fn route(request_line: &str, ready: bool) -> Reply {
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    if method != Some("GET") {
+        return (405, "method not allowed");
+    }
+    match path {
+        Some("/healthz") => (200, "ok"),
+        Some("/readyz") => match ready {
+            true => (200, "ready"),
+            false => (503, "not ready"),
+        },
+        _ => (404, "not found"),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    }
+}
+
+async fn respond(mut socket: TcpStream, ready: bool) {
+    let mut buf = [0; 1024];
+    let n = match socket.read(&mut buf[..]).await {
+        Ok(n) => n,
+        Err(_) => return,
+    };
+    let text = String::from_utf8_lossy(&buf[..n]);
+    let (status, body) = route(text.lines().next().unwrap_or_default(), ready);
+    let resp = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    );
+    let _ = socket.write_all(resp.as_bytes()).await;
+}
+
+/// Serve health and readiness endpoints over HTTP at the bind address.
+/// The server is reported as ready once `ready` is set.
+pub async fn serve_http(bind: String, ready: Arc<AtomicBool>) {
+    let mut listener = match TcpListener::bind(&bind).await {
+        Ok(l) => l,
+        Err(_) => {
+            error!("An invalid URL was provided for HTTP endpoints: {}", &bind);
+            std::process::exit(1);
+        }
+    };
+    info!("Serving health endpoints at: {}", &bind);
+    loop {
+        if let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(respond(socket, ready.load(Ordering::SeqCst)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route("GET /healthz HTTP/1.1", false), (200, "ok"));
+        assert_eq!(route("GET /readyz HTTP/1.1", false), (503, "not ready"));
+        assert_eq!(route("GET /readyz HTTP/1.1", true), (200, "ready"));
+        assert_eq!(route("GET /metrics HTTP/1.1", true), (404, "not found"));
+        assert_eq!(
+            route("POST /healthz HTTP/1.1", true),
+            (405, "method not allowed")
+        );
+        assert_eq!(route("", true), (405, "method not allowed"));
+    }
+}
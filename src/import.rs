@@ -0,0 +1,103 @@
+//! Parsing of delimited files for bulk imports with `kiba-import`.
+
+/// Format of a delimited file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Comma-separated values, where fields containing commas or quotes are
+    /// enclosed in double quotes and quotes are escaped by doubling them
+    Csv,
+    /// Tab-separated values, where fields never contain tabs
+    Tsv,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!(
+                "Format must be either \"csv\" or \"tsv\", found \"{}\"",
+                name
+            )),
+        }
+    }
+}
+
+/// Split a line of a delimited file into its fields.
+/// Quoted CSV fields cannot span several lines.
+pub fn parse_record(line: &str, format: Format) -> Result<Vec<String>, String> {
+    let line = line.trim_end_matches(&['\r', '\n'][..]);
+    match format {
+        Format::Tsv => Ok(line.split('\t').map(|f| f.to_string()).collect()),
+        Format::Csv => parse_csv(line),
+    }
+}
+
+fn parse_csv(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("Unterminated quoted field".to_string()),
+                }
+            }
+            if !matches!(chars.peek(), Some(',') | None) {
+                return Err("Expected a comma after a quoted field".to_string());
+            }
+        }
+        // Read up to the next comma, or return at the end of the line
+        loop {
+            match chars.next() {
+                Some(',') => break,
+                Some(c) => field.push(c),
+                None => {
+                    fields.push(field);
+                    return Ok(fields);
+                }
+            }
+        }
+        fields.push(field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        let s = |fields: &[&str]| -> Vec<String> { fields.iter().map(|f| f.to_string()).collect() };
+        assert_eq!(
+            parse_record("user:1,shoyo,24\r\n", Format::Csv),
+            Ok(s(&["user:1", "shoyo", "24"]))
+        );
+        assert_eq!(
+            parse_record("user:1,\"Inokuchi, Shoyo\",\"say \"\"hi\"\"\"", Format::Csv),
+            Ok(s(&["user:1", "Inokuchi, Shoyo", "say \"hi\""]))
+        );
+        assert_eq!(
+            parse_record("a,,b,", Format::Csv),
+            Ok(s(&["a", "", "b", ""]))
+        );
+        assert_eq!(parse_record("", Format::Csv), Ok(s(&[""])));
+        assert_eq!(
+            parse_record("user:1\t\"shoyo\"\t24\n", Format::Tsv),
+            Ok(s(&["user:1", "\"shoyo\"", "24"]))
+        );
+        assert!(parse_record("a,\"b", Format::Csv).is_err());
+        assert!(parse_record("a,\"b\"c", Format::Csv).is_err());
+
+        assert_eq!(Format::parse("TSV"), Ok(Format::Tsv));
+        assert!(Format::parse("json").is_err());
+    }
+}
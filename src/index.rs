@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Number of results returned by a search if not specified
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// A condition on the value of an indexed hash field
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Field value is exactly equal to the value
+    Eq { field: String, value: String },
+    /// Field value is a number between min and max (inclusive)
+    Range { field: String, min: f64, max: f64 },
+}
+
+/// Index over the values of a single field
+#[derive(Debug, Default)]
+struct FieldIndex {
+    /// Keys by exact field value
+    values: HashMap<String, BTreeSet<String>>,
+    /// Keys by numeric field value, for values that parse as numbers
+    numbers: BTreeMap<u64, BTreeSet<String>>,
+}
+
+/// A secondary index over the fields of hashes whose keys start with a prefix.
+///
+/// Each declared field is indexed both by exact value and, if the value is
+/// numeric, by number, so that keys can be found by equality or range filters
+/// without scanning the keyspace.
+#[derive(Debug)]
+pub struct Index {
+    prefix: String,
+    fields: HashMap<String, FieldIndex>,
+}
+
+impl Index {
+    pub fn new(prefix: String, fields: Vec<String>) -> Self {
+        Self {
+            prefix,
+            fields: fields
+                .into_iter()
+                .map(|f| (f, FieldIndex::default()))
+                .collect(),
+        }
+    }
+
+    /// Return the prefix of keys covered by this index.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Return the names of the fields covered by this index.
+    pub fn fields(&self) -> Vec<&String> {
+        self.fields.keys().collect()
+    }
+
+    /// Return if the field of the hash stored at key is covered by this index.
+    pub fn covers(&self, key: &str, field: &str) -> bool {
+        key.starts_with(&self.prefix) && self.fields.contains_key(field)
+    }
+
+    /// Record that the field of the hash stored at key has the value.
+    pub fn insert(&mut self, key: &str, field: &str, value: &str) {
+        if let Some(index) = self.fields.get_mut(field) {
+            index
+                .values
+                .entry(value.to_string())
+                .or_default()
+                .insert(key.to_string());
+            if let Some(n) = parse_number(value) {
+                index
+                    .numbers
+                    .entry(sortable(n))
+                    .or_default()
+                    .insert(key.to_string());
+            }
+        }
+    }
+
+    /// Remove the record that the field of the hash stored at key has the value.
+    pub fn remove(&mut self, key: &str, field: &str, value: &str) {
+        if let Some(index) = self.fields.get_mut(field) {
+            if let Some(keys) = index.values.get_mut(value) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    index.values.remove(value);
+                }
+            }
+            if let Some(n) = parse_number(value) {
+                if let Some(keys) = index.numbers.get_mut(&sortable(n)) {
+                    keys.remove(key);
+                    if keys.is_empty() {
+                        index.numbers.remove(&sortable(n));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return keys of hashes matching all filters in lexicographic order.
+    /// If a filter references a field that is not indexed, return an error.
+    pub fn search(&self, filters: &[Filter]) -> Result<Vec<String>, String> {
+        let mut result: Option<HashSet<&String>> = None;
+        for filter in filters {
+            let matches = self.matches(filter)?;
+            result = Some(match result {
+                Some(keys) => keys.intersection(&matches).cloned().collect(),
+                None => matches,
+            });
+        }
+        let mut keys: Vec<String> = result
+            .unwrap_or_default()
+            .into_iter()
+            .map(|k| k.to_string())
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn matches(&self, filter: &Filter) -> Result<HashSet<&String>, String> {
+        let field = match filter {
+            Filter::Eq { field, .. } | Filter::Range { field, .. } => field,
+        };
+        let index = match self.fields.get(field) {
+            Some(index) => index,
+            None => return Err(format!("Field \"{}\" is not indexed", field)),
+        };
+        Ok(match filter {
+            Filter::Eq { value, .. } => index.values.get(value).into_iter().flatten().collect(),
+            Filter::Range { min, max, .. } => {
+                if min > max {
+                    return Ok(HashSet::new());
+                }
+                index
+                    .numbers
+                    .range(sortable(*min)..=sortable(*max))
+                    .flat_map(|(_, keys)| keys)
+                    .collect()
+            }
+        })
+    }
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok().filter(|n| !n.is_nan())
+}
+
+/// Map a float to an integer with the same ordering.
+fn sortable(n: f64) -> u64 {
+    let bits = n.to_bits();
+    match bits >> 63 {
+        0 => bits | (1 << 63),
+        _ => !bits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eq(field: &str, value: &str) -> Filter {
+        Filter::Eq {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn range(field: &str, min: f64, max: f64) -> Filter {
+        Filter::Range {
+            field: field.to_string(),
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn test_index_search() {
+        let mut index = Index::new(
+            "user:".to_string(),
+            vec!["city".to_string(), "age".to_string()],
+        );
+        assert!(index.covers("user:1", "city"));
+        assert!(!index.covers("user:1", "name"));
+        assert!(!index.covers("item:1", "city"));
+
+        index.insert("user:1", "city", "Tokyo");
+        index.insert("user:1", "age", "30");
+        index.insert("user:2", "city", "Tokyo");
+        index.insert("user:2", "age", "-5");
+        index.insert("user:3", "city", "Osaka");
+        index.insert("user:3", "age", "41.5");
+
+        assert_eq!(
+            index.search(&[eq("city", "Tokyo")]).unwrap(),
+            vec!["user:1", "user:2"]
+        );
+        assert_eq!(
+            index.search(&[range("age", -10.0, 35.0)]).unwrap(),
+            vec!["user:1", "user:2"]
+        );
+        assert_eq!(
+            index
+                .search(&[eq("city", "Tokyo"), range("age", 0.0, f64::INFINITY)])
+                .unwrap(),
+            vec!["user:1"]
+        );
+        assert!(index.search(&[eq("name", "Jane")]).is_err());
+
+        // Removing an entry updates both value and numeric indexes
+        index.remove("user:1", "age", "30");
+        assert_eq!(
+            index.search(&[range("age", 0.0, 100.0)]).unwrap(),
+            vec!["user:3"]
+        );
+    }
+}
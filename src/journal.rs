@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// A request recorded in a journal
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Time the request was received, in milliseconds since the Unix epoch
+    pub at: i64,
+
+    /// Description of the request
+    pub request: String,
+
+    /// Time taken to produce the response
+    pub duration: Duration,
+}
+
+/// The most recent requests of a client connection, dumped with DEBUG JOURNAL.
+///
+/// Journals let operators reconstruct what a misbehaving client did without
+/// logging every request of every client. They are only kept if enabled in
+/// the config, and are discarded when their client disconnects.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Journal {
+    capacity: usize,
+    entries: VecDeque<Entry>,
+}
+
+impl Journal {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Record a request, discarding the oldest entry if the journal is full.
+    pub fn record(&mut self, entry: Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Return a line describing each entry, from oldest to newest.
+    pub fn lines(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|e| format!("{} {}us {}", e.at, e.duration.as_micros(), e.request))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let entry = |at| Entry {
+            at,
+            request: "Ping".to_string(),
+            duration: Duration::from_micros(15),
+        };
+        let mut journal = Journal::new(2);
+        assert!(journal.lines().is_empty());
+        journal.record(entry(1));
+        journal.record(entry(2));
+        journal.record(entry(3));
+        assert_eq!(journal.lines(), vec!["2 15us Ping", "3 15us Ping"]);
+
+        let mut journal = Journal::new(0);
+        journal.record(entry(1));
+        assert!(journal.lines().is_empty());
+    }
+}
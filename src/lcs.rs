@@ -0,0 +1,107 @@
+/// Operands of an LCS computation
+#[derive(Clone, Debug, PartialEq)]
+pub enum LcsInput {
+    /// Names of two keys holding strings
+    Keys(String, String),
+    /// Two literal strings
+    Strings(String, String),
+}
+
+/// A contiguous run of characters shared by both strings, as inclusive
+/// character ranges into the first and second string respectively
+#[derive(Debug, PartialEq)]
+pub struct LcsMatch {
+    pub a: (usize, usize),
+    pub b: (usize, usize),
+    pub len: usize,
+}
+
+/// Longest common subsequence of two strings and the runs that compose it
+#[derive(Debug, PartialEq)]
+pub struct Lcs {
+    pub sequence: String,
+    pub matches: Vec<LcsMatch>,
+}
+
+/// Compute the longest common subsequence of two strings by character.
+/// Matching runs are returned from the end of the strings towards the start.
+/// Time complexity: O(N * M)
+pub fn lcs(a: &str, b: &str) -> Lcs {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let width = b.len() + 1;
+
+    // table[i * width + j] is the LCS length of a[..i] and b[..j]
+    let mut table = vec![0usize; (a.len() + 1) * width];
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            table[i * width + j] = match a[i - 1] == b[j - 1] {
+                true => table[(i - 1) * width + j - 1] + 1,
+                false => table[(i - 1) * width + j].max(table[i * width + j - 1]),
+            };
+        }
+    }
+
+    let mut sequence = Vec::with_capacity(table[a.len() * width + b.len()]);
+    let mut matches: Vec<LcsMatch> = Vec::new();
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 && j > 0 {
+        if a[i - 1] == b[j - 1] {
+            sequence.push(a[i - 1]);
+            match matches.last_mut() {
+                // Extend the current run if it is contiguous in both strings
+                Some(m) if m.a.0 == i && m.b.0 == j => {
+                    m.a.0 -= 1;
+                    m.b.0 -= 1;
+                    m.len += 1;
+                }
+                _ => matches.push(LcsMatch {
+                    a: (i - 1, i - 1),
+                    b: (j - 1, j - 1),
+                    len: 1,
+                }),
+            }
+            i -= 1;
+            j -= 1;
+        } else if table[(i - 1) * width + j] >= table[i * width + j - 1] {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    sequence.reverse();
+    Lcs {
+        sequence: sequence.into_iter().collect(),
+        matches,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lcs() {
+        let result = lcs("ohmytext", "mynewtext");
+        assert_eq!(result.sequence, "mytext");
+        assert_eq!(
+            result.matches,
+            vec![
+                LcsMatch {
+                    a: (4, 7),
+                    b: (5, 8),
+                    len: 4
+                },
+                LcsMatch {
+                    a: (2, 3),
+                    b: (0, 1),
+                    len: 2
+                },
+            ]
+        );
+
+        let result = lcs("", "abc");
+        assert_eq!(result.sequence, "");
+        assert!(result.matches.is_empty());
+    }
+}
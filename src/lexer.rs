@@ -5,10 +5,16 @@ use std::str::Chars;
 pub enum Operator {
     MetaOp(MetaOp),
     MiscOp(MiscOp),
+    KeyOp(KeyOp),
     StringOp(StringOp),
     ListOp(ListOp),
     SetOp(SetOp),
     HashOp(HashOp),
+    BloomOp(BloomOp),
+    CuckooOp(CuckooOp),
+    TimeSeriesOp(TimeSeriesOp),
+    VectorOp(VectorOp),
+    SearchOp(SearchOp),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -21,16 +27,61 @@ pub enum MetaOp {
 #[derive(Clone, Debug, PartialEq)]
 pub enum MiscOp {
     Ping,
+    Command,
+    Namespace,
+    Priority,
+    Format,
+    Debug,
+    Info,
+    Stats,
+    Snapshot,
+    Compact,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyOp {
+    Del,
+    Exists,
+    Type,
+    Rename,
+    RenameNx,
+    Keys,
+    RandomKey,
+    DbSize,
+    FlushDb,
+    FlushAll,
+    KeyRange,
+    Scan,
+    Expire,
+    PExpire,
+    ExpireAt,
+    PExpireAt,
+    Ttl,
+    PTtl,
+    ExpireTime,
+    PExpireTime,
+    Persist,
+    Version,
+    WaitKey,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum StringOp {
     Get,
+    GetDel,
     Set,
+    SetEx,
+    PSetEx,
     Incr,
     Decr,
     IncrBy,
     DecrBy,
+    IncrByFloat,
+    Append,
+    GetRange,
+    SetRange,
+    StrLen,
+    StrAlgo,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -54,6 +105,615 @@ pub enum HashOp {
     HGet,
     HSet,
     HDel,
+    HIncrByFloat,
+    HExpire,
+    HPExpire,
+    HTtl,
+    HPersist,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BloomOp {
+    BFReserve,
+    BFAdd,
+    BFMAdd,
+    BFExists,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CuckooOp {
+    CFAdd,
+    CFExists,
+    CFDel,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimeSeriesOp {
+    TSCreate,
+    TSAdd,
+    TSRange,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VectorOp {
+    VAdd,
+    VSim,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchOp {
+    FTCreate,
+    FTSearch,
+}
+
+/// Behavioral properties of a command, reported by COMMAND INFO
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flag {
+    /// Never modifies the store
+    ReadOnly,
+    /// May modify the store
+    Write,
+    /// Administrative command that inspects or controls the server
+    Admin,
+    /// May block the client until a condition is met
+    Blocking,
+    /// May grow memory usage, so is rejected when a memory quota is exceeded
+    DenyOom,
+}
+
+impl Flag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flag::ReadOnly => "readonly",
+            Flag::Write => "write",
+            Flag::Admin => "admin",
+            Flag::Blocking => "blocking",
+            Flag::DenyOom => "denyoom",
+        }
+    }
+}
+
+/// Groups of commands that access rules can allow or deny together, derived
+/// from the flags of each command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Category {
+    /// Commands that never modify the store
+    Read,
+    /// Commands that may modify the store
+    Write,
+    /// Administrative commands
+    Admin,
+    /// Administrative commands that may modify the store, such as FLUSHALL
+    Dangerous,
+}
+
+impl Category {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Read => "read",
+            Category::Write => "write",
+            Category::Admin => "admin",
+            Category::Dangerous => "dangerous",
+        }
+    }
+
+    /// Return the category with a name (case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Category::Read,
+            Category::Write,
+            Category::Admin,
+            Category::Dangerous,
+        ]
+        .iter()
+        .find(|category| category.name().eq_ignore_ascii_case(name))
+        .copied()
+    }
+}
+
+/// Commands that a rule applies to
+#[derive(Clone, Copy, Debug)]
+pub enum Selector {
+    /// Every command, written `@all`
+    All,
+    /// Commands in a category, such as `@read`
+    Category(Category),
+    /// A single command, such as `get`
+    Command(&'static Command),
+}
+
+/// A rule that allows (`+`) or denies (`-`) the commands it selects,
+/// such as `+@read` or `-flushall`
+#[derive(Clone, Copy, Debug)]
+pub struct CommandRule {
+    pub allow: bool,
+    pub selector: Selector,
+}
+
+impl CommandRule {
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let (allow, selector) = match rule.split_at(rule.len().min(1)) {
+            ("+", selector) => (true, selector),
+            ("-", selector) => (false, selector),
+            _ => return Err(format!("Rule \"{}\" must start with '+' or '-'", rule)),
+        };
+        let selector = match selector.strip_prefix('@') {
+            Some(name) if name.eq_ignore_ascii_case("all") => Selector::All,
+            Some(name) => match Category::parse(name) {
+                Some(category) => Selector::Category(category),
+                None => return Err(format!("Unrecognized category \"{}\"", name)),
+            },
+            None => match lookup_command(selector) {
+                Some(cmd) => Selector::Command(cmd),
+                None => return Err(format!("Unrecognized command \"{}\"", selector)),
+            },
+        };
+        Ok(Self { allow, selector })
+    }
+
+    pub fn selects(&self, cmd: &Command) -> bool {
+        match self.selector {
+            Selector::All => true,
+            Selector::Category(category) => cmd.categories().contains(&category),
+            Selector::Command(selected) => selected.name == cmd.name,
+        }
+    }
+}
+
+/// Return if a command is allowed by a list of rules. Later rules take
+/// precedence over earlier ones, and commands that no rule selects are denied.
+pub fn is_allowed(rules: &[CommandRule], cmd: &Command) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.selects(cmd))
+        .is_some_and(|rule| rule.allow)
+}
+
+/// An entry in the command table
+#[derive(Debug)]
+pub struct Command {
+    pub name: &'static str,
+    pub op: Operator,
+    pub flags: &'static [Flag],
+}
+
+impl Command {
+    pub fn has_flag(&self, flag: Flag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Return the categories that the command belongs to.
+    pub fn categories(&self) -> Vec<Category> {
+        let mut categories = Vec::new();
+        if self.has_flag(Flag::ReadOnly) {
+            categories.push(Category::Read);
+        }
+        if self.has_flag(Flag::Write) {
+            categories.push(Category::Write);
+        }
+        if self.has_flag(Flag::Admin) {
+            categories.push(Category::Admin);
+            if self.has_flag(Flag::Write) {
+                categories.push(Category::Dangerous);
+            }
+        }
+        categories
+    }
+}
+
+/// Every command recognized by the server.
+/// Adding a command requires an entry here with its flags.
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "PING",
+        op: Operator::MiscOp(MiscOp::Ping),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "COMMAND",
+        op: Operator::MiscOp(MiscOp::Command),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "NAMESPACE",
+        op: Operator::MiscOp(MiscOp::Namespace),
+        flags: &[],
+    },
+    Command {
+        name: "PRIORITY",
+        op: Operator::MiscOp(MiscOp::Priority),
+        flags: &[],
+    },
+    Command {
+        name: "FORMAT",
+        op: Operator::MiscOp(MiscOp::Format),
+        flags: &[],
+    },
+    Command {
+        name: "DEBUG",
+        op: Operator::MiscOp(MiscOp::Debug),
+        flags: &[Flag::Admin],
+    },
+    Command {
+        name: "INFO",
+        op: Operator::MiscOp(MiscOp::Info),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "STATS",
+        op: Operator::MiscOp(MiscOp::Stats),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "SNAPSHOT",
+        op: Operator::MiscOp(MiscOp::Snapshot),
+        flags: &[Flag::ReadOnly, Flag::Admin],
+    },
+    Command {
+        name: "COMPACT",
+        op: Operator::MiscOp(MiscOp::Compact),
+        flags: &[Flag::Admin],
+    },
+    Command {
+        name: "DEL",
+        op: Operator::KeyOp(KeyOp::Del),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "EXISTS",
+        op: Operator::KeyOp(KeyOp::Exists),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "TYPE",
+        op: Operator::KeyOp(KeyOp::Type),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "RENAME",
+        op: Operator::KeyOp(KeyOp::Rename),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "RENAMENX",
+        op: Operator::KeyOp(KeyOp::RenameNx),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "KEYS",
+        op: Operator::KeyOp(KeyOp::Keys),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "RANDOMKEY",
+        op: Operator::KeyOp(KeyOp::RandomKey),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "DBSIZE",
+        op: Operator::KeyOp(KeyOp::DbSize),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "FLUSHDB",
+        op: Operator::KeyOp(KeyOp::FlushDb),
+        flags: &[Flag::Write, Flag::Admin],
+    },
+    Command {
+        name: "FLUSHALL",
+        op: Operator::KeyOp(KeyOp::FlushAll),
+        flags: &[Flag::Write, Flag::Admin],
+    },
+    Command {
+        name: "KEYRANGE",
+        op: Operator::KeyOp(KeyOp::KeyRange),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "SCAN",
+        op: Operator::KeyOp(KeyOp::Scan),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "EXPIRE",
+        op: Operator::KeyOp(KeyOp::Expire),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "PEXPIRE",
+        op: Operator::KeyOp(KeyOp::PExpire),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "EXPIREAT",
+        op: Operator::KeyOp(KeyOp::ExpireAt),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "PEXPIREAT",
+        op: Operator::KeyOp(KeyOp::PExpireAt),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "TTL",
+        op: Operator::KeyOp(KeyOp::Ttl),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "PTTL",
+        op: Operator::KeyOp(KeyOp::PTtl),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "EXPIRETIME",
+        op: Operator::KeyOp(KeyOp::ExpireTime),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "PEXPIRETIME",
+        op: Operator::KeyOp(KeyOp::PExpireTime),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "PERSIST",
+        op: Operator::KeyOp(KeyOp::Persist),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "VERSION",
+        op: Operator::KeyOp(KeyOp::Version),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "WAITKEY",
+        op: Operator::KeyOp(KeyOp::WaitKey),
+        flags: &[Flag::ReadOnly, Flag::Blocking],
+    },
+    Command {
+        name: "GET",
+        op: Operator::StringOp(StringOp::Get),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "GETDEL",
+        op: Operator::StringOp(StringOp::GetDel),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "SET",
+        op: Operator::StringOp(StringOp::Set),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "SETEX",
+        op: Operator::StringOp(StringOp::SetEx),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "PSETEX",
+        op: Operator::StringOp(StringOp::PSetEx),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "INCR",
+        op: Operator::StringOp(StringOp::Incr),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "DECR",
+        op: Operator::StringOp(StringOp::Decr),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "INCRBY",
+        op: Operator::StringOp(StringOp::IncrBy),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "DECRBY",
+        op: Operator::StringOp(StringOp::DecrBy),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "INCRBYFLOAT",
+        op: Operator::StringOp(StringOp::IncrByFloat),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "APPEND",
+        op: Operator::StringOp(StringOp::Append),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "GETRANGE",
+        op: Operator::StringOp(StringOp::GetRange),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "SETRANGE",
+        op: Operator::StringOp(StringOp::SetRange),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "STRLEN",
+        op: Operator::StringOp(StringOp::StrLen),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "STRALGO",
+        op: Operator::StringOp(StringOp::StrAlgo),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "LPUSH",
+        op: Operator::ListOp(ListOp::LPush),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "RPUSH",
+        op: Operator::ListOp(ListOp::RPush),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "LPOP",
+        op: Operator::ListOp(ListOp::LPop),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "RPOP",
+        op: Operator::ListOp(ListOp::RPop),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "SADD",
+        op: Operator::SetOp(SetOp::SAdd),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "SREM",
+        op: Operator::SetOp(SetOp::SRem),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "SISMEMBER",
+        op: Operator::SetOp(SetOp::SIsMember),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "SMEMBERS",
+        op: Operator::SetOp(SetOp::SMembers),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "HGET",
+        op: Operator::HashOp(HashOp::HGet),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "HSET",
+        op: Operator::HashOp(HashOp::HSet),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "HDEL",
+        op: Operator::HashOp(HashOp::HDel),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "HINCRBYFLOAT",
+        op: Operator::HashOp(HashOp::HIncrByFloat),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "HEXPIRE",
+        op: Operator::HashOp(HashOp::HExpire),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "HPEXPIRE",
+        op: Operator::HashOp(HashOp::HPExpire),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "HTTL",
+        op: Operator::HashOp(HashOp::HTtl),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "HPERSIST",
+        op: Operator::HashOp(HashOp::HPersist),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "BF.RESERVE",
+        op: Operator::BloomOp(BloomOp::BFReserve),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "BF.ADD",
+        op: Operator::BloomOp(BloomOp::BFAdd),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "BF.MADD",
+        op: Operator::BloomOp(BloomOp::BFMAdd),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "BF.EXISTS",
+        op: Operator::BloomOp(BloomOp::BFExists),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "CF.ADD",
+        op: Operator::CuckooOp(CuckooOp::CFAdd),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "CF.EXISTS",
+        op: Operator::CuckooOp(CuckooOp::CFExists),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "CF.DEL",
+        op: Operator::CuckooOp(CuckooOp::CFDel),
+        flags: &[Flag::Write],
+    },
+    Command {
+        name: "TS.CREATE",
+        op: Operator::TimeSeriesOp(TimeSeriesOp::TSCreate),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "TS.ADD",
+        op: Operator::TimeSeriesOp(TimeSeriesOp::TSAdd),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "TS.RANGE",
+        op: Operator::TimeSeriesOp(TimeSeriesOp::TSRange),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "VADD",
+        op: Operator::VectorOp(VectorOp::VAdd),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "VSIM",
+        op: Operator::VectorOp(VectorOp::VSim),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "FT.CREATE",
+        op: Operator::SearchOp(SearchOp::FTCreate),
+        flags: &[Flag::Write, Flag::DenyOom],
+    },
+    Command {
+        name: "FT.SEARCH",
+        op: Operator::SearchOp(SearchOp::FTSearch),
+        flags: &[Flag::ReadOnly],
+    },
+    Command {
+        name: "QUIT",
+        op: Operator::MetaOp(MetaOp::Quit),
+        flags: &[],
+    },
+];
+
+impl Operator {
+    /// Return the table entry for this operator, if it is a command.
+    pub fn command(&self) -> Option<&'static Command> {
+        COMMANDS.iter().find(|cmd| &cmd.op == self)
+    }
+}
+
+/// Return the table entry for a command name (case-insensitive).
+pub fn lookup_command(name: &str) -> Option<&'static Command> {
+    COMMANDS
+        .iter()
+        .find(|cmd| cmd.name.eq_ignore_ascii_case(name))
 }
 
 type Stream<'a> = Peekable<Chars<'a>>;
@@ -77,27 +737,9 @@ impl<'a> Lexer<'a> {
         let mut stream = self.input.chars().peekable();
 
         if let Some(op) = self.next_token(&mut pos, &mut stream) {
-            result.op = match op.to_uppercase().as_str() {
-                "PING" => Operator::MiscOp(MiscOp::Ping),
-                "GET" => Operator::StringOp(StringOp::Get),
-                "SET" => Operator::StringOp(StringOp::Set),
-                "INCR" => Operator::StringOp(StringOp::Incr),
-                "DECR" => Operator::StringOp(StringOp::Decr),
-                "INCRBY" => Operator::StringOp(StringOp::IncrBy),
-                "DECRBY" => Operator::StringOp(StringOp::DecrBy),
-                "LPUSH" => Operator::ListOp(ListOp::LPush),
-                "RPUSH" => Operator::ListOp(ListOp::RPush),
-                "LPOP" => Operator::ListOp(ListOp::LPop),
-                "RPOP" => Operator::ListOp(ListOp::RPop),
-                "SADD" => Operator::SetOp(SetOp::SAdd),
-                "SREM" => Operator::SetOp(SetOp::SRem),
-                "SISMEMBER" => Operator::SetOp(SetOp::SIsMember),
-                "SMEMBERS" => Operator::SetOp(SetOp::SMembers),
-                "HGET" => Operator::HashOp(HashOp::HGet),
-                "HSET" => Operator::HashOp(HashOp::HSet),
-                "HDEL" => Operator::HashOp(HashOp::HDel),
-                "QUIT" => Operator::MetaOp(MetaOp::Quit),
-                _ => Operator::MetaOp(MetaOp::Unrecognized),
+            result.op = match lookup_command(op) {
+                Some(cmd) => cmd.op.clone(),
+                None => Operator::MetaOp(MetaOp::Unrecognized),
             }
         }
         while let Some(token) = self.next_token(&mut pos, &mut stream) {
@@ -144,7 +786,7 @@ impl<'a> Lexer<'a> {
         }
 
         let j = *pos;
-        if let Some(_) = stream.peek() {
+        if stream.peek().is_some() {
             self.consume_char(pos, stream); // Consume right quotation mark
         }
         &self.input[i..j]
@@ -186,4 +828,75 @@ impl<'a> LexerResult<'a> {
             argv: Vec::new(),
         }
     }
+
+    /// Build the result for a request whose arguments are already delimited,
+    /// with the command name as the first argument.
+    pub fn from_args(args: Vec<&'a str>) -> Self {
+        let mut args = args.into_iter();
+        let op = match args.next() {
+            Some(name) => match lookup_command(name) {
+                Some(cmd) => cmd.op.clone(),
+                None => Operator::MetaOp(MetaOp::Unrecognized),
+            },
+            None => Operator::MetaOp(MetaOp::NoOp),
+        };
+        Self {
+            op,
+            argv: args.collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_table() {
+        for cmd in COMMANDS {
+            // Every command is found by name and by operator
+            assert_eq!(lookup_command(&cmd.name.to_lowercase()).unwrap().op, cmd.op);
+            assert_eq!(cmd.op.command().unwrap().name, cmd.name);
+
+            // Commands cannot both read only and write
+            assert!(!(cmd.has_flag(Flag::ReadOnly) && cmd.has_flag(Flag::Write)));
+
+            // Only commands that write can grow memory usage
+            assert!(!cmd.has_flag(Flag::DenyOom) || cmd.has_flag(Flag::Write));
+        }
+        assert!(lookup_command("NOTACOMMAND").is_none());
+    }
+
+    #[test]
+    fn test_command_rules() {
+        let get = lookup_command("GET").unwrap();
+        let set = lookup_command("SET").unwrap();
+        let flushall = lookup_command("FLUSHALL").unwrap();
+        assert_eq!(get.categories(), vec![Category::Read]);
+        assert_eq!(
+            flushall.categories(),
+            vec![Category::Write, Category::Admin, Category::Dangerous]
+        );
+
+        let rules = |rules: &[&str]| -> Vec<CommandRule> {
+            rules
+                .iter()
+                .map(|rule| CommandRule::parse(rule).unwrap())
+                .collect()
+        };
+        let readonly = rules(&["+@read"]);
+        assert!(is_allowed(&readonly, get));
+        assert!(!is_allowed(&readonly, set));
+
+        // Later rules take precedence
+        let safe = rules(&["+@all", "-@dangerous", "+flushall"]);
+        assert!(is_allowed(&safe, set));
+        assert!(is_allowed(&safe, flushall));
+        assert!(!is_allowed(&safe, lookup_command("FLUSHDB").unwrap()));
+
+        assert!(CommandRule::parse("@read").is_err());
+        assert!(CommandRule::parse("+@nope").is_err());
+        assert!(CommandRule::parse("-notacommand").is_err());
+        assert!(CommandRule::parse("").is_err());
+    }
 }
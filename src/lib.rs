@@ -1,6 +1,55 @@
+//! Kiba is an in-memory, multithreaded key-value store.
+//!
+//! Besides running as a server, the crate can be used as a library: requests
+//! can be executed directly against a store, or a server can be embedded.
+//! The `client` module connects to a running server.
+//!
+//! ```
+//! use kiba::{execute, Request, StdStore, Store};
+//!
+//! let mut store: StdStore = Store::new();
+//! let mut rt = tokio::runtime::Runtime::new().unwrap();
+//! let req = Request::Set {
+//!     key: "foo".to_string(),
+//!     val: "bar".to_string(),
+//! };
+//! assert_eq!(rt.block_on(execute(req, &mut store)).body, "OK");
+//! ```
+
+pub mod bloom;
+pub mod chaos;
+pub mod client;
 pub mod config;
+pub mod cuckoo;
 pub mod executor;
+pub mod fill;
+pub mod glob;
+pub mod http;
+pub mod import;
+pub mod index;
+pub mod journal;
+pub mod lcs;
 pub mod lexer;
+pub mod metrics;
 pub mod parser;
+pub mod protocol;
+pub mod proxy;
+pub mod record;
+pub mod rng;
 pub mod server;
+#[cfg(test)]
+mod sim;
+pub mod slot;
+pub mod snapshot;
 pub mod store;
+pub mod timeseries;
+pub mod vector;
+pub mod webhook;
+
+pub use config::Config;
+pub use executor::{execute, Request, Response};
+#[cfg(feature = "derive")]
+pub use kiba_derive::KibaHash;
+pub use record::KibaHash;
+pub use server::Server;
+pub use store::{ExpiringStore, FnvStore, StdStore, Store};
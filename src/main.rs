@@ -1,38 +1,106 @@
 use kiba::config::parse_config;
-use kiba::server::start_server;
+use kiba::server::{build_runtime, start_server};
+use kiba::store::{StdStore, Store};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
 
 #[macro_use]
 extern crate log;
 
-#[tokio::main]
-async fn main() {
+/// Counts the bytes read through it, so that the position of an unreadable
+/// record can be reported
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Load the snapshot at path without starting a server, and report the
+/// number of keys of each type or the first record that could not be read.
+/// Return if the snapshot is valid.
+fn verify(path: &str) -> bool {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Could not open \"{}\": {}", path, e);
+            return false;
+        }
+    };
+    let mut reader = CountingReader {
+        inner: BufReader::new(file),
+        count: 0,
+    };
+    match <StdStore>::deserialize(&mut reader) {
+        Ok(store) => {
+            println!("Snapshot at \"{}\" is valid ({} bytes)", path, reader.count);
+            for (kind, count) in store.type_counts().unwrap() {
+                println!("  {}: {}", kind, count);
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!(
+                "Snapshot at \"{}\" is unreadable at byte {}: {}",
+                path, reader.count, e
+            );
+            false
+        }
+    }
+}
+
+fn main() {
+    let argv: Vec<String> = std::env::args().collect();
+    if argv.get(1).map(String::as_str) == Some("--verify") {
+        let path = match argv.get(2) {
+            Some(path) => path,
+            None => {
+                eprintln!("Usage: kiba --verify <snapshot>");
+                std::process::exit(2);
+            }
+        };
+        std::process::exit(if verify(path) { 0 } else { 1 });
+    }
+
     std::env::set_var("RUST_LOG", "trace");
     env_logger::init();
 
-    println!("");
+    println!();
     println!("██╗  ██╗██╗██████╗  █████╗ ");
     println!("██║ ██╔╝██║██╔══██╗██╔══██╗");
     println!("█████╔╝ ██║██████╔╝███████║");
     println!("██╔═██╗ ██║██╔══██╗██╔══██║");
     println!("██║  ██╗██║██████╔╝██║  ██║");
     println!("╚═╝  ╚═╝╚═╝╚═════╝ ╚═╝  ╚═╝");
-    println!("");
+    println!();
     println!("Kiba Server 0.1 (unstable)");
     println!("===========================");
 
-    let argv: Vec<String> = std::env::args().collect();
-    let config;
-    match argv.len() {
+    let config = match argv.len() {
         1 => {
             info!("Initializing server with default configuration...");
-            config = parse_config(None);
-
+            parse_config(None)
         }
         _ => {
             let path = &argv[1];
             info!("Initializing server with configuration file at: {}", &path);
-            config = parse_config(Some(path));
+            parse_config(Some(path))
         }
-    }
-    let _ = start_server(config).await;
+    };
+    // The runtime is built from the config rather than by `#[tokio::main]`,
+    // so that its scheduler and number of threads can be configured
+    let mut runtime = match build_runtime(&config) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            error!("Could not start the runtime: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let _ = runtime.block_on(start_server(config));
 }
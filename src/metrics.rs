@@ -0,0 +1,677 @@
+//! Per-operation metrics for stores.
+//!
+//! `InstrumentedStore` wraps any store and reports the duration of every
+//! operation to a `Metrics` implementation, so that metrics can be collected
+//! without timing code in the executor or in each store implementation.
+
+use crate::index::Filter;
+use crate::rng::Rng;
+use crate::store::{ExpiringStore, Iter, OperationalError, Store};
+use crate::timeseries::Aggregation;
+use crate::vector::Metric;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+type Result<T> = std::result::Result<T, OperationalError>;
+
+/// Receives the duration of each operation on an instrumented store.
+/// Operations without a key are reported with an empty key.
+pub trait Metrics: Default {
+    fn on_op(&self, op: &'static str, key: &str, duration: Duration);
+}
+
+/// Number of calls and total duration of an operation
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OpStats {
+    pub calls: u64,
+    pub total: Duration,
+}
+
+/// Metrics that aggregate the calls and duration of each operation
+#[derive(Debug, Default)]
+pub struct OpMetrics {
+    ops: Mutex<HashMap<&'static str, OpStats>>,
+}
+
+impl OpMetrics {
+    /// Return the aggregated stats of each operation called so far, by name.
+    pub fn snapshot(&self) -> Vec<(&'static str, OpStats)> {
+        let mut ops: Vec<(&'static str, OpStats)> = self
+            .ops
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(op, stats)| (*op, *stats))
+            .collect();
+        ops.sort_by_key(|(op, _)| *op);
+        ops
+    }
+}
+
+impl Metrics for OpMetrics {
+    fn on_op(&self, op: &'static str, _key: &str, duration: Duration) {
+        let mut ops = self.ops.lock().unwrap();
+        let stats = ops.entry(op).or_default();
+        stats.calls += 1;
+        stats.total += duration;
+    }
+}
+
+/// A store that reports the duration of each operation on an inner store.
+#[derive(Debug)]
+pub struct InstrumentedStore<S: Store, M: Metrics = OpMetrics> {
+    inner: S,
+    metrics: M,
+}
+
+impl<S: Store, M: Metrics> InstrumentedStore<S, M> {
+    /// Return the metrics collected for the store.
+    pub fn metrics(&self) -> &M {
+        &self.metrics
+    }
+
+    /// Return the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Store, M: Metrics> Store for InstrumentedStore<S, M> {
+    fn new() -> Self {
+        Self {
+            inner: S::new(),
+            metrics: M::default(),
+        }
+    }
+
+    // Keyspace Operations
+
+    fn exists(&self, key: String) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.exists(key);
+        self.metrics.on_op("exists", &op_key, start.elapsed());
+        res
+    }
+
+    fn key_type(&self, key: String) -> Result<Option<&'static str>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.key_type(key);
+        self.metrics.on_op("key_type", &op_key, start.elapsed());
+        res
+    }
+
+    fn rename(&mut self, key: String, newkey: String) -> Result<()> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.rename(key, newkey);
+        self.metrics.on_op("rename", &op_key, start.elapsed());
+        res
+    }
+
+    fn renamenx(&mut self, key: String, newkey: String) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.renamenx(key, newkey);
+        self.metrics.on_op("renamenx", &op_key, start.elapsed());
+        res
+    }
+
+    fn flushdb(&mut self) -> Result<()> {
+        let start = Instant::now();
+        let res = self.inner.flushdb();
+        self.metrics.on_op("flushdb", "", start.elapsed());
+        res
+    }
+
+    fn dbsize(&self) -> Result<u64> {
+        let start = Instant::now();
+        let res = self.inner.dbsize();
+        self.metrics.on_op("dbsize", "", start.elapsed());
+        res
+    }
+
+    fn random_key(&self, rng: &mut Rng) -> Result<Option<String>> {
+        let start = Instant::now();
+        let res = self.inner.random_key(rng);
+        self.metrics.on_op("random_key", "", start.elapsed());
+        res
+    }
+
+    fn del(&mut self, key: String) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.del(key);
+        self.metrics.on_op("del", &op_key, start.elapsed());
+        res
+    }
+
+    fn cardinality(&self, key: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.cardinality(key);
+        self.metrics.on_op("cardinality", &op_key, start.elapsed());
+        res
+    }
+
+    fn memory_usage(&self) -> Result<u64> {
+        let start = Instant::now();
+        let res = self.inner.memory_usage();
+        self.metrics.on_op("memory_usage", "", start.elapsed());
+        res
+    }
+
+    fn key_memory_usage(&self, key: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.key_memory_usage(key);
+        self.metrics
+            .on_op("key_memory_usage", &op_key, start.elapsed());
+        res
+    }
+
+    fn changes(&self) -> Result<u64> {
+        let start = Instant::now();
+        let res = self.inner.changes();
+        self.metrics.on_op("changes", "", start.elapsed());
+        res
+    }
+
+    fn type_counts(&self) -> Result<Vec<(&'static str, u64)>> {
+        let start = Instant::now();
+        let res = self.inner.type_counts();
+        self.metrics.on_op("type_counts", "", start.elapsed());
+        res
+    }
+
+    fn spare_capacity(&self) -> Result<u64> {
+        let start = Instant::now();
+        let res = self.inner.spare_capacity();
+        self.metrics.on_op("spare_capacity", "", start.elapsed());
+        res
+    }
+
+    fn compact(&mut self) -> Result<u64> {
+        let start = Instant::now();
+        let res = self.inner.compact();
+        self.metrics.on_op("compact", "", start.elapsed());
+        res
+    }
+
+    // Serialization
+
+    fn serialize(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let start = Instant::now();
+        let res = self.inner.serialize(writer);
+        self.metrics.on_op("serialize", "", start.elapsed());
+        res
+    }
+
+    fn deserialize(reader: &mut dyn Read) -> io::Result<Self> {
+        Ok(Self {
+            inner: S::deserialize(reader)?,
+            metrics: M::default(),
+        })
+    }
+
+    // Iteration
+
+    fn keys(&self) -> Iter<'_, &String> {
+        self.inner.keys()
+    }
+
+    fn keys_of_type(&self, kind: &str) -> Iter<'_, &String> {
+        self.inner.keys_of_type(kind)
+    }
+
+    fn strings(&self) -> Iter<'_, (&String, &String)> {
+        self.inner.strings()
+    }
+
+    fn lists(&self) -> Iter<'_, (&String, Vec<&String>)> {
+        self.inner.lists()
+    }
+
+    fn sets(&self) -> Iter<'_, (&String, Vec<&String>)> {
+        self.inner.sets()
+    }
+
+    fn hashes(&self) -> Iter<'_, (&String, Vec<(&String, &String)>)> {
+        self.inner.hashes()
+    }
+
+    fn scan(
+        &self,
+        cursor: u64,
+        pattern: Option<&str>,
+        kind: Option<&str>,
+        count: u64,
+    ) -> Result<(u64, Vec<String>)> {
+        let start = Instant::now();
+        let res = self.inner.scan(cursor, pattern, kind, count);
+        self.metrics.on_op("scan", "", start.elapsed());
+        res
+    }
+
+    // Strings Operations
+
+    fn get(&self, key: String) -> Result<Option<String>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.get(key);
+        self.metrics.on_op("get", &op_key, start.elapsed());
+        res
+    }
+
+    fn getdel(&mut self, key: String) -> Result<Option<String>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.getdel(key);
+        self.metrics.on_op("getdel", &op_key, start.elapsed());
+        res
+    }
+
+    fn set(&mut self, key: String, val: String) -> Result<Option<String>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.set(key, val);
+        self.metrics.on_op("set", &op_key, start.elapsed());
+        res
+    }
+
+    fn incr(&mut self, key: String) -> Result<i64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.incr(key);
+        self.metrics.on_op("incr", &op_key, start.elapsed());
+        res
+    }
+
+    fn decr(&mut self, key: String) -> Result<i64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.decr(key);
+        self.metrics.on_op("decr", &op_key, start.elapsed());
+        res
+    }
+
+    fn incrby(&mut self, key: String, delta: i64) -> Result<i64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.incrby(key, delta);
+        self.metrics.on_op("incrby", &op_key, start.elapsed());
+        res
+    }
+
+    fn decrby(&mut self, key: String, delta: i64) -> Result<i64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.decrby(key, delta);
+        self.metrics.on_op("decrby", &op_key, start.elapsed());
+        res
+    }
+
+    fn incrbyfloat(&mut self, key: String, delta: f64) -> Result<f64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.incrbyfloat(key, delta);
+        self.metrics.on_op("incrbyfloat", &op_key, start.elapsed());
+        res
+    }
+
+    fn append(&mut self, key: String, val: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.append(key, val);
+        self.metrics.on_op("append", &op_key, start.elapsed());
+        res
+    }
+
+    fn getrange(&self, key: String, start: i64, end: i64) -> Result<String> {
+        let op_key = key.clone();
+        let started = Instant::now();
+        let res = self.inner.getrange(key, start, end);
+        self.metrics.on_op("getrange", &op_key, started.elapsed());
+        res
+    }
+
+    fn setrange(&mut self, key: String, offset: u64, val: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.setrange(key, offset, val);
+        self.metrics.on_op("setrange", &op_key, start.elapsed());
+        res
+    }
+
+    fn strlen(&self, key: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.strlen(key);
+        self.metrics.on_op("strlen", &op_key, start.elapsed());
+        res
+    }
+
+    // Lists Operations
+
+    fn lpush(&mut self, key: String, val: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.lpush(key, val);
+        self.metrics.on_op("lpush", &op_key, start.elapsed());
+        res
+    }
+
+    fn rpush(&mut self, key: String, val: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.rpush(key, val);
+        self.metrics.on_op("rpush", &op_key, start.elapsed());
+        res
+    }
+
+    fn lpop(&mut self, key: String) -> Result<Option<String>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.lpop(key);
+        self.metrics.on_op("lpop", &op_key, start.elapsed());
+        res
+    }
+
+    fn rpop(&mut self, key: String) -> Result<Option<String>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.rpop(key);
+        self.metrics.on_op("rpop", &op_key, start.elapsed());
+        res
+    }
+
+    // Sets Operations
+
+    fn sadd(&mut self, key: String, val: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.sadd(key, val);
+        self.metrics.on_op("sadd", &op_key, start.elapsed());
+        res
+    }
+
+    fn srem(&mut self, key: String, val: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.srem(key, val);
+        self.metrics.on_op("srem", &op_key, start.elapsed());
+        res
+    }
+
+    fn sismember(&self, key: String, val: String) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.sismember(key, val);
+        self.metrics.on_op("sismember", &op_key, start.elapsed());
+        res
+    }
+
+    fn smembers(&self, key: String) -> Result<Vec<String>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.smembers(key);
+        self.metrics.on_op("smembers", &op_key, start.elapsed());
+        res
+    }
+
+    // Hashes Operations
+
+    fn hget(&self, key: String, field: String) -> Result<Option<String>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.hget(key, field);
+        self.metrics.on_op("hget", &op_key, start.elapsed());
+        res
+    }
+
+    fn hset(&mut self, key: String, field: String, val: String) -> Result<Option<String>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.hset(key, field, val);
+        self.metrics.on_op("hset", &op_key, start.elapsed());
+        res
+    }
+
+    fn hdel(&mut self, key: String, field: String) -> Result<u64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.hdel(key, field);
+        self.metrics.on_op("hdel", &op_key, start.elapsed());
+        res
+    }
+
+    fn hincrbyfloat(&mut self, key: String, field: String, delta: f64) -> Result<f64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.hincrbyfloat(key, field, delta);
+        self.metrics.on_op("hincrbyfloat", &op_key, start.elapsed());
+        res
+    }
+
+    // Bloom Filter Operations
+
+    fn bf_reserve(&mut self, key: String, error_rate: f64, capacity: u64) -> Result<()> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.bf_reserve(key, error_rate, capacity);
+        self.metrics.on_op("bf_reserve", &op_key, start.elapsed());
+        res
+    }
+
+    fn bf_add(&mut self, key: String, item: String) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.bf_add(key, item);
+        self.metrics.on_op("bf_add", &op_key, start.elapsed());
+        res
+    }
+
+    fn bf_madd(&mut self, key: String, items: Vec<String>) -> Result<Vec<bool>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.bf_madd(key, items);
+        self.metrics.on_op("bf_madd", &op_key, start.elapsed());
+        res
+    }
+
+    fn bf_exists(&self, key: String, item: String) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.bf_exists(key, item);
+        self.metrics.on_op("bf_exists", &op_key, start.elapsed());
+        res
+    }
+
+    // Cuckoo Filter Operations
+
+    fn cf_add(&mut self, key: String, item: String) -> Result<()> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.cf_add(key, item);
+        self.metrics.on_op("cf_add", &op_key, start.elapsed());
+        res
+    }
+
+    fn cf_exists(&self, key: String, item: String) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.cf_exists(key, item);
+        self.metrics.on_op("cf_exists", &op_key, start.elapsed());
+        res
+    }
+
+    fn cf_del(&mut self, key: String, item: String) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.cf_del(key, item);
+        self.metrics.on_op("cf_del", &op_key, start.elapsed());
+        res
+    }
+
+    // Time Series Operations
+
+    fn ts_create(&mut self, key: String, retention: u64) -> Result<()> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.ts_create(key, retention);
+        self.metrics.on_op("ts_create", &op_key, start.elapsed());
+        res
+    }
+
+    fn ts_add(&mut self, key: String, timestamp: i64, value: f64) -> Result<i64> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.ts_add(key, timestamp, value);
+        self.metrics.on_op("ts_add", &op_key, start.elapsed());
+        res
+    }
+
+    fn ts_range(
+        &self,
+        key: String,
+        from: i64,
+        to: i64,
+        aggregation: Option<(Aggregation, u64)>,
+    ) -> Result<Vec<(i64, f64)>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.ts_range(key, from, to, aggregation);
+        self.metrics.on_op("ts_range", &op_key, start.elapsed());
+        res
+    }
+
+    // Vector Operations
+
+    fn vadd(&mut self, key: String, element: String, vector: Vec<f64>) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.vadd(key, element, vector);
+        self.metrics.on_op("vadd", &op_key, start.elapsed());
+        res
+    }
+
+    fn vsim(
+        &self,
+        key: String,
+        query: Vec<f64>,
+        metric: Metric,
+        count: usize,
+    ) -> Result<Vec<(String, f64)>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.vsim(key, query, metric, count);
+        self.metrics.on_op("vsim", &op_key, start.elapsed());
+        res
+    }
+
+    // Search Operations
+
+    fn ft_create(&mut self, index: String, prefix: String, fields: Vec<String>) -> Result<()> {
+        let op_key = index.clone();
+        let start = Instant::now();
+        let res = self.inner.ft_create(index, prefix, fields);
+        self.metrics.on_op("ft_create", &op_key, start.elapsed());
+        res
+    }
+
+    fn ft_search(
+        &self,
+        index: String,
+        filters: Vec<Filter>,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<String>> {
+        let op_key = index.clone();
+        let start = Instant::now();
+        let res = self.inner.ft_search(index, filters, offset, count);
+        self.metrics.on_op("ft_search", &op_key, start.elapsed());
+        res
+    }
+}
+
+impl<S: ExpiringStore, M: Metrics> ExpiringStore for InstrumentedStore<S, M> {
+    fn set_expiry(&mut self, key: String, at: Option<i64>) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.set_expiry(key, at);
+        self.metrics.on_op("set_expiry", &op_key, start.elapsed());
+        res
+    }
+
+    fn get_expiry(&self, key: String) -> Result<Option<i64>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.get_expiry(key);
+        self.metrics.on_op("get_expiry", &op_key, start.elapsed());
+        res
+    }
+
+    fn purge_expired(&mut self, now: i64, limit: u64) -> Result<Vec<String>> {
+        let start = Instant::now();
+        let res = self.inner.purge_expired(now, limit);
+        self.metrics.on_op("purge_expired", "", start.elapsed());
+        res
+    }
+
+    fn set_field_expiry(&mut self, key: String, field: String, at: Option<i64>) -> Result<bool> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.set_field_expiry(key, field, at);
+        self.metrics
+            .on_op("set_field_expiry", &op_key, start.elapsed());
+        res
+    }
+
+    fn get_field_expiry(&self, key: String, field: String) -> Result<Option<i64>> {
+        let op_key = key.clone();
+        let start = Instant::now();
+        let res = self.inner.get_field_expiry(key, field);
+        self.metrics
+            .on_op("get_field_expiry", &op_key, start.elapsed());
+        res
+    }
+
+    fn purge_expired_fields(&mut self, now: i64, limit: u64) -> Result<u64> {
+        let start = Instant::now();
+        let res = self.inner.purge_expired_fields(now, limit);
+        self.metrics
+            .on_op("purge_expired_fields", "", start.elapsed());
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{execute, Request};
+    use crate::store::StdStore;
+
+    #[tokio::test]
+    async fn test_instrumented_store() {
+        let mut store: InstrumentedStore<StdStore> = Store::new();
+        let set = Request::Set {
+            key: "foo".to_string(),
+            val: "bar".to_string(),
+        };
+        execute(set, &mut store).await;
+        for _ in 0..2 {
+            let get = Request::Get {
+                key: "foo".to_string(),
+            };
+            assert_eq!(execute(get, &mut store).await.body, "\"bar\"");
+        }
+        assert_eq!(store.inner().dbsize().unwrap(), 1);
+
+        let ops = store.metrics().snapshot();
+        let calls: Vec<(&str, u64)> = ops.iter().map(|(op, s)| (*op, s.calls)).collect();
+        assert_eq!(calls, vec![("get", 2), ("set", 1)]);
+    }
+}
@@ -1,6 +1,14 @@
-use crate::executor::Request;
+use crate::bloom::{ScalableBloomFilter, MAX_RESERVED_BYTES};
+use crate::chaos::ChaosAction;
+use crate::executor::{Priority, Request, SetCondition, SetExpiry, DEFAULT_SCAN_COUNT};
+use crate::fill::{Fill, FillType, MAX_BYTES, MAX_COUNT, MAX_ELEMENTS, MAX_SIZE};
+use crate::index::{Filter, DEFAULT_LIMIT};
+use crate::lcs::LcsInput;
 use crate::lexer::*;
-use log::error;
+use crate::protocol::{Frame, ReplyFormat};
+use crate::store::TYPE_NAMES;
+use crate::timeseries::Aggregation;
+use crate::vector::{Metric, DEFAULT_COUNT};
 
 fn invalid_argc_request(expected: usize, actual: usize) -> Request {
     Request::Invalid {
@@ -11,6 +19,15 @@ fn invalid_argc_request(expected: usize, actual: usize) -> Request {
     }
 }
 
+fn invalid_min_argc_request(minimum: usize, actual: usize) -> Request {
+    Request::Invalid {
+        error: format!(
+            "Unexpected number of arguments. Expected at least {}, got {}",
+            minimum, actual
+        ),
+    }
+}
+
 async fn validate_misc_op(op: MiscOp, argv: Vec<&str>) -> Request {
     let argc = argv.len();
     match op {
@@ -20,242 +37,2255 @@ async fn validate_misc_op(op: MiscOp, argv: Vec<&str>) -> Request {
             }
             Request::Ping
         }
-    }
-}
-
-async fn validate_string_op(op: StringOp, argv: Vec<&str>) -> Request {
-    let argc = argv.len();
-    match op {
-        StringOp::Get => {
-            if argc != 1 {
-                return invalid_argc_request(1, argc);
+        MiscOp::Command => {
+            if argc == 0 {
+                return Request::CommandInfo { names: vec![] };
             }
-            Request::Get {
-                key: argv[0].to_string(),
+            match argv[0].to_uppercase().as_str() {
+                "INFO" => Request::CommandInfo {
+                    names: argv[1..].iter().map(|n| n.to_string()).collect(),
+                },
+                _ => Request::Invalid {
+                    error: format!("Unrecognized subcommand \"{}\"", argv[0]),
+                },
             }
         }
-        StringOp::Set => {
-            if argc != 2 {
-                return invalid_argc_request(2, argc);
+        MiscOp::Namespace => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
             }
-            Request::Set {
-                key: argv[0].to_string(),
-                val: argv[1].to_string(),
+            Request::Namespace {
+                name: argv[0].to_string(),
             }
         }
-        StringOp::Incr => {
+        MiscOp::Priority => {
             if argc != 1 {
                 return invalid_argc_request(1, argc);
             }
-            Request::Incr {
-                key: argv[0].to_string(),
+            match argv[0].to_lowercase().as_str() {
+                "normal" => Request::Priority {
+                    priority: Priority::Normal,
+                },
+                "low" => Request::Priority {
+                    priority: Priority::Low,
+                },
+                _ => Request::Invalid {
+                    error: format!(
+                        "Priority must be either \"normal\" or \"low\", found \"{}\"",
+                        argv[0]
+                    ),
+                },
             }
         }
-        StringOp::Decr => {
+        MiscOp::Format => {
             if argc != 1 {
                 return invalid_argc_request(1, argc);
             }
-            Request::Decr {
-                key: argv[0].to_string(),
+            match argv[0].to_lowercase().as_str() {
+                "human" => Request::Format {
+                    format: ReplyFormat::Human,
+                },
+                "resp" => Request::Format {
+                    format: ReplyFormat::Resp,
+                },
+                _ => Request::Invalid {
+                    error: format!(
+                        "Format must be either \"human\" or \"resp\", found \"{}\"",
+                        argv[0]
+                    ),
+                },
             }
         }
-        StringOp::IncrBy => {
-            if argc != 2 {
-                return invalid_argc_request(2, argc);
-            }
-            let delta = argv[1].to_string().parse::<i64>();
-            match delta {
-                Ok(d) => Request::IncrBy {
-                    key: argv[0].to_string(),
-                    delta: d,
+        MiscOp::Debug => {
+            if argc == 0 {
+                return invalid_min_argc_request(1, argc);
+            }
+            match argv[0].to_uppercase().as_str() {
+                "CHAOS" => validate_chaos(argv[1..].to_vec()),
+                "QUICKFILL" => validate_quickfill(argv[1..].to_vec()),
+                "JOURNAL" => {
+                    if argc != 2 {
+                        return invalid_argc_request(1, argc - 1);
+                    }
+                    match argv[1].parse::<u64>() {
+                        Ok(client) => Request::DebugJournal { client },
+                        Err(_) => Request::Invalid {
+                            error: "Client ID must be a non-negative integer".to_string(),
+                        },
+                    }
+                }
+                _ => Request::Invalid {
+                    error: format!("Unrecognized subcommand \"{}\"", argv[0]),
                 },
-                Err(_) => Request::Invalid {
-                    error: format!("Value to increment by is a non-integer"),
+            }
+        }
+        MiscOp::Stats => {
+            if argc == 0 {
+                return invalid_min_argc_request(1, argc);
+            }
+            match argv[0].to_uppercase().as_str() {
+                "PREFIX" => {
+                    if argc != 2 {
+                        return invalid_argc_request(1, argc - 1);
+                    }
+                    Request::StatsPrefix {
+                        prefix: argv[1].to_string(),
+                    }
+                }
+                _ => Request::Invalid {
+                    error: format!("Unrecognized subcommand \"{}\"", argv[0]),
                 },
             }
         }
-        StringOp::DecrBy => {
-            if argc != 2 {
-                return invalid_argc_request(2, argc);
+        MiscOp::Snapshot => {
+            if argc != 0 {
+                return invalid_argc_request(0, argc);
             }
-            let delta = argv[1].to_string().parse::<i64>();
-            match delta {
-                Ok(d) => Request::DecrBy {
-                    key: argv[0].to_string(),
-                    delta: d,
+            Request::Snapshot
+        }
+        MiscOp::Compact => {
+            if argc != 0 {
+                return invalid_argc_request(0, argc);
+            }
+            Request::Compact
+        }
+        MiscOp::Info => {
+            if argc > 1 {
+                return invalid_argc_request(1, argc);
+            }
+            if argc == 0 {
+                return Request::Info { section: None };
+            }
+            match argv[0].to_lowercase().as_str() {
+                "all" => Request::Info { section: None },
+                section @ ("keyspace" | "stats") => Request::Info {
+                    section: Some(section.to_string()),
                 },
-                Err(_) => Request::Invalid {
-                    error: format!("Value to decrement by is a non-integer"),
+                _ => Request::Invalid {
+                    error: format!("Unrecognized section \"{}\"", argv[0]),
                 },
             }
         }
     }
 }
 
-async fn validate_list_op(op: ListOp, argv: Vec<&str>) -> Request {
+async fn validate_key_op(op: KeyOp, argv: Vec<&str>) -> Request {
     let argc = argv.len();
     match op {
-        ListOp::LPush => {
+        KeyOp::Del => {
+            if argc < 1 {
+                return invalid_min_argc_request(1, argc);
+            }
+            Request::Del {
+                keys: argv.iter().map(|key| key.to_string()).collect(),
+            }
+        }
+        KeyOp::Exists => {
+            if argc < 1 {
+                return invalid_min_argc_request(1, argc);
+            }
+            Request::Exists {
+                keys: argv.iter().map(|key| key.to_string()).collect(),
+            }
+        }
+        KeyOp::Type => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::Type {
+                key: argv[0].to_string(),
+            }
+        }
+        KeyOp::Rename => {
             if argc != 2 {
                 return invalid_argc_request(2, argc);
             }
-            Request::LPush {
+            Request::Rename {
                 key: argv[0].to_string(),
-                val: argv[1].to_string(),
+                newkey: argv[1].to_string(),
             }
         }
-        ListOp::RPush => {
+        KeyOp::RenameNx => {
             if argc != 2 {
                 return invalid_argc_request(2, argc);
             }
-            Request::RPush {
+            Request::RenameNx {
                 key: argv[0].to_string(),
-                val: argv[1].to_string(),
+                newkey: argv[1].to_string(),
             }
         }
-        ListOp::LPop => {
+        KeyOp::Keys => {
             if argc != 1 {
                 return invalid_argc_request(1, argc);
             }
-            Request::LPop {
-                key: argv[0].to_string(),
+            Request::Keys {
+                pattern: argv[0].to_string(),
             }
         }
-        ListOp::RPop => {
-            if argc != 1 {
-                return invalid_argc_request(1, argc);
+        KeyOp::RandomKey => {
+            if argc != 0 {
+                return invalid_argc_request(0, argc);
             }
-            Request::RPop {
-                key: argv[0].to_string(),
+            Request::RandomKey
+        }
+        KeyOp::DbSize => {
+            if argc != 0 {
+                return invalid_argc_request(0, argc);
             }
+            Request::DbSize
         }
-    }
-}
-
-async fn validate_set_op(op: SetOp, argv: Vec<&str>) -> Request {
-    let argc = argv.len();
-    match op {
-        SetOp::SAdd => {
+        KeyOp::FlushDb => {
+            if argc != 0 {
+                return invalid_argc_request(0, argc);
+            }
+            Request::FlushDb
+        }
+        KeyOp::FlushAll => {
+            if argc != 0 {
+                return invalid_argc_request(0, argc);
+            }
+            Request::FlushAll
+        }
+        KeyOp::KeyRange => {
+            let limit = match argc {
+                2 => None,
+                4 if argv[2].to_uppercase() == "LIMIT" => match argv[3].parse::<u64>() {
+                    Ok(n) => Some(n),
+                    Err(_) => {
+                        return Request::Invalid {
+                            error: "Limit must be a non-negative integer".to_string(),
+                        }
+                    }
+                },
+                4 => return invalid_option_request(argv[2]),
+                _ => return invalid_argc_request(2, argc),
+            };
+            Request::KeyRange {
+                start: argv[0].to_string(),
+                end: argv[1].to_string(),
+                limit,
+            }
+        }
+        KeyOp::Scan => {
+            if argc == 0 {
+                return invalid_min_argc_request(1, argc);
+            }
+            let cursor = match argv[0].parse::<u64>() {
+                Ok(c) => c,
+                Err(_) => {
+                    return Request::Invalid {
+                        error: "Cursor must be a non-negative integer".to_string(),
+                    }
+                }
+            };
+            let mut pattern = None;
+            let mut kind = None;
+            let mut count = DEFAULT_SCAN_COUNT;
+            for option in argv[1..].chunks(2) {
+                if option.len() != 2 {
+                    return Request::Invalid {
+                        error: format!("Expected a value for option \"{}\"", option[0]),
+                    };
+                }
+                match option[0].to_uppercase().as_str() {
+                    "MATCH" => pattern = Some(option[1].to_string()),
+                    "TYPE" => match TYPE_NAMES
+                        .iter()
+                        .find(|t| t.eq_ignore_ascii_case(option[1]))
+                    {
+                        Some(t) => kind = Some(t.to_string()),
+                        None => {
+                            return Request::Invalid {
+                                error: format!("Unrecognized type \"{}\"", option[1]),
+                            }
+                        }
+                    },
+                    "COUNT" => match option[1].parse::<u64>() {
+                        Ok(c) if c > 0 => count = c,
+                        _ => {
+                            return Request::Invalid {
+                                error: "Count must be a positive integer".to_string(),
+                            }
+                        }
+                    },
+                    _ => return invalid_option_request(option[0]),
+                }
+            }
+            Request::Scan {
+                cursor,
+                pattern,
+                kind,
+                count,
+            }
+        }
+        KeyOp::Expire | KeyOp::PExpire => {
             if argc != 2 {
                 return invalid_argc_request(2, argc);
             }
-            Request::SAdd {
-                key: argv[0].to_string(),
-                val: argv[1].to_string(),
+            let key = argv[0].to_string();
+            match (op, argv[1].parse::<u64>()) {
+                (KeyOp::Expire, Ok(seconds)) => Request::Expire { key, seconds },
+                (_, Ok(millis)) => Request::PExpire { key, millis },
+                (KeyOp::Expire, Err(_)) => Request::Invalid {
+                    error: "Seconds must be a non-negative integer".to_string(),
+                },
+                (_, Err(_)) => Request::Invalid {
+                    error: "Milliseconds must be a non-negative integer".to_string(),
+                },
             }
         }
-        SetOp::SRem => {
+        KeyOp::ExpireAt | KeyOp::PExpireAt => {
             if argc != 2 {
                 return invalid_argc_request(2, argc);
             }
-            Request::SRem {
+            let key = argv[0].to_string();
+            match (op, argv[1].parse::<u64>()) {
+                (KeyOp::ExpireAt, Ok(timestamp)) => Request::ExpireAt { key, timestamp },
+                (_, Ok(timestamp)) => Request::PExpireAt { key, timestamp },
+                (_, Err(_)) => Request::Invalid {
+                    error: "Timestamp must be a non-negative integer".to_string(),
+                },
+            }
+        }
+        KeyOp::Ttl => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::Ttl {
                 key: argv[0].to_string(),
-                val: argv[1].to_string(),
             }
         }
-        SetOp::SIsMember => {
-            if argc != 2 {
-                return invalid_argc_request(2, argc);
+        KeyOp::PTtl => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
             }
-            Request::SIsMember {
+            Request::PTtl {
                 key: argv[0].to_string(),
-                val: argv[1].to_string(),
             }
         }
-        SetOp::SMembers => {
+        KeyOp::ExpireTime => {
             if argc != 1 {
                 return invalid_argc_request(1, argc);
             }
-            Request::SMembers {
+            Request::ExpireTime {
                 key: argv[0].to_string(),
             }
         }
-    }
-}
-
-async fn validate_hash_op(op: HashOp, argv: Vec<&str>) -> Request {
-    let argc = argv.len();
-    match op {
-        HashOp::HGet => {
-            if argc != 2 {
-                return invalid_argc_request(2, argc);
+        KeyOp::PExpireTime => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
             }
-            Request::HGet {
+            Request::PExpireTime {
                 key: argv[0].to_string(),
-                field: argv[1].to_string(),
             }
         }
-        HashOp::HSet => {
-            if argc != 3 {
-                return invalid_argc_request(3, argc);
+        KeyOp::Persist => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
             }
-            Request::HSet {
+            Request::Persist {
                 key: argv[0].to_string(),
-                field: argv[1].to_string(),
-                val: argv[2].to_string(),
             }
         }
-        HashOp::HDel => {
-            if argc != 2 {
+        KeyOp::Version => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::Version {
+                key: argv[0].to_string(),
+            }
+        }
+        KeyOp::WaitKey => {
+            if argc != 1 && argc != 2 {
                 return invalid_argc_request(2, argc);
             }
-            Request::HDel {
+            let timeout = match argv.get(1).map(|t| t.parse::<u64>()) {
+                None | Some(Ok(0)) => None,
+                Some(Ok(secs)) => Some(secs),
+                Some(Err(_)) => {
+                    return Request::Invalid {
+                        error: "Timeout must be a non-negative integer".to_string(),
+                    }
+                }
+            };
+            Request::WaitKey {
                 key: argv[0].to_string(),
-                field: argv[1].to_string(),
+                timeout,
             }
         }
     }
 }
 
-async fn validate_meta_op(op: MetaOp, _argv: Vec<&str>) -> Request {
-    match op {
-        MetaOp::NoOp => Request::NoOp,
-        MetaOp::Quit => Request::Quit,
-        MetaOp::Unrecognized => Request::Invalid {
-            error: format!("Unrecognized operator"),
-        },
+fn parse_probability(arg: &str) -> Result<f64, Request> {
+    match arg.parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err(Request::Invalid {
+            error: "Probability must be a number between 0 and 1".to_string(),
+        }),
     }
 }
 
-async fn parse(tokens: LexerResult<'_>) -> Request {
-    match tokens.op {
-        Operator::MiscOp(op) => validate_misc_op(op, tokens.argv).await,
-        Operator::StringOp(op) => validate_string_op(op, tokens.argv).await,
-        Operator::ListOp(op) => validate_list_op(op, tokens.argv).await,
-        Operator::SetOp(op) => validate_set_op(op, tokens.argv).await,
-        Operator::HashOp(op) => validate_hash_op(op, tokens.argv).await,
-        Operator::MetaOp(op) => validate_meta_op(op, tokens.argv).await,
+fn validate_chaos(argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    if argc == 0 {
+        return Request::DebugChaos {
+            action: ChaosAction::Status,
+        };
+    }
+    let setting = argv[0].to_uppercase();
+    let expected = match setting.as_str() {
+        "STATUS" | "OFF" => 1,
+        _ => 2,
+    };
+    if argc != expected {
+        return invalid_argc_request(expected, argc);
     }
+    let action = match setting.as_str() {
+        "STATUS" => ChaosAction::Status,
+        "OFF" => ChaosAction::Off,
+        "LATENCY" => match argv[1].parse::<u64>() {
+            Ok(ms) => ChaosAction::Latency(ms),
+            Err(_) => {
+                return Request::Invalid {
+                    error: "Latency must be a non-negative integer".to_string(),
+                }
+            }
+        },
+        "DROP" => match parse_probability(argv[1]) {
+            Ok(p) => ChaosAction::Drop(p),
+            Err(req) => return req,
+        },
+        "DISCONNECT" => match parse_probability(argv[1]) {
+            Ok(p) => ChaosAction::Disconnect(p),
+            Err(req) => return req,
+        },
+        _ => return invalid_option_request(argv[0]),
+    };
+    Request::DebugChaos { action }
 }
 
-pub async fn parse_request(bytes: &[u8]) -> Request {
-    let text = match std::str::from_utf8(bytes) {
-        Ok(txt) => txt,
-        Err(_) => {
-            error!("Input bytestream could not be converted into valid UTF-8");
-            std::process::exit(1);
+/// Parse a range such as "16-256", or a single value such as "16".
+fn parse_range(arg: &str) -> Option<(u64, u64)> {
+    let (min, max) = match arg.split_once('-') {
+        Some((min, max)) => (min.parse().ok()?, max.parse().ok()?),
+        None => {
+            let n = arg.parse().ok()?;
+            (n, n)
         }
     };
-    let mut lexer = Lexer::new(text);
-    let tokens = lexer.tokenize().await;
-    parse(tokens).await
+    match min <= max {
+        true => Some((min, max)),
+        false => None,
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Parse a list of types with optional weights, such as "string:3,hash".
+fn parse_fill_types(arg: &str) -> Option<Vec<(FillType, u64)>> {
+    arg.split(',')
+        .map(|entry| {
+            let (name, weight) = match entry.split_once(':') {
+                Some((name, weight)) => (name, weight.parse().ok().filter(|w| *w > 0)?),
+                None => (entry, 1),
+            };
+            Some((FillType::parse(name)?, weight))
+        })
+        .collect()
+}
 
-    #[tokio::test]
-    async fn test_parse_request_misc() {
-        assert_eq!(parse_request(b"PING").await, Request::Ping);
+fn validate_quickfill(argv: Vec<&str>) -> Request {
+    if argv.is_empty() {
+        return invalid_min_argc_request(1, argv.len());
+    }
+    let count = match argv[0].parse::<u64>() {
+        Ok(count) if count <= MAX_COUNT => count,
+        Ok(_) => {
+            return Request::Invalid {
+                error: format!("Count must be at most {}", MAX_COUNT),
+            }
+        }
+        Err(_) => {
+            return Request::Invalid {
+                error: "Count must be a non-negative integer".to_string(),
+            }
+        }
+    };
+    let mut fill = Fill {
+        count,
+        ..Fill::default()
+    };
+    for option in argv[1..].chunks(2) {
+        if option.len() != 2 {
+            return Request::Invalid {
+                error: format!("Expected a value for option \"{}\"", option[0]),
+            };
+        }
+        let invalid = |error: &str| Request::Invalid {
+            error: error.to_string(),
+        };
+        let val = option[1];
+        match option[0].to_uppercase().as_str() {
+            "PREFIX" => fill.prefix = val.to_string(),
+            "TYPES" => {
+                match parse_fill_types(val) {
+                    Some(types) => fill.types = types,
+                    None => return invalid(
+                        "Types must be a list of string, list, set or hash, with optional weights",
+                    ),
+                }
+            }
+            "SIZE" => match parse_range(val) {
+                Some(size) if size.1 <= MAX_SIZE => fill.size = size,
+                Some(_) => return invalid(&format!("Size must be at most {} bytes", MAX_SIZE)),
+                None => return invalid("Size must be a non-negative integer or a range"),
+            },
+            "ELEMENTS" => match val.parse::<u64>() {
+                Ok(elements) if elements > MAX_ELEMENTS => {
+                    return invalid(&format!("Elements must be at most {}", MAX_ELEMENTS))
+                }
+                Ok(elements) if elements > 0 => fill.elements = elements,
+                _ => return invalid("Elements must be a positive integer"),
+            },
+            "TTL" => match parse_range(val) {
+                Some(ttl) => fill.ttl = Some(ttl),
+                None => return invalid("TTL must be a non-negative integer or a range"),
+            },
+            "EXPIRING" => match parse_probability(val) {
+                Ok(p) => fill.expiring = p,
+                Err(req) => return req,
+            },
+            "SEED" => match val.parse::<u64>() {
+                Ok(seed) => fill.seed = seed,
+                Err(_) => return invalid("Seed must be a non-negative integer"),
+            },
+            _ => return invalid_option_request(option[0]),
+        }
+    }
+    if fill.max_bytes() > MAX_BYTES {
+        return Request::Invalid {
+            error: format!(
+                "Dataset would exceed the maximum size of {} bytes",
+                MAX_BYTES
+            ),
+        };
+    }
+    Request::DebugQuickfill { fill }
+}
+
+async fn validate_string_op(op: StringOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    match op {
+        StringOp::Get => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::Get {
+                key: argv[0].to_string(),
+            }
+        }
+        StringOp::GetDel => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::GetDel {
+                key: argv[0].to_string(),
+            }
+        }
+        StringOp::Set => {
+            if argc == 4 && argv[2].eq_ignore_ascii_case("IFVERSION") {
+                return match argv[3].parse::<u64>() {
+                    Ok(version) => Request::SetIfVersion {
+                        key: argv[0].to_string(),
+                        val: argv[1].to_string(),
+                        version,
+                    },
+                    Err(_) => Request::Invalid {
+                        error: "Version must be a non-negative integer".to_string(),
+                    },
+                };
+            }
+            match argc {
+                0 | 1 => invalid_argc_request(2, argc),
+                2 => Request::Set {
+                    key: argv[0].to_string(),
+                    val: argv[1].to_string(),
+                },
+                _ => validate_set_options(argv),
+            }
+        }
+        // SETEX and PSETEX are executed as SET with EX or PX
+        StringOp::SetEx | StringOp::PSetEx => {
+            if argc != 3 {
+                return invalid_argc_request(3, argc);
+            }
+            let millis = match (op, argv[1].parse::<u64>()) {
+                (StringOp::SetEx, Ok(seconds)) if seconds > 0 => seconds.saturating_mul(1000),
+                (StringOp::PSetEx, Ok(millis)) if millis > 0 => millis,
+                (StringOp::SetEx, _) => {
+                    return Request::Invalid {
+                        error: "Seconds must be a positive integer".to_string(),
+                    }
+                }
+                _ => {
+                    return Request::Invalid {
+                        error: "Milliseconds must be a positive integer".to_string(),
+                    }
+                }
+            };
+            Request::SetWith {
+                key: argv[0].to_string(),
+                val: argv[2].to_string(),
+                condition: None,
+                expiry: SetExpiry::After(millis),
+            }
+        }
+        StringOp::Incr => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::Incr {
+                key: argv[0].to_string(),
+            }
+        }
+        StringOp::Decr => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::Decr {
+                key: argv[0].to_string(),
+            }
+        }
+        StringOp::IncrBy => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            let delta = argv[1].to_string().parse::<i64>();
+            match delta {
+                Ok(d) => Request::IncrBy {
+                    key: argv[0].to_string(),
+                    delta: d,
+                },
+                Err(_) => Request::Invalid {
+                    error: "Value to increment by is a non-integer".to_string(),
+                },
+            }
+        }
+        StringOp::DecrBy => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            let delta = argv[1].to_string().parse::<i64>();
+            match delta {
+                Ok(d) => Request::DecrBy {
+                    key: argv[0].to_string(),
+                    delta: d,
+                },
+                Err(_) => Request::Invalid {
+                    error: "Value to decrement by is a non-integer".to_string(),
+                },
+            }
+        }
+        StringOp::IncrByFloat => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            match parse_float_delta(argv[1]) {
+                Ok(delta) => Request::IncrByFloat {
+                    key: argv[0].to_string(),
+                    delta,
+                },
+                Err(req) => req,
+            }
+        }
+        StringOp::Append => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::Append {
+                key: argv[0].to_string(),
+                val: argv[1].to_string(),
+            }
+        }
+        StringOp::GetRange => {
+            if argc != 3 {
+                return invalid_argc_request(3, argc);
+            }
+            match (argv[1].parse::<i64>(), argv[2].parse::<i64>()) {
+                (Ok(start), Ok(end)) => Request::GetRange {
+                    key: argv[0].to_string(),
+                    start,
+                    end,
+                },
+                _ => Request::Invalid {
+                    error: "Start and end offsets must be integers".to_string(),
+                },
+            }
+        }
+        StringOp::SetRange => {
+            if argc != 3 {
+                return invalid_argc_request(3, argc);
+            }
+            match argv[1].parse::<u64>() {
+                Ok(offset) => Request::SetRange {
+                    key: argv[0].to_string(),
+                    offset,
+                    val: argv[2].to_string(),
+                },
+                Err(_) => Request::Invalid {
+                    error: "Offset must be a non-negative integer".to_string(),
+                },
+            }
+        }
+        StringOp::StrLen => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::StrLen {
+                key: argv[0].to_string(),
+            }
+        }
+        StringOp::StrAlgo => validate_stralgo(argv),
+    }
+}
+
+fn validate_stralgo(argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    if argc < 4 {
+        return invalid_min_argc_request(4, argc);
+    }
+    if argv[0].to_uppercase() != "LCS" {
+        return Request::Invalid {
+            error: format!("Unrecognized algorithm \"{}\"", argv[0]),
+        };
+    }
+    let input = match argv[1].to_uppercase().as_str() {
+        "KEYS" => LcsInput::Keys(argv[2].to_string(), argv[3].to_string()),
+        "STRINGS" => LcsInput::Strings(argv[2].to_string(), argv[3].to_string()),
+        _ => return invalid_option_request(argv[1]),
+    };
+    let mut len = false;
+    let mut idx = false;
+    let mut min_match_len = 0;
+    let mut with_match_len = false;
+    let mut options = argv[4..].iter();
+    while let Some(option) = options.next() {
+        match option.to_uppercase().as_str() {
+            "LEN" => len = true,
+            "IDX" => idx = true,
+            "WITHMATCHLEN" => with_match_len = true,
+            "MINMATCHLEN" => match options.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) => min_match_len = n,
+                _ => {
+                    return Request::Invalid {
+                        error: "Minimum match length must be a non-negative integer".to_string(),
+                    }
+                }
+            },
+            _ => return invalid_option_request(option),
+        }
+    }
+    if len && idx {
+        return Request::Invalid {
+            error: "LEN and IDX cannot be combined. IDX already reports the length".to_string(),
+        };
+    }
+    Request::StrAlgoLcs {
+        input,
+        len,
+        idx,
+        min_match_len,
+        with_match_len,
+    }
+}
+
+async fn validate_list_op(op: ListOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    match op {
+        ListOp::LPush => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::LPush {
+                key: argv[0].to_string(),
+                val: argv[1].to_string(),
+            }
+        }
+        ListOp::RPush => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::RPush {
+                key: argv[0].to_string(),
+                val: argv[1].to_string(),
+            }
+        }
+        ListOp::LPop => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::LPop {
+                key: argv[0].to_string(),
+            }
+        }
+        ListOp::RPop => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::RPop {
+                key: argv[0].to_string(),
+            }
+        }
+    }
+}
+
+async fn validate_set_op(op: SetOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    match op {
+        SetOp::SAdd => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::SAdd {
+                key: argv[0].to_string(),
+                val: argv[1].to_string(),
+            }
+        }
+        SetOp::SRem => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::SRem {
+                key: argv[0].to_string(),
+                val: argv[1].to_string(),
+            }
+        }
+        SetOp::SIsMember => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::SIsMember {
+                key: argv[0].to_string(),
+                val: argv[1].to_string(),
+            }
+        }
+        SetOp::SMembers => {
+            if argc != 1 {
+                return invalid_argc_request(1, argc);
+            }
+            Request::SMembers {
+                key: argv[0].to_string(),
+            }
+        }
+    }
+}
+
+async fn validate_hash_op(op: HashOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    match op {
+        HashOp::HGet => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::HGet {
+                key: argv[0].to_string(),
+                field: argv[1].to_string(),
+            }
+        }
+        HashOp::HSet => {
+            if argc != 3 {
+                return invalid_argc_request(3, argc);
+            }
+            Request::HSet {
+                key: argv[0].to_string(),
+                field: argv[1].to_string(),
+                val: argv[2].to_string(),
+            }
+        }
+        HashOp::HIncrByFloat => {
+            if argc != 3 {
+                return invalid_argc_request(3, argc);
+            }
+            match parse_float_delta(argv[2]) {
+                Ok(delta) => Request::HIncrByFloat {
+                    key: argv[0].to_string(),
+                    field: argv[1].to_string(),
+                    delta,
+                },
+                Err(req) => req,
+            }
+        }
+        HashOp::HDel => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::HDel {
+                key: argv[0].to_string(),
+                field: argv[1].to_string(),
+            }
+        }
+        HashOp::HExpire | HashOp::HPExpire => {
+            if argc != 3 {
+                return invalid_argc_request(3, argc);
+            }
+            let (key, field) = (argv[0].to_string(), argv[1].to_string());
+            match (op, argv[2].parse::<u64>()) {
+                (HashOp::HExpire, Ok(seconds)) => Request::HExpire {
+                    key,
+                    field,
+                    seconds,
+                },
+                (_, Ok(millis)) => Request::HPExpire { key, field, millis },
+                (HashOp::HExpire, Err(_)) => Request::Invalid {
+                    error: "Seconds must be a non-negative integer".to_string(),
+                },
+                (_, Err(_)) => Request::Invalid {
+                    error: "Milliseconds must be a non-negative integer".to_string(),
+                },
+            }
+        }
+        HashOp::HTtl => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::HTtl {
+                key: argv[0].to_string(),
+                field: argv[1].to_string(),
+            }
+        }
+        HashOp::HPersist => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::HPersist {
+                key: argv[0].to_string(),
+                field: argv[1].to_string(),
+            }
+        }
+    }
+}
+
+async fn validate_bloom_op(op: BloomOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    match op {
+        BloomOp::BFReserve => {
+            if argc != 3 {
+                return invalid_argc_request(3, argc);
+            }
+            let error_rate = match argv[1].parse::<f64>() {
+                Ok(e) if e > 0.0 && e < 1.0 => e,
+                _ => {
+                    return Request::Invalid {
+                        error: "Error rate must be a number between 0 and 1".to_string(),
+                    }
+                }
+            };
+            let capacity = match argv[2].parse::<u64>() {
+                Ok(c) if c > 0 => c,
+                _ => {
+                    return Request::Invalid {
+                        error: "Capacity must be a positive integer".to_string(),
+                    }
+                }
+            };
+            if ScalableBloomFilter::reserved_bytes(error_rate, capacity) > MAX_RESERVED_BYTES {
+                return Request::Invalid {
+                    error: format!(
+                        "Filter would exceed the maximum size of {} bytes",
+                        MAX_RESERVED_BYTES
+                    ),
+                };
+            }
+            Request::BFReserve {
+                key: argv[0].to_string(),
+                error_rate,
+                capacity,
+            }
+        }
+        BloomOp::BFAdd => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::BFAdd {
+                key: argv[0].to_string(),
+                item: argv[1].to_string(),
+            }
+        }
+        BloomOp::BFMAdd => {
+            if argc < 2 {
+                return invalid_min_argc_request(2, argc);
+            }
+            Request::BFMAdd {
+                key: argv[0].to_string(),
+                items: argv[1..].iter().map(|s| s.to_string()).collect(),
+            }
+        }
+        BloomOp::BFExists => {
+            if argc != 2 {
+                return invalid_argc_request(2, argc);
+            }
+            Request::BFExists {
+                key: argv[0].to_string(),
+                item: argv[1].to_string(),
+            }
+        }
+    }
+}
+
+async fn validate_cuckoo_op(op: CuckooOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    if argc != 2 {
+        return invalid_argc_request(2, argc);
+    }
+    let key = argv[0].to_string();
+    let item = argv[1].to_string();
+    match op {
+        CuckooOp::CFAdd => Request::CFAdd { key, item },
+        CuckooOp::CFExists => Request::CFExists { key, item },
+        CuckooOp::CFDel => Request::CFDel { key, item },
+    }
+}
+
+fn parse_retention(arg: &str) -> Result<u64, Request> {
+    arg.parse::<u64>().map_err(|_| Request::Invalid {
+        error: "Retention must be a non-negative integer".to_string(),
+    })
+}
+
+fn parse_range_bound(arg: &str) -> Result<i64, Request> {
+    match arg {
+        "-" => Ok(i64::MIN),
+        "+" => Ok(i64::MAX),
+        _ => arg.parse::<i64>().map_err(|_| Request::Invalid {
+            error: "Range bounds must be integers, \"-\" or \"+\"".to_string(),
+        }),
+    }
+}
+
+fn parse_aggregation(agg: &str, duration: &str) -> Result<(Aggregation, u64), Request> {
+    let agg = match agg.to_uppercase().as_str() {
+        "AVG" => Aggregation::Avg,
+        "MIN" => Aggregation::Min,
+        "MAX" => Aggregation::Max,
+        _ => {
+            return Err(Request::Invalid {
+                error: "Aggregation must be one of AVG, MIN or MAX".to_string(),
+            })
+        }
+    };
+    match duration.parse::<u64>() {
+        Ok(d) if d > 0 => Ok((agg, d)),
+        _ => Err(Request::Invalid {
+            error: "Bucket duration must be a positive integer".to_string(),
+        }),
+    }
+}
+
+/// Parse the amount to increment by of a float increment.
+fn parse_float_delta(delta: &str) -> Result<f64, Request> {
+    match delta.parse::<f64>() {
+        Ok(delta) if delta.is_finite() => Ok(delta),
+        _ => Err(Request::Invalid {
+            error: "Value to increment by is not a finite number".to_string(),
+        }),
+    }
+}
+
+/// Validate SET with options following its key and value.
+fn validate_set_options(argv: Vec<&str>) -> Request {
+    let mut condition = None;
+    let mut expiry = None;
+    let mut options = argv[2..].iter();
+    while let Some(option) = options.next() {
+        let (repeated, name) = match option.to_uppercase().as_str() {
+            "NX" => (
+                condition.replace(SetCondition::Absent).is_some(),
+                "NX and XX",
+            ),
+            "XX" => (
+                condition.replace(SetCondition::Present).is_some(),
+                "NX and XX",
+            ),
+            "KEEPTTL" => (
+                expiry.replace(SetExpiry::Keep).is_some(),
+                "EX, PX and KEEPTTL",
+            ),
+            "EX" | "PX" => {
+                let value = match options.next() {
+                    Some(value) => value,
+                    None => {
+                        return Request::Invalid {
+                            error: format!("Expected a value for option \"{}\"", option),
+                        }
+                    }
+                };
+                let millis = match (option.eq_ignore_ascii_case("EX"), value.parse::<u64>()) {
+                    (true, Ok(seconds)) if seconds > 0 => seconds.saturating_mul(1000),
+                    (false, Ok(millis)) if millis > 0 => millis,
+                    (true, _) => {
+                        return Request::Invalid {
+                            error: "Seconds must be a positive integer".to_string(),
+                        }
+                    }
+                    (false, _) => {
+                        return Request::Invalid {
+                            error: "Milliseconds must be a positive integer".to_string(),
+                        }
+                    }
+                };
+                (
+                    expiry.replace(SetExpiry::After(millis)).is_some(),
+                    "EX, PX and KEEPTTL",
+                )
+            }
+            _ => return invalid_option_request(option),
+        };
+        if repeated {
+            return Request::Invalid {
+                error: format!("Only one of {} can be given", name),
+            };
+        }
+    }
+    Request::SetWith {
+        key: argv[0].to_string(),
+        val: argv[1].to_string(),
+        condition,
+        expiry: expiry.unwrap_or(SetExpiry::Discard),
+    }
+}
+
+fn invalid_option_request(option: &str) -> Request {
+    Request::Invalid {
+        error: format!("Unrecognized option \"{}\"", option),
+    }
+}
+
+async fn validate_timeseries_op(op: TimeSeriesOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    match op {
+        TimeSeriesOp::TSCreate => {
+            let retention = match argc {
+                1 => 0,
+                3 if argv[1].to_uppercase() == "RETENTION" => match parse_retention(argv[2]) {
+                    Ok(r) => r,
+                    Err(req) => return req,
+                },
+                3 => return invalid_option_request(argv[1]),
+                _ => return invalid_argc_request(1, argc),
+            };
+            Request::TSCreate {
+                key: argv[0].to_string(),
+                retention,
+            }
+        }
+        TimeSeriesOp::TSAdd => {
+            if argc != 3 {
+                return invalid_argc_request(3, argc);
+            }
+            let timestamp = match argv[1] {
+                "*" => None,
+                ts => match ts.parse::<i64>() {
+                    Ok(t) => Some(t),
+                    Err(_) => {
+                        return Request::Invalid {
+                            error: "Timestamp must be an integer or \"*\"".to_string(),
+                        }
+                    }
+                },
+            };
+            let value = match argv[2].parse::<f64>() {
+                Ok(v) if v.is_finite() => v,
+                _ => {
+                    return Request::Invalid {
+                        error: "Value must be a number".to_string(),
+                    }
+                }
+            };
+            Request::TSAdd {
+                key: argv[0].to_string(),
+                timestamp,
+                value,
+            }
+        }
+        TimeSeriesOp::TSRange => {
+            if argc != 3 && argc != 6 {
+                return invalid_argc_request(3, argc);
+            }
+            let from = match parse_range_bound(argv[1]) {
+                Ok(b) => b,
+                Err(req) => return req,
+            };
+            let to = match parse_range_bound(argv[2]) {
+                Ok(b) => b,
+                Err(req) => return req,
+            };
+            let aggregation = match argc {
+                6 if argv[3].to_uppercase() == "AGGREGATION" => {
+                    match parse_aggregation(argv[4], argv[5]) {
+                        Ok(agg) => Some(agg),
+                        Err(req) => return req,
+                    }
+                }
+                6 => return invalid_option_request(argv[3]),
+                _ => None,
+            };
+            Request::TSRange {
+                key: argv[0].to_string(),
+                from,
+                to,
+                aggregation,
+            }
+        }
+    }
+}
+
+async fn validate_vector_op(op: VectorOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    match op {
+        VectorOp::VAdd => {
+            if argc < 3 {
+                return invalid_min_argc_request(3, argc);
+            }
+            let vector: std::result::Result<Vec<f64>, _> =
+                argv[2..].iter().map(|v| v.parse::<f64>()).collect();
+            match vector {
+                Ok(v) if v.iter().all(|c| c.is_finite()) => Request::VAdd {
+                    key: argv[0].to_string(),
+                    element: argv[1].to_string(),
+                    vector: v,
+                },
+                _ => Request::Invalid {
+                    error: "Vector components must be numbers".to_string(),
+                },
+            }
+        }
+        VectorOp::VSim => {
+            if argc < 2 {
+                return invalid_min_argc_request(2, argc);
+            }
+            let query: Vec<f64> = argv[1..]
+                .iter()
+                .map_while(|v| v.parse::<f64>().ok().filter(|c| c.is_finite()))
+                .collect();
+            if query.is_empty() {
+                return Request::Invalid {
+                    error: "Vector components must be numbers".to_string(),
+                };
+            }
+            let mut metric = Metric::Cosine;
+            let mut count = DEFAULT_COUNT;
+            let options = &argv[1 + query.len()..];
+            if !options.len().is_multiple_of(2) {
+                return invalid_option_request(options[options.len() - 1]);
+            }
+            for pair in options.chunks(2) {
+                match pair[0].to_uppercase().as_str() {
+                    "METRIC" => {
+                        metric = match pair[1].to_uppercase().as_str() {
+                            "COSINE" => Metric::Cosine,
+                            "EUCLIDEAN" => Metric::Euclidean,
+                            _ => {
+                                return Request::Invalid {
+                                    error: "Metric must be one of COSINE or EUCLIDEAN".to_string(),
+                                }
+                            }
+                        }
+                    }
+                    "COUNT" => {
+                        count = match pair[1].parse::<usize>() {
+                            Ok(c) if c > 0 => c,
+                            _ => {
+                                return Request::Invalid {
+                                    error: "Count must be a positive integer".to_string(),
+                                }
+                            }
+                        }
+                    }
+                    _ => return invalid_option_request(pair[0]),
+                }
+            }
+            Request::VSim {
+                key: argv[0].to_string(),
+                query,
+                metric,
+                count,
+            }
+        }
+    }
+}
+
+/// Parse a search query consisting of whitespace-separated filters of the
+/// form `@field:value` (equality) or `@field:[min max]` (numeric range).
+fn parse_query(query: &str) -> Option<Vec<Filter>> {
+    let mut filters = Vec::new();
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+        let (field, after) = rest.strip_prefix('@')?.split_once(':')?;
+        if field.is_empty() {
+            return None;
+        }
+        let field = field.to_string();
+        rest = match after.strip_prefix('[') {
+            Some(range) => {
+                let (bounds, after) = range.split_once(']')?;
+                let bounds: Vec<&str> = bounds.split_whitespace().collect();
+                if bounds.len() != 2 {
+                    return None;
+                }
+                let min = bounds[0].parse::<f64>().ok()?;
+                let max = bounds[1].parse::<f64>().ok()?;
+                filters.push(Filter::Range { field, min, max });
+                after
+            }
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                if end == 0 {
+                    return None;
+                }
+                filters.push(Filter::Eq {
+                    field,
+                    value: after[..end].to_string(),
+                });
+                &after[end..]
+            }
+        }
+        .trim_start();
+    }
+    match filters.is_empty() {
+        true => None,
+        false => Some(filters),
+    }
+}
+
+async fn validate_search_op(op: SearchOp, argv: Vec<&str>) -> Request {
+    let argc = argv.len();
+    match op {
+        SearchOp::FTCreate => {
+            if argc < 5 {
+                return invalid_min_argc_request(5, argc);
+            }
+            if argv[1].to_uppercase() != "PREFIX" {
+                return invalid_option_request(argv[1]);
+            }
+            if argv[3].to_uppercase() != "SCHEMA" {
+                return invalid_option_request(argv[3]);
+            }
+            Request::FTCreate {
+                index: argv[0].to_string(),
+                prefix: argv[2].to_string(),
+                fields: argv[4..].iter().map(|f| f.to_string()).collect(),
+            }
+        }
+        SearchOp::FTSearch => {
+            if argc != 2 && argc != 5 {
+                return invalid_argc_request(2, argc);
+            }
+            let filters = match parse_query(argv[1]) {
+                Some(f) => f,
+                None => return Request::Invalid {
+                    error:
+                        "Invalid query. Expected filters such as @field:value or @field:[min max]"
+                            .to_string(),
+                },
+            };
+            let (offset, count) = match argc {
+                5 if argv[2].to_uppercase() == "LIMIT" => {
+                    match (argv[3].parse::<usize>(), argv[4].parse::<usize>()) {
+                        (Ok(o), Ok(c)) => (o, c),
+                        _ => {
+                            return Request::Invalid {
+                                error: "Limit offset and count must be non-negative integers"
+                                    .to_string(),
+                            }
+                        }
+                    }
+                }
+                5 => return invalid_option_request(argv[2]),
+                _ => (0, DEFAULT_LIMIT),
+            };
+            Request::FTSearch {
+                index: argv[0].to_string(),
+                filters,
+                offset,
+                count,
+            }
+        }
+    }
+}
+
+async fn validate_meta_op(op: MetaOp, _argv: Vec<&str>) -> Request {
+    match op {
+        MetaOp::NoOp => Request::NoOp,
+        MetaOp::Quit => Request::Quit,
+        MetaOp::Unrecognized => Request::Invalid {
+            error: "Unrecognized operator".to_string(),
+        },
+    }
+}
+
+async fn parse(tokens: LexerResult<'_>) -> Request {
+    match tokens.op {
+        Operator::MiscOp(op) => validate_misc_op(op, tokens.argv).await,
+        Operator::KeyOp(op) => validate_key_op(op, tokens.argv).await,
+        Operator::StringOp(op) => validate_string_op(op, tokens.argv).await,
+        Operator::ListOp(op) => validate_list_op(op, tokens.argv).await,
+        Operator::SetOp(op) => validate_set_op(op, tokens.argv).await,
+        Operator::HashOp(op) => validate_hash_op(op, tokens.argv).await,
+        Operator::BloomOp(op) => validate_bloom_op(op, tokens.argv).await,
+        Operator::CuckooOp(op) => validate_cuckoo_op(op, tokens.argv).await,
+        Operator::TimeSeriesOp(op) => validate_timeseries_op(op, tokens.argv).await,
+        Operator::VectorOp(op) => validate_vector_op(op, tokens.argv).await,
+        Operator::SearchOp(op) => validate_search_op(op, tokens.argv).await,
+        Operator::MetaOp(op) => validate_meta_op(op, tokens.argv).await,
+    }
+}
+
+pub async fn parse_request(bytes: &[u8]) -> Request {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(txt) => txt,
+        Err(_) => {
+            return Request::Invalid {
+                error: "Arguments must be valid UTF-8".to_string(),
+            }
+        }
+    };
+    let mut lexer = Lexer::new(text);
+    let tokens = lexer.tokenize().await;
+    parse(tokens).await
+}
+
+/// Parse a request delimited in a client's input.
+pub async fn parse_frame(frame: Frame<'_>) -> Request {
+    let args = match frame {
+        Frame::Inline(bytes) => return parse_request(bytes).await,
+        Frame::Multibulk(args) => args,
+    };
+    let mut argv = Vec::with_capacity(args.len());
+    for arg in args {
+        match std::str::from_utf8(arg) {
+            Ok(arg) => argv.push(arg),
+            Err(_) => {
+                return Request::Invalid {
+                    error: "Arguments must be valid UTF-8".to_string(),
+                }
+            }
+        }
+    }
+    parse(LexerResult::from_args(argv)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_parse_request_misc() {
+        assert_eq!(parse_request(b"PING").await, Request::Ping);
+        assert_eq!(
+            parse_request("\u{0}PING\u{0}\u{0}\u{0}".as_bytes()).await,
+            Request::Ping
+        );
+        assert_eq!(
+            parse_request(b"PING extra args").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 0, got 2".to_string()
+            }
+        );
+        assert_eq!(parse_request(b"snapshot").await, Request::Snapshot);
+        assert_eq!(parse_request(b"COMPACT").await, Request::Compact);
+    }
+
+    #[tokio::test]
+    async fn test_parse_frame() {
+        let args: Vec<&[u8]> = vec![b"set", b"key", b"two words"];
+        assert_eq!(
+            parse_frame(Frame::Multibulk(args)).await,
+            Request::Set {
+                key: "key".to_string(),
+                val: "two words".to_string()
+            }
+        );
+        assert_eq!(parse_frame(Frame::Inline(b"PING\n")).await, Request::Ping);
+        assert_eq!(
+            parse_frame(Frame::Inline(b"\x00\xff\xfe\n")).await,
+            Request::Invalid {
+                error: "Arguments must be valid UTF-8".to_string()
+            }
+        );
+        assert_eq!(parse_frame(Frame::Multibulk(vec![])).await, Request::NoOp);
+        assert_eq!(
+            parse_frame(Frame::Multibulk(vec![b"GET", b"\xff"])).await,
+            Request::Invalid {
+                error: "Arguments must be valid UTF-8".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_command() {
+        assert_eq!(
+            parse_request(b"COMMAND").await,
+            Request::CommandInfo { names: vec![] }
+        );
+        assert_eq!(
+            parse_request(b"command info get set").await,
+            Request::CommandInfo {
+                names: vec!["get".to_string(), "set".to_string()]
+            }
+        );
+        assert_eq!(
+            parse_request(b"COMMAND DOCS get").await,
+            Request::Invalid {
+                error: "Unrecognized subcommand \"DOCS\"".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_namespace() {
+        assert_eq!(
+            parse_request(b"NAMESPACE app1").await,
+            Request::Namespace {
+                name: "app1".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"NAMESPACE").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 1, got 0".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_priority() {
+        assert_eq!(
+            parse_request(b"PRIORITY LOW").await,
+            Request::Priority {
+                priority: Priority::Low
+            }
+        );
+        assert_eq!(
+            parse_request(b"PRIORITY normal").await,
+            Request::Priority {
+                priority: Priority::Normal
+            }
+        );
+        assert_eq!(
+            parse_request(b"PRIORITY high").await,
+            Request::Invalid {
+                error: "Priority must be either \"normal\" or \"low\", found \"high\"".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_format() {
+        assert_eq!(
+            parse_request(b"FORMAT RESP").await,
+            Request::Format {
+                format: ReplyFormat::Resp
+            }
+        );
+        assert_eq!(
+            parse_request(b"FORMAT human").await,
+            Request::Format {
+                format: ReplyFormat::Human
+            }
+        );
+        assert_eq!(
+            parse_request(b"FORMAT json").await,
+            Request::Invalid {
+                error: "Format must be either \"human\" or \"resp\", found \"json\"".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_info() {
+        assert_eq!(
+            parse_request(b"INFO").await,
+            Request::Info { section: None }
+        );
+        assert_eq!(
+            parse_request(b"INFO all").await,
+            Request::Info { section: None }
+        );
+        assert_eq!(
+            parse_request(b"INFO KEYSPACE").await,
+            Request::Info {
+                section: Some("keyspace".to_string())
+            }
+        );
+        assert_eq!(
+            parse_request(b"INFO memory").await,
+            Request::Invalid {
+                error: "Unrecognized section \"memory\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"INFO stats keyspace").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 1, got 2".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_stats() {
+        assert_eq!(
+            parse_request(b"STATS prefix user:").await,
+            Request::StatsPrefix {
+                prefix: "user:".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"STATS PREFIX").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 1, got 0".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"STATS KEYS user:").await,
+            Request::Invalid {
+                error: "Unrecognized subcommand \"KEYS\"".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_keyrange() {
+        assert_eq!(
+            parse_request(b"KEYRANGE user:1000 user:2000").await,
+            Request::KeyRange {
+                start: "user:1000".to_string(),
+                end: "user:2000".to_string(),
+                limit: None,
+            }
+        );
+        assert_eq!(
+            parse_request(b"KEYRANGE a z limit 10").await,
+            Request::KeyRange {
+                start: "a".to_string(),
+                end: "z".to_string(),
+                limit: Some(10),
+            }
+        );
+        assert_eq!(
+            parse_request(b"KEYRANGE a z LIMIT -1").await,
+            Request::Invalid {
+                error: "Limit must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"KEYRANGE a z COUNT 10").await,
+            Request::Invalid {
+                error: "Unrecognized option \"COUNT\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"KEYRANGE a").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 2, got 1".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_keys() {
+        assert_eq!(
+            parse_request(b"KEYS user:*").await,
+            Request::Keys {
+                pattern: "user:*".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"KEYS").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 1, got 0".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_type() {
+        assert_eq!(
+            parse_request(b"TYPE foo").await,
+            Request::Type {
+                key: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"TYPE foo bar").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 1, got 2".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_rename() {
+        assert_eq!(
+            parse_request(b"RENAME foo bar").await,
+            Request::Rename {
+                key: "foo".to_string(),
+                newkey: "bar".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"renamenx foo bar").await,
+            Request::RenameNx {
+                key: "foo".to_string(),
+                newkey: "bar".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"RENAME foo").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 2, got 1".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_randomkey_dbsize() {
+        assert_eq!(parse_request(b"randomkey").await, Request::RandomKey);
+        assert_eq!(parse_request(b"DBSIZE").await, Request::DbSize);
+        assert_eq!(parse_request(b"flushdb").await, Request::FlushDb);
+        assert_eq!(parse_request(b"FLUSHALL").await, Request::FlushAll);
+        assert_eq!(
+            parse_request(b"DBSIZE foo").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 0, got 1".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_scan() {
+        assert_eq!(
+            parse_request(b"SCAN 0").await,
+            Request::Scan {
+                cursor: 0,
+                pattern: None,
+                kind: None,
+                count: DEFAULT_SCAN_COUNT,
+            }
+        );
+        assert_eq!(
+            parse_request(b"scan 17 count 100 match user:*").await,
+            Request::Scan {
+                cursor: 17,
+                pattern: Some("user:*".to_string()),
+                kind: None,
+                count: 100,
+            }
+        );
+        assert_eq!(
+            parse_request(b"SCAN -1").await,
+            Request::Invalid {
+                error: "Cursor must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SCAN 0 COUNT 0").await,
+            Request::Invalid {
+                error: "Count must be a positive integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SCAN 0 MATCH").await,
+            Request::Invalid {
+                error: "Expected a value for option \"MATCH\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SCAN 0 TYPE List").await,
+            Request::Scan {
+                cursor: 0,
+                pattern: None,
+                kind: Some("list".to_string()),
+                count: DEFAULT_SCAN_COUNT,
+            }
+        );
+        assert_eq!(
+            parse_request(b"SCAN 0 TYPE zset").await,
+            Request::Invalid {
+                error: "Unrecognized type \"zset\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SCAN").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected at least 1, got 0".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_del() {
+        assert_eq!(
+            parse_request(b"DEL foo").await,
+            Request::Del {
+                keys: vec!["foo".to_string()]
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEL foo bar baz").await,
+            Request::Del {
+                keys: vec!["foo".to_string(), "bar".to_string(), "baz".to_string()]
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEL").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected at least 1, got 0".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_exists() {
+        assert_eq!(
+            parse_request(b"EXISTS foo bar").await,
+            Request::Exists {
+                keys: vec!["foo".to_string(), "bar".to_string()]
+            }
+        );
+        assert_eq!(
+            parse_request(b"EXISTS").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected at least 1, got 0".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_waitkey() {
+        assert_eq!(
+            parse_request(b"WAITKEY foo").await,
+            Request::WaitKey {
+                key: "foo".to_string(),
+                timeout: None
+            }
+        );
+        assert_eq!(
+            parse_request(b"WAITKEY foo 0").await,
+            Request::WaitKey {
+                key: "foo".to_string(),
+                timeout: None
+            }
+        );
+        assert_eq!(
+            parse_request(b"WAITKEY foo 5").await,
+            Request::WaitKey {
+                key: "foo".to_string(),
+                timeout: Some(5)
+            }
+        );
+        assert_eq!(
+            parse_request(b"WAITKEY foo soon").await,
+            Request::Invalid {
+                error: "Timeout must be a non-negative integer".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_expiry() {
+        assert_eq!(
+            parse_request(b"EXPIRE foo 10").await,
+            Request::Expire {
+                key: "foo".to_string(),
+                seconds: 10
+            }
+        );
+        assert_eq!(
+            parse_request(b"EXPIRE foo soon").await,
+            Request::Invalid {
+                error: "Seconds must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"PEXPIRE foo 1500").await,
+            Request::PExpire {
+                key: "foo".to_string(),
+                millis: 1500
+            }
+        );
+        assert_eq!(
+            parse_request(b"PEXPIRE foo -1").await,
+            Request::Invalid {
+                error: "Milliseconds must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"ttl foo").await,
+            Request::Ttl {
+                key: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"PTTL foo").await,
+            Request::PTtl {
+                key: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"EXPIREAT foo 1700000000").await,
+            Request::ExpireAt {
+                key: "foo".to_string(),
+                timestamp: 1700000000
+            }
+        );
+        assert_eq!(
+            parse_request(b"PEXPIREAT foo 1700000000000").await,
+            Request::PExpireAt {
+                key: "foo".to_string(),
+                timestamp: 1700000000000
+            }
+        );
+        assert_eq!(
+            parse_request(b"EXPIREAT foo tomorrow").await,
+            Request::Invalid {
+                error: "Timestamp must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"PEXPIRETIME foo").await,
+            Request::PExpireTime {
+                key: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"PERSIST foo").await,
+            Request::Persist {
+                key: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"PERSIST").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 1, got 0".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_field_expiry() {
+        assert_eq!(
+            parse_request(b"HEXPIRE session token 10").await,
+            Request::HExpire {
+                key: "session".to_string(),
+                field: "token".to_string(),
+                seconds: 10
+            }
+        );
+        assert_eq!(
+            parse_request(b"HPEXPIRE session token 1500").await,
+            Request::HPExpire {
+                key: "session".to_string(),
+                field: "token".to_string(),
+                millis: 1500
+            }
+        );
+        assert_eq!(
+            parse_request(b"HEXPIRE session token -1").await,
+            Request::Invalid {
+                error: "Seconds must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"HPEXPIRE session token soon").await,
+            Request::Invalid {
+                error: "Milliseconds must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"httl session token").await,
+            Request::HTtl {
+                key: "session".to_string(),
+                field: "token".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"HPERSIST session").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 2, got 1".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_versions() {
+        assert_eq!(
+            parse_request(b"VERSION foo").await,
+            Request::Version {
+                key: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar ifversion 12").await,
+            Request::SetIfVersion {
+                key: "foo".to_string(),
+                val: "bar".to_string(),
+                version: 12
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar IFVERSION -1").await,
+            Request::Invalid {
+                error: "Version must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar IFNOTVERSION 12").await,
+            Request::Invalid {
+                error: "Unrecognized option \"IFNOTVERSION\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar IFVERSION").await,
+            Request::Invalid {
+                error: "Unrecognized option \"IFVERSION\"".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_set_options() {
+        assert_eq!(
+            parse_request(b"SETEX foo 60 bar").await,
+            Request::SetWith {
+                key: "foo".to_string(),
+                val: "bar".to_string(),
+                condition: None,
+                expiry: SetExpiry::After(60000)
+            }
+        );
+        assert_eq!(
+            parse_request(b"psetex foo 1500 bar").await,
+            Request::SetWith {
+                key: "foo".to_string(),
+                val: "bar".to_string(),
+                condition: None,
+                expiry: SetExpiry::After(1500)
+            }
+        );
+        assert_eq!(
+            parse_request(b"SETEX foo 0 bar").await,
+            Request::Invalid {
+                error: "Seconds must be a positive integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"PSETEX foo bar").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 3, got 2".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar EX 60 NX").await,
+            Request::SetWith {
+                key: "foo".to_string(),
+                val: "bar".to_string(),
+                condition: Some(SetCondition::Absent),
+                expiry: SetExpiry::After(60000)
+            }
+        );
+        assert_eq!(
+            parse_request(b"set foo bar xx keepttl").await,
+            Request::SetWith {
+                key: "foo".to_string(),
+                val: "bar".to_string(),
+                condition: Some(SetCondition::Present),
+                expiry: SetExpiry::Keep
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar PX 1500").await,
+            Request::SetWith {
+                key: "foo".to_string(),
+                val: "bar".to_string(),
+                condition: None,
+                expiry: SetExpiry::After(1500)
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar EX 0").await,
+            Request::Invalid {
+                error: "Seconds must be a positive integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar PX").await,
+            Request::Invalid {
+                error: "Expected a value for option \"PX\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar NX XX").await,
+            Request::Invalid {
+                error: "Only one of NX and XX can be given".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SET foo bar EX 10 KEEPTTL").await,
+            Request::Invalid {
+                error: "Only one of EX, PX and KEEPTTL can be given".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_debug() {
+        assert_eq!(
+            parse_request(b"DEBUG JOURNAL 3").await,
+            Request::DebugJournal { client: 3 }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG JOURNAL me").await,
+            Request::Invalid {
+                error: "Client ID must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG CHAOS").await,
+            Request::DebugChaos {
+                action: ChaosAction::Status
+            }
+        );
+        assert_eq!(
+            parse_request(b"debug chaos latency 100").await,
+            Request::DebugChaos {
+                action: ChaosAction::Latency(100)
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG CHAOS DROP 0.5").await,
+            Request::DebugChaos {
+                action: ChaosAction::Drop(0.5)
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG CHAOS DISCONNECT 1").await,
+            Request::DebugChaos {
+                action: ChaosAction::Disconnect(1.0)
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG CHAOS OFF").await,
+            Request::DebugChaos {
+                action: ChaosAction::Off
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG CHAOS DROP 2").await,
+            Request::Invalid {
+                error: "Probability must be a number between 0 and 1".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG CHAOS LATENCY").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 2, got 1".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG CHAOS EXPLODE 1").await,
+            Request::Invalid {
+                error: "Unrecognized option \"EXPLODE\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG SLEEP 1").await,
+            Request::Invalid {
+                error: "Unrecognized subcommand \"SLEEP\"".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_quickfill() {
+        assert_eq!(
+            parse_request(b"DEBUG QUICKFILL 1000").await,
+            Request::DebugQuickfill {
+                fill: Fill {
+                    count: 1000,
+                    ..Fill::default()
+                }
+            }
+        );
+        assert_eq!(
+            parse_request(
+                b"DEBUG QUICKFILL 10 prefix sess: types string:3,hash size 16-256 \
+                  elements 8 ttl 60-3600 expiring 0.5 seed 7"
+            )
+            .await,
+            Request::DebugQuickfill {
+                fill: Fill {
+                    count: 10,
+                    prefix: "sess:".to_string(),
+                    types: vec![(FillType::String, 3), (FillType::Hash, 1)],
+                    size: (16, 256),
+                    elements: 8,
+                    ttl: Some((60, 3600)),
+                    expiring: 0.5,
+                    seed: 7,
+                }
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG QUICKFILL 10 TYPES string:0").await,
+            Request::Invalid {
+                error: "Types must be a list of string, list, set or hash, with optional weights"
+                    .to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG QUICKFILL 10 SIZE 256-16").await,
+            Request::Invalid {
+                error: "Size must be a non-negative integer or a range".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG QUICKFILL 10 ELEMENTS 0").await,
+            Request::Invalid {
+                error: "Elements must be a positive integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG QUICKFILL 1000000000000").await,
+            Request::Invalid {
+                error: "Count must be at most 10000000".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG QUICKFILL 10 SIZE 1000000000").await,
+            Request::Invalid {
+                error: "Size must be at most 1048576 bytes".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG QUICKFILL 10 ELEMENTS 1000000").await,
+            Request::Invalid {
+                error: "Elements must be at most 100000".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"DEBUG QUICKFILL 10000000 SIZE 1024").await,
+            Request::Invalid {
+                error: "Dataset would exceed the maximum size of 1073741824 bytes".to_string()
+            }
+        );
         assert_eq!(
-            parse_request("\u{0}PING\u{0}\u{0}\u{0}".as_bytes()).await,
-            Request::Ping
+            parse_request(b"DEBUG QUICKFILL 10 TTL").await,
+            Request::Invalid {
+                error: "Expected a value for option \"TTL\"".to_string()
+            }
         );
         assert_eq!(
-            parse_request(b"PING extra args").await,
+            parse_request(b"DEBUG QUICKFILL many").await,
             Request::Invalid {
-                error: "Unexpected number of arguments. Expected 0, got 2".to_string()
+                error: "Count must be a non-negative integer".to_string()
             }
         );
     }
@@ -274,6 +2304,18 @@ mod tests {
                 error: "Unexpected number of arguments. Expected 1, got 0".to_string()
             }
         );
+        assert_eq!(
+            parse_request(b"GETDEL foo").await,
+            Request::GetDel {
+                key: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"GETDEL foo bar").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 1, got 2".to_string()
+            }
+        );
         // Mixed case
         assert_eq!(
             parse_request(b"gEt foo").await,
@@ -399,6 +2441,121 @@ mod tests {
                 error: "Value to decrement by is a non-integer".to_string()
             }
         );
+        assert_eq!(
+            parse_request(b"INCRBYFLOAT foo -1.5e2").await,
+            Request::IncrByFloat {
+                key: "foo".to_string(),
+                delta: -150.0
+            }
+        );
+        assert_eq!(
+            parse_request(b"INCRBYFLOAT foo inf").await,
+            Request::Invalid {
+                error: "Value to increment by is not a finite number".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"APPEND foo bar").await,
+            Request::Append {
+                key: "foo".to_string(),
+                val: "bar".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"GETRANGE foo 0 -1").await,
+            Request::GetRange {
+                key: "foo".to_string(),
+                start: 0,
+                end: -1
+            }
+        );
+        assert_eq!(
+            parse_request(b"SETRANGE foo 6 bar").await,
+            Request::SetRange {
+                key: "foo".to_string(),
+                offset: 6,
+                val: "bar".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"SETRANGE foo -1 bar").await,
+            Request::Invalid {
+                error: "Offset must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"GETRANGE foo 0 end").await,
+            Request::Invalid {
+                error: "Start and end offsets must be integers".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"STRLEN foo").await,
+            Request::StrLen {
+                key: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"STRLEN").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 1, got 0".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_stralgo() {
+        assert_eq!(
+            parse_request(b"STRALGO LCS KEYS foo bar").await,
+            Request::StrAlgoLcs {
+                input: LcsInput::Keys("foo".to_string(), "bar".to_string()),
+                len: false,
+                idx: false,
+                min_match_len: 0,
+                with_match_len: false
+            }
+        );
+        assert_eq!(
+            parse_request(b"stralgo lcs strings ohmytext mynewtext IDX MINMATCHLEN 4 WITHMATCHLEN")
+                .await,
+            Request::StrAlgoLcs {
+                input: LcsInput::Strings("ohmytext".to_string(), "mynewtext".to_string()),
+                len: false,
+                idx: true,
+                min_match_len: 4,
+                with_match_len: true
+            }
+        );
+        assert_eq!(
+            parse_request(b"STRALGO LCS KEYS foo bar LEN IDX").await,
+            Request::Invalid {
+                error: "LEN and IDX cannot be combined. IDX already reports the length".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"STRALGO LCS KEYS foo bar MINMATCHLEN").await,
+            Request::Invalid {
+                error: "Minimum match length must be a non-negative integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"STRALGO LCS VALUES foo bar").await,
+            Request::Invalid {
+                error: "Unrecognized option \"VALUES\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"STRALGO DIFF KEYS foo bar").await,
+            Request::Invalid {
+                error: "Unrecognized algorithm \"DIFF\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"STRALGO LCS KEYS foo").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected at least 4, got 3".to_string()
+            }
+        );
     }
 
     #[tokio::test]
@@ -546,6 +2703,20 @@ mod tests {
                 error: "Unexpected number of arguments. Expected 3, got 2".to_string()
             }
         );
+        assert_eq!(
+            parse_request(b"HINCRBYFLOAT foo price 0.25").await,
+            Request::HIncrByFloat {
+                key: "foo".to_string(),
+                field: "price".to_string(),
+                delta: 0.25
+            }
+        );
+        assert_eq!(
+            parse_request(b"HINCRBYFLOAT foo price abc").await,
+            Request::Invalid {
+                error: "Value to increment by is not a finite number".to_string()
+            }
+        );
         assert_eq!(
             parse_request(b"HDel foo name").await,
             Request::HDel {
@@ -561,6 +2732,317 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_parse_request_blooms() {
+        assert_eq!(
+            parse_request(b"BF.RESERVE foo 0.001 1000").await,
+            Request::BFReserve {
+                key: "foo".to_string(),
+                error_rate: 0.001,
+                capacity: 1000
+            }
+        );
+        assert_eq!(
+            parse_request(b"BF.RESERVE foo 1.5 1000").await,
+            Request::Invalid {
+                error: "Error rate must be a number between 0 and 1".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"BF.RESERVE foo 0.01 0").await,
+            Request::Invalid {
+                error: "Capacity must be a positive integer".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"BF.RESERVE foo 0.01 1000000000000000000").await,
+            Request::Invalid {
+                error: "Filter would exceed the maximum size of 268435456 bytes".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"BF.RESERVE foo 1e-300 100000000").await,
+            Request::Invalid {
+                error: "Filter would exceed the maximum size of 268435456 bytes".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"bf.add foo apples").await,
+            Request::BFAdd {
+                key: "foo".to_string(),
+                item: "apples".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"BF.MADD foo apples oranges").await,
+            Request::BFMAdd {
+                key: "foo".to_string(),
+                items: vec!["apples".to_string(), "oranges".to_string()]
+            }
+        );
+        assert_eq!(
+            parse_request(b"BF.MADD foo").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected at least 2, got 1".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"BF.EXISTS foo apples").await,
+            Request::BFExists {
+                key: "foo".to_string(),
+                item: "apples".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"BF.EXISTS foo").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 2, got 1".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_cuckoos() {
+        assert_eq!(
+            parse_request(b"CF.ADD foo apples").await,
+            Request::CFAdd {
+                key: "foo".to_string(),
+                item: "apples".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"cf.exists foo apples").await,
+            Request::CFExists {
+                key: "foo".to_string(),
+                item: "apples".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"CF.DEL foo apples").await,
+            Request::CFDel {
+                key: "foo".to_string(),
+                item: "apples".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"CF.ADD foo apples oranges").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected 2, got 3".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_timeseries() {
+        assert_eq!(
+            parse_request(b"TS.CREATE temp").await,
+            Request::TSCreate {
+                key: "temp".to_string(),
+                retention: 0
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.CREATE temp RETENTION 60000").await,
+            Request::TSCreate {
+                key: "temp".to_string(),
+                retention: 60000
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.CREATE temp RETAIN 60000").await,
+            Request::Invalid {
+                error: "Unrecognized option \"RETAIN\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.ADD temp 1000 21.5").await,
+            Request::TSAdd {
+                key: "temp".to_string(),
+                timestamp: Some(1000),
+                value: 21.5
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.ADD temp * 21.5").await,
+            Request::TSAdd {
+                key: "temp".to_string(),
+                timestamp: None,
+                value: 21.5
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.ADD temp now 21.5").await,
+            Request::Invalid {
+                error: "Timestamp must be an integer or \"*\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.ADD temp 1000 warm").await,
+            Request::Invalid {
+                error: "Value must be a number".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.RANGE temp - +").await,
+            Request::TSRange {
+                key: "temp".to_string(),
+                from: i64::MIN,
+                to: i64::MAX,
+                aggregation: None
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.RANGE temp 0 5000 AGGREGATION avg 1000").await,
+            Request::TSRange {
+                key: "temp".to_string(),
+                from: 0,
+                to: 5000,
+                aggregation: Some((Aggregation::Avg, 1000))
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.RANGE temp 0 5000 AGGREGATION sum 1000").await,
+            Request::Invalid {
+                error: "Aggregation must be one of AVG, MIN or MAX".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"TS.RANGE temp 0 5000 AGGREGATION max 0").await,
+            Request::Invalid {
+                error: "Bucket duration must be a positive integer".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_vectors() {
+        assert_eq!(
+            parse_request(b"VADD docs intro 0.1 0.2 0.3").await,
+            Request::VAdd {
+                key: "docs".to_string(),
+                element: "intro".to_string(),
+                vector: vec![0.1, 0.2, 0.3]
+            }
+        );
+        assert_eq!(
+            parse_request(b"VADD docs intro").await,
+            Request::Invalid {
+                error: "Unexpected number of arguments. Expected at least 3, got 2".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"VADD docs intro 0.1 high").await,
+            Request::Invalid {
+                error: "Vector components must be numbers".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"VSIM docs 0.1 0.2 0.3").await,
+            Request::VSim {
+                key: "docs".to_string(),
+                query: vec![0.1, 0.2, 0.3],
+                metric: Metric::Cosine,
+                count: 10
+            }
+        );
+        assert_eq!(
+            parse_request(b"VSIM docs 1 2 METRIC euclidean COUNT 3").await,
+            Request::VSim {
+                key: "docs".to_string(),
+                query: vec![1.0, 2.0],
+                metric: Metric::Euclidean,
+                count: 3
+            }
+        );
+        assert_eq!(
+            parse_request(b"VSIM docs METRIC euclidean").await,
+            Request::Invalid {
+                error: "Vector components must be numbers".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"VSIM docs 1 2 METRIC manhattan").await,
+            Request::Invalid {
+                error: "Metric must be one of COSINE or EUCLIDEAN".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"VSIM docs 1 2 COUNT").await,
+            Request::Invalid {
+                error: "Unrecognized option \"COUNT\"".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_request_search() {
+        assert_eq!(
+            parse_request(b"FT.CREATE users PREFIX user: SCHEMA city age").await,
+            Request::FTCreate {
+                index: "users".to_string(),
+                prefix: "user:".to_string(),
+                fields: vec!["city".to_string(), "age".to_string()]
+            }
+        );
+        assert_eq!(
+            parse_request(b"FT.CREATE users PREFIX user: FIELDS city").await,
+            Request::Invalid {
+                error: "Unrecognized option \"FIELDS\"".to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"FT.SEARCH users @city:Tokyo").await,
+            Request::FTSearch {
+                index: "users".to_string(),
+                filters: vec![Filter::Eq {
+                    field: "city".to_string(),
+                    value: "Tokyo".to_string()
+                }],
+                offset: 0,
+                count: 10
+            }
+        );
+        assert_eq!(
+            parse_request(b"FT.SEARCH users \"@city:Tokyo @age:[20 +inf]\" LIMIT 5 20").await,
+            Request::FTSearch {
+                index: "users".to_string(),
+                filters: vec![
+                    Filter::Eq {
+                        field: "city".to_string(),
+                        value: "Tokyo".to_string()
+                    },
+                    Filter::Range {
+                        field: "age".to_string(),
+                        min: 20.0,
+                        max: f64::INFINITY
+                    }
+                ],
+                offset: 5,
+                count: 20
+            }
+        );
+        assert_eq!(
+            parse_request(b"FT.SEARCH users \"@age:[20]\"").await,
+            Request::Invalid {
+                error: "Invalid query. Expected filters such as @field:value or @field:[min max]"
+                    .to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"FT.SEARCH users city:Tokyo").await,
+            Request::Invalid {
+                error: "Invalid query. Expected filters such as @field:value or @field:[min max]"
+                    .to_string()
+            }
+        );
+        assert_eq!(
+            parse_request(b"FT.SEARCH users @city:Tokyo LIMIT 0 many").await,
+            Request::Invalid {
+                error: "Limit offset and count must be non-negative integers".to_string()
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_parse_request_meta() {
         assert_eq!(
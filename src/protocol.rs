@@ -0,0 +1,418 @@
+//! Framing of requests read from a client connection.
+//!
+//! Two request formats are accepted on the same connection:
+//! - Inline requests are plain text, such as `SET foo bar`, ending with a
+//!   line feed or at the end of the data received so far. Arguments are
+//!   separated by whitespace and may be quoted.
+//! - Multibulk requests start with `*` and the number of arguments, followed
+//!   by each argument prefixed with `$` and its length in bytes, for example
+//!   `*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n`. Since arguments are
+//!   delimited by length, they may contain any bytes.
+//!
+//! Requests that exceed the configured limits are rejected before they are
+//! buffered in full, so that a client cannot exhaust the server's memory.
+//!
+//! Replies are written in the human-readable format that the CLI prints,
+//! unless a connection selects RESP with `FORMAT resp`. Both formats are
+//! encoded from a typed `Reply`, so that a key such as `(nil)` is still sent
+//! to RESP clients as a string.
+
+use crate::server::TERMINATOR;
+use std::fmt;
+
+/// Byte that starts a multibulk request
+pub const MULTIBULK_PREFIX: u8 = b'*';
+
+/// Byte that starts an argument of a multibulk request
+pub const BULK_PREFIX: u8 = b'$';
+
+/// Maximum length of the line holding a length, such as `$3\r\n`
+const MAX_LENGTH_LINE: usize = 32;
+
+/// Format of the replies written to a client connection
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplyFormat {
+    /// The text printed by the CLI, such as `(integer) 1`, ending with `\r\n`
+    Human,
+    /// The Redis serialization protocol, for programmatic clients
+    Resp,
+}
+
+/// A reply to a request
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply {
+    /// A status such as `OK` or `PONG`, printed as is
+    Status(String),
+    /// A string value, printed in quotes
+    Str(String),
+    /// A string printed without quotes, such as a key or member in an array
+    Bare(String),
+    Integer(i64),
+    Array(Vec<Reply>),
+    Nil,
+    Error(String),
+}
+
+/// Write a reply in the human-readable format, without its terminator.
+/// Continuation lines of nested arrays are indented to align with the start
+/// of their element.
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reply::Status(s) | Reply::Bare(s) => write!(f, "{}", s),
+            Reply::Str(s) => write!(f, "\"{}\"", s),
+            Reply::Integer(int) => write!(f, "(integer) {}", int),
+            Reply::Array(elements) if elements.is_empty() => write!(f, "(empty list or set)"),
+            Reply::Array(elements) => {
+                for (idx, element) in elements.iter().enumerate() {
+                    let prefix = format!("{}) ", idx + 1);
+                    if idx > 0 {
+                        writeln!(f)?;
+                    }
+                    let text = element.to_string();
+                    let indent = format!("\n{:width$}", "", width = prefix.len());
+                    write!(f, "{}{}", prefix, text.replace('\n', &indent))?;
+                }
+                Ok(())
+            }
+            Reply::Nil => write!(f, "(nil)"),
+            Reply::Error(e) => write!(f, "(error) {}", e),
+        }
+    }
+}
+
+/// Compare a reply with its human-readable format
+impl PartialEq<str> for Reply {
+    fn eq(&self, other: &str) -> bool {
+        let text = self.to_string();
+        text == other
+    }
+}
+
+impl PartialEq<&str> for Reply {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Reply {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+/// Limits on the requests that a client can send. A limit of 0 disables it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// Maximum number of arguments in a request, including the command name
+    pub max_args: usize,
+
+    /// Maximum size of a request in bytes
+    pub max_size: usize,
+}
+
+impl Limits {
+    fn check_args(&self, argc: usize) -> Result<(), String> {
+        match self.max_args > 0 && argc > self.max_args {
+            true => Err(format!(
+                "Protocol error: too many arguments, limit is {}",
+                self.max_args
+            )),
+            false => Ok(()),
+        }
+    }
+
+    fn check_size(&self, size: usize) -> Result<(), String> {
+        match self.max_size > 0 && size > self.max_size {
+            true => Err(format!(
+                "Protocol error: request is too large, limit is {} bytes",
+                self.max_size
+            )),
+            false => Ok(()),
+        }
+    }
+}
+
+/// A single request delimited in a client's input
+#[derive(Debug, PartialEq)]
+pub enum Frame<'a> {
+    Inline(&'a [u8]),
+    Multibulk(Vec<&'a [u8]>),
+}
+
+/// Read the first request in a buffer.
+/// Return the request and the number of bytes it occupies, or `None` if the
+/// buffer does not yet hold a complete request. Inline requests are complete
+/// once their newline arrives, or at `eof` if the client closed the connection
+/// after the last line. Return an error if the input is not a valid request or
+/// exceeds the limits, after which the connection cannot be recovered.
+pub fn read_frame<'a>(
+    buf: &'a [u8],
+    limits: &Limits,
+    eof: bool,
+) -> Result<Option<(Frame<'a>, usize)>, String> {
+    match buf.first() {
+        None => Ok(None),
+        Some(&MULTIBULK_PREFIX) => read_multibulk(buf, limits),
+        Some(_) => {
+            let len = match buf.iter().position(|&b| b == b'\n') {
+                Some(i) => i + 1,
+                None if eof => buf.len(),
+                None => {
+                    limits.check_size(buf.len())?;
+                    return Ok(None);
+                }
+            };
+            limits.check_size(len)?;
+            // Arguments are counted as whitespace-separated words, which
+            // counts a quoted argument containing whitespace more than once
+            let words = buf[..len]
+                .split(|b| b.is_ascii_whitespace() || *b == 0)
+                .filter(|word| !word.is_empty())
+                .count();
+            limits.check_args(words)?;
+            Ok(Some((Frame::Inline(&buf[..len]), len)))
+        }
+    }
+}
+
+fn read_multibulk<'a>(
+    buf: &'a [u8],
+    limits: &Limits,
+) -> Result<Option<(Frame<'a>, usize)>, String> {
+    let (argc, mut pos) = match read_length(buf, 0, MULTIBULK_PREFIX)? {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+    limits.check_args(argc)?;
+    let mut args = Vec::with_capacity(argc.min(64));
+    for _ in 0..argc {
+        let (len, start) = match read_length(buf, pos, BULK_PREFIX)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let end = start.saturating_add(len);
+        limits.check_size(end.saturating_add(2))?;
+        if buf.len() < end.saturating_add(2) {
+            return Ok(None);
+        }
+        if &buf[end..end + 2] != b"\r\n" {
+            return Err("Protocol error: expected '\\r\\n' after argument".to_string());
+        }
+        args.push(&buf[start..end]);
+        pos = end + 2;
+    }
+    Ok(Some((Frame::Multibulk(args), pos)))
+}
+
+/// Read a length line such as `$3\r\n` starting at `pos`.
+/// Return the length and the position following the line.
+fn read_length(buf: &[u8], pos: usize, prefix: u8) -> Result<Option<(usize, usize)>, String> {
+    let line = &buf[pos..];
+    let end = match line.windows(2).position(|w| w == b"\r\n") {
+        Some(end) => end,
+        None => {
+            // A line that starts incorrectly or runs too long is invalid
+            // however it continues
+            return match line.first() {
+                Some(&b) if b != prefix => Err(unexpected_byte(b, prefix)),
+                _ if line.len() > MAX_LENGTH_LINE => Err(invalid_length(prefix)),
+                _ => Ok(None),
+            };
+        }
+    };
+    if line[0] != prefix {
+        return Err(unexpected_byte(line[0], prefix));
+    }
+    let len = std::str::from_utf8(&line[1..end])
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok());
+    match len {
+        Some(len) => Ok(Some((len, pos + end + 2))),
+        None => Err(invalid_length(prefix)),
+    }
+}
+
+fn invalid_length(prefix: u8) -> String {
+    format!(
+        "Protocol error: invalid {} length",
+        match prefix {
+            MULTIBULK_PREFIX => "multibulk",
+            _ => "bulk",
+        }
+    )
+}
+
+fn unexpected_byte(found: u8, expected: u8) -> String {
+    format!(
+        "Protocol error: expected '{}', got '{}'",
+        expected as char,
+        (found as char).escape_default()
+    )
+}
+
+/// Encode a reply in a reply format, including its terminator.
+pub fn encode_reply(reply: &Reply, format: ReplyFormat) -> Vec<u8> {
+    let mut out = Vec::new();
+    match format {
+        ReplyFormat::Human => {
+            out.extend_from_slice(reply.to_string().as_bytes());
+            out.extend_from_slice(TERMINATOR);
+        }
+        ReplyFormat::Resp => encode_resp(reply, &mut out),
+    }
+    out
+}
+
+fn encode_resp(reply: &Reply, out: &mut Vec<u8>) {
+    match reply {
+        // Simple strings cannot contain line breaks, such as in INFO replies
+        Reply::Status(s) if !s.contains(&['\r', '\n'][..]) => {
+            out.extend_from_slice(format!("+{}\r\n", s).as_bytes());
+        }
+        Reply::Status(s) | Reply::Str(s) | Reply::Bare(s) => {
+            out.extend_from_slice(format!("${}\r\n{}\r\n", s.len(), s).as_bytes());
+        }
+        Reply::Integer(int) => out.extend_from_slice(format!(":{}\r\n", int).as_bytes()),
+        Reply::Array(elements) => {
+            out.extend_from_slice(
+                format!("{}{}\r\n", MULTIBULK_PREFIX as char, elements.len()).as_bytes(),
+            );
+            for element in elements {
+                encode_resp(element, out);
+            }
+        }
+        Reply::Nil => out.extend_from_slice(b"$-1\r\n"),
+        Reply::Error(e) => {
+            // Errors start with a code, such as BUSY, or the generic ERR
+            let code = e.split(' ').next().unwrap_or_default();
+            let has_code = code.len() > 1 && code.bytes().all(|b| b.is_ascii_uppercase());
+            let e = e.replace(&['\r', '\n'][..], " ");
+            match has_code {
+                true => out.extend_from_slice(format!("-{}\r\n", e).as_bytes()),
+                false => out.extend_from_slice(format!("-ERR {}\r\n", e).as_bytes()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_frame() {
+        let none = Limits::default();
+        assert_eq!(read_frame(b"", &none, false), Ok(None));
+        assert_eq!(read_frame(b"PING", &none, false), Ok(None));
+        assert_eq!(
+            read_frame(b"PING", &none, true),
+            Ok(Some((Frame::Inline(b"PING"), 4)))
+        );
+        assert_eq!(
+            read_frame(b"PING\nGET foo\n", &none, false),
+            Ok(Some((Frame::Inline(b"PING\n"), 5)))
+        );
+
+        let input = b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$5\r\nb r\r\n\r\nPING";
+        let args: Vec<&[u8]> = vec![b"SET", b"foo", b"b r\r\n"];
+        assert_eq!(
+            read_frame(input, &none, false),
+            Ok(Some((Frame::Multibulk(args), input.len() - 4)))
+        );
+        assert_eq!(
+            read_frame(b"*0\r\n", &none, false),
+            Ok(Some((Frame::Multibulk(vec![]), 4)))
+        );
+
+        // Incomplete requests wait for more input
+        for i in 1..input.len() - 4 {
+            assert_eq!(read_frame(&input[..i], &none, false), Ok(None));
+        }
+        let inline = b"SET foo bar\n";
+        for i in 1..inline.len() {
+            assert_eq!(read_frame(&inline[..i], &none, false), Ok(None));
+        }
+
+        // Malformed requests are rejected
+        assert!(read_frame(b"*x\r\n", &none, false).is_err());
+        assert!(read_frame(b"*1\r\n:3\r\n", &none, false).is_err());
+        assert!(read_frame(b"*1\r\n:", &none, false).is_err());
+        assert!(read_frame(b"*1\r\n$-1\r\n", &none, false).is_err());
+        assert!(read_frame(b"*1\r\n$3\r\nfoobar\r\n", &none, false).is_err());
+        assert!(read_frame(&[b'*'; 64], &none, false).is_err());
+    }
+
+    #[test]
+    fn test_read_frame_limits() {
+        let limits = Limits {
+            max_args: 3,
+            max_size: 32,
+        };
+        assert!(read_frame(b"SET foo bar\n", &limits, false).is_ok());
+        assert!(read_frame(b"SET foo bar baz\n", &limits, false).is_err());
+        assert!(read_frame(&[b'a'; 33], &limits, false).is_err());
+        assert!(read_frame(
+            b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n",
+            &limits,
+            false
+        )
+        .is_ok());
+        assert!(read_frame(b"*4\r\n", &limits, false).is_err());
+
+        // Oversized arguments are rejected before they are received
+        assert!(read_frame(b"*2\r\n$3\r\nGET\r\n$100\r\n", &limits, false).is_err());
+        assert!(read_frame(b"*1\r\n$18446744073709551615\r\n", &limits, false).is_err());
+    }
+
+    #[test]
+    fn test_encode_reply() {
+        let resp = |reply| String::from_utf8(encode_reply(&reply, ReplyFormat::Resp)).unwrap();
+        assert_eq!(
+            encode_reply(&Reply::Integer(1), ReplyFormat::Human),
+            b"(integer) 1\r\n"
+        );
+        assert_eq!(resp(Reply::Status("OK".to_string())), "+OK\r\n");
+        assert_eq!(resp(Reply::Str("bar".to_string())), "$3\r\nbar\r\n");
+        assert_eq!(resp(Reply::Integer(-2)), ":-2\r\n");
+        assert_eq!(resp(Reply::Nil), "$-1\r\n");
+        assert_eq!(resp(Reply::Array(vec![])), "*0\r\n");
+        let scan = Reply::Array(vec![
+            Reply::Str("0".to_string()),
+            Reply::Array(vec![
+                Reply::Bare("foo".to_string()),
+                Reply::Bare("bar".to_string()),
+            ]),
+        ]);
+        assert_eq!(scan, "1) \"0\"\n2) 1) foo\n   2) bar");
+        assert_eq!(
+            resp(scan),
+            "*2\r\n$1\r\n0\r\n*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n"
+        );
+        assert_eq!(
+            resp(Reply::Bare("# Stats\nnamespaces:1".to_string())),
+            "$20\r\n# Stats\nnamespaces:1\r\n"
+        );
+        assert_eq!(
+            resp(Reply::Error("Version mismatch".to_string())),
+            "-ERR Version mismatch\r\n"
+        );
+        assert_eq!(
+            resp(Reply::Error("BUSY The server is overloaded".to_string())),
+            "-BUSY The server is overloaded\r\n"
+        );
+
+        // Keys that look like other replies in the human-readable format are
+        // still sent as strings
+        let keys = Reply::Array(vec![
+            Reply::Bare("(nil)".to_string()),
+            Reply::Bare("(integer) 5".to_string()),
+            Reply::Bare("1) x".to_string()),
+        ]);
+        assert_eq!(keys, "1) (nil)\n2) (integer) 5\n3) 1) x");
+        assert_eq!(
+            resp(keys),
+            "*3\r\n$5\r\n(nil)\r\n$11\r\n(integer) 5\r\n$4\r\n1) x\r\n"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Parsing of the PROXY protocol header sent by load balancers.
+//!
+//! A proxy that forwards TCP connections, such as HAProxy, can prefix each
+//! connection with a header holding the address of the client it accepted.
+//! Both versions of the header are accepted:
+//! - Version 1 is a line of text, such as
+//!   `PROXY TCP4 192.168.0.1 192.168.0.11 56324 6464\r\n`.
+//! - Version 2 is binary, starting with a fixed 12-byte signature.
+//!
+//! See https://www.haproxy.org/download/2.0/doc/proxy-protocol.txt
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Prefix of a version 1 header
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+/// Maximum length of a version 1 header, including the terminator
+const V1_MAX_LENGTH: usize = 107;
+
+/// Signature that starts a version 2 header
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Length of a version 2 header before its addresses
+const V2_HEADER_LENGTH: usize = 16;
+
+/// Read a PROXY protocol header at the start of a buffer.
+/// Return the source address of the proxied connection and the number of
+/// bytes the header occupies, or `None` if the buffer does not yet hold a
+/// complete header. The address is `None` if the proxy did not forward a
+/// TCP connection, such as for its own health checks.
+pub fn read_proxy_header(buf: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, String> {
+    if is_prefix(buf, V1_PREFIX) {
+        read_v1(buf)
+    } else if is_prefix(buf, V2_SIGNATURE) {
+        read_v2(buf)
+    } else {
+        Err("Expected a PROXY protocol header".to_string())
+    }
+}
+
+/// Return if the buffer and the prefix agree up to the length of either.
+fn is_prefix(buf: &[u8], prefix: &[u8]) -> bool {
+    let len = buf.len().min(prefix.len());
+    buf[..len] == prefix[..len]
+}
+
+fn read_v1(buf: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, String> {
+    let end = match buf.windows(2).position(|w| w == b"\r\n") {
+        Some(end) if end + 2 <= V1_MAX_LENGTH => end,
+        None if buf.len() < V1_MAX_LENGTH => return Ok(None),
+        _ => return Err("PROXY protocol header is too long".to_string()),
+    };
+    let line = match std::str::from_utf8(&buf[V1_PREFIX.len()..end]) {
+        Ok(line) => line,
+        Err(_) => return Err(invalid_header()),
+    };
+    let fields: Vec<&str> = line.split(' ').collect();
+    let source = match fields[0] {
+        // The rest of the line is ignored for unknown protocols
+        "UNKNOWN" => None,
+        "TCP4" | "TCP6" if fields.len() == 5 => {
+            let ip = fields[1].parse::<IpAddr>().map_err(|_| invalid_header())?;
+            let port = fields[3].parse::<u16>().map_err(|_| invalid_header())?;
+            match (fields[0], ip) {
+                ("TCP4", IpAddr::V4(_)) | ("TCP6", IpAddr::V6(_)) => {}
+                _ => return Err(invalid_header()),
+            }
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err(invalid_header()),
+    };
+    Ok(Some((source, end + 2)))
+}
+
+fn read_v2(buf: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, String> {
+    if buf.len() < V2_HEADER_LENGTH {
+        return Ok(None);
+    }
+    let (version, command) = (buf[12] >> 4, buf[12] & 0x0F);
+    if version != 2 {
+        return Err(invalid_header());
+    }
+    let len = V2_HEADER_LENGTH + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if buf.len() < len {
+        return Ok(None);
+    }
+    let addrs = &buf[V2_HEADER_LENGTH..len];
+    let source = match command {
+        // Connections established by the proxy itself carry no address
+        0x0 => None,
+        0x1 => match buf[13] {
+            // TCP over IPv4
+            0x11 if addrs.len() >= 12 => {
+                let mut ip = [0; 4];
+                ip.copy_from_slice(&addrs[..4]);
+                let port = u16::from_be_bytes([addrs[8], addrs[9]]);
+                Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::from(ip)), port))
+            }
+            // TCP over IPv6
+            0x21 if addrs.len() >= 36 => {
+                let mut ip = [0; 16];
+                ip.copy_from_slice(&addrs[..16]);
+                let port = u16::from_be_bytes([addrs[32], addrs[33]]);
+                Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(ip)), port))
+            }
+            0x11 | 0x21 => return Err(invalid_header()),
+            // Other protocols are accepted, but have no usable address
+            _ => None,
+        },
+        _ => return Err(invalid_header()),
+    };
+    Ok(Some((source, len)))
+}
+
+fn invalid_header() -> String {
+    "Invalid PROXY protocol header".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_proxy_header_v1() {
+        let header = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 6464\r\nPING\n";
+        let source = "192.168.0.1:56324".parse().unwrap();
+        assert_eq!(
+            read_proxy_header(header),
+            Ok(Some((Some(source), header.len() - 5)))
+        );
+        let header = b"PROXY TCP6 ::1 ::1 56324 6464\r\n";
+        let source = "[::1]:56324".parse().unwrap();
+        assert_eq!(
+            read_proxy_header(header),
+            Ok(Some((Some(source), header.len())))
+        );
+        assert_eq!(
+            read_proxy_header(b"PROXY UNKNOWN\r\n"),
+            Ok(Some((None, 15)))
+        );
+
+        // Incomplete headers wait for more input
+        assert_eq!(read_proxy_header(b""), Ok(None));
+        assert_eq!(read_proxy_header(b"PRO"), Ok(None));
+        assert_eq!(read_proxy_header(b"PROXY TCP4 192.168"), Ok(None));
+
+        assert!(read_proxy_header(b"PING\r\n").is_err());
+        assert!(read_proxy_header(b"PROXY TCP4 ::1 ::1 56324 6464\r\n").is_err());
+        assert!(read_proxy_header(b"PROXY TCP4 192.168.0.1 192.168.0.11 x 6464\r\n").is_err());
+        assert!(read_proxy_header(b"PROXY UDP4\r\n").is_err());
+        assert!(read_proxy_header(&[b'P'; 200][..]).is_err());
+    }
+
+    #[test]
+    fn test_read_proxy_header_v2() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[192, 168, 0, 1, 192, 168, 0, 11]);
+        header.extend_from_slice(&56324u16.to_be_bytes());
+        header.extend_from_slice(&6464u16.to_be_bytes());
+        let source = "192.168.0.1:56324".parse().unwrap();
+        assert_eq!(
+            read_proxy_header(&header),
+            Ok(Some((Some(source), header.len())))
+        );
+        for i in 0..header.len() {
+            assert_eq!(read_proxy_header(&header[..i]), Ok(None));
+        }
+
+        // Local connections from the proxy carry no address
+        let mut local = V2_SIGNATURE.to_vec();
+        local.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        assert_eq!(read_proxy_header(&local), Ok(Some((None, 16))));
+
+        let mut invalid = header.clone();
+        invalid[12] = 0x11;
+        assert!(read_proxy_header(&invalid).is_err());
+        let mut short = V2_SIGNATURE.to_vec();
+        short.extend_from_slice(&[0x21, 0x11, 0, 4, 192, 168, 0, 1]);
+        assert!(read_proxy_header(&short).is_err());
+    }
+}
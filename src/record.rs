@@ -0,0 +1,90 @@
+//! Mapping of Rust types to hashes.
+//!
+//! With the `derive` feature enabled, `KibaHash` can be derived for structs
+//! with named fields, storing each field as a field of a hash:
+//!
+//! ```ignore
+//! use kiba::KibaHash;
+//!
+//! #[derive(KibaHash)]
+//! struct User {
+//!     name: String,
+//!     age: u32,
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// A type that can be stored as the fields of a hash
+pub trait KibaHash: Sized {
+    /// Names of the hash fields that the type is stored in
+    const FIELDS: &'static [&'static str];
+
+    /// Return the hash fields and values representing the value.
+    fn to_hash(&self) -> Vec<(String, String)>;
+
+    /// Build a value from hash fields.
+    fn from_hash(fields: &HashMap<String, String>) -> Result<Self, String>;
+}
+
+/// Parse the value of a hash field.
+pub fn field<T: FromStr>(fields: &HashMap<String, String>, name: &str) -> Result<T, String> {
+    match fields.get(name) {
+        Some(val) => val
+            .parse()
+            .map_err(|_| format!("Invalid value for field \"{}\": \"{}\"", name, val)),
+        None => Err(format!("Missing field \"{}\"", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct User {
+        name: String,
+        age: u32,
+    }
+
+    impl KibaHash for User {
+        const FIELDS: &'static [&'static str] = &["name", "age"];
+
+        fn to_hash(&self) -> Vec<(String, String)> {
+            vec![
+                ("name".to_string(), self.name.clone()),
+                ("age".to_string(), self.age.to_string()),
+            ]
+        }
+
+        fn from_hash(fields: &HashMap<String, String>) -> Result<Self, String> {
+            Ok(Self {
+                name: field(fields, "name")?,
+                age: field(fields, "age")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_field() {
+        let user = User {
+            name: "shoyo".to_string(),
+            age: 24,
+        };
+        let mut fields: HashMap<String, String> = user.to_hash().into_iter().collect();
+        let user = User::from_hash(&fields).unwrap();
+        assert_eq!(user.name, "shoyo");
+        assert_eq!(user.age, 24);
+
+        fields.insert("age".to_string(), "old".to_string());
+        assert_eq!(
+            User::from_hash(&fields).err().unwrap(),
+            "Invalid value for field \"age\": \"old\""
+        );
+        fields.remove("age");
+        assert_eq!(
+            User::from_hash(&fields).err().unwrap(),
+            "Missing field \"age\""
+        );
+    }
+}
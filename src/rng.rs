@@ -0,0 +1,74 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Mixed into seeds so that small seeds still produce well-distributed states
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// A small, fast pseudorandom number generator (xorshift64*).
+///
+/// Not suitable for cryptographic use. Generators created with the same seed
+/// produce the same sequence, which makes randomized behavior reproducible.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero
+        let state = match seed ^ GOLDEN_GAMMA {
+            0 => GOLDEN_GAMMA,
+            state => state,
+        };
+        Self { state }
+    }
+
+    /// Create a generator seeded from the system clock.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Return a float uniformly distributed in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Return an integer uniformly distributed in [0, n).
+    pub fn below(&mut self, n: u64) -> u64 {
+        match n {
+            0 => 0,
+            _ => self.next_u64() % n,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let seq: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(seq, (0..10).map(|_| b.next_u64()).collect::<Vec<u64>>());
+        assert_ne!(Rng::new(43).next_u64(), seq[0]);
+
+        let mut rng = Rng::new(0);
+        for _ in 0..1000 {
+            let f = rng.next_f64();
+            assert!((0.0..1.0).contains(&f));
+            assert!(rng.below(7) < 7);
+        }
+    }
+}
@@ -1,12 +1,37 @@
-use crate::config::Config;
-use crate::executor::{execute, Request, Response};
-use crate::parser::parse_request;
-use crate::store::{StdStore, Store};
+use crate::chaos::{Chaos, Fault};
+use crate::config::{Config, HashFunction, RuntimeFlavor};
+use crate::executor::{
+    f_bare, f_empty, f_err, f_nil, f_uint, f_vec, unix_millis, Executor, Priority, Request,
+    Response, DEFAULT_NAMESPACE,
+};
+use crate::http::serve_http;
+use crate::journal::{Entry, Journal};
+use crate::lexer::Flag;
+use crate::parser::parse_frame;
+use crate::protocol::{encode_reply, read_frame, Limits, ReplyFormat};
+use crate::proxy::read_proxy_header;
+use crate::rng::Rng;
+use crate::store::{ExpiringStore, FnvStore, StdStore};
+use crate::webhook::{Event, MAX_CONCURRENT_DELIVERIES};
 use log::*;
-use std::net::SocketAddr;
+use net2::unix::UnixTcpBuilderExt;
+use net2::TcpBuilder;
+use std::collections::HashMap;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, BufReader};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::io::BufWriter;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::prelude::*;
-use tokio::sync::{mpsc, oneshot};
+use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::broadcast::{self, RecvError};
+use tokio::sync::{mpsc, oneshot, Notify, RwLock as AsyncRwLock, Semaphore};
+use tokio::time::{delay_for, timeout};
 
 /// Server's representation of a client
 pub struct ClientConnection {
@@ -26,100 +51,1124 @@ impl ClientConnection {
     }
 }
 
+/// Addresses of connected clients by identifier
+type ClientRegistry = Arc<Mutex<HashMap<u64, SocketAddr>>>;
+
+/// Fault-injection settings shared by client connections, if chaos mode is enabled
+type SharedChaos = Option<Arc<RwLock<Chaos>>>;
+
+/// Recent requests of each connected client, if journals are enabled
+type SharedJournals = Option<Arc<Mutex<HashMap<u64, Journal>>>>;
+
+/// Executor shared by client connections, used to execute reads concurrently
+type SharedExecutor<S> = Arc<AsyncRwLock<Executor<S>>>;
+
+/// Namespace and key of each write made by the executor thread
+type KeyChanges = broadcast::Sender<(String, String)>;
+
+/// Number of writes buffered for tasks waiting for a key to change
+const CHANGES_CAPACITY: usize = 1024;
+
+/// Requests waiting for the executor thread, shared by client connections
+#[derive(Debug, Default)]
+struct Backlog {
+    /// Number of requests sent to or waiting to be sent to the executor
+    depth: AtomicUsize,
+
+    /// Number of requests rejected instead of being queued
+    shed: AtomicU64,
+
+    /// Depth beyond which requests from low-priority clients are rejected,
+    /// or 0 to never reject them
+    limit: usize,
+}
+
+impl Backlog {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    /// Return if a request from a client with the priority can be queued,
+    /// counting it as waiting if so.
+    fn admit(&self, priority: Priority) -> bool {
+        if priority == Priority::Low
+            && self.limit > 0
+            && self.depth.load(Ordering::SeqCst) >= self.limit
+        {
+            self.shed.fetch_add(1, Ordering::SeqCst);
+            return false;
+        }
+        self.depth.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    /// Count a request as taken off the queue, returning the remaining depth.
+    fn take(&self) -> usize {
+        self.depth.fetch_sub(1, Ordering::SeqCst) - 1
+    }
+}
+
+/// Channels between a client connection and the executor thread
+#[derive(Clone)]
+struct ExecutorLink {
+    tx: mpsc::Sender<Message>,
+    backlog: Arc<Backlog>,
+    changes: KeyChanges,
+}
+
+/// Wait until the executor thread writes a key of a namespace. Writes are
+/// watched from when this is called, not from when the future is first polled.
+/// Writes that were missed because the waiting task fell behind may have
+/// included the key, so they end the wait as well.
+fn wait_for_change(changes: &KeyChanges, namespace: &str, key: &str) -> impl Future<Output = ()> {
+    let mut rx = changes.subscribe();
+    let (namespace, key) = (namespace.to_string(), key.to_string());
+    async move {
+        loop {
+            match rx.recv().await {
+                Ok((ns, k)) if ns == namespace && k == key => return,
+                Ok(_) => continue,
+                Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+/// Settings that apply to every client connection
+#[derive(Clone, Copy)]
+struct ClientSettings {
+    limits: Limits,
+    proxy_protocol: bool,
+}
+
+/// A handle used to observe and stop a running server from another task
+#[derive(Clone)]
+pub struct ServerHandle {
+    shutdown: Arc<Notify>,
+    clients: ClientRegistry,
+    changes: KeyChanges,
+}
+
+impl ServerHandle {
+    /// Stop accepting connections and drain existing ones, as on SIGTERM.
+    pub fn shutdown(&self) {
+        self.shutdown.notify();
+    }
+
+    /// Return the number of connected clients.
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Wait until a key of a namespace is next written by a client, such as
+    /// by SET or DEL. Keys removed because they expired are not reported.
+    /// Writes made after this is called end the wait, even if they are made
+    /// before the returned future is awaited.
+    pub fn wait_for_key_change(&self, namespace: &str, key: &str) -> impl Future<Output = ()> {
+        wait_for_change(&self.changes, namespace, key)
+    }
+}
+
+/// Capacity of the buffer for reading requests from each client
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// Capacity of the buffer for writing responses to each client
+const WRITE_BUFFER_SIZE: usize = 4096;
+
+/// Sequence that ends every reply, so that clients can tell where one ends
+pub const TERMINATOR: &[u8] = b"\r\n";
+
 /// Message sent between a server's threads to mutate the data store
 #[derive(Debug)]
 struct Message {
     /// Request contains the mutation to be executed by the executor thread
     req: Request,
 
+    /// Namespace whose keyspace the request operates on
+    namespace: String,
+
     /// A single-use channel to pass a response back from the executor thread
     pipe: oneshot::Sender<Response>,
 }
 
-pub async fn start_server(config: Config) -> Result<(), Box<dyn std::error::Error>> {
-    let mut store: StdStore = Store::new();
+/// Bind a listener to the configured address.
+/// If `reuse-port` is enabled, the socket is bound with SO_REUSEPORT so that a
+/// new server process can bind the same address while this one drains.
+fn bind_listener(config: &Config) -> io::Result<TcpListener> {
+    let addr = match config.bind.to_socket_addrs()?.next() {
+        Some(addr) => addr,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "no address")),
+    };
+    let builder = match addr {
+        SocketAddr::V4(_) => TcpBuilder::new_v4()?,
+        SocketAddr::V6(_) => TcpBuilder::new_v6()?,
+    };
+    builder.reuse_address(true)?;
+    builder.reuse_port(config.reuse_port)?;
+    let listener = builder.bind(addr)?.listen(1024)?;
+    listener.set_nonblocking(true)?;
+    TcpListener::from_std(listener)
+}
+
+/// Load a store from the snapshot at path.
+fn load_snapshot<S: ExpiringStore>(path: &str) -> io::Result<S> {
+    let store = File::open(path).and_then(|file| S::deserialize(&mut BufReader::new(file)));
+    if let Err(e) = &store {
+        error!("Could not restore snapshot at {}: {}", path, e);
+    }
+    store
+}
+
+/// A server instance that owns its listener, client registry and executor.
+pub struct Server {
+    config: Config,
+    listener: TcpListener,
+    clients: ClientRegistry,
+    shutdown: Arc<Notify>,
+    changes: KeyChanges,
+
+    /// Set once the server is accepting client connections
+    ready: Arc<AtomicBool>,
+}
+
+impl Server {
+    /// Bind a server to the addresses in the config.
+    /// HTTP endpoints, if configured, are served immediately.
+    pub async fn bind(config: Config) -> io::Result<Self> {
+        let ready = Arc::new(AtomicBool::new(false));
+        if let Some(bind) = &config.http_bind {
+            tokio::spawn(serve_http(bind.to_string(), ready.clone()));
+        }
+        let listener = bind_listener(&config)?;
+        Ok(Self {
+            config,
+            listener,
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: Arc::new(Notify::new()),
+            changes: broadcast::channel(CHANGES_CAPACITY).0,
+            ready,
+        })
+    }
+
+    /// Return the address that the server is listening on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Return a handle that can observe and stop the server once it is running.
+    pub fn handle(&self) -> ServerHandle {
+        ServerHandle {
+            shutdown: self.shutdown.clone(),
+            clients: self.clients.clone(),
+            changes: self.changes.clone(),
+        }
+    }
+
+    /// Accept and serve clients until SIGTERM is received or the server is
+    /// shut down through a handle, then drain existing connections.
+    pub async fn run(self) -> io::Result<()> {
+        match self.config.hasher {
+            HashFunction::SipHash => self.serve::<StdStore>().await,
+            HashFunction::Fnv => {
+                info!("Using the FNV hashing algorithm");
+                self.serve::<FnvStore>().await
+            }
+        }
+    }
+
+    async fn serve<S>(mut self) -> io::Result<()>
+    where
+        S: ExpiringStore + Send + Sync + 'static,
+    {
+        let backlog = Arc::new(Backlog::new(self.config.shed_backlog));
+        let (tx, executor) =
+            spawn_executor::<S>(&self.config, backlog.clone(), self.changes.clone());
+        let link = ExecutorLink {
+            tx,
+            backlog,
+            changes: self.changes.clone(),
+        };
+        // Clients are only accepted once the snapshot has been restored
+        if let Some(path) = &self.config.warmup {
+            let store = load_snapshot::<S>(path)?;
+            let keys = executor.write().await.restore(DEFAULT_NAMESPACE, store);
+            info!("Restored {} key(s) from snapshot at: {}", keys, path);
+        }
+
+        // Reads bypass the executor thread only if concurrent reads are enabled
+        let reads = match self.config.concurrent_reads {
+            true => Some(executor),
+            false => None,
+        };
+
+        info!("Ready to accept connections at: {}", self.local_addr()?);
+        self.ready.store(true, Ordering::SeqCst);
+
+        // On SIGTERM, stop accepting connections and drain existing ones
+        let mut terminate = signal(SignalKind::terminate())?;
+
+        let chaos = match self.config.chaos {
+            true => {
+                warn!("Chaos mode is enabled");
+                Some(Arc::new(RwLock::new(Chaos::new())))
+            }
+            false => None,
+        };
+        let journals: SharedJournals = match self.config.journal_size {
+            0 => None,
+            _ => Some(Arc::new(Mutex::new(HashMap::new()))),
+        };
+        let settings = ClientSettings {
+            limits: Limits {
+                max_args: self.config.max_request_args,
+                max_size: self.config.max_request_size,
+            },
+            proxy_protocol: self.config.proxy_protocol,
+        };
+        let mut client_id: u64 = 0;
+
+        loop {
+            let (socket, addr) = tokio::select! {
+                accepted = self.listener.accept() => accepted?,
+                _ = terminate.recv() => break,
+                _ = self.shutdown.notified() => break,
+            };
+
+            let client = ClientConnection::new(client_id, socket, addr);
+            client_id += 1;
+
+            info!(
+                "Successfully established inbound TCP connection with: {}",
+                &client.addr
+            );
+
+            self.clients.lock().unwrap().insert(client.id, client.addr);
+            if let Some(journals) = &journals {
+                let journal = Journal::new(self.config.journal_size);
+                journals.lock().unwrap().insert(client.id, journal);
+            }
+            tokio::spawn(handle_client(
+                client,
+                link.clone(),
+                reads.clone(),
+                chaos.clone(),
+                journals.clone(),
+                settings,
+                self.clients.clone(),
+            ));
+        }
+
+        drop(self.listener);
+        self.ready.store(false, Ordering::SeqCst);
+        let clients = self.clients;
+        let remaining = || clients.lock().unwrap().len();
+        info!(
+            "Stopped accepting connections, draining {} client(s)",
+            remaining()
+        );
+        let deadline = Instant::now() + Duration::from_secs(self.config.drain_timeout);
+        while remaining() > 0 {
+            if Instant::now() >= deadline {
+                warn!("Drain timed out, closing {} client(s)", remaining());
+                break;
+            }
+            delay_for(Duration::from_millis(100)).await;
+        }
+        info!("Shutting down");
+        Ok(())
+    }
+}
+
+/// Spawn the executor thread, which executes requests sent through the
+/// returned channel in order. Return the channel and the shared executor.
+fn spawn_executor<S>(
+    config: &Config,
+    backlog: Arc<Backlog>,
+    changes: KeyChanges,
+) -> (mpsc::Sender<Message>, SharedExecutor<S>)
+where
+    S: ExpiringStore + Send + Sync + 'static,
+{
+    let mut inner = Executor::new(config.clone());
+    // Keys that expire are reported to the webhook, if any
+    if config.webhook.is_some() {
+        inner.track_expired();
+    }
+    let executor: SharedExecutor<S> = Arc::new(AsyncRwLock::new(inner));
+    let shared = executor.clone();
+
+    // Events are passed to a separate task so that deliveries never block the executor
+    let events = match config.webhook.clone() {
+        Some(hook) => {
+            let (etx, mut erx) = mpsc::unbounded_channel::<Event>();
+            let deliveries = Arc::new(Semaphore::new(MAX_CONCURRENT_DELIVERIES));
+            tokio::spawn(async move {
+                while let Some(event) = erx.recv().await {
+                    if !hook.matches(&event) {
+                        continue;
+                    }
+                    let permit = deliveries.clone().acquire_owned().await;
+                    let hook = hook.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = hook.deliver(&event).await {
+                            warn!("Dropped webhook event for key \"{}\": {}", event.key, e);
+                        }
+                        drop(permit);
+                    });
+                }
+            });
+            info!(
+                "Sending webhook events to: {}",
+                config.webhook_url.as_deref().unwrap_or_default()
+            );
+            Some(etx)
+        }
+        None => None,
+    };
+
+    // Expired keys are purged periodically so that keys which are never
+    // accessed again do not hold memory indefinitely
+    let purger = executor.clone();
+    let expired_events = events.clone();
+    let interval = Duration::from_millis(config.purge_interval);
+    tokio::spawn(async move {
+        loop {
+            delay_for(interval).await;
+            let mut executor = purger.write().await;
+            let purged = executor.purge_expired();
+            if purged > 0 {
+                debug!("Purged {} expired key(s)", purged);
+            }
+            send_expired(&expired_events, executor.take_expired());
+            let freed = executor.auto_compact();
+            if freed > 0 {
+                debug!("Compacted {} byte(s) of spare capacity", freed);
+            }
+        }
+    });
     debug!("Initialized data store");
 
     let (tx, mut rx) = mpsc::channel(config.cbound);
     debug!("Initialized executor thread channel");
 
-    let _executor = tokio::spawn(async move {
+    tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
             let msg: Message = msg; // Make type of `msg` explicit to compiler
-            let resp = execute(msg.req, &mut store).await;
-            let _ = msg.pipe.send(resp);
+            let Message {
+                req,
+                namespace,
+                pipe,
+            } = msg;
+            // Capture the keys and command of writes before the request is consumed
+            let change = match req.command() {
+                Some(cmd) if cmd.has_flag(Flag::Write) => req
+                    .keys()
+                    .iter()
+                    .map(|key| (key.to_string(), cmd.name.to_lowercase()))
+                    .collect(),
+                _ => vec![],
+            };
+            let depth = backlog.take();
+            let mut executor = executor.write().await;
+            executor.record_queue(depth as u64, backlog.shed.load(Ordering::SeqCst));
+            let before = executor.changes(&namespace);
+            let resp = executor.run(req, &namespace).await;
+            // Writes that failed or changed nothing are not reported
+            let changed = executor.changes(&namespace) != before;
+            let expired = executor.take_expired();
+            drop(executor);
+            send_expired(&events, expired);
+            if changed {
+                for (key, _) in change.iter() {
+                    let _ = changes.send((namespace.clone(), key.clone()));
+                }
+            }
+            if let Some(events) = &events {
+                if changed {
+                    for (key, event) in change {
+                        let _ = events.send(Event {
+                            key,
+                            event,
+                            db: namespace.clone(),
+                            timestamp: unix_millis(),
+                        });
+                    }
+                }
+            }
+            let _ = pipe.send(resp);
         }
     });
+    (tx, shared)
+}
 
-    let mut listener = match TcpListener::bind(&config.bind).await {
-        Ok(l) => l,
-        Err(_) => {
-            error!("An invalid URL was provided: {}", &config.bind);
-            std::process::exit(1);
+/// Report keys removed because they expired as "expired" events.
+fn send_expired(events: &Option<mpsc::UnboundedSender<Event>>, expired: Vec<(String, String)>) {
+    if let Some(events) = events {
+        for (db, key) in expired {
+            let _ = events.send(Event {
+                key,
+                event: "expired".to_string(),
+                db,
+                timestamp: unix_millis(),
+            });
         }
-    };
-    info!("Ready to accept connections at: {}", &config.bind);
+    }
+}
 
-    // TODO: Consider tracking client connections
-    let _clients: Vec<&ClientConnection> = Vec::new();
-    let mut client_id: u64 = 0;
+/// Serve requests from a client until it disconnects.
+async fn handle_client<S: ExpiringStore + Send + Sync>(
+    mut client: ClientConnection,
+    mut link: ExecutorLink,
+    reads: Option<SharedExecutor<S>>,
+    chaos: SharedChaos,
+    journals: SharedJournals,
+    settings: ClientSettings,
+    clients: ClientRegistry,
+) {
+    let limits = settings.limits;
+    // Timeout for terminating after receiving several no-op requests in succession
+    let mut timeout = 10;
 
-    loop {
-        let (socket, addr) = listener.accept().await?;
+    let mut namespace = DEFAULT_NAMESPACE.to_string();
+    let mut priority = Priority::Normal;
+    let mut format = ReplyFormat::Human;
+    let mut rng = Rng::from_time();
+
+    // Buffers are allocated once and reused for the lifetime of the connection
+    let (mut reader, writer) = client.socket.split();
+    let mut input: Vec<u8> = Vec::with_capacity(READ_BUFFER_SIZE);
+    let mut chunk = [0; READ_BUFFER_SIZE];
+    let mut writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, writer);
 
-        let mut client = ClientConnection::new(client_id, socket, addr);
-        client_id += 1;
+    if settings.proxy_protocol {
+        match read_proxy(&mut reader, &mut input, &mut chunk).await {
+            Ok(Some(addr)) => {
+                info!(
+                    "Client {} ({}) is connected through a proxy from: {}",
+                    client.id, &client.addr, addr
+                );
+                client.addr = addr;
+                clients.lock().unwrap().insert(client.id, addr);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(
+                    "Closing connection with client {} ({}): {}",
+                    client.id, &client.addr, e
+                );
+                clients.lock().unwrap().remove(&client.id);
+                if let Some(journals) = &journals {
+                    journals.lock().unwrap().remove(&client.id);
+                }
+                return;
+            }
+        }
+    }
 
+    // Set once the client closes the connection, after which an unterminated
+    // inline request at the end of the input is still run
+    let mut eof = false;
+    loop {
+        let (req, len) = match read_frame(&input, &limits, eof) {
+            Ok(Some((frame, len))) => (parse_frame(frame).await, len),
+            Ok(None) if eof => break,
+            // Wait for the rest of the request
+            Ok(None) => match reader.read(&mut chunk).await {
+                Ok(n) if n > 0 => {
+                    input.extend_from_slice(&chunk[..n]);
+                    continue;
+                }
+                // Client closed the connection
+                Ok(_) => {
+                    eof = true;
+                    continue;
+                }
+                Err(_) => break,
+            },
+            // Malformed or oversized requests close the connection, since the
+            // rest of the input cannot be delimited
+            Err(e) => {
+                warn!(
+                    "Closing connection with client {} ({}): {}",
+                    client.id, &client.addr, e
+                );
+                let _ = writer.write_all(&encode_reply(&f_err(e), format)).await;
+                let _ = writer.flush().await;
+                break;
+            }
+        };
+        input.drain(..len);
         info!(
-            "Successfully established inbound TCP connection with: {}",
-            &client.addr
+            "Received a request from client {} ({}):",
+            client.id, &client.addr
         );
+        info!("  -> \"{:?}\"", &req);
+        let received = Instant::now();
+        let journaled = journals
+            .as_ref()
+            .map(|_| (unix_millis(), format!("{:?}", req)));
 
-        let mut txc = tx.clone();
-        let _task = tokio::spawn(async move {
-            // Timeout for terminating after receiving several no-op requests in succession
-            let mut timeout = 10;
-
-            loop {
-                let mut buf = [0; 512];
-                let _ = client.socket.read(&mut buf[..]).await;
-
-                let req = parse_request(&buf).await;
-                info!("Received a request from client {} ({}):", client.id, &client.addr);
-                info!("  -> \"{:?}\"", &req);
-
-                match req {
-                    Request::Quit => {
-                        info!(
-                            "Received a QUIT request from client {} ({})",
-                            client.id, &client.addr
-                        );
-                        break;
-                    },
-                    Request::NoOp => {
-                        timeout -= 1;
-                        if timeout <= 0 {
-                            info!(
-                                "Timed out connection due to successive no-ops from client {} ({})",
-                                client.id, &client.addr
-                            );
-                            break;
-                        }
-                    },
-                    _ => timeout = 10,
+        match req {
+            Request::Quit => {
+                info!(
+                    "Received a QUIT request from client {} ({})",
+                    client.id, &client.addr
+                );
+                break;
+            }
+            Request::Namespace { ref name } => {
+                info!(
+                    "Client {} ({}) selected namespace \"{}\"",
+                    client.id, &client.addr, name
+                );
+                namespace = name.to_string();
+                timeout = 10;
+            }
+            Request::Priority { priority: p } => {
+                priority = p;
+                timeout = 10;
+            }
+            Request::Format { format: f } => {
+                format = f;
+                timeout = 10;
+            }
+            Request::DebugChaos { action } if chaos.is_some() => {
+                let settings = chaos.as_ref().unwrap().write().unwrap().apply(action);
+                let body = f_vec(settings.into_iter().map(f_bare).collect());
+                let _ = writer.write_all(&encode_reply(&body, format)).await;
+                let _ = writer.flush().await;
+                continue;
+            }
+            Request::DebugJournal { client: id } if journals.is_some() => {
+                let body = match journals.as_ref().unwrap().lock().unwrap().get(&id) {
+                    Some(journal) if journal.lines().is_empty() => f_empty(),
+                    Some(journal) => f_vec(journal.lines().into_iter().map(f_bare).collect()),
+                    None => f_err(format!("No client with ID {}", id)),
+                };
+                let _ = writer.write_all(&encode_reply(&body, format)).await;
+                let _ = writer.flush().await;
+                continue;
+            }
+            Request::NoOp => {
+                timeout -= 1;
+                if timeout <= 0 {
+                    info!(
+                        "Timed out connection due to successive no-ops from client {} ({})",
+                        client.id, &client.addr
+                    );
+                    break;
                 }
+            }
+            _ => timeout = 10,
+        }
 
+        let resp = match (req, &reads) {
+            (Request::WaitKey { key, timeout: secs }, _) => {
+                wait_key(&link.changes, &namespace, &key, secs).await
+            }
+            (req, Some(executor)) if req.is_read_only() => {
+                executor.read().await.run_read(req, &namespace).await
+            }
+            _ if !link.backlog.admit(priority) => Response {
+                body: f_err("BUSY The server is overloaded, try again later".to_string()),
+            },
+            (req, _) => {
                 let (send_pipe, recv_pipe) = oneshot::channel();
                 let msg = Message {
-                    req: req,
+                    req,
+                    namespace: namespace.clone(),
                     pipe: send_pipe,
                 };
+                let _ = link.tx.send(msg).await;
+                recv_pipe.await.unwrap()
+            }
+        };
+        if let (Some(journals), Some((at, request))) = (&journals, journaled) {
+            if let Some(journal) = journals.lock().unwrap().get_mut(&client.id) {
+                journal.record(Entry {
+                    at,
+                    request,
+                    duration: received.elapsed(),
+                });
+            }
+        }
+
+        if let Some(chaos) = &chaos {
+            let (latency, fault) = {
+                let chaos = chaos.read().unwrap();
+                (chaos.latency(), chaos.next_fault(&mut rng))
+            };
+            delay_for(latency).await;
+            match fault {
+                Some(Fault::Drop) => continue,
+                Some(Fault::Disconnect) => break,
+                None => {}
+            }
+        }
+        let _ = writer.write_all(&encode_reply(&resp.body, format)).await;
+
+        // Only flush once there are no further requests waiting to be
+        // handled, so that pipelined responses share a single write
+        if input.is_empty() {
+            let _ = writer.flush().await;
+        }
+    }
+    clients.lock().unwrap().remove(&client.id);
+    if let Some(journals) = &journals {
+        journals.lock().unwrap().remove(&client.id);
+    }
+}
+
+/// Wait for a key to be written, as requested by WAITKEY.
+/// Reply 1 once it is written, or nil if the timeout passes first.
+async fn wait_key(changes: &KeyChanges, namespace: &str, key: &str, secs: Option<u64>) -> Response {
+    let changed = wait_for_change(changes, namespace, key);
+    let body = match secs {
+        Some(secs) => match timeout(Duration::from_secs(secs), changed).await {
+            Ok(()) => f_uint(1),
+            Err(_) => f_nil(),
+        },
+        None => {
+            changed.await;
+            f_uint(1)
+        }
+    };
+    Response { body }
+}
 
-                let _ = txc.send(msg).await;
+/// Read the PROXY protocol header that starts a connection through a proxy,
+/// leaving the input that follows it in the buffer. Return the address of
+/// the client that the proxy accepted, if the proxy sent one.
+async fn read_proxy<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    input: &mut Vec<u8>,
+    chunk: &mut [u8],
+) -> Result<Option<SocketAddr>, String> {
+    loop {
+        if let Some((source, len)) = read_proxy_header(input)? {
+            input.drain(..len);
+            return Ok(source);
+        }
+        match reader.read(chunk).await {
+            Ok(n) if n > 0 => input.extend_from_slice(&chunk[..n]),
+            _ => return Err("Connection closed before the PROXY protocol header".to_string()),
+        }
+    }
+}
 
-                let resp = recv_pipe.await.unwrap();
-                let _ = client.socket.write_all(resp.body.as_bytes()).await;
+/// Build the runtime that the server runs on, as configured.
+pub fn build_runtime(config: &Config) -> io::Result<Runtime> {
+    let mut builder = RuntimeBuilder::new();
+    builder.enable_all().thread_name("kiba-worker");
+    match config.runtime {
+        RuntimeFlavor::MultiThread => {
+            builder.threaded_scheduler();
+            if config.worker_threads > 0 {
+                builder.core_threads(config.worker_threads);
+            }
+            match config.worker_threads {
+                0 => info!("Using a multi-thread runtime with a thread per CPU core"),
+                n => info!("Using a multi-thread runtime with {} worker thread(s)", n),
             }
-        });
+        }
+        RuntimeFlavor::CurrentThread => {
+            builder.basic_scheduler();
+            info!("Using a current-thread runtime");
+        }
+    }
+    builder.build()
+}
+
+pub async fn start_server(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let server = match Server::bind(config.clone()).await {
+        Ok(server) => server,
+        Err(_) => {
+            error!("An invalid URL was provided: {}", &config.bind);
+            std::process::exit(1);
+        }
+    };
+    server.run().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use crate::store::Store;
+
+    #[tokio::test]
+    async fn test_server_run() {
+        let mut config = parse_config(None);
+        config.bind = "127.0.0.1:0".to_string();
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"PING\n").await.unwrap();
+        let mut buf = [0; 16];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"PONG\r\n");
+        assert_eq!(handle.client_count(), 1);
+
+        // Shutting down waits for connected clients to disconnect
+        handle.shutdown();
+        drop(stream);
+        running.await.unwrap().unwrap();
+        assert_eq!(handle.client_count(), 0);
+    }
+
+    #[test]
+    fn test_backlog() {
+        let backlog = Backlog::new(2);
+        assert!(backlog.admit(Priority::Low));
+        assert!(backlog.admit(Priority::Low));
+        assert!(!backlog.admit(Priority::Low));
+        assert!(backlog.admit(Priority::Normal));
+        assert_eq!(backlog.take(), 2);
+        assert_eq!(backlog.take(), 1);
+        assert!(backlog.admit(Priority::Low));
+        assert_eq!(backlog.shed.load(Ordering::SeqCst), 1);
+
+        // Requests are never shed without a limit
+        let backlog = Backlog::new(0);
+        for _ in 0..10 {
+            assert!(backlog.admit(Priority::Low));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_warmup() {
+        let mut store: StdStore = Store::new();
+        store.set("foo".to_string(), "bar".to_string()).unwrap();
+        let path = std::env::temp_dir().join(format!("kiba-warmup-{}.kdb", std::process::id()));
+        store.serialize(&mut File::create(&path).unwrap()).unwrap();
+
+        let mut config = parse_config(None);
+        config.bind = "127.0.0.1:0".to_string();
+        config.warmup = Some(path.to_str().unwrap().to_string());
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET foo\n").await.unwrap();
+        let mut buf = [0; 16];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"\"bar\"\r\n");
+
+        handle.shutdown();
+        drop(stream);
+        running.await.unwrap().unwrap();
+
+        // The server does not start without its snapshot
+        std::fs::remove_file(&path).unwrap();
+        let mut config = parse_config(None);
+        config.bind = "127.0.0.1:0".to_string();
+        config.warmup = Some(path.to_str().unwrap().to_string());
+        let server = Server::bind(config).await.unwrap();
+        assert!(server.run().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_reply_format() {
+        let mut config = parse_config(None);
+        config.bind = "127.0.0.1:0".to_string();
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"SET foo bar\nFORMAT resp\nGET foo\nGET baz\nINCR foo\nFORMAT human\nGET foo\n",
+            )
+            .await
+            .unwrap();
+        let expected = "OK\r\n+OK\r\n$3\r\nbar\r\n$-1\r\n\
+                        -ERR Value stored at key cannot be represented as a 64-bit integer\r\n\
+                        OK\r\n\"bar\"\r\n";
+        let mut replies = Vec::new();
+        let mut buf = [0; 256];
+        while replies.len() < expected.len() {
+            let n = stream.read(&mut buf).await.unwrap();
+            replies.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(String::from_utf8(replies).unwrap(), expected);
+
+        handle.shutdown();
+        drop(stream);
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_journal() {
+        let mut config = parse_config(None);
+        config.bind = "127.0.0.1:0".to_string();
+        config.journal_size = 2;
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        let mut buf = [0; 256];
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        for req in &[&b"SET foo bar\n"[..], b"GET foo\n", b"PING\n"] {
+            client.write_all(req).await.unwrap();
+            let n = client.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+        }
+
+        // Only the most recent requests are kept
+        let mut debug = TcpStream::connect(addr).await.unwrap();
+        debug.write_all(b"DEBUG JOURNAL 0\n").await.unwrap();
+        let n = debug.read(&mut buf).await.unwrap();
+        let journal = String::from_utf8_lossy(&buf[..n]).to_string();
+        assert!(journal.starts_with("1) "));
+        assert!(!journal.contains("Set"));
+        assert!(journal.contains("Get { key: \"foo\" }"));
+        assert!(journal.contains("\n2) ") && journal.ends_with("Ping\r\n"));
+
+        debug.write_all(b"DEBUG JOURNAL 7\n").await.unwrap();
+        let n = debug.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"(error) No client with ID 7\r\n");
+
+        handle.shutdown();
+        drop(client);
+        drop(debug);
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_waitkey() {
+        let mut config = parse_config(None);
+        config.bind = "127.0.0.1:0".to_string();
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        // The wait starts when the future is created, not when it is awaited
+        let waiting = handle.wait_for_key_change(DEFAULT_NAMESPACE, "foo");
+        let subscribers = handle.changes.receiver_count();
+        let mut waiting_client = TcpStream::connect(addr).await.unwrap();
+        waiting_client.write_all(b"WAITKEY foo\n").await.unwrap();
+        while handle.changes.receiver_count() == subscribers {
+            delay_for(Duration::from_millis(1)).await;
+        }
+        let mut writer = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0; 16];
+
+        // Writes to other keys don't end the wait, and waits can time out
+        writer.write_all(b"WAITKEY foo 1\n").await.unwrap();
+        let n = writer.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"(nil)\r\n");
+        for req in [&b"SET bar 1\n"[..], &b"SET foo 1\n"[..]].iter() {
+            writer.write_all(req).await.unwrap();
+            writer.read_exact(&mut buf[..4]).await.unwrap();
+            assert_eq!(&buf[..4], b"OK\r\n");
+        }
+        let n = waiting_client.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"(integer) 1\r\n");
+        waiting.await;
+
+        handle.shutdown();
+        drop(waiting_client);
+        drop(writer);
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_concurrent_reads() {
+        let config = Config::builder()
+            .bind("127.0.0.1:0")
+            .concurrent_reads(true)
+            .hasher(HashFunction::Fnv)
+            .build()
+            .unwrap();
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0; 16];
+        for (req, resp) in [("SET foo bar\n", "OK\r\n"), ("GET foo\n", "\"bar\"\r\n")].iter() {
+            stream.write_all(req.as_bytes()).await.unwrap();
+            let n = stream.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], resp.as_bytes());
+        }
+
+        handle.shutdown();
+        drop(stream);
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_request_formats() {
+        let mut config = parse_config(None);
+        config.bind = "127.0.0.1:0".to_string();
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        // Inline and multibulk requests are accepted on the same connection
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$9\r\ntwo words\r\n")
+            .await
+            .unwrap();
+        stream.write_all(b"*2\r\n$3\r\nGET").await.unwrap();
+        stream.write_all(b"\r\n$3\r\nfoo\r\n").await.unwrap();
+        stream.write_all(b"GET foo\n").await.unwrap();
+        let expected = "OK\r\n\"two words\"\r\n\"two words\"\r\n";
+        let mut replies = Vec::new();
+        let mut buf = [0; 64];
+        while replies.len() < expected.len() {
+            let n = stream.read(&mut buf).await.unwrap();
+            replies.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(String::from_utf8(replies).unwrap(), expected);
+
+        // Inline requests split across reads wait for their newline, and
+        // invalid UTF-8 is rejected without closing the connection
+        stream.write_all(b"SET foo sp").await.unwrap();
+        stream.flush().await.unwrap();
+        delay_for(Duration::from_millis(50)).await;
+        stream
+            .write_all(b"lit\n\x00\xff\xfe\nGET foo\n")
+            .await
+            .unwrap();
+        let expected = "OK\r\n(error) Arguments must be valid UTF-8\r\n\"split\"\r\n";
+        let mut replies = Vec::new();
+        while replies.len() < expected.len() {
+            let n = stream.read(&mut buf).await.unwrap();
+            replies.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(String::from_utf8(replies).unwrap(), expected);
+
+        // An unterminated inline request is run when the client closes the
+        // connection
+        let mut other = TcpStream::connect(addr).await.unwrap();
+        other.write_all(b"GET foo").await.unwrap();
+        other.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut reply = String::new();
+        other.read_to_string(&mut reply).await.unwrap();
+        assert_eq!(reply, "\"split\"\r\n");
+
+        // Malformed multibulk requests close the connection
+        stream.write_all(b"*1\r\n:3\r\n").await.unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await.unwrap();
+        assert_eq!(reply, "(error) Protocol error: expected '$', got ':'\r\n");
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_request_limits() {
+        let config = Config::builder()
+            .bind("127.0.0.1:0")
+            .max_request_args(3)
+            .max_request_size(64)
+            .build()
+            .unwrap();
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"SET foo bar\n").await.unwrap();
+        let mut buf = [0; 16];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"OK\r\n");
+
+        // An oversized argument is rejected as soon as its length is received
+        stream
+            .write_all(b"*2\r\n$3\r\nGET\r\n$1000\r\n")
+            .await
+            .unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await.unwrap();
+        assert_eq!(
+            reply,
+            "(error) Protocol error: request is too large, limit is 64 bytes\r\n"
+        );
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"SADD set a b c\n").await.unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await.unwrap();
+        assert_eq!(
+            reply,
+            "(error) Protocol error: too many arguments, limit is 3\r\n"
+        );
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_proxy_protocol() {
+        let config = Config::builder()
+            .bind("127.0.0.1:0")
+            .proxy_protocol(true)
+            .build()
+            .unwrap();
+        let server = Server::bind(config).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.handle();
+        let running = tokio::spawn(server.run());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"PROXY TCP4 10.0.0.1 10.0.0.2 5000 6464\r\nPING\n")
+            .await
+            .unwrap();
+        let mut buf = [0; 16];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"PONG\r\n");
+        let source: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let clients: Vec<SocketAddr> = handle.clients.lock().unwrap().values().cloned().collect();
+        assert_eq!(clients, vec![source]);
+        drop(stream);
+
+        // Connections without a header are closed
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"PING\n").await.unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await.unwrap();
+        assert_eq!(reply, "");
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_build_runtime() {
+        for config in [
+            Config::default(),
+            Config::builder().worker_threads(2).build().unwrap(),
+            Config::builder()
+                .runtime(RuntimeFlavor::CurrentThread)
+                .build()
+                .unwrap(),
+        ]
+        .iter()
+        {
+            let mut runtime = build_runtime(config).unwrap();
+            let spawned = runtime.block_on(async { tokio::spawn(async { 1 + 1 }).await });
+            assert_eq!(spawned.unwrap(), 2);
+        }
     }
 }
@@ -0,0 +1,204 @@
+//! Deterministic simulation harness for testing the server in-process.
+//!
+//! A simulation drives the parser and executor with simulated clients instead
+//! of sockets. Clients run scripts of commands that are interleaved in an order
+//! chosen by a seeded random number generator, and time is virtual, so any
+//! failing interleaving can be replayed exactly from its seed.
+
+use crate::config::Config;
+use crate::executor::{Executor, Request, DEFAULT_NAMESPACE};
+use crate::parser::parse_request;
+use crate::rng::Rng;
+use crate::store::StdStore;
+use std::collections::VecDeque;
+
+/// Maximum virtual time in milliseconds that passes between two steps
+const MAX_STEP: u64 = 10;
+
+/// A simulated client connection
+struct Client {
+    namespace: String,
+    script: VecDeque<String>,
+    connected: bool,
+}
+
+/// A command sent by a client and the response it received
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exchange {
+    pub client: usize,
+    pub time: i64,
+    pub input: String,
+    pub output: String,
+}
+
+pub struct Simulation {
+    executor: Executor<StdStore>,
+    clients: Vec<Client>,
+    rng: Rng,
+    now: i64,
+}
+
+impl Simulation {
+    /// Create a simulation starting at virtual time 0.
+    /// The executor's random choices, such as the jitter of times to live,
+    /// are made from the same seed.
+    pub fn new(seed: u64, config: Config) -> Self {
+        let mut rng = Rng::new(seed);
+        Self {
+            executor: Executor::with_clock(config, rng.next_u64(), 0),
+            clients: Vec::new(),
+            rng,
+            now: 0,
+        }
+    }
+
+    /// Add a client that will send the commands in its script.
+    /// Return the client's identifier.
+    pub fn add_client(&mut self, script: Vec<&str>) -> usize {
+        self.clients.push(Client {
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            script: script.into_iter().map(|s| s.to_string()).collect(),
+            connected: true,
+        });
+        self.clients.len() - 1
+    }
+
+    /// Advance virtual time, which is when keys expire.
+    pub fn advance(&mut self, ms: u64) {
+        self.now += ms as i64;
+        self.executor.set_time(self.now);
+    }
+
+    /// Send a command from a client immediately and return the response,
+    /// handling connection-level requests as a server connection would.
+    pub async fn send(&mut self, client: usize, input: &str) -> String {
+        let req = parse_request(input.as_bytes()).await;
+        match req {
+            Request::Quit => self.clients[client].connected = false,
+            Request::Namespace { ref name } => self.clients[client].namespace = name.to_string(),
+            _ => {}
+        }
+        let namespace = self.clients[client].namespace.clone();
+        self.executor.run(req, &namespace).await.body.to_string()
+    }
+
+    /// Run client scripts to completion, choosing the next client to send a
+    /// command and the time that passes between commands at random.
+    /// Return every exchange in the order it was executed.
+    pub async fn run(&mut self) -> Vec<Exchange> {
+        let mut exchanges = Vec::new();
+        loop {
+            let pending: Vec<usize> = (0..self.clients.len())
+                .filter(|&i| self.clients[i].connected && !self.clients[i].script.is_empty())
+                .collect();
+            if pending.is_empty() {
+                return exchanges;
+            }
+            let client = pending[self.rng.below(pending.len() as u64) as usize];
+            let step = self.rng.below(MAX_STEP + 1);
+            self.advance(step);
+
+            let input = self.clients[client].script.pop_front().unwrap();
+            let output = self.send(client, &input).await;
+            exchanges.push(Exchange {
+                client,
+                time: self.now,
+                input,
+                output,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    async fn simulate(seed: u64) -> Vec<Exchange> {
+        let mut sim = Simulation::new(seed, parse_config(None));
+        sim.add_client(vec!["SET counter 0", "INCR counter", "INCR counter"]);
+        sim.add_client(vec![
+            "INCRBY counter 10",
+            "GET counter",
+            "QUIT",
+            "GET counter",
+        ]);
+        sim.add_client(vec!["NAMESPACE other", "GET counter", "TS.ADD temp * 1.5"]);
+        sim.run().await
+    }
+
+    #[tokio::test]
+    async fn test_simulation_is_deterministic() {
+        let first = simulate(7).await;
+        assert_eq!(first, simulate(7).await);
+
+        // Every command runs once, in order per client, except after QUIT
+        assert_eq!(first.len(), 9);
+        for client in 0..3 {
+            let inputs: Vec<&str> = first
+                .iter()
+                .filter(|e| e.client == client)
+                .map(|e| e.input.as_str())
+                .collect();
+            let expected = match client {
+                0 => vec!["SET counter 0", "INCR counter", "INCR counter"],
+                1 => vec!["INCRBY counter 10", "GET counter", "QUIT"],
+                _ => vec!["NAMESPACE other", "GET counter", "TS.ADD temp * 1.5"],
+            };
+            assert_eq!(inputs, expected);
+        }
+
+        // Namespaces are isolated, and time is virtual
+        let other: Vec<&Exchange> = first.iter().filter(|e| e.client == 2).collect();
+        assert_eq!(other[1].output, "(nil)");
+        assert_eq!(other[2].output, format!("(integer) {}", other[2].time));
+    }
+
+    #[tokio::test]
+    async fn test_simulation_interleavings() {
+        // Different seeds explore different interleavings of the same scripts
+        let mut orders: Vec<Vec<usize>> = Vec::new();
+        for seed in 0..20 {
+            let order = simulate(seed).await.iter().map(|e| e.client).collect();
+            if !orders.contains(&order) {
+                orders.push(order);
+            }
+        }
+        assert!(orders.len() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_simulation_expiry() {
+        // Keys expire only as virtual time is advanced
+        let mut sim = Simulation::new(0, parse_config(None));
+        let client = sim.add_client(vec![]);
+        assert_eq!(sim.send(client, "SET foo bar").await, "OK");
+        assert_eq!(sim.send(client, "EXPIRE foo 1").await, "(integer) 1");
+        sim.advance(999);
+        assert_eq!(sim.send(client, "PTTL foo").await, "(integer) 1");
+        assert_eq!(sim.send(client, "GET foo").await, "\"bar\"");
+        sim.advance(1);
+        assert_eq!(sim.send(client, "GET foo").await, "(nil)");
+        assert_eq!(sim.send(client, "EXISTS foo").await, "(integer) 0");
+    }
+
+    #[tokio::test]
+    async fn test_simulation_seeds_executor() {
+        // Jittered times to live are replayed from the seed
+        async fn replay(seed: u64) -> Vec<String> {
+            let config = Config::builder().ttl_jitter(50).build().unwrap();
+            let mut sim = Simulation::new(seed, config);
+            let client = sim.add_client(vec![]);
+            let mut outputs = Vec::new();
+            for i in 0..10 {
+                sim.send(client, &format!("SET key{} {}", i, i)).await;
+                sim.send(client, &format!("EXPIRE key{} 100", i)).await;
+                outputs.push(sim.send(client, &format!("PTTL key{}", i)).await);
+            }
+            outputs
+        }
+        assert_eq!(replay(3).await, replay(3).await);
+        assert_ne!(replay(3).await, replay(4).await);
+    }
+}
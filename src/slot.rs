@@ -0,0 +1,62 @@
+//! Assignment of keys to hash slots.
+//!
+//! Keys are hashed with CRC16 (XMODEM) into one of `SLOT_COUNT` slots, as in
+//! Redis Cluster, so that keys can be spread across servers by slot. If a key
+//! contains a hash tag, only the tag is hashed, so that related keys such as
+//! `{user:1}:profile` and `{user:1}:settings` are assigned the same slot.
+
+/// Number of hash slots that keys are assigned to
+pub const SLOT_COUNT: u16 = 16384;
+
+/// Return the hash tag of a key: the text between the first `{` and the
+/// first `}` after it, if that text is not empty.
+pub fn hash_tag(key: &str) -> Option<&str> {
+    let start = key.find('{')? + 1;
+    let len = key[start..].find('}')?;
+    match len {
+        0 => None,
+        _ => Some(&key[start..start + len]),
+    }
+}
+
+/// Return the slot that a key is assigned to.
+pub fn key_slot(key: &str) -> u16 {
+    let hashed = hash_tag(key).unwrap_or(key);
+    crc16(hashed.as_bytes()) % SLOT_COUNT
+}
+
+/// CRC16 with the XMODEM parameters (polynomial 0x1021, initial value 0).
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_slot("foo"), 12182);
+        assert_eq!(key_slot("user:1000"), 1649);
+
+        // Only the first non-empty tag is hashed
+        assert_eq!(hash_tag("{user:1}:profile"), Some("user:1"));
+        assert_eq!(key_slot("{user:1}:profile"), key_slot("{user:1}:settings"));
+        assert_eq!(key_slot("{user:1}:profile"), key_slot("user:1"));
+        assert_eq!(hash_tag("foo{}{bar}"), None);
+        assert_eq!(key_slot("foo{}{bar}"), crc16(b"foo{}{bar}") % SLOT_COUNT);
+        assert_eq!(hash_tag("foo{bar}{zap}"), Some("bar"));
+        assert_eq!(hash_tag("foo{bar"), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Binary encoding of store contents.
+//!
+//! A snapshot starts with a header of the magic bytes and a format version,
+//! followed by entries that each start with a tag identifying the type of
+//! the entry, and ends with `TAG_END`. Integers and floats are written in
+//! little-endian byte order, and strings are prefixed with their length.
+
+use std::io::{self, Read, Write};
+
+/// Bytes that identify a snapshot
+pub const MAGIC: &[u8; 4] = b"KIBA";
+
+/// Version of the snapshot format, incremented on incompatible changes
+pub const VERSION: u8 = 2;
+
+// Entry tags
+
+pub const TAG_END: u8 = 0;
+pub const TAG_STRING: u8 = 1;
+pub const TAG_LIST: u8 = 2;
+pub const TAG_HASH: u8 = 3;
+pub const TAG_SET: u8 = 4;
+pub const TAG_BLOOM: u8 = 5;
+pub const TAG_CUCKOO: u8 = 6;
+pub const TAG_TIMESERIES: u8 = 7;
+pub const TAG_VECTOR: u8 = 8;
+pub const TAG_INDEX: u8 = 9;
+pub const TAG_EXPIRY: u8 = 10;
+pub const TAG_FIELD_EXPIRY: u8 = 11;
+
+/// Return an error for data that is not a valid snapshot.
+pub fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Encode a snapshot as hexadecimal text, so that it can be sent in a reply.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        hex.push_str(&format!("{:02x}", b));
+    }
+    hex
+}
+
+/// Decode a snapshot encoded with `to_hex`.
+pub fn from_hex(hex: &str) -> io::Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(invalid_data("Hexadecimal text has an odd length"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| invalid_data("Invalid hexadecimal text"))
+        })
+        .collect()
+}
+
+/// Writes values in the snapshot encoding
+pub struct Encoder<'a> {
+    writer: &'a mut dyn Write,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(writer: &'a mut dyn Write) -> Self {
+        Self { writer }
+    }
+
+    /// Write the snapshot header.
+    pub fn header(&mut self) -> io::Result<()> {
+        self.writer.write_all(MAGIC)?;
+        self.u8(VERSION)
+    }
+
+    pub fn u8(&mut self, n: u8) -> io::Result<()> {
+        self.writer.write_all(&[n])
+    }
+
+    pub fn u64(&mut self, n: u64) -> io::Result<()> {
+        self.writer.write_all(&n.to_le_bytes())
+    }
+
+    pub fn i64(&mut self, n: i64) -> io::Result<()> {
+        self.writer.write_all(&n.to_le_bytes())
+    }
+
+    pub fn f64(&mut self, n: f64) -> io::Result<()> {
+        self.u64(n.to_bits())
+    }
+
+    pub fn str(&mut self, s: &str) -> io::Result<()> {
+        self.u64(s.len() as u64)?;
+        self.writer.write_all(s.as_bytes())
+    }
+}
+
+/// Reads values in the snapshot encoding
+pub struct Decoder<'a> {
+    reader: &'a mut dyn Read,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(reader: &'a mut dyn Read) -> Self {
+        Self { reader }
+    }
+
+    /// Read and check the snapshot header.
+    pub fn header(&mut self) -> io::Result<()> {
+        let mut magic = [0; 4];
+        self.reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a snapshot"));
+        }
+        match self.u8()? {
+            VERSION => Ok(()),
+            _ => Err(invalid_data("Unsupported snapshot version")),
+        }
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0; 1];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    pub fn u64(&mut self) -> io::Result<u64> {
+        let mut buf = [0; 8];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    pub fn i64(&mut self) -> io::Result<i64> {
+        let mut buf = [0; 8];
+        self.reader.read_exact(&mut buf)?;
+        Ok(i64::from_le_bytes(buf))
+    }
+
+    pub fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    pub fn string(&mut self) -> io::Result<String> {
+        let len = self.u64()?;
+        // Read through a limit rather than allocating the length up front,
+        // so that a corrupt length cannot exhaust memory
+        let mut buf = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut buf)?;
+        if buf.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(buf).map_err(|_| invalid_data("String is not valid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let mut buf = Vec::new();
+        let mut enc = Encoder::new(&mut buf);
+        enc.header().unwrap();
+        enc.u8(TAG_STRING).unwrap();
+        enc.u64(u64::MAX).unwrap();
+        enc.i64(-42).unwrap();
+        enc.f64(0.5).unwrap();
+        enc.str("キー").unwrap();
+
+        let mut reader = &buf[..];
+        let mut dec = Decoder::new(&mut reader);
+        dec.header().unwrap();
+        assert_eq!(dec.u8().unwrap(), TAG_STRING);
+        assert_eq!(dec.u64().unwrap(), u64::MAX);
+        assert_eq!(dec.i64().unwrap(), -42);
+        assert_eq!(dec.f64().unwrap(), 0.5);
+        assert_eq!(dec.string().unwrap(), "キー");
+        assert!(dec.u8().is_err());
+
+        // Truncated and foreign data are rejected
+        let mut reader = &buf[..buf.len() - 1];
+        let mut dec = Decoder::new(&mut reader);
+        dec.header().unwrap();
+        dec.u8().unwrap();
+        dec.u64().unwrap();
+        dec.i64().unwrap();
+        dec.f64().unwrap();
+        assert!(dec.string().is_err());
+        let mut reader = &b"REDIS0009"[..];
+        assert!(Decoder::new(&mut reader).header().is_err());
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(b"KIBA\x01\xff"), "4b49424101ff");
+        assert_eq!(from_hex("4b49424101FF").unwrap(), b"KIBA\x01\xff");
+        assert_eq!(from_hex("").unwrap(), b"");
+        assert!(from_hex("4b4").is_err());
+        assert!(from_hex("4g").is_err());
+        assert!(from_hex("キー").is_err());
+    }
+}
@@ -1,7 +1,38 @@
+use crate::bloom::{ScalableBloomFilter, DEFAULT_CAPACITY, DEFAULT_ERROR_RATE};
+use crate::cuckoo::{CuckooFilter, DEFAULT_BUCKETS};
+use crate::glob::glob_match;
+use crate::index::{Filter, Index};
+use crate::rng::Rng;
+use crate::snapshot::*;
+use crate::timeseries::{Aggregation, TimeSeries};
+use crate::vector::{Metric, VectorIndex};
+use fnv::FnvBuildHasher;
+use std::collections::hash_map::RandomState;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash};
+use std::io::{self, Read, Write};
+use std::mem::size_of;
 
 type Result<T> = std::result::Result<T, OperationalError>;
 
+/// An iterator over the contents of a store
+pub type Iter<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+
+/// Maximum length in bytes of a string written by `Store::setrange`
+pub const MAX_STRING_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Names of the types of values, as reported by `Store::type_counts`
+pub const TYPE_NAMES: &[&str] = &[
+    "string",
+    "list",
+    "hash",
+    "set",
+    "bloom",
+    "cuckoo",
+    "timeseries",
+    "vector",
+];
+
 /// A shared interface for implementations of store.
 /// Time and space complexities of each function are not guaranteed and
 /// depends on each implementation. (hash vs. btree, vec vs. linked list etc.)
@@ -9,6 +40,153 @@ pub trait Store {
     /// Create a new store.
     fn new() -> Self;
 
+    // Keyspace Operations
+
+    /// Return if a value of any type is stored at key.
+    /// Time complexity: O(1)
+    fn exists(&self, key: String) -> Result<bool>;
+
+    /// Return the name of the type of the value stored at key, as listed in
+    /// `TYPE_NAMES`, or None if the key does not exist. A key holding values of
+    /// several types reports the first of them in `TYPE_NAMES`.
+    /// Time complexity: O(1)
+    fn key_type(&self, key: String) -> Result<Option<&'static str>>;
+
+    /// Return the number of keys in the store.
+    /// Time complexity: O(1)
+    fn dbsize(&self) -> Result<u64>;
+
+    /// Return a key chosen uniformly at random, or None if the store is empty.
+    /// A key holding values of several types is proportionally more likely.
+    /// Time complexity: O(N), where N is the number of keys holding the chosen type
+    fn random_key(&self, rng: &mut Rng) -> Result<Option<String>>;
+
+    /// Remove the value of any type stored at key.
+    /// Return if the key existed.
+    /// Time complexity: O(N), where N is the number of elements in the value
+    fn del(&mut self, key: String) -> Result<bool>;
+
+    /// Move the values of every type stored at key, along with their expiries,
+    /// to newkey, removing any values already stored at newkey.
+    /// If the key does not exist, return an error.
+    /// Time complexity: O(N), where N is the number of elements in both values
+    fn rename(&mut self, key: String, newkey: String) -> Result<()>;
+
+    /// Move the values stored at key to newkey, as with `rename`, only if
+    /// newkey does not exist. Return if the key was renamed.
+    /// If the key does not exist, return an error.
+    /// Time complexity: O(N), where N is the number of elements in the value
+    fn renamenx(&mut self, key: String, newkey: String) -> Result<bool>;
+
+    /// Remove every key from the store. Search index definitions are kept,
+    /// but no longer hold any entries.
+    /// Time complexity: O(N), where N is the total number of elements stored
+    fn flushdb(&mut self) -> Result<()>;
+
+    /// Return the number of elements in the value stored at key.
+    /// Strings count as a single element.
+    /// If the key does not exist, return 0.
+    /// Time complexity: O(1)
+    fn cardinality(&self, key: String) -> Result<u64>;
+
+    /// Return the approximate number of bytes used by keys and values in the store.
+    /// Time complexity: O(1)
+    fn memory_usage(&self) -> Result<u64>;
+
+    /// Return the approximate number of bytes used by key and the values
+    /// stored at it. If the key does not exist, return 0.
+    /// Time complexity: O(N), where N is the number of elements stored at key
+    fn key_memory_usage(&self, key: String) -> Result<u64>;
+
+    /// Return the number of writes that changed the store since it was
+    /// created. Writes that leave the store as it was, such as removing a
+    /// member that does not exist, are not counted.
+    /// Time complexity: O(1)
+    fn changes(&self) -> Result<u64>;
+
+    /// Return the number of keys holding each data type.
+    /// Time complexity: O(1)
+    fn type_counts(&self) -> Result<Vec<(&'static str, u64)>>;
+
+    /// Return the approximate number of bytes allocated for keys that the
+    /// store no longer holds, such as after many keys have been deleted.
+    /// Capacity of the values stored at keys is not included.
+    /// Time complexity: O(1)
+    fn spare_capacity(&self) -> Result<u64>;
+
+    /// Release capacity allocated beyond the contents of the store and of the
+    /// values stored at its keys. Return the approximate number of bytes freed.
+    /// Time complexity: O(N), where N is the total number of elements stored
+    fn compact(&mut self) -> Result<u64>;
+
+    // Serialization
+
+    /// Write a snapshot of the contents of the store in the format described
+    /// in the `snapshot` module.
+    /// Time complexity: O(N), where N is the total number of elements stored
+    fn serialize(&self, writer: &mut dyn Write) -> io::Result<()>;
+
+    /// Create a store from a snapshot written by `serialize`.
+    /// Time complexity: O(N), where N is the total number of elements stored
+    fn deserialize(reader: &mut dyn Read) -> io::Result<Self>
+    where
+        Self: Sized;
+
+    // Iteration
+
+    /// Return an iterator over every key in the store, in an unspecified order
+    /// that is stable as long as the store is not modified.
+    fn keys(&self) -> Iter<'_, &String>;
+
+    /// Return an iterator over keys holding values of a type, named as in
+    /// `TYPE_NAMES`. Unrecognized types hold no keys.
+    fn keys_of_type(&self, kind: &str) -> Iter<'_, &String>;
+
+    /// Return an iterator over keys and values of strings.
+    fn strings(&self) -> Iter<'_, (&String, &String)>;
+
+    /// Return an iterator over keys and elements of lists, from head to tail.
+    fn lists(&self) -> Iter<'_, (&String, Vec<&String>)>;
+
+    /// Return an iterator over keys and members of sets.
+    fn sets(&self) -> Iter<'_, (&String, Vec<&String>)>;
+
+    /// Return an iterator over keys and field-value pairs of hashes.
+    fn hashes(&self) -> Iter<'_, (&String, Vec<(&String, &String)>)>;
+
+    /// Iterate over up to count keys starting at cursor, returning the keys that
+    /// match the glob-style pattern (if any) and the cursor to continue from.
+    /// A returned cursor of 0 means that the iteration is complete.
+    /// If a type is given, only keys holding values of that type are iterated
+    /// over, and the same type must be given for the rest of the iteration.
+    /// Keys added or removed during an iteration may be missed or returned
+    /// more than once.
+    /// Time complexity: O(cursor + count)
+    fn scan(
+        &self,
+        cursor: u64,
+        pattern: Option<&str>,
+        kind: Option<&str>,
+        count: u64,
+    ) -> Result<(u64, Vec<String>)> {
+        let keys = match kind {
+            Some(kind) => self.keys_of_type(kind),
+            None => self.keys(),
+        };
+        let mut keys = keys.skip(cursor as usize);
+        let mut matched = Vec::new();
+        for key in keys.by_ref().take(count as usize) {
+            if pattern.is_none_or(|p| glob_match(p, key)) {
+                matched.push(key.to_string());
+            }
+        }
+        let next = match keys.next() {
+            Some(_) => cursor + count,
+            None => 0,
+        };
+        Ok((next, matched))
+    }
+
     // Strings Operations
 
     /// Get the value of a key.
@@ -16,6 +194,11 @@ pub trait Store {
     /// Time complexity: O(1)
     fn get(&self, key: String) -> Result<Option<String>>;
 
+    /// Get the value of a key and delete the key, as with `del`.
+    /// If the key does not hold a string, do nothing and return None.
+    /// Time complexity: O(N), where N is the number of elements stored at key
+    fn getdel(&mut self, key: String) -> Result<Option<String>>;
+
     /// Set the value of a key.
     /// If the key already existed, return previous value.
     /// Otherwise, return None.
@@ -54,6 +237,42 @@ pub trait Store {
     /// Time complexity: O(1)
     fn decrby(&mut self, key: String, delta: i64) -> Result<i64>;
 
+    /// Increment the value of a key by a floating point amount.
+    /// Return the updated value.
+    /// If the key does not exist, return an error (unlike Redis).
+    /// If the value cannot be interpreted as a finite number, or the result
+    /// would not be finite, return an error.
+    /// Time complexity: O(1)
+    fn incrbyfloat(&mut self, key: String, delta: f64) -> Result<f64>;
+
+    /// Append a value to the end of the string stored at key.
+    /// Return the length in bytes of the string after the append.
+    /// If the key does not exist, create an empty string before performing the operation.
+    /// Time complexity: O(1) amortized
+    fn append(&mut self, key: String, val: String) -> Result<u64>;
+
+    /// Return the bytes of the string stored at key between the start and end
+    /// offsets inclusive. Negative offsets count back from the end of the
+    /// string, so -1 is the last byte. Offsets beyond the string are clamped
+    /// to it, and a range that splits a multi-byte character replaces it with
+    /// U+FFFD. If the key does not exist, return an empty string.
+    /// Time complexity: O(N), where N is the length of the returned string
+    fn getrange(&self, key: String, start: i64, end: i64) -> Result<String>;
+
+    /// Overwrite the bytes of the string stored at key starting at offset with
+    /// a value, padding the string with zero bytes if it is shorter than offset.
+    /// Return the length in bytes of the string after the write. If the write
+    /// would start or end within a multi-byte character, return an error. If
+    /// the key does not exist and the value is not empty, create an empty
+    /// string before performing the operation.
+    /// Time complexity: O(N), where N is the length of the string after the write
+    fn setrange(&mut self, key: String, offset: u64, val: String) -> Result<u64>;
+
+    /// Return the length in bytes of the string stored at key.
+    /// If the key does not exist, return 0.
+    /// Time complexity: O(1)
+    fn strlen(&self, key: String) -> Result<u64>;
+
     // Lists Operations
 
     /// Insert value at the head of list stored at key.
@@ -122,17 +341,269 @@ pub trait Store {
     /// If the key or field does not exist, do nothing (and return 0).
     /// Time complexity: O(1)
     fn hdel(&mut self, key: String, field: String) -> Result<u64>;
+
+    /// Increment field in the hash stored at key by a floating point amount,
+    /// keeping any expiry of the field.
+    /// Return the updated value.
+    /// If the key or field does not exist, return an error (unlike Redis).
+    /// If the value cannot be interpreted as a finite number, or the result
+    /// would not be finite, return an error.
+    /// Time complexity: O(1)
+    fn hincrbyfloat(&mut self, key: String, field: String, delta: f64) -> Result<f64>;
+
+    // Bloom Filter Operations
+
+    /// Create an empty scalable bloom filter at key with the specified error rate
+    /// and initial capacity.
+    /// If the key already exists, return an error.
+    /// Time complexity: O(1)
+    fn bf_reserve(&mut self, key: String, error_rate: f64, capacity: u64) -> Result<()>;
+
+    /// Add item to the bloom filter stored at key.
+    /// Return true if the item was newly added, and false if it may have already existed.
+    /// If the key does not exist, create a filter with default parameters before
+    /// performing the operation.
+    /// Time complexity: O(K), where K is the number of hash functions
+    fn bf_add(&mut self, key: String, item: String) -> Result<bool>;
+
+    /// Add multiple items to the bloom filter stored at key.
+    /// Return whether each item was newly added, in the same order as the items.
+    /// If the key does not exist, create a filter with default parameters before
+    /// performing the operation.
+    /// Time complexity: O(K * N), where N is the number of items
+    fn bf_madd(&mut self, key: String, items: Vec<String>) -> Result<Vec<bool>>;
+
+    /// Return if item may exist in the bloom filter stored at key.
+    /// If the key does not exist, return false.
+    /// Time complexity: O(K)
+    fn bf_exists(&self, key: String, item: String) -> Result<bool>;
+
+    // Cuckoo Filter Operations
+
+    /// Add item to the cuckoo filter stored at key.
+    /// Items may be added multiple times, and each addition must be deleted separately.
+    /// If the key does not exist, create an empty filter before performing the operation.
+    /// Time complexity: O(1) amortized
+    fn cf_add(&mut self, key: String, item: String) -> Result<()>;
+
+    /// Return if item may exist in the cuckoo filter stored at key.
+    /// If the key does not exist, return false.
+    /// Time complexity: O(1)
+    fn cf_exists(&self, key: String, item: String) -> Result<bool>;
+
+    /// Remove one occurrence of item from the cuckoo filter stored at key.
+    /// Return true if the item was found and removed.
+    /// If the key does not exist, return false.
+    /// Time complexity: O(1)
+    fn cf_del(&mut self, key: String, item: String) -> Result<bool>;
+
+    // Time Series Operations
+
+    /// Create an empty time series at key.
+    /// Samples older than the retention period (in milliseconds) relative to the
+    /// latest sample are discarded. A retention of 0 keeps samples indefinitely.
+    /// If the key already exists, return an error.
+    /// Time complexity: O(1)
+    fn ts_create(&mut self, key: String, retention: u64) -> Result<()>;
+
+    /// Append a sample to the time series stored at key.
+    /// Return the timestamp of the sample.
+    /// If the key does not exist, create a series without retention before
+    /// performing the operation.
+    /// If the sample is older than the retention period, return an error.
+    /// Time complexity: O(log N)
+    fn ts_add(&mut self, key: String, timestamp: i64, value: f64) -> Result<i64>;
+
+    /// Return samples between from and to (inclusive) in the time series stored
+    /// at key, optionally downsampled into buckets of the specified duration.
+    /// If the key does not exist, return an error.
+    /// Time complexity: O(log N + M), where M is the number of samples in range
+    fn ts_range(
+        &self,
+        key: String,
+        from: i64,
+        to: i64,
+        aggregation: Option<(Aggregation, u64)>,
+    ) -> Result<Vec<(i64, f64)>>;
+
+    // Vector Operations
+
+    /// Add or replace the vector for element in the vector index stored at key.
+    /// Return true if the element was newly added.
+    /// If the key does not exist, create an index with the dimension of the vector
+    /// before performing the operation.
+    /// If the dimension of the vector does not match the index, return an error.
+    /// Time complexity: O(D), where D is the dimension of the vector
+    fn vadd(&mut self, key: String, element: String, vector: Vec<f64>) -> Result<bool>;
+
+    /// Return up to count elements in the vector index stored at key that are most
+    /// similar to the query vector, along with their similarity or distance.
+    /// If the key does not exist, return an empty vector.
+    /// If the dimension of the query does not match the index, return an error.
+    /// Time complexity: O(N * D + N log N)
+    fn vsim(
+        &self,
+        key: String,
+        query: Vec<f64>,
+        metric: Metric,
+        count: usize,
+    ) -> Result<Vec<(String, f64)>>;
+
+    // Search Operations
+
+    /// Create a secondary index over the given fields of hashes whose keys start
+    /// with prefix. Existing hashes are indexed immediately, and subsequent hash
+    /// operations keep the index up to date.
+    /// If the index already exists, return an error.
+    /// Time complexity: O(N * F), where N is the number of hashes and F the number of fields
+    fn ft_create(&mut self, index: String, prefix: String, fields: Vec<String>) -> Result<()>;
+
+    /// Return keys of hashes matching all filters in the index, in lexicographic
+    /// order, skipping the first offset results and returning at most count.
+    /// If the index does not exist or a filter references a field that is not
+    /// indexed, return an error.
+    /// Time complexity: O(M log M), where M is the number of keys matching each filter
+    fn ft_search(
+        &self,
+        index: String,
+        filters: Vec<Filter>,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<String>>;
+}
+
+/// An extension of store for implementations that support expiring keys.
+/// Expiry times are Unix timestamps in milliseconds.
+pub trait ExpiringStore: Store {
+    /// Set the time at which key expires, or remove its expiry if None.
+    /// Return false if the key does not exist.
+    /// Time complexity: O(1)
+    fn set_expiry(&mut self, key: String, at: Option<i64>) -> Result<bool>;
+
+    /// Return the time at which key expires.
+    /// If the key does not exist or has no expiry, return None.
+    /// Time complexity: O(1)
+    fn get_expiry(&self, key: String) -> Result<Option<i64>>;
+
+    /// Remove up to limit keys that expired at or before now.
+    /// Return the removed keys.
+    /// Time complexity: O(N), where N is the number of keys with an expiry
+    fn purge_expired(&mut self, now: i64, limit: u64) -> Result<Vec<String>>;
+
+    /// Set the time at which a field of the hash stored at key expires, or
+    /// remove its expiry if None.
+    /// Return false if the key or field does not exist.
+    /// Time complexity: O(1)
+    fn set_field_expiry(&mut self, key: String, field: String, at: Option<i64>) -> Result<bool>;
+
+    /// Return the time at which a field of the hash stored at key expires.
+    /// If the key or field does not exist or has no expiry, return None.
+    /// Time complexity: O(1)
+    fn get_field_expiry(&self, key: String, field: String) -> Result<Option<i64>>;
+
+    /// Remove up to limit hash fields that expired at or before now.
+    /// Return the number of removed fields.
+    /// Time complexity: O(N), where N is the number of fields with an expiry
+    fn purge_expired_fields(&mut self, now: i64, limit: u64) -> Result<u64>;
 }
 
+/// A store backed by the standard library's collections.
+/// Keys are hashed with the hasher built by `H`, SipHash 1-3 by default.
 #[derive(Debug)]
-pub struct StdStore {
-    strings: HashMap<String, String>,
-    lists: HashMap<String, VecDeque<String>>,
-    hashes: HashMap<String, HashMap<String, String>>,
-    sets: HashMap<String, HashSet<String>>,
+pub struct StdStore<H = RandomState> {
+    strings: HashMap<String, String, H>,
+    lists: HashMap<String, VecDeque<String>, H>,
+    hashes: HashMap<String, HashMap<String, String, H>, H>,
+    sets: HashMap<String, HashSet<String, H>, H>,
+    blooms: HashMap<String, ScalableBloomFilter, H>,
+    cuckoos: HashMap<String, CuckooFilter, H>,
+    timeseries: HashMap<String, TimeSeries, H>,
+    vectors: HashMap<String, VectorIndex, H>,
+    indexes: HashMap<String, Index, H>,
+    expiries: HashMap<String, i64, H>,
+    field_expiries: HashMap<String, HashMap<String, i64, H>, H>,
+
+    /// Number of writes that changed the store
+    changes: u64,
+
+    /// Approximate number of bytes used by keys and values, updated by each
+    /// write so that it need not be recounted
+    memory: u64,
 }
 
-impl StdStore {
+/// A store that hashes keys with the Fowler-Noll-Vo (FNV) hashing algorithm
+pub type FnvStore = StdStore<FnvBuildHasher>;
+
+impl<H: BuildHasher + Default> StdStore<H> {
+    /// Remove the expiry of a hash field, if any.
+    /// Return if the field had an expiry.
+    fn remove_field_expiry(&mut self, key: &str, field: &str) -> bool {
+        match self.field_expiries.get_mut(key) {
+            Some(fields) => {
+                let removed = fields.remove(field).is_some();
+                if fields.is_empty() {
+                    self.field_expiries.remove(key);
+                }
+                removed
+            }
+            None => false,
+        }
+    }
+
+    /// Update secondary indexes covering a hash field whose value changed from
+    /// old to new (None if the field did not exist or was removed).
+    fn reindex(&mut self, key: &str, field: &str, old: Option<&str>, new: Option<&str>) {
+        for index in self.indexes.values_mut() {
+            if !index.covers(key, field) {
+                continue;
+            }
+            if let Some(old) = old {
+                index.remove(key, field, old);
+            }
+            if let Some(new) = new {
+                index.insert(key, field, new);
+            }
+        }
+    }
+
+    /// Count the number of bytes used by keys and values.
+    fn count_memory(&self) -> u64 {
+        let len = |s: &String| s.len() as u64;
+        let mut total = 0;
+        for (key, val) in self.strings.iter() {
+            total += len(key) + len(val);
+        }
+        for (key, list) in self.lists.iter() {
+            total += len(key) + list.iter().map(len).sum::<u64>();
+        }
+        for (key, hash) in self.hashes.iter() {
+            total += len(key) + hash.iter().map(|(f, v)| len(f) + len(v)).sum::<u64>();
+        }
+        for (key, set) in self.sets.iter() {
+            total += len(key) + set.iter().map(len).sum::<u64>();
+        }
+        for (key, filter) in self.blooms.iter() {
+            total += len(key) + filter.memory_usage();
+        }
+        for (key, filter) in self.cuckoos.iter() {
+            total += len(key) + filter.memory_usage();
+        }
+        for (key, series) in self.timeseries.iter() {
+            total += len(key) + series.memory_usage();
+        }
+        for (key, index) in self.vectors.iter() {
+            total += len(key) + index.memory_usage();
+        }
+        total
+    }
+
+    /// Move the value stored at key in a map to newkey.
+    fn move_key<V>(map: &mut HashMap<String, V, H>, key: &str, newkey: &str) {
+        if let Some(val) = map.remove(key) {
+            map.insert(newkey.to_string(), val);
+        }
+    }
+
     fn update_int(&mut self, key: String, delta: i64) -> Result<i64> {
         match self.strings.get_mut(&key) {
             Some(val) => match val.to_string().parse::<i64>() {
@@ -140,43 +611,570 @@ impl StdStore {
                     let check = int.checked_add(delta);
                     match check {
                         Some(sum) => {
-                            *val = sum.to_string();
+                            let sum_str = sum.to_string();
+                            if *val != sum_str {
+                                self.memory = self.memory + sum_str.len() as u64 - val.len() as u64;
+                                *val = sum_str;
+                                self.changes += 1;
+                            }
                             Ok(sum)
                         }
-                        None => {
-                            return Err(OperationalError {
-                                message: format!(
-                                    "Operation would cause integer to go out-of-bounds"
-                                ),
-                            })
-                        }
+                        None => Err(OperationalError {
+                            message: "Operation would cause integer to go out-of-bounds"
+                                .to_string(),
+                        }),
                     }
                 }
-                Err(_) => {
-                    return Err(OperationalError {
-                        message: format!(
-                            "Value stored at key cannot be represented as a 64-bit integer"
-                        ),
-                    })
-                }
+                Err(_) => Err(OperationalError {
+                    message: "Value stored at key cannot be represented as a 64-bit integer"
+                        .to_string(),
+                }),
             },
-            None => {
-                return Err(OperationalError {
-                    message: format!("Specified key does not exist"),
-                })
-            }
+            None => Err(OperationalError {
+                message: "Specified key does not exist".to_string(),
+            }),
+        }
+    }
+}
+
+/// Increment a number stored as a string by a floating point amount.
+/// Values are formatted with as few digits as are needed to read them back.
+fn update_float(val: &mut String, delta: f64) -> Result<f64> {
+    let float = match val.parse::<f64>() {
+        Ok(float) if float.is_finite() => float,
+        _ => {
+            return Err(OperationalError {
+                message: "Value cannot be represented as a finite number".to_string(),
+            })
         }
+    };
+    let sum = float + delta;
+    if !sum.is_finite() {
+        return Err(OperationalError {
+            message: "Operation would produce a non-finite number".to_string(),
+        });
     }
+    *val = sum.to_string();
+    Ok(sum)
 }
 
-impl Store for StdStore {
+/// Return the approximate number of bytes allocated by a map for entries
+/// that it doesn't hold.
+fn spare_bytes<K, V, H>(map: &HashMap<K, V, H>) -> u64 {
+    ((map.capacity() - map.len()) * size_of::<(K, V)>()) as u64
+}
+
+/// Release the spare capacity of a map. Return the approximate number of bytes freed.
+fn shrink_map<K: Eq + Hash, V, H: BuildHasher>(map: &mut HashMap<K, V, H>) -> u64 {
+    let spare = spare_bytes(map);
+    map.shrink_to_fit();
+    spare - spare_bytes(map)
+}
+
+impl<H: BuildHasher + Default> Store for StdStore<H> {
     fn new() -> Self {
-        StdStore {
-            strings: HashMap::new(),
-            lists: HashMap::new(),
-            hashes: HashMap::new(),
-            sets: HashMap::new(),
+        Self {
+            strings: HashMap::default(),
+            lists: HashMap::default(),
+            hashes: HashMap::default(),
+            sets: HashMap::default(),
+            blooms: HashMap::default(),
+            cuckoos: HashMap::default(),
+            timeseries: HashMap::default(),
+            vectors: HashMap::default(),
+            indexes: HashMap::default(),
+            expiries: HashMap::default(),
+            field_expiries: HashMap::default(),
+            changes: 0,
+            memory: 0,
+        }
+    }
+
+    // Keyspace Operations
+
+    fn exists(&self, key: String) -> Result<bool> {
+        Ok(self.strings.contains_key(&key)
+            || self.lists.contains_key(&key)
+            || self.hashes.contains_key(&key)
+            || self.sets.contains_key(&key)
+            || self.blooms.contains_key(&key)
+            || self.cuckoos.contains_key(&key)
+            || self.timeseries.contains_key(&key)
+            || self.vectors.contains_key(&key))
+    }
+
+    fn key_type(&self, key: String) -> Result<Option<&'static str>> {
+        let kind = match () {
+            _ if self.strings.contains_key(&key) => "string",
+            _ if self.lists.contains_key(&key) => "list",
+            _ if self.hashes.contains_key(&key) => "hash",
+            _ if self.sets.contains_key(&key) => "set",
+            _ if self.blooms.contains_key(&key) => "bloom",
+            _ if self.cuckoos.contains_key(&key) => "cuckoo",
+            _ if self.timeseries.contains_key(&key) => "timeseries",
+            _ if self.vectors.contains_key(&key) => "vector",
+            _ => return Ok(None),
+        };
+        Ok(Some(kind))
+    }
+
+    fn dbsize(&self) -> Result<u64> {
+        let size = self.strings.len()
+            + self.lists.len()
+            + self.hashes.len()
+            + self.sets.len()
+            + self.blooms.len()
+            + self.cuckoos.len()
+            + self.timeseries.len()
+            + self.vectors.len();
+        Ok(size as u64)
+    }
+
+    fn random_key(&self, rng: &mut Rng) -> Result<Option<String>> {
+        let mut n = rng.below(self.dbsize()?) as usize;
+        // Find the map holding the nth key, then walk to it
+        for kind in TYPE_NAMES {
+            let len = match *kind {
+                "string" => self.strings.len(),
+                "list" => self.lists.len(),
+                "hash" => self.hashes.len(),
+                "set" => self.sets.len(),
+                "bloom" => self.blooms.len(),
+                "cuckoo" => self.cuckoos.len(),
+                "timeseries" => self.timeseries.len(),
+                _ => self.vectors.len(),
+            };
+            if n < len {
+                return Ok(self.keys_of_type(kind).nth(n).cloned());
+            }
+            n -= len;
+        }
+        Ok(None)
+    }
+
+    fn del(&mut self, key: String) -> Result<bool> {
+        self.memory -= self.key_memory_usage(key.clone())?;
+        self.expiries.remove(&key);
+        self.field_expiries.remove(&key);
+        let mut removed = false;
+        if let Some(hash) = self.hashes.remove(&key) {
+            for (field, val) in hash.iter() {
+                self.reindex(&key, field, Some(val), None);
+            }
+            removed = true;
+        }
+        // A key can hold values of several types, which are all removed
+        removed |= self.strings.remove(&key).is_some();
+        removed |= self.lists.remove(&key).is_some();
+        removed |= self.sets.remove(&key).is_some();
+        removed |= self.blooms.remove(&key).is_some();
+        removed |= self.cuckoos.remove(&key).is_some();
+        removed |= self.timeseries.remove(&key).is_some();
+        removed |= self.vectors.remove(&key).is_some();
+        if removed {
+            self.changes += 1;
+        }
+        Ok(removed)
+    }
+
+    fn rename(&mut self, key: String, newkey: String) -> Result<()> {
+        if !self.exists(key.clone())? {
+            return Err(OperationalError {
+                message: "Specified key does not exist".to_string(),
+            });
+        }
+        if key == newkey {
+            return Ok(());
+        }
+        self.changes += 1;
+        self.del(newkey.clone())?;
+        self.memory -= self.key_memory_usage(key.clone())?;
+        if let Some(hash) = self.hashes.remove(&key) {
+            for (field, val) in hash.iter() {
+                self.reindex(&key, field, Some(val), None);
+                self.reindex(&newkey, field, None, Some(val));
+            }
+            self.hashes.insert(newkey.clone(), hash);
+        }
+        Self::move_key(&mut self.strings, &key, &newkey);
+        Self::move_key(&mut self.lists, &key, &newkey);
+        Self::move_key(&mut self.sets, &key, &newkey);
+        Self::move_key(&mut self.blooms, &key, &newkey);
+        Self::move_key(&mut self.cuckoos, &key, &newkey);
+        Self::move_key(&mut self.timeseries, &key, &newkey);
+        Self::move_key(&mut self.vectors, &key, &newkey);
+        Self::move_key(&mut self.expiries, &key, &newkey);
+        Self::move_key(&mut self.field_expiries, &key, &newkey);
+        self.memory += self.key_memory_usage(newkey)?;
+        Ok(())
+    }
+
+    fn renamenx(&mut self, key: String, newkey: String) -> Result<bool> {
+        if !self.exists(key.clone())? {
+            return Err(OperationalError {
+                message: "Specified key does not exist".to_string(),
+            });
+        }
+        if self.exists(newkey.clone())? {
+            return Ok(false);
+        }
+        self.rename(key, newkey)?;
+        Ok(true)
+    }
+
+    fn flushdb(&mut self) -> Result<()> {
+        if self.dbsize()? > 0 {
+            self.changes += 1;
+        }
+        self.memory = 0;
+        self.strings.clear();
+        self.lists.clear();
+        self.hashes.clear();
+        self.sets.clear();
+        self.blooms.clear();
+        self.cuckoos.clear();
+        self.timeseries.clear();
+        self.vectors.clear();
+        self.expiries.clear();
+        self.field_expiries.clear();
+        for index in self.indexes.values_mut() {
+            let fields = index.fields().into_iter().cloned().collect();
+            *index = Index::new(index.prefix().to_string(), fields);
+        }
+        Ok(())
+    }
+
+    fn cardinality(&self, key: String) -> Result<u64> {
+        if self.strings.contains_key(&key) {
+            return Ok(1);
+        }
+        let len = self
+            .lists
+            .get(&key)
+            .map(|list| list.len() as u64)
+            .or_else(|| self.hashes.get(&key).map(|hash| hash.len() as u64))
+            .or_else(|| self.sets.get(&key).map(|set| set.len() as u64))
+            .or_else(|| self.blooms.get(&key).map(|filter| filter.len()))
+            .or_else(|| self.cuckoos.get(&key).map(|filter| filter.len()))
+            .or_else(|| self.timeseries.get(&key).map(|series| series.len() as u64))
+            .or_else(|| self.vectors.get(&key).map(|index| index.len() as u64));
+        Ok(len.unwrap_or(0))
+    }
+
+    fn serialize(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let mut enc = Encoder::new(writer);
+        enc.header()?;
+        for (key, val) in self.strings.iter() {
+            enc.u8(TAG_STRING)?;
+            enc.str(key)?;
+            enc.str(val)?;
+        }
+        for (key, list) in self.lists.iter() {
+            enc.u8(TAG_LIST)?;
+            enc.str(key)?;
+            enc.u64(list.len() as u64)?;
+            for val in list.iter() {
+                enc.str(val)?;
+            }
+        }
+        for (key, hash) in self.hashes.iter() {
+            enc.u8(TAG_HASH)?;
+            enc.str(key)?;
+            enc.u64(hash.len() as u64)?;
+            for (field, val) in hash.iter() {
+                enc.str(field)?;
+                enc.str(val)?;
+            }
+        }
+        for (key, set) in self.sets.iter() {
+            enc.u8(TAG_SET)?;
+            enc.str(key)?;
+            enc.u64(set.len() as u64)?;
+            for val in set.iter() {
+                enc.str(val)?;
+            }
+        }
+        for (key, filter) in self.blooms.iter() {
+            enc.u8(TAG_BLOOM)?;
+            enc.str(key)?;
+            filter.encode(&mut enc)?;
+        }
+        for (key, filter) in self.cuckoos.iter() {
+            enc.u8(TAG_CUCKOO)?;
+            enc.str(key)?;
+            filter.encode(&mut enc)?;
+        }
+        for (key, series) in self.timeseries.iter() {
+            enc.u8(TAG_TIMESERIES)?;
+            enc.str(key)?;
+            series.encode(&mut enc)?;
+        }
+        for (key, index) in self.vectors.iter() {
+            enc.u8(TAG_VECTOR)?;
+            enc.str(key)?;
+            index.encode(&mut enc)?;
+        }
+        // Only index definitions are written, since indexes are rebuilt from hashes
+        for (name, index) in self.indexes.iter() {
+            enc.u8(TAG_INDEX)?;
+            enc.str(name)?;
+            enc.str(index.prefix())?;
+            let fields = index.fields();
+            enc.u64(fields.len() as u64)?;
+            for field in fields {
+                enc.str(field)?;
+            }
+        }
+        for (key, at) in self.expiries.iter() {
+            enc.u8(TAG_EXPIRY)?;
+            enc.str(key)?;
+            enc.i64(*at)?;
+        }
+        for (key, fields) in self.field_expiries.iter() {
+            for (field, at) in fields.iter() {
+                enc.u8(TAG_FIELD_EXPIRY)?;
+                enc.str(key)?;
+                enc.str(field)?;
+                enc.i64(*at)?;
+            }
+        }
+        enc.u8(TAG_END)
+    }
+
+    fn deserialize(reader: &mut dyn Read) -> io::Result<Self> {
+        let mut store: Self = Store::new();
+        let mut dec = Decoder::new(reader);
+        dec.header()?;
+        let mut indexes = Vec::new();
+        loop {
+            let tag = dec.u8()?;
+            if tag == TAG_END {
+                break;
+            }
+            let key = dec.string()?;
+            match tag {
+                TAG_STRING => {
+                    store.strings.insert(key, dec.string()?);
+                }
+                TAG_LIST => {
+                    let mut list = VecDeque::new();
+                    for _ in 0..dec.u64()? {
+                        list.push_back(dec.string()?);
+                    }
+                    store.lists.insert(key, list);
+                }
+                TAG_HASH => {
+                    let mut hash = HashMap::default();
+                    for _ in 0..dec.u64()? {
+                        let field = dec.string()?;
+                        hash.insert(field, dec.string()?);
+                    }
+                    store.hashes.insert(key, hash);
+                }
+                TAG_SET => {
+                    let mut set = HashSet::default();
+                    for _ in 0..dec.u64()? {
+                        set.insert(dec.string()?);
+                    }
+                    store.sets.insert(key, set);
+                }
+                TAG_BLOOM => {
+                    store
+                        .blooms
+                        .insert(key, ScalableBloomFilter::decode(&mut dec)?);
+                }
+                TAG_CUCKOO => {
+                    store.cuckoos.insert(key, CuckooFilter::decode(&mut dec)?);
+                }
+                TAG_TIMESERIES => {
+                    store.timeseries.insert(key, TimeSeries::decode(&mut dec)?);
+                }
+                TAG_VECTOR => {
+                    store.vectors.insert(key, VectorIndex::decode(&mut dec)?);
+                }
+                TAG_INDEX => {
+                    let prefix = dec.string()?;
+                    let mut fields = Vec::new();
+                    for _ in 0..dec.u64()? {
+                        fields.push(dec.string()?);
+                    }
+                    indexes.push((key, prefix, fields));
+                }
+                TAG_EXPIRY => {
+                    store.expiries.insert(key, dec.i64()?);
+                }
+                TAG_FIELD_EXPIRY => {
+                    let field = dec.string()?;
+                    store
+                        .field_expiries
+                        .entry(key)
+                        .or_default()
+                        .insert(field, dec.i64()?);
+                }
+                _ => return Err(invalid_data("Unrecognized entry in snapshot")),
+            }
+        }
+        for (name, prefix, fields) in indexes {
+            if store.ft_create(name, prefix, fields).is_err() {
+                return Err(invalid_data("Duplicate index in snapshot"));
+            }
+        }
+        store.memory = store.count_memory();
+        Ok(store)
+    }
+
+    fn keys(&self) -> Iter<'_, &String> {
+        Box::new(
+            self.strings
+                .keys()
+                .chain(self.lists.keys())
+                .chain(self.hashes.keys())
+                .chain(self.sets.keys())
+                .chain(self.blooms.keys())
+                .chain(self.cuckoos.keys())
+                .chain(self.timeseries.keys())
+                .chain(self.vectors.keys()),
+        )
+    }
+
+    fn keys_of_type(&self, kind: &str) -> Iter<'_, &String> {
+        match kind {
+            "string" => Box::new(self.strings.keys()),
+            "list" => Box::new(self.lists.keys()),
+            "hash" => Box::new(self.hashes.keys()),
+            "set" => Box::new(self.sets.keys()),
+            "bloom" => Box::new(self.blooms.keys()),
+            "cuckoo" => Box::new(self.cuckoos.keys()),
+            "timeseries" => Box::new(self.timeseries.keys()),
+            "vector" => Box::new(self.vectors.keys()),
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn strings(&self) -> Iter<'_, (&String, &String)> {
+        Box::new(self.strings.iter())
+    }
+
+    fn lists(&self) -> Iter<'_, (&String, Vec<&String>)> {
+        Box::new(
+            self.lists
+                .iter()
+                .map(|(k, list)| (k, list.iter().collect())),
+        )
+    }
+
+    fn sets(&self) -> Iter<'_, (&String, Vec<&String>)> {
+        Box::new(self.sets.iter().map(|(k, set)| (k, set.iter().collect())))
+    }
+
+    fn hashes(&self) -> Iter<'_, (&String, Vec<(&String, &String)>)> {
+        Box::new(
+            self.hashes
+                .iter()
+                .map(|(k, hash)| (k, hash.iter().collect())),
+        )
+    }
+
+    fn changes(&self) -> Result<u64> {
+        Ok(self.changes)
+    }
+
+    fn type_counts(&self) -> Result<Vec<(&'static str, u64)>> {
+        Ok(vec![
+            ("string", self.strings.len() as u64),
+            ("list", self.lists.len() as u64),
+            ("hash", self.hashes.len() as u64),
+            ("set", self.sets.len() as u64),
+            ("bloom", self.blooms.len() as u64),
+            ("cuckoo", self.cuckoos.len() as u64),
+            ("timeseries", self.timeseries.len() as u64),
+            ("vector", self.vectors.len() as u64),
+        ])
+    }
+
+    fn spare_capacity(&self) -> Result<u64> {
+        Ok(spare_bytes(&self.strings)
+            + spare_bytes(&self.lists)
+            + spare_bytes(&self.hashes)
+            + spare_bytes(&self.sets)
+            + spare_bytes(&self.blooms)
+            + spare_bytes(&self.cuckoos)
+            + spare_bytes(&self.timeseries)
+            + spare_bytes(&self.vectors)
+            + spare_bytes(&self.indexes)
+            + spare_bytes(&self.expiries)
+            + spare_bytes(&self.field_expiries))
+    }
+
+    fn compact(&mut self) -> Result<u64> {
+        let mut freed = 0;
+        for val in self.strings.values_mut() {
+            let spare = val.capacity() - val.len();
+            val.shrink_to_fit();
+            freed += (spare - (val.capacity() - val.len())) as u64;
+        }
+        for list in self.lists.values_mut() {
+            let spare = list.capacity() - list.len();
+            list.shrink_to_fit();
+            freed += ((spare - (list.capacity() - list.len())) * size_of::<String>()) as u64;
+        }
+        for set in self.sets.values_mut() {
+            let spare = set.capacity() - set.len();
+            set.shrink_to_fit();
+            freed += ((spare - (set.capacity() - set.len())) * size_of::<String>()) as u64;
+        }
+        for hash in self.hashes.values_mut() {
+            freed += shrink_map(hash);
+        }
+        for fields in self.field_expiries.values_mut() {
+            freed += shrink_map(fields);
+        }
+        freed += shrink_map(&mut self.strings)
+            + shrink_map(&mut self.lists)
+            + shrink_map(&mut self.hashes)
+            + shrink_map(&mut self.sets)
+            + shrink_map(&mut self.blooms)
+            + shrink_map(&mut self.cuckoos)
+            + shrink_map(&mut self.timeseries)
+            + shrink_map(&mut self.vectors)
+            + shrink_map(&mut self.indexes)
+            + shrink_map(&mut self.expiries)
+            + shrink_map(&mut self.field_expiries);
+        Ok(freed)
+    }
+
+    fn memory_usage(&self) -> Result<u64> {
+        Ok(self.memory)
+    }
+
+    fn key_memory_usage(&self, key: String) -> Result<u64> {
+        let len = |s: &String| s.len() as u64;
+        let mut total = 0;
+        if let Some(val) = self.strings.get(&key) {
+            total += len(&key) + len(val);
+        }
+        if let Some(list) = self.lists.get(&key) {
+            total += len(&key) + list.iter().map(len).sum::<u64>();
+        }
+        if let Some(hash) = self.hashes.get(&key) {
+            total += len(&key) + hash.iter().map(|(f, v)| len(f) + len(v)).sum::<u64>();
+        }
+        if let Some(set) = self.sets.get(&key) {
+            total += len(&key) + set.iter().map(len).sum::<u64>();
+        }
+        if let Some(filter) = self.blooms.get(&key) {
+            total += len(&key) + filter.memory_usage();
+        }
+        if let Some(filter) = self.cuckoos.get(&key) {
+            total += len(&key) + filter.memory_usage();
         }
+        if let Some(series) = self.timeseries.get(&key) {
+            total += len(&key) + series.memory_usage();
+        }
+        if let Some(index) = self.vectors.get(&key) {
+            total += len(&key) + index.memory_usage();
+        }
+        Ok(total)
     }
 
     // Strings Operations
@@ -188,11 +1186,28 @@ impl Store for StdStore {
         }
     }
 
+    fn getdel(&mut self, key: String) -> Result<Option<String>> {
+        let val = self.strings.remove(&key);
+        if let Some(val) = val.as_ref() {
+            self.memory -= (key.len() + val.len()) as u64;
+            self.del(key)?;
+            self.changes += 1;
+        }
+        Ok(val)
+    }
+
     fn set(&mut self, key: String, val: String) -> Result<Option<String>> {
-        match self.strings.insert(key, val) {
-            Some(val) => Ok(Some(val)),
-            None => Ok(None),
+        // Overwriting a string discards its expiry
+        let had_expiry = self.expiries.remove(&key).is_some();
+        self.memory += (key.len() + val.len()) as u64;
+        let old = self.strings.insert(key.clone(), val.clone());
+        if let Some(old) = old.as_ref() {
+            self.memory -= (key.len() + old.len()) as u64;
         }
+        if had_expiry || old.as_ref() != Some(&val) {
+            self.changes += 1;
+        }
+        Ok(old)
     }
 
     fn incr(&mut self, key: String) -> Result<i64> {
@@ -211,18 +1226,117 @@ impl Store for StdStore {
         self.update_int(key, -delta)
     }
 
-    /// Lists Operations
+    fn incrbyfloat(&mut self, key: String, delta: f64) -> Result<f64> {
+        match self.strings.get_mut(&key) {
+            Some(val) => {
+                let old = val.clone();
+                let sum = update_float(val, delta)?;
+                if *val != old {
+                    self.memory = self.memory + val.len() as u64 - old.len() as u64;
+                    self.changes += 1;
+                }
+                Ok(sum)
+            }
+            None => Err(OperationalError {
+                message: "Specified key does not exist".to_string(),
+            }),
+        }
+    }
+
+    fn append(&mut self, key: String, val: String) -> Result<u64> {
+        let created = !self.strings.contains_key(&key);
+        if created || !val.is_empty() {
+            self.changes += 1;
+        }
+        if created {
+            self.memory += key.len() as u64;
+        }
+        self.memory += val.len() as u64;
+        let s = self.strings.entry(key).or_default();
+        s.push_str(&val);
+        Ok(s.len() as u64)
+    }
+
+    fn getrange(&self, key: String, start: i64, end: i64) -> Result<String> {
+        let bytes = match self.strings.get(&key) {
+            Some(val) => val.as_bytes(),
+            None => return Ok(String::new()),
+        };
+        let len = bytes.len() as i64;
+        let start = match start < 0 {
+            true => (len + start).max(0),
+            false => start,
+        };
+        let end = match end < 0 {
+            true => len + end,
+            false => end.min(len - 1),
+        };
+        if start > end || start >= len {
+            return Ok(String::new());
+        }
+        Ok(String::from_utf8_lossy(&bytes[start as usize..=end as usize]).into_owned())
+    }
+
+    fn setrange(&mut self, key: String, offset: u64, val: String) -> Result<u64> {
+        if offset.saturating_add(val.len() as u64) > MAX_STRING_SIZE {
+            return Err(OperationalError {
+                message: "String exceeds maximum allowed size".to_string(),
+            });
+        }
+        if val.is_empty() {
+            return self.strlen(key);
+        }
+        let offset = offset as usize;
+        let end = offset + val.len();
+        if let Some(old) = self.strings.get(&key) {
+            let splits = |i: usize| i < old.len() && !old.is_char_boundary(i);
+            if splits(offset) || splits(end) {
+                return Err(OperationalError {
+                    message: "Offset would split a multi-byte character".to_string(),
+                });
+            }
+        }
+        let old = self.strings.remove(&key);
+        let mut s = old.clone().unwrap_or_default();
+        if s.len() < offset {
+            s.push_str(&"\0".repeat(offset - s.len()));
+        }
+        s.replace_range(offset..end.min(s.len()), &val);
+        let len = s.len() as u64;
+        if old.as_ref() != Some(&s) {
+            self.changes += 1;
+        }
+        self.memory = match old {
+            Some(old) => self.memory + len - old.len() as u64,
+            None => self.memory + key.len() as u64 + len,
+        };
+        self.strings.insert(key, s);
+        Ok(len)
+    }
+
+    fn strlen(&self, key: String) -> Result<u64> {
+        match self.strings.get(&key) {
+            Some(val) => Ok(val.len() as u64),
+            None => Ok(0),
+        }
+    }
+
+    // Lists Operations
 
     fn lpush(&mut self, key: String, val: String) -> Result<u64> {
         match self.lists.get_mut(&key) {
             Some(list) => {
+                self.memory += val.len() as u64;
                 list.push_front(val);
+                self.changes += 1;
                 Ok(list.len() as u64)
             }
             None => {
+                self.memory += (key.len() + val.len()) as u64;
                 let mut list = VecDeque::new();
                 list.push_front(val);
                 self.lists.insert(key, list);
+                self.changes += 1;
                 Ok(1)
             }
         }
@@ -231,13 +1345,17 @@ impl Store for StdStore {
     fn rpush(&mut self, key: String, val: String) -> Result<u64> {
         match self.lists.get_mut(&key) {
             Some(list) => {
+                self.memory += val.len() as u64;
                 list.push_back(val);
+                self.changes += 1;
                 Ok(list.len() as u64)
             }
             None => {
+                self.memory += (key.len() + val.len()) as u64;
                 let mut list = VecDeque::new();
                 list.push_back(val);
                 self.lists.insert(key, list);
+                self.changes += 1;
                 Ok(1)
             }
         }
@@ -245,30 +1363,50 @@ impl Store for StdStore {
 
     fn lpop(&mut self, key: String) -> Result<Option<String>> {
         match self.lists.get_mut(&key) {
-            Some(list) => Ok(list.pop_front()),
+            Some(list) => {
+                let val = list.pop_front();
+                if let Some(val) = val.as_ref() {
+                    self.memory -= val.len() as u64;
+                    self.changes += 1;
+                }
+                Ok(val)
+            }
             None => Ok(None),
         }
     }
 
     fn rpop(&mut self, key: String) -> Result<Option<String>> {
         match self.lists.get_mut(&key) {
-            Some(list) => Ok(list.pop_back()),
+            Some(list) => {
+                let val = list.pop_back();
+                if let Some(val) = val.as_ref() {
+                    self.memory -= val.len() as u64;
+                    self.changes += 1;
+                }
+                Ok(val)
+            }
             None => Ok(None),
         }
     }
 
-    /// Sets Operations
+    // Sets Operations
 
     fn sadd(&mut self, key: String, val: String) -> Result<u64> {
         match self.sets.get_mut(&key) {
             Some(set) => {
-                set.insert(val);
+                let len = val.len() as u64;
+                if set.insert(val) {
+                    self.memory += len;
+                    self.changes += 1;
+                }
                 Ok(set.len() as u64)
             }
             None => {
-                let mut set = HashSet::new();
+                self.memory += (key.len() + val.len()) as u64;
+                let mut set = HashSet::default();
                 set.insert(val);
                 self.sets.insert(key, set);
+                self.changes += 1;
                 Ok(1)
             }
         }
@@ -277,7 +1415,10 @@ impl Store for StdStore {
     fn srem(&mut self, key: String, val: String) -> Result<u64> {
         match self.sets.get_mut(&key) {
             Some(set) => {
-                set.remove(&val);
+                if set.remove(&val) {
+                    self.memory -= val.len() as u64;
+                    self.changes += 1;
+                }
                 Ok(set.len() as u64)
             }
             None => Ok(0),
@@ -298,7 +1439,7 @@ impl Store for StdStore {
         }
     }
 
-    /// Hashes Operations
+    // Hashes Operations
 
     fn hget(&self, key: String, field: String) -> Result<Option<String>> {
         match self.hashes.get(&key) {
@@ -311,24 +1452,302 @@ impl Store for StdStore {
     }
 
     fn hset(&mut self, key: String, field: String, val: String) -> Result<Option<String>> {
-        match self.hashes.get_mut(&key) {
-            Some(hash) => Ok(hash.insert(field, val)),
+        // Overwriting a field discards its expiry
+        let had_expiry = self.remove_field_expiry(&key, &field);
+        if !self.indexes.is_empty() {
+            let old = self.hget(key.clone(), field.clone())?;
+            self.reindex(&key, &field, old.as_deref(), Some(&val));
+        }
+        let old = match self.hashes.get_mut(&key) {
+            Some(hash) => {
+                self.memory += (field.len() + val.len()) as u64;
+                let old = hash.insert(field.clone(), val.clone());
+                if let Some(old) = old.as_ref() {
+                    self.memory -= (field.len() + old.len()) as u64;
+                }
+                old
+            }
             None => {
-                let mut hash = HashMap::new();
-                hash.insert(field, val);
+                self.memory += (key.len() + field.len() + val.len()) as u64;
+                let mut hash = HashMap::default();
+                hash.insert(field, val.clone());
                 self.hashes.insert(key, hash);
-                Ok(None)
+                None
             }
+        };
+        if had_expiry || old.as_ref() != Some(&val) {
+            self.changes += 1;
         }
+        Ok(old)
     }
 
-    fn hdel(&mut self, key: String, field: String) -> Result<u64> {
-        match self.hashes.get_mut(&key) {
-            Some(hash) => match hash.remove(&field) {
-                Some(_) => Ok(1),
-                None => Ok(0),
-            },
-            None => Ok(0),
+    fn hincrbyfloat(&mut self, key: String, field: String, delta: f64) -> Result<f64> {
+        let val = match self.hashes.get_mut(&key) {
+            Some(hash) => match hash.get_mut(&field) {
+                Some(val) => val,
+                None => {
+                    return Err(OperationalError {
+                        message: "Specified field does not exist".to_string(),
+                    })
+                }
+            },
+            None => {
+                return Err(OperationalError {
+                    message: "Specified key does not exist".to_string(),
+                })
+            }
+        };
+        let old = val.clone();
+        let sum = update_float(val, delta)?;
+        if *val != old {
+            self.memory = self.memory + val.len() as u64 - old.len() as u64;
+            self.changes += 1;
+        }
+        if !self.indexes.is_empty() {
+            let new = sum.to_string();
+            self.reindex(&key, &field, Some(&old), Some(&new));
+        }
+        Ok(sum)
+    }
+
+    fn hdel(&mut self, key: String, field: String) -> Result<u64> {
+        self.remove_field_expiry(&key, &field);
+        if !self.indexes.is_empty() {
+            let old = self.hget(key.clone(), field.clone())?;
+            self.reindex(&key, &field, old.as_deref(), None);
+        }
+        match self.hashes.get_mut(&key) {
+            Some(hash) => match hash.remove(&field) {
+                Some(val) => {
+                    self.memory -= (field.len() + val.len()) as u64;
+                    self.changes += 1;
+                    Ok(1)
+                }
+                None => Ok(0),
+            },
+            None => Ok(0),
+        }
+    }
+
+    // Bloom Filter Operations
+
+    fn bf_reserve(&mut self, key: String, error_rate: f64, capacity: u64) -> Result<()> {
+        if self.blooms.contains_key(&key) {
+            return Err(OperationalError {
+                message: "Specified key already exists".to_string(),
+            });
+        }
+        let filter = ScalableBloomFilter::new(error_rate, capacity);
+        self.memory += key.len() as u64 + filter.memory_usage();
+        self.blooms.insert(key, filter);
+        self.changes += 1;
+        Ok(())
+    }
+
+    fn bf_add(&mut self, key: String, item: String) -> Result<bool> {
+        let created = !self.blooms.contains_key(&key);
+        if created {
+            self.memory += key.len() as u64;
+        }
+        let filter = self
+            .blooms
+            .entry(key)
+            .or_insert_with(|| ScalableBloomFilter::new(DEFAULT_ERROR_RATE, DEFAULT_CAPACITY));
+        let before = if created { 0 } else { filter.memory_usage() };
+        let added = filter.add(&item);
+        self.memory += filter.memory_usage() - before;
+        if created || added {
+            self.changes += 1;
+        }
+        Ok(added)
+    }
+
+    fn bf_madd(&mut self, key: String, items: Vec<String>) -> Result<Vec<bool>> {
+        let created = !self.blooms.contains_key(&key);
+        if created {
+            self.memory += key.len() as u64;
+        }
+        let filter = self
+            .blooms
+            .entry(key)
+            .or_insert_with(|| ScalableBloomFilter::new(DEFAULT_ERROR_RATE, DEFAULT_CAPACITY));
+        let before = if created { 0 } else { filter.memory_usage() };
+        let added: Vec<bool> = items.iter().map(|item| filter.add(item)).collect();
+        self.memory += filter.memory_usage() - before;
+        if created || added.contains(&true) {
+            self.changes += 1;
+        }
+        Ok(added)
+    }
+
+    fn bf_exists(&self, key: String, item: String) -> Result<bool> {
+        match self.blooms.get(&key) {
+            Some(filter) => Ok(filter.contains(&item)),
+            None => Ok(false),
+        }
+    }
+
+    // Cuckoo Filter Operations
+
+    fn cf_add(&mut self, key: String, item: String) -> Result<()> {
+        let created = !self.cuckoos.contains_key(&key);
+        if created {
+            self.memory += key.len() as u64;
+        }
+        let filter = self
+            .cuckoos
+            .entry(key)
+            .or_insert_with(|| CuckooFilter::new(DEFAULT_BUCKETS));
+        let before = if created { 0 } else { filter.memory_usage() };
+        filter.add(&item);
+        self.memory += filter.memory_usage() - before;
+        self.changes += 1;
+        Ok(())
+    }
+
+    fn cf_exists(&self, key: String, item: String) -> Result<bool> {
+        match self.cuckoos.get(&key) {
+            Some(filter) => Ok(filter.contains(&item)),
+            None => Ok(false),
+        }
+    }
+
+    fn cf_del(&mut self, key: String, item: String) -> Result<bool> {
+        match self.cuckoos.get_mut(&key) {
+            Some(filter) => {
+                let deleted = filter.delete(&item);
+                if deleted {
+                    self.changes += 1;
+                }
+                Ok(deleted)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Time Series Operations
+
+    fn ts_create(&mut self, key: String, retention: u64) -> Result<()> {
+        if self.timeseries.contains_key(&key) {
+            return Err(OperationalError {
+                message: "Specified key already exists".to_string(),
+            });
+        }
+        self.memory += key.len() as u64;
+        self.timeseries.insert(key, TimeSeries::new(retention));
+        self.changes += 1;
+        Ok(())
+    }
+
+    fn ts_add(&mut self, key: String, timestamp: i64, value: f64) -> Result<i64> {
+        if !self.timeseries.contains_key(&key) {
+            self.memory += key.len() as u64;
+            self.changes += 1;
+        }
+        let series = self
+            .timeseries
+            .entry(key)
+            .or_insert_with(|| TimeSeries::new(0));
+        let before = series.memory_usage();
+        let res = series.add(timestamp, value);
+        self.memory = self.memory + series.memory_usage() - before;
+        match res {
+            Ok(_) => {
+                self.changes += 1;
+                Ok(timestamp)
+            }
+            Err(message) => Err(OperationalError { message }),
+        }
+    }
+
+    fn ts_range(
+        &self,
+        key: String,
+        from: i64,
+        to: i64,
+        aggregation: Option<(Aggregation, u64)>,
+    ) -> Result<Vec<(i64, f64)>> {
+        match self.timeseries.get(&key) {
+            Some(series) => Ok(series.range(from, to, aggregation)),
+            None => Err(OperationalError {
+                message: "Specified key does not exist".to_string(),
+            }),
+        }
+    }
+
+    // Vector Operations
+
+    fn vadd(&mut self, key: String, element: String, vector: Vec<f64>) -> Result<bool> {
+        let dim = vector.len();
+        if !self.vectors.contains_key(&key) {
+            self.memory += key.len() as u64;
+        }
+        let index = self
+            .vectors
+            .entry(key)
+            .or_insert_with(|| VectorIndex::new(dim));
+        let len = (element.len() + dim * 8) as u64;
+        let added = index
+            .add(element, vector)
+            .map_err(|message| OperationalError { message })?;
+        if added {
+            self.memory += len;
+        }
+        self.changes += 1;
+        Ok(added)
+    }
+
+    fn vsim(
+        &self,
+        key: String,
+        query: Vec<f64>,
+        metric: Metric,
+        count: usize,
+    ) -> Result<Vec<(String, f64)>> {
+        match self.vectors.get(&key) {
+            Some(index) => index
+                .search(&query, metric, count)
+                .map_err(|message| OperationalError { message }),
+            None => Ok(vec![]),
+        }
+    }
+
+    // Search Operations
+
+    fn ft_create(&mut self, index: String, prefix: String, fields: Vec<String>) -> Result<()> {
+        if self.indexes.contains_key(&index) {
+            return Err(OperationalError {
+                message: "Specified index already exists".to_string(),
+            });
+        }
+        let mut idx = Index::new(prefix, fields);
+        for (key, hash) in self.hashes.iter() {
+            for (field, val) in hash.iter() {
+                if idx.covers(key, field) {
+                    idx.insert(key, field, val);
+                }
+            }
+        }
+        self.indexes.insert(index, idx);
+        self.changes += 1;
+        Ok(())
+    }
+
+    fn ft_search(
+        &self,
+        index: String,
+        filters: Vec<Filter>,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<String>> {
+        match self.indexes.get(&index) {
+            Some(idx) => match idx.search(&filters) {
+                Ok(keys) => Ok(keys.into_iter().skip(offset).take(count).collect()),
+                Err(message) => Err(OperationalError { message }),
+            },
+            None => Err(OperationalError {
+                message: "Specified index does not exist".to_string(),
+            }),
         }
     }
 }
@@ -338,10 +1757,477 @@ pub struct OperationalError {
     pub message: String,
 }
 
+impl<H: BuildHasher + Default> ExpiringStore for StdStore<H> {
+    fn set_expiry(&mut self, key: String, at: Option<i64>) -> Result<bool> {
+        if !self.exists(key.clone())? {
+            return Ok(false);
+        }
+        let old = match at {
+            Some(at) => self.expiries.insert(key, at),
+            None => self.expiries.remove(&key),
+        };
+        if old != at {
+            self.changes += 1;
+        }
+        Ok(true)
+    }
+
+    fn get_expiry(&self, key: String) -> Result<Option<i64>> {
+        Ok(self.expiries.get(&key).copied())
+    }
+
+    fn purge_expired(&mut self, now: i64, limit: u64) -> Result<Vec<String>> {
+        let expired: Vec<String> = self
+            .expiries
+            .iter()
+            .filter(|(_, &at)| at <= now)
+            .take(limit as usize)
+            .map(|(key, _)| key.to_string())
+            .collect();
+        for key in expired.iter() {
+            self.del(key.to_string())?;
+        }
+        Ok(expired)
+    }
+
+    fn set_field_expiry(&mut self, key: String, field: String, at: Option<i64>) -> Result<bool> {
+        if self.hget(key.clone(), field.clone())?.is_none() {
+            return Ok(false);
+        }
+        let changed = match at {
+            Some(at) => {
+                self.field_expiries
+                    .entry(key)
+                    .or_default()
+                    .insert(field, at)
+                    != Some(at)
+            }
+            None => self.remove_field_expiry(&key, &field),
+        };
+        if changed {
+            self.changes += 1;
+        }
+        Ok(true)
+    }
+
+    fn get_field_expiry(&self, key: String, field: String) -> Result<Option<i64>> {
+        Ok(self
+            .field_expiries
+            .get(&key)
+            .and_then(|fields| fields.get(&field))
+            .copied())
+    }
+
+    fn purge_expired_fields(&mut self, now: i64, limit: u64) -> Result<u64> {
+        let expired: Vec<(String, String)> = self
+            .field_expiries
+            .iter()
+            .flat_map(|(key, fields)| fields.iter().map(move |(field, at)| (key, field, at)))
+            .filter(|(_, _, &at)| at <= now)
+            .take(limit as usize)
+            .map(|(key, field, _)| (key.to_string(), field.to_string()))
+            .collect();
+        for (key, field) in expired.iter() {
+            self.hdel(key.to_string(), field.to_string())?;
+        }
+        Ok(expired.len() as u64)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_std_keyspace() {
+        let mut store: StdStore = Store::new();
+        assert_eq!(store.dbsize().unwrap(), 0);
+        assert_eq!(store.memory_usage().unwrap(), 0);
+        assert!(!store.exists("foo".to_string()).unwrap());
+
+        let _ = store.set("foo".to_string(), "bar".to_string());
+        let _ = store.sadd("baz".to_string(), "item1".to_string());
+        assert!(store.exists("foo".to_string()).unwrap());
+        assert!(store.exists("baz".to_string()).unwrap());
+        assert_eq!(store.dbsize().unwrap(), 2);
+        assert_eq!(store.memory_usage().unwrap(), 14);
+        assert_eq!(store.key_memory_usage("foo".to_string()).unwrap(), 6);
+        assert_eq!(store.key_memory_usage("baz".to_string()).unwrap(), 8);
+        assert_eq!(store.key_memory_usage("dne".to_string()).unwrap(), 0);
+
+        let _ = store.sadd("baz".to_string(), "item2".to_string());
+        assert_eq!(store.cardinality("foo".to_string()).unwrap(), 1);
+        assert_eq!(store.cardinality("baz".to_string()).unwrap(), 2);
+        assert_eq!(store.cardinality("dne".to_string()).unwrap(), 0);
+
+        assert_eq!(store.key_type("foo".to_string()).unwrap(), Some("string"));
+        assert_eq!(store.key_type("baz".to_string()).unwrap(), Some("set"));
+        assert_eq!(store.key_type("dne".to_string()).unwrap(), None);
+
+        let counts = store.type_counts().unwrap();
+        assert!(counts.contains(&("string", 1)));
+        assert!(counts.contains(&("set", 1)));
+        assert_eq!(counts.iter().map(|(_, n)| n).sum::<u64>(), 2);
+
+        // Deleting a key removes its values of every type
+        let _ = store.rpush("foo".to_string(), "item".to_string());
+        let _ = store.hset("foo".to_string(), "field".to_string(), "val".to_string());
+        assert!(store.del("foo".to_string()).unwrap());
+        assert!(!store.exists("foo".to_string()).unwrap());
+        assert!(!store.del("foo".to_string()).unwrap());
+        assert_eq!(store.dbsize().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_std_rename() {
+        let mut store: StdStore = Store::new();
+        let _ = store.set("foo".to_string(), "bar".to_string());
+        let _ = store.rpush("foo".to_string(), "item".to_string());
+        let _ = store.set_expiry("foo".to_string(), Some(i64::MAX));
+        let _ = store.sadd("baz".to_string(), "item".to_string());
+
+        // Values of every type and the expiry move, replacing the destination
+        store.rename("foo".to_string(), "baz".to_string()).unwrap();
+        assert!(!store.exists("foo".to_string()).unwrap());
+        assert_eq!(store.get("baz".to_string()).unwrap().unwrap(), "bar");
+        assert_eq!(store.lpop("baz".to_string()).unwrap().unwrap(), "item");
+        assert!(store.smembers("baz".to_string()).unwrap().is_empty());
+        assert_eq!(store.get_expiry("baz".to_string()).unwrap(), Some(i64::MAX));
+        assert_eq!(store.get_expiry("foo".to_string()).unwrap(), None);
+        assert!(store.rename("foo".to_string(), "qux".to_string()).is_err());
+        store.rename("baz".to_string(), "baz".to_string()).unwrap();
+        assert!(store.exists("baz".to_string()).unwrap());
+
+        let _ = store.set("qux".to_string(), "val".to_string());
+        assert!(!store
+            .renamenx("baz".to_string(), "qux".to_string())
+            .unwrap());
+        assert!(store
+            .renamenx("baz".to_string(), "foo".to_string())
+            .unwrap());
+        assert_eq!(store.get("foo".to_string()).unwrap().unwrap(), "bar");
+        assert!(store
+            .renamenx("dne".to_string(), "new".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn test_std_compact() {
+        let mut store: StdStore = Store::new();
+        for i in 0..1000 {
+            let _ = store.set(i.to_string(), "val".to_string());
+        }
+        let _ = store.rpush("list".to_string(), "item".to_string());
+        let _ = store.rpush("list".to_string(), "item".to_string());
+        let _ = store.rpop("list".to_string());
+        for i in 0..1000 {
+            let _ = store.del(i.to_string());
+        }
+        // Maps keep a small minimum capacity
+        let spare = store.spare_capacity().unwrap();
+        assert!(store.compact().unwrap() > 0);
+        assert!(store.spare_capacity().unwrap() < spare / 10);
+        assert_eq!(store.compact().unwrap(), 0);
+        assert_eq!(store.cardinality("list".to_string()).unwrap(), 1);
+        assert_eq!(
+            store.rpop("list".to_string()).unwrap(),
+            Some("item".to_string())
+        );
+    }
+
+    #[test]
+    fn test_std_flushdb() {
+        let mut store: StdStore = Store::new();
+        store
+            .ft_create(
+                "idx".to_string(),
+                "user:".to_string(),
+                vec!["name".to_string()],
+            )
+            .unwrap();
+        let _ = store.set("foo".to_string(), "bar".to_string());
+        let _ = store.hset(
+            "user:1".to_string(),
+            "name".to_string(),
+            "shoyo".to_string(),
+        );
+        let _ = store.set_expiry("foo".to_string(), Some(i64::MAX));
+        store.flushdb().unwrap();
+        assert_eq!(store.dbsize().unwrap(), 0);
+        assert_eq!(store.get_expiry("foo".to_string()).unwrap(), None);
+
+        // Indexes are kept, and index hashes written after the flush
+        let _ = store.hset(
+            "user:2".to_string(),
+            "name".to_string(),
+            "shoyo".to_string(),
+        );
+        let filters = vec![Filter::Eq {
+            field: "name".to_string(),
+            value: "shoyo".to_string(),
+        }];
+        assert_eq!(
+            store.ft_search("idx".to_string(), filters, 0, 10).unwrap(),
+            vec!["user:2".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_std_random_key() {
+        let mut store: StdStore = Store::new();
+        let mut rng = Rng::new(0);
+        assert_eq!(store.random_key(&mut rng).unwrap(), None);
+
+        let _ = store.set("foo".to_string(), "bar".to_string());
+        let _ = store.rpush("baz".to_string(), "item".to_string());
+        let _ = store.sadd("qux".to_string(), "item".to_string());
+        let mut seen = HashSet::new();
+        for _ in 0..100 {
+            seen.insert(store.random_key(&mut rng).unwrap().unwrap());
+        }
+        assert_eq!(seen.len(), 3);
+    }
+
+    #[test]
+    fn test_std_scan() {
+        let mut store: StdStore = Store::new();
+        assert_eq!(store.scan(0, None, None, 10).unwrap(), (0, vec![]));
+        for i in 0..25 {
+            let _ = store.set(format!("user:{}", i), "bar".to_string());
+        }
+        let _ = store.lpush("list".to_string(), "a".to_string());
+        let _ = store.lpush("list".to_string(), "b".to_string());
+        let _ = store.hset("hash".to_string(), "f".to_string(), "v".to_string());
+        let _ = store.sadd("set".to_string(), "m".to_string());
+
+        assert_eq!(store.keys().count(), 28);
+        assert_eq!(store.strings().count(), 25);
+        assert_eq!(
+            store.lists().collect::<Vec<_>>(),
+            vec![(
+                &"list".to_string(),
+                vec![&"b".to_string(), &"a".to_string()]
+            )]
+        );
+        assert_eq!(store.sets().next().unwrap().1, vec![&"m".to_string()]);
+        assert_eq!(
+            store.hashes().next().unwrap().1,
+            vec![(&"f".to_string(), &"v".to_string())]
+        );
+
+        // A full iteration returns every matching key exactly once
+        let mut cursor = 0;
+        let mut found = Vec::new();
+        loop {
+            let (next, keys) = store.scan(cursor, Some("user:1*"), None, 4).unwrap();
+            found.extend(keys);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        found.sort();
+        let mut expected: Vec<String> = (0..25)
+            .map(|i| format!("user:{}", i))
+            .filter(|k| k.starts_with("user:1"))
+            .collect();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_std_serialize() {
+        let s = |s: &str| s.to_string();
+        let mut store: StdStore = Store::new();
+        let _ = store.set(s("str"), s("val"));
+        let _ = store.rpush(s("list"), s("a"));
+        let _ = store.rpush(s("list"), s("b"));
+        let _ = store.sadd(s("set"), s("m"));
+        let _ = store.hset(s("user:1"), s("city"), s("Tokyo"));
+        let _ = store.bf_add(s("bloom"), s("item"));
+        let _ = store.cf_add(s("cuckoo"), s("item"));
+        let _ = store.ts_create(s("series"), 1000);
+        let _ = store.ts_add(s("series"), 10, 1.5);
+        let _ = store.vadd(s("vectors"), s("e"), vec![1.0, 0.0]);
+        let _ = store.ft_create(s("users"), s("user:"), vec![s("city")]);
+
+        let mut buf = Vec::new();
+        store.serialize(&mut buf).unwrap();
+        let mut reader = &buf[..];
+        let mut copy = <StdStore>::deserialize(&mut reader).unwrap();
+
+        assert_eq!(copy.dbsize().unwrap(), store.dbsize().unwrap());
+        assert_eq!(copy.memory_usage().unwrap(), store.memory_usage().unwrap());
+        assert_eq!(copy.get(s("str")).unwrap(), Some(s("val")));
+        assert_eq!(copy.lpop(s("list")).unwrap(), Some(s("a")));
+        assert!(copy.sismember(s("set"), s("m")).unwrap());
+        assert!(copy.bf_exists(s("bloom"), s("item")).unwrap());
+        assert!(copy.cf_exists(s("cuckoo"), s("item")).unwrap());
+        assert_eq!(
+            copy.ts_range(s("series"), 0, 100, None).unwrap(),
+            vec![(10, 1.5)]
+        );
+        assert_eq!(
+            copy.vsim(s("vectors"), vec![1.0, 0.0], Metric::Cosine, 1)
+                .unwrap()[0]
+                .0,
+            "e"
+        );
+        let city = vec![Filter::Eq {
+            field: s("city"),
+            value: s("Tokyo"),
+        }];
+        assert_eq!(
+            copy.ft_search(s("users"), city, 0, 10).unwrap(),
+            vec![s("user:1")]
+        );
+
+        // Truncated snapshots are rejected
+        let mut reader = &buf[..buf.len() - 1];
+        assert!(<StdStore>::deserialize(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_std_expiry() {
+        let s = |s: &str| s.to_string();
+        let mut store: StdStore = Store::new();
+        assert!(!store.set_expiry(s("foo"), Some(100)).unwrap());
+        let _ = store.set(s("foo"), s("bar"));
+        let _ = store.hset(s("user:1"), s("city"), s("Tokyo"));
+        let _ = store.ft_create(s("users"), s("user:"), vec![s("city")]);
+        assert_eq!(store.get_expiry(s("foo")).unwrap(), None);
+        assert!(store.set_expiry(s("foo"), Some(100)).unwrap());
+        assert!(store.set_expiry(s("user:1"), Some(200)).unwrap());
+        assert_eq!(store.get_expiry(s("foo")).unwrap(), Some(100));
+
+        // Expiries are preserved by snapshots
+        let mut buf = Vec::new();
+        store.serialize(&mut buf).unwrap();
+        let mut reader = &buf[..];
+        let copy = <StdStore>::deserialize(&mut reader).unwrap();
+        assert_eq!(copy.get_expiry(s("user:1")).unwrap(), Some(200));
+
+        assert!(store.purge_expired(99, 10).unwrap().is_empty());
+        assert_eq!(store.purge_expired(100, 10).unwrap(), vec![s("foo")]);
+        assert!(!store.exists(s("foo")).unwrap());
+        assert_eq!(store.get_expiry(s("foo")).unwrap(), None);
+
+        // Removed hashes are removed from indexes
+        assert_eq!(store.purge_expired(1000, 10).unwrap(), vec![s("user:1")]);
+        let city = vec![Filter::Eq {
+            field: s("city"),
+            value: s("Tokyo"),
+        }];
+        assert!(store.ft_search(s("users"), city, 0, 10).unwrap().is_empty());
+
+        // Persisting or overwriting a string removes its expiry
+        let _ = store.set(s("foo"), s("bar"));
+        store.set_expiry(s("foo"), Some(100)).unwrap();
+        store.set_expiry(s("foo"), None).unwrap();
+        assert_eq!(store.get_expiry(s("foo")).unwrap(), None);
+        store.set_expiry(s("foo"), Some(100)).unwrap();
+        let _ = store.set(s("foo"), s("baz"));
+        assert_eq!(store.get_expiry(s("foo")).unwrap(), None);
+        assert!(store.del(s("foo")).unwrap());
+        assert!(!store.del(s("foo")).unwrap());
+    }
+
+    #[test]
+    fn test_std_field_expiry() {
+        let s = |s: &str| s.to_string();
+        let mut store: StdStore = Store::new();
+        assert!(!store
+            .set_field_expiry(s("session"), s("token"), Some(100))
+            .unwrap());
+        let _ = store.hset(s("session"), s("token"), s("abc"));
+        let _ = store.hset(s("session"), s("user"), s("shoyo"));
+        assert!(!store
+            .set_field_expiry(s("session"), s("missing"), Some(100))
+            .unwrap());
+        assert!(store
+            .set_field_expiry(s("session"), s("token"), Some(100))
+            .unwrap());
+        assert_eq!(
+            store.get_field_expiry(s("session"), s("token")).unwrap(),
+            Some(100)
+        );
+        assert_eq!(
+            store.get_field_expiry(s("session"), s("user")).unwrap(),
+            None
+        );
+
+        // Field expiries are preserved by snapshots
+        let mut buf = Vec::new();
+        store.serialize(&mut buf).unwrap();
+        let mut reader = &buf[..];
+        let copy = <StdStore>::deserialize(&mut reader).unwrap();
+        assert_eq!(
+            copy.get_field_expiry(s("session"), s("token")).unwrap(),
+            Some(100)
+        );
+
+        // Only expired fields are removed, leaving the rest of the hash
+        assert_eq!(store.purge_expired_fields(99, 10).unwrap(), 0);
+        assert_eq!(store.purge_expired_fields(100, 10).unwrap(), 1);
+        assert_eq!(store.hget(s("session"), s("token")).unwrap(), None);
+        assert_eq!(
+            store.hget(s("session"), s("user")).unwrap(),
+            Some(s("shoyo"))
+        );
+        assert_eq!(
+            store.get_field_expiry(s("session"), s("token")).unwrap(),
+            None
+        );
+
+        // Persisting, overwriting or deleting a field removes its expiry
+        store
+            .set_field_expiry(s("session"), s("user"), Some(100))
+            .unwrap();
+        store
+            .set_field_expiry(s("session"), s("user"), None)
+            .unwrap();
+        assert_eq!(
+            store.get_field_expiry(s("session"), s("user")).unwrap(),
+            None
+        );
+        store
+            .set_field_expiry(s("session"), s("user"), Some(100))
+            .unwrap();
+        let _ = store.hset(s("session"), s("user"), s("kiba"));
+        assert_eq!(
+            store.get_field_expiry(s("session"), s("user")).unwrap(),
+            None
+        );
+        store
+            .set_field_expiry(s("session"), s("user"), Some(100))
+            .unwrap();
+        assert!(store.del(s("session")).unwrap());
+        assert_eq!(
+            store.get_field_expiry(s("session"), s("user")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fnv_store() {
+        let s = |s: &str| s.to_string();
+        let mut store: FnvStore = Store::new();
+        let _ = store.set(s("foo"), s("bar"));
+        let _ = store.sadd(s("baz"), s("item"));
+        let _ = store.hset(s("qux"), s("field"), s("val"));
+        assert_eq!(store.get(s("foo")).unwrap(), Some(s("bar")));
+        assert!(store.sismember(s("baz"), s("item")).unwrap());
+        assert_eq!(store.hget(s("qux"), s("field")).unwrap(), Some(s("val")));
+        assert_eq!(store.dbsize().unwrap(), 3);
+
+        // Snapshots don't depend on the hasher
+        let mut buf = Vec::new();
+        store.serialize(&mut buf).unwrap();
+        let mut reader = &buf[..];
+        let copy = <StdStore>::deserialize(&mut reader).unwrap();
+        assert_eq!(copy.get(s("foo")).unwrap(), Some(s("bar")));
+    }
+
     #[test]
     fn test_std_get_set() {
         let mut store: StdStore = Store::new();
@@ -365,6 +2251,26 @@ mod tests {
     }
 
     #[test]
+    fn test_std_getdel() {
+        let mut store: StdStore = Store::new();
+        let _ = store.set("foo".to_string(), "bar".to_string());
+        let _ = store.set_expiry("foo".to_string(), Some(i64::MAX));
+        let _ = store.sadd("baz".to_string(), "item".to_string());
+        assert_eq!(
+            store.getdel("foo".to_string()).unwrap(),
+            Some("bar".to_string())
+        );
+        assert!(!store.exists("foo".to_string()).unwrap());
+        assert_eq!(store.get_expiry("foo".to_string()).unwrap(), None);
+        assert_eq!(store.getdel("foo".to_string()).unwrap(), None);
+
+        // Keys without a string are left unchanged
+        assert_eq!(store.getdel("baz".to_string()).unwrap(), None);
+        assert!(store.exists("baz".to_string()).unwrap());
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant, clippy::bool_assert_comparison)]
     fn test_std_incr_decr() {
         let mut store: StdStore = Store::new();
         let _ = store.set("foo".to_string(), 5.to_string());
@@ -394,6 +2300,115 @@ mod tests {
             None
         );
         assert_eq!(store.incr("z".to_string()).is_ok(), false);
+
+        let _ = store.set("f".to_string(), "10.5".to_string());
+        assert_eq!(store.incrbyfloat("f".to_string(), 0.1).unwrap(), 10.6);
+        assert_eq!(store.get("f".to_string()).unwrap().unwrap(), "10.6");
+        assert_eq!(store.incrbyfloat("f".to_string(), -0.6).unwrap(), 10.0);
+        assert_eq!(store.get("f".to_string()).unwrap().unwrap(), "10");
+        assert!(store.incrbyfloat("f".to_string(), f64::MAX).is_ok());
+        assert!(store.incrbyfloat("f".to_string(), f64::MAX).is_err());
+        let _ = store.set("nan".to_string(), "NaN".to_string());
+        assert!(store.incrbyfloat("nan".to_string(), 1.0).is_err());
+        assert!(store.incrbyfloat("dne".to_string(), 1.0).is_err());
+
+        let _ = store.hset("h".to_string(), "price".to_string(), "3".to_string());
+        store
+            .set_field_expiry("h".to_string(), "price".to_string(), Some(i64::MAX))
+            .unwrap();
+        assert_eq!(
+            store
+                .hincrbyfloat("h".to_string(), "price".to_string(), 1.5)
+                .unwrap(),
+            4.5
+        );
+        assert_eq!(
+            store
+                .get_field_expiry("h".to_string(), "price".to_string())
+                .unwrap(),
+            Some(i64::MAX)
+        );
+        assert!(store
+            .hincrbyfloat("h".to_string(), "dne".to_string(), 1.0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_std_append_getrange() {
+        let mut store: StdStore = Store::new();
+        assert_eq!(store.strlen("foo".to_string()).unwrap(), 0);
+        assert_eq!(
+            store
+                .append("foo".to_string(), "Hello".to_string())
+                .unwrap(),
+            5
+        );
+        assert_eq!(
+            store
+                .append("foo".to_string(), " World".to_string())
+                .unwrap(),
+            11
+        );
+        assert_eq!(store.strlen("foo".to_string()).unwrap(), 11);
+
+        let range = |start, end| store.getrange("foo".to_string(), start, end).unwrap();
+        assert_eq!(range(0, 4), "Hello");
+        assert_eq!(range(-5, -1), "World");
+        assert_eq!(range(6, 100), "World");
+        assert_eq!(range(-100, 0), "H");
+        assert_eq!(range(5, 2), "");
+        assert_eq!(range(20, 30), "");
+        assert_eq!(store.getrange("dne".to_string(), 0, -1).unwrap(), "");
+
+        let mut setrange = |key: &str, offset, val: &str| {
+            store
+                .setrange(key.to_string(), offset, val.to_string())
+                .unwrap()
+        };
+        assert_eq!(setrange("foo", 6, "Redis"), 11);
+        assert_eq!(setrange("pad", 2, "ab"), 4);
+        assert_eq!(setrange("dne", 0, ""), 0);
+        assert_eq!(
+            store.get("foo".to_string()).unwrap().unwrap(),
+            "Hello Redis"
+        );
+        assert_eq!(store.get("pad".to_string()).unwrap().unwrap(), "\0\0ab");
+        assert!(!store.exists("dne".to_string()).unwrap());
+        assert!(store
+            .setrange("foo".to_string(), MAX_STRING_SIZE, "a".to_string())
+            .is_err());
+        store
+            .setrange("foo".to_string(), 6, "World".to_string())
+            .unwrap();
+
+        // Writes may not split multi-byte characters
+        store.set("utf8".to_string(), "é".to_string()).unwrap();
+        assert!(store
+            .setrange("utf8".to_string(), 1, "x".to_string())
+            .is_err());
+        assert!(store
+            .setrange("utf8".to_string(), 0, "x".to_string())
+            .is_err());
+        assert_eq!(store.get("utf8".to_string()).unwrap().unwrap(), "é");
+        assert_eq!(
+            store
+                .setrange("utf8".to_string(), 0, "ab".to_string())
+                .unwrap(),
+            2
+        );
+        assert_eq!(store.get("utf8".to_string()).unwrap().unwrap(), "ab");
+        assert_eq!(
+            store
+                .setrange("utf8".to_string(), 1, "é!".to_string())
+                .unwrap(),
+            4
+        );
+        assert_eq!(store.get("utf8".to_string()).unwrap().unwrap(), "aé!");
+
+        // Appending keeps the expiry of the string
+        store.set_expiry("foo".to_string(), Some(i64::MAX)).unwrap();
+        store.append("foo".to_string(), "!".to_string()).unwrap();
+        assert_eq!(store.get_expiry("foo".to_string()).unwrap(), Some(i64::MAX));
     }
 
     #[test]
@@ -433,6 +2448,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_std_sets() {
         let mut store: StdStore = Store::new();
 
@@ -538,4 +2554,288 @@ mod tests {
             None
         );
     }
+
+    #[test]
+    fn test_std_blooms() {
+        let mut store: StdStore = Store::new();
+        assert!(!store
+            .bf_exists("foo".to_string(), "item1".to_string())
+            .unwrap());
+        assert!(store.bf_reserve("foo".to_string(), 0.001, 1000).is_ok());
+        assert!(store.bf_reserve("foo".to_string(), 0.001, 1000).is_err());
+        assert!(store
+            .bf_add("foo".to_string(), "item1".to_string())
+            .unwrap());
+        assert!(!store
+            .bf_add("foo".to_string(), "item1".to_string())
+            .unwrap());
+        assert_eq!(
+            store
+                .bf_madd(
+                    "foo".to_string(),
+                    vec!["item1".to_string(), "item2".to_string()]
+                )
+                .unwrap(),
+            vec![false, true]
+        );
+        assert!(store
+            .bf_exists("foo".to_string(), "item2".to_string())
+            .unwrap());
+
+        // Adding to a non-existent key creates a filter
+        assert!(store
+            .bf_add("bar".to_string(), "item1".to_string())
+            .unwrap());
+        assert!(store
+            .bf_exists("bar".to_string(), "item1".to_string())
+            .unwrap());
+    }
+
+    #[test]
+    fn test_std_cuckoos() {
+        let mut store: StdStore = Store::new();
+        assert!(!store
+            .cf_exists("foo".to_string(), "item1".to_string())
+            .unwrap());
+        assert!(!store
+            .cf_del("foo".to_string(), "item1".to_string())
+            .unwrap());
+        assert!(store.cf_add("foo".to_string(), "item1".to_string()).is_ok());
+        assert!(store
+            .cf_exists("foo".to_string(), "item1".to_string())
+            .unwrap());
+        assert!(store
+            .cf_del("foo".to_string(), "item1".to_string())
+            .unwrap());
+        assert!(!store
+            .cf_exists("foo".to_string(), "item1".to_string())
+            .unwrap());
+    }
+
+    #[test]
+    fn test_std_timeseries() {
+        let mut store: StdStore = Store::new();
+        assert!(store
+            .ts_range("foo".to_string(), i64::MIN, i64::MAX, None)
+            .is_err());
+        assert!(store.ts_create("foo".to_string(), 1000).is_ok());
+        assert!(store.ts_create("foo".to_string(), 1000).is_err());
+        assert_eq!(store.ts_add("foo".to_string(), 1000, 1.0).unwrap(), 1000);
+        assert_eq!(store.ts_add("foo".to_string(), 2500, 2.0).unwrap(), 2500);
+
+        // Samples outside of the retention period are rejected and trimmed
+        assert!(store.ts_add("foo".to_string(), 1000, 3.0).is_err());
+        assert_eq!(
+            store
+                .ts_range("foo".to_string(), i64::MIN, i64::MAX, None)
+                .unwrap(),
+            vec![(2500, 2.0)]
+        );
+
+        // Adding to a non-existent key creates a series
+        assert_eq!(store.ts_add("bar".to_string(), 0, 5.0).unwrap(), 0);
+        assert_eq!(
+            store
+                .ts_range("bar".to_string(), 0, 0, Some((Aggregation::Avg, 10)))
+                .unwrap(),
+            vec![(0, 5.0)]
+        );
+    }
+
+    #[test]
+    fn test_std_vectors() {
+        let mut store: StdStore = Store::new();
+        assert_eq!(
+            store
+                .vsim("foo".to_string(), vec![1.0, 0.0], Metric::Cosine, 10)
+                .unwrap(),
+            vec![]
+        );
+        assert!(store
+            .vadd("foo".to_string(), "x".to_string(), vec![1.0, 0.0])
+            .unwrap());
+        assert!(store
+            .vadd("foo".to_string(), "y".to_string(), vec![0.0, 1.0])
+            .unwrap());
+        assert!(!store
+            .vadd("foo".to_string(), "y".to_string(), vec![0.0, 2.0])
+            .unwrap());
+        assert!(store
+            .vadd("foo".to_string(), "z".to_string(), vec![1.0, 1.0, 1.0])
+            .is_err());
+        assert_eq!(
+            store
+                .vsim("foo".to_string(), vec![0.0, 3.0], Metric::Cosine, 1)
+                .unwrap(),
+            vec![("y".to_string(), 1.0)]
+        );
+        assert!(store
+            .vsim("foo".to_string(), vec![0.0], Metric::Cosine, 1)
+            .is_err());
+    }
+
+    #[test]
+    fn test_std_search() {
+        let mut store: StdStore = Store::new();
+        let city = |v: &str| {
+            vec![Filter::Eq {
+                field: "city".to_string(),
+                value: v.to_string(),
+            }]
+        };
+        let _ = store.hset(
+            "user:1".to_string(),
+            "city".to_string(),
+            "Tokyo".to_string(),
+        );
+        let _ = store.hset(
+            "item:1".to_string(),
+            "city".to_string(),
+            "Tokyo".to_string(),
+        );
+        assert!(store
+            .ft_search("users".to_string(), city("Tokyo"), 0, 10)
+            .is_err());
+
+        // Existing hashes are indexed on creation
+        assert!(store
+            .ft_create(
+                "users".to_string(),
+                "user:".to_string(),
+                vec!["city".to_string()]
+            )
+            .is_ok());
+        assert!(store
+            .ft_create("users".to_string(), "user:".to_string(), vec![])
+            .is_err());
+        assert_eq!(
+            store
+                .ft_search("users".to_string(), city("Tokyo"), 0, 10)
+                .unwrap(),
+            vec!["user:1".to_string()]
+        );
+
+        // Hash operations keep the index up to date
+        let _ = store.hset(
+            "user:2".to_string(),
+            "city".to_string(),
+            "Tokyo".to_string(),
+        );
+        let _ = store.hset(
+            "user:1".to_string(),
+            "city".to_string(),
+            "Osaka".to_string(),
+        );
+        assert_eq!(
+            store
+                .ft_search("users".to_string(), city("Tokyo"), 0, 10)
+                .unwrap(),
+            vec!["user:2".to_string()]
+        );
+        let _ = store.hdel("user:1".to_string(), "city".to_string());
+        assert_eq!(
+            store
+                .ft_search("users".to_string(), city("Osaka"), 0, 10)
+                .unwrap(),
+            Vec::<String>::new()
+        );
+        assert!(store
+            .ft_search(
+                "users".to_string(),
+                vec![Filter::Eq {
+                    field: "name".to_string(),
+                    value: "Jane".to_string()
+                }],
+                0,
+                10
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_std_changes() {
+        let mut store: StdStore = Store::new();
+        let s = |s: &str| s.to_string();
+        assert_eq!(store.changes().unwrap(), 0);
+        store.sadd(s("foo"), s("item1")).unwrap();
+        store.set(s("bar"), s("baz")).unwrap();
+        assert_eq!(store.changes().unwrap(), 2);
+
+        // Writes that leave the store as it was are not counted
+        store.sadd(s("foo"), s("item1")).unwrap();
+        store.srem(s("foo"), s("item2")).unwrap();
+        store.hdel(s("hash"), s("field")).unwrap();
+        store.set(s("bar"), s("baz")).unwrap();
+        store.set_expiry(s("bar"), None).unwrap();
+        store.rpop(s("list")).unwrap();
+        store.del(s("dne")).unwrap();
+        assert_eq!(store.changes().unwrap(), 2);
+
+        store.srem(s("foo"), s("item1")).unwrap();
+        store.set_expiry(s("bar"), Some(1000)).unwrap();
+        store.del(s("bar")).unwrap();
+        assert_eq!(store.changes().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_std_memory_tracking() {
+        let mut store: StdStore = Store::new();
+        let s = |s: &str| s.to_string();
+        let check = |store: &StdStore| {
+            assert_eq!(store.memory_usage().unwrap(), store.count_memory());
+        };
+
+        store.set(s("str"), s("abc")).unwrap();
+        store.set(s("str"), s("abcdef")).unwrap();
+        store.append(s("str"), s("gh")).unwrap();
+        store.setrange(s("str"), 10, s("xyz")).unwrap();
+        store.set(s("num"), s("9")).unwrap();
+        store.incr(s("num")).unwrap();
+        store.incrbyfloat(s("num"), 0.5).unwrap();
+        check(&store);
+
+        store.lpush(s("list"), s("a")).unwrap();
+        store.rpush(s("list"), s("bcd")).unwrap();
+        store.lpop(s("list")).unwrap();
+        store.sadd(s("set"), s("a")).unwrap();
+        store.sadd(s("set"), s("bb")).unwrap();
+        store.srem(s("set"), s("a")).unwrap();
+        check(&store);
+
+        store.hset(s("hash"), s("f1"), s("v1")).unwrap();
+        store.hset(s("hash"), s("f1"), s("value")).unwrap();
+        store.hset(s("hash"), s("f2"), s("1")).unwrap();
+        store.hincrbyfloat(s("hash"), s("f2"), 1.25).unwrap();
+        store.hdel(s("hash"), s("f1")).unwrap();
+        check(&store);
+
+        store.bf_reserve(s("bloom"), 0.01, 10).unwrap();
+        for i in 0..50 {
+            store.bf_add(s("bloom"), format!("item{}", i)).unwrap();
+        }
+        store.bf_madd(s("bloom2"), vec![s("a"), s("b")]).unwrap();
+        store.cf_add(s("cuckoo"), s("a")).unwrap();
+        store.cf_del(s("cuckoo"), s("a")).unwrap();
+        store.ts_create(s("ts"), 1000).unwrap();
+        store.ts_add(s("ts"), 1000, 1.0).unwrap();
+        store.ts_add(s("ts"), 3000, 2.0).unwrap();
+        store.vadd(s("vec"), s("a"), vec![1.0, 2.0]).unwrap();
+        store.vadd(s("vec"), s("a"), vec![3.0, 4.0]).unwrap();
+        check(&store);
+
+        // Keys holding values of several types are counted once per type
+        store.set(s("list"), s("shared")).unwrap();
+        store.rename(s("list"), s("renamed")).unwrap();
+        store.rename(s("str"), s("hash")).unwrap();
+        store.getdel(s("num")).unwrap();
+        store.del(s("set")).unwrap();
+        check(&store);
+
+        let mut buf = Vec::new();
+        store.serialize(&mut buf).unwrap();
+        let copy = <StdStore>::deserialize(&mut &buf[..]).unwrap();
+        check(&copy);
+        store.flushdb().unwrap();
+        assert_eq!(store.memory_usage().unwrap(), 0);
+    }
 }
@@ -0,0 +1,180 @@
+use crate::snapshot::{Decoder, Encoder};
+use std::collections::BTreeMap;
+use std::io;
+
+/// Function used to downsample the samples within each bucket of a range query
+#[derive(Clone, Debug, PartialEq)]
+pub enum Aggregation {
+    Avg,
+    Min,
+    Max,
+}
+
+/// A series of timestamped floating-point samples ordered by timestamp.
+///
+/// Timestamps are in milliseconds. If a retention period is set, samples
+/// older than the retention period (relative to the latest sample) are
+/// discarded as new samples are added.
+#[derive(Debug)]
+pub struct TimeSeries {
+    samples: BTreeMap<i64, f64>,
+    retention: u64,
+}
+
+impl TimeSeries {
+    /// Create an empty time series.
+    /// A retention of 0 keeps samples indefinitely.
+    pub fn new(retention: u64) -> Self {
+        Self {
+            samples: BTreeMap::new(),
+            retention,
+        }
+    }
+
+    /// Add a sample to the series, overwriting any sample with the same timestamp.
+    /// Return an error if the sample is older than the retention period.
+    pub fn add(&mut self, timestamp: i64, value: f64) -> Result<(), String> {
+        if let Some(oldest) = self.oldest_retained() {
+            if timestamp < oldest {
+                return Err("Timestamp is older than the retention period".to_string());
+            }
+        }
+        self.samples.insert(timestamp, value);
+        if let Some(oldest) = self.oldest_retained() {
+            self.samples = self.samples.split_off(&oldest);
+        }
+        Ok(())
+    }
+
+    /// Return samples with timestamps between from and to (inclusive).
+    /// If an aggregation is specified, samples are grouped into buckets of the
+    /// given duration (aligned to multiples of the duration) and each bucket is
+    /// reduced to a single sample timestamped at the start of the bucket.
+    pub fn range(
+        &self,
+        from: i64,
+        to: i64,
+        aggregation: Option<(Aggregation, u64)>,
+    ) -> Vec<(i64, f64)> {
+        if from > to {
+            return vec![];
+        }
+        let samples = self.samples.range(from..=to).map(|(&t, &v)| (t, v));
+        let (agg, duration) = match aggregation {
+            Some((agg, duration)) => (agg, duration as i64),
+            None => return samples.collect(),
+        };
+
+        let mut buckets: Vec<(i64, Vec<f64>)> = Vec::new();
+        for (t, v) in samples {
+            let start = t - t.rem_euclid(duration);
+            match buckets.last_mut() {
+                Some((s, values)) if *s == start => values.push(v),
+                _ => buckets.push((start, vec![v])),
+            }
+        }
+        buckets
+            .into_iter()
+            .map(|(start, values)| {
+                let reduced = match agg {
+                    Aggregation::Avg => values.iter().sum::<f64>() / values.len() as f64,
+                    Aggregation::Min => values.iter().cloned().fold(f64::INFINITY, f64::min),
+                    Aggregation::Max => values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                };
+                (start, reduced)
+            })
+            .collect()
+    }
+
+    /// Return the number of samples in the series.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Return the approximate number of bytes used by the samples.
+    pub fn memory_usage(&self) -> u64 {
+        (self.samples.len() * (std::mem::size_of::<i64>() + std::mem::size_of::<f64>())) as u64
+    }
+
+    /// Write the series in the snapshot encoding.
+    pub fn encode(&self, enc: &mut Encoder) -> io::Result<()> {
+        enc.u64(self.retention)?;
+        enc.u64(self.samples.len() as u64)?;
+        for (timestamp, value) in self.samples.iter() {
+            enc.i64(*timestamp)?;
+            enc.f64(*value)?;
+        }
+        Ok(())
+    }
+
+    /// Read a series written by `encode`.
+    pub fn decode(dec: &mut Decoder) -> io::Result<Self> {
+        let mut series = Self::new(dec.u64()?);
+        for _ in 0..dec.u64()? {
+            let timestamp = dec.i64()?;
+            series.samples.insert(timestamp, dec.f64()?);
+        }
+        Ok(series)
+    }
+
+    fn oldest_retained(&self) -> Option<i64> {
+        if self.retention == 0 {
+            return None;
+        }
+        self.samples
+            .keys()
+            .next_back()
+            .map(|&latest| latest.saturating_sub(self.retention as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeseries_range() {
+        let mut series = TimeSeries::new(0);
+        assert!(series.is_empty());
+        for t in 0..10 {
+            series.add(t * 100, t as f64).unwrap();
+        }
+        assert_eq!(series.len(), 10);
+        assert_eq!(
+            series.range(200, 400, None),
+            vec![(200, 2.0), (300, 3.0), (400, 4.0)]
+        );
+        assert_eq!(series.range(400, 200, None), vec![]);
+
+        // Aggregations over buckets of 300ms
+        assert_eq!(
+            series.range(0, 999, Some((Aggregation::Avg, 300))),
+            vec![(0, 1.0), (300, 4.0), (600, 7.0), (900, 9.0)]
+        );
+        assert_eq!(
+            series.range(0, 999, Some((Aggregation::Min, 300))),
+            vec![(0, 0.0), (300, 3.0), (600, 6.0), (900, 9.0)]
+        );
+        assert_eq!(
+            series.range(100, 999, Some((Aggregation::Max, 500))),
+            vec![(0, 4.0), (500, 9.0)]
+        );
+    }
+
+    #[test]
+    fn test_timeseries_retention() {
+        let mut series = TimeSeries::new(1000);
+        series.add(0, 1.0).unwrap();
+        series.add(500, 2.0).unwrap();
+        series.add(1500, 3.0).unwrap();
+        assert_eq!(
+            series.range(i64::MIN, i64::MAX, None),
+            vec![(500, 2.0), (1500, 3.0)]
+        );
+        assert!(series.add(100, 4.0).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::snapshot::{Decoder, Encoder};
+use std::collections::HashMap;
+use std::io;
+
+/// Number of results returned by a similarity search if not specified
+pub const DEFAULT_COUNT: usize = 10;
+
+/// Measure used to rank vectors in a similarity search
+#[derive(Clone, Debug, PartialEq)]
+pub enum Metric {
+    /// Cosine similarity, ranked from most to least similar
+    Cosine,
+    /// Euclidean distance, ranked from nearest to farthest
+    Euclidean,
+}
+
+/// A collection of named float vectors of equal dimension that supports
+/// exact k-nearest-neighbor search.
+///
+/// The dimension of the index is fixed by the first vector added to it.
+#[derive(Debug)]
+pub struct VectorIndex {
+    dim: usize,
+    vectors: HashMap<String, Vec<f64>>,
+}
+
+impl VectorIndex {
+    pub fn new(dim: usize) -> Self {
+        Self {
+            dim,
+            vectors: HashMap::new(),
+        }
+    }
+
+    /// Add or replace the vector for an element.
+    /// Return true if the element was newly added.
+    /// If the vector's dimension does not match the index or a component is
+    /// not finite, return an error.
+    pub fn add(&mut self, element: String, vector: Vec<f64>) -> Result<bool, String> {
+        self.check_vector(&vector)?;
+        Ok(self.vectors.insert(element, vector).is_none())
+    }
+
+    /// Return up to count elements ranked by similarity to the query vector,
+    /// along with their similarity (cosine) or distance (euclidean).
+    /// If the query's dimension does not match the index or a component is
+    /// not finite, return an error.
+    pub fn search(
+        &self,
+        query: &[f64],
+        metric: Metric,
+        count: usize,
+    ) -> Result<Vec<(String, f64)>, String> {
+        self.check_vector(query)?;
+        let mut scored: Vec<(String, f64)> = self
+            .vectors
+            .iter()
+            .map(|(element, vector)| {
+                let score = match metric {
+                    Metric::Cosine => cosine_similarity(query, vector),
+                    Metric::Euclidean => euclidean_distance(query, vector),
+                };
+                (element.to_string(), score)
+            })
+            .collect();
+        scored.sort_by(|(e1, s1), (e2, s2)| {
+            let ord = match metric {
+                Metric::Cosine => s2.total_cmp(s1),
+                Metric::Euclidean => s1.total_cmp(s2),
+            };
+            ord.then_with(|| e1.cmp(e2))
+        });
+        scored.truncate(count);
+        Ok(scored)
+    }
+
+    /// Return the number of elements in the index.
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Return the approximate number of bytes used by elements and vectors.
+    pub fn memory_usage(&self) -> u64 {
+        self.vectors
+            .iter()
+            .map(|(element, vector)| (element.len() + vector.len() * 8) as u64)
+            .sum()
+    }
+
+    /// Write the index in the snapshot encoding.
+    pub fn encode(&self, enc: &mut Encoder) -> io::Result<()> {
+        enc.u64(self.dim as u64)?;
+        enc.u64(self.vectors.len() as u64)?;
+        for (element, vector) in self.vectors.iter() {
+            enc.str(element)?;
+            for x in vector.iter() {
+                enc.f64(*x)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an index written by `encode`.
+    pub fn decode(dec: &mut Decoder) -> io::Result<Self> {
+        let mut index = Self::new(dec.u64()? as usize);
+        for _ in 0..dec.u64()? {
+            let element = dec.string()?;
+            let mut vector = Vec::new();
+            for _ in 0..index.dim {
+                vector.push(dec.f64()?);
+            }
+            index.vectors.insert(element, vector);
+        }
+        Ok(index)
+    }
+
+    fn check_vector(&self, vector: &[f64]) -> Result<(), String> {
+        if vector.len() != self.dim {
+            return Err(format!(
+                "Vector dimension mismatch. Expected {}, got {}",
+                self.dim,
+                vector.len()
+            ));
+        }
+        if !vector.iter().all(|x| x.is_finite()) {
+            return Err("Vector components must be finite".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Return the cosine similarity of two finite vectors, which is always finite.
+/// Similarity doesn't depend on magnitude, so both vectors are scaled to
+/// components of at most 1 to keep their products from overflowing.
+fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    let (a, b) = (scaled(a, max_abs(a)), scaled(b, max_abs(b)));
+    let dot: f64 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    (dot / (norm_a * norm_b)).clamp(-1.0, 1.0)
+}
+
+/// Return the euclidean distance between two finite vectors. Differences are
+/// scaled down before they are squared so that they can't overflow, and
+/// distances too large to represent are clamped to the largest finite float.
+fn euclidean_distance(a: &[f64], b: &[f64]) -> f64 {
+    let diffs: Vec<f64> = a.iter().zip(b).map(|(x, y)| x - y).collect();
+    let max = max_abs(&diffs);
+    if !max.is_finite() {
+        return f64::MAX;
+    }
+    let sum: f64 = scaled(&diffs, max).iter().map(|d| d * d).sum();
+    (max * sum.sqrt()).min(f64::MAX)
+}
+
+fn max_abs(v: &[f64]) -> f64 {
+    v.iter().fold(0.0, |max, x| max.max(x.abs()))
+}
+
+/// Return a vector divided by a scale, unless the scale is 0.
+fn scaled(v: &[f64], scale: f64) -> Vec<f64> {
+    match scale {
+        0.0 => v.to_vec(),
+        _ => v.iter().map(|x| x / scale).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_search() {
+        let mut index = VectorIndex::new(2);
+        assert!(index.is_empty());
+        assert!(index.add("east".to_string(), vec![1.0, 0.0]).unwrap());
+        assert!(index.add("north".to_string(), vec![0.0, 1.0]).unwrap());
+        assert!(index.add("far".to_string(), vec![10.0, 1.0]).unwrap());
+        assert!(!index.add("far".to_string(), vec![10.0, 0.5]).unwrap());
+        assert!(index.add("bad".to_string(), vec![1.0]).is_err());
+        assert_eq!(index.len(), 3);
+
+        let by_cosine = index.search(&[1.0, 0.0], Metric::Cosine, 2).unwrap();
+        assert_eq!(by_cosine[0], ("east".to_string(), 1.0));
+        assert_eq!(by_cosine[1].0, "far".to_string());
+
+        let by_distance = index.search(&[0.0, 0.0], Metric::Euclidean, 10).unwrap();
+        let order: Vec<&str> = by_distance.iter().map(|(e, _)| e.as_str()).collect();
+        assert_eq!(order, vec!["east", "north", "far"]);
+
+        assert!(index.search(&[1.0, 2.0, 3.0], Metric::Cosine, 1).is_err());
+    }
+    #[test]
+    fn test_vector_search_extremes() {
+        // Scores of large vectors stay finite, so they are still ranked
+        let mut index = VectorIndex::new(2);
+        index.add("same".to_string(), vec![1e200, 1e200]).unwrap();
+        index
+            .add("opposite".to_string(), vec![-1e200, -1e200])
+            .unwrap();
+        index
+            .add("across".to_string(), vec![-f64::MAX, 0.0])
+            .unwrap();
+        assert!(index
+            .add("inf".to_string(), vec![f64::INFINITY, 0.0])
+            .is_err());
+        assert!(index.add("nan".to_string(), vec![f64::NAN, 0.0]).is_err());
+
+        let by_cosine = index.search(&[1e200, 1e200], Metric::Cosine, 10).unwrap();
+        let order: Vec<&str> = by_cosine.iter().map(|(e, _)| e.as_str()).collect();
+        assert_eq!(order, vec!["same", "across", "opposite"]);
+        assert!((by_cosine[0].1 - 1.0).abs() < 1e-12);
+        assert!((by_cosine[2].1 + 1.0).abs() < 1e-12);
+
+        let by_distance = index
+            .search(&[1e200, 1e200], Metric::Euclidean, 10)
+            .unwrap();
+        let order: Vec<&str> = by_distance.iter().map(|(e, _)| e.as_str()).collect();
+        assert_eq!(order, vec!["same", "opposite", "across"]);
+        assert!((by_distance[1].1 / 1e200 - 8f64.sqrt()).abs() < 1e-12);
+
+        // Distances too large to represent are clamped
+        let by_distance = index
+            .search(&[f64::MAX, 0.0], Metric::Euclidean, 10)
+            .unwrap();
+        assert!(by_distance
+            .iter()
+            .all(|(_, distance)| *distance == f64::MAX));
+
+        assert!(index.search(&[f64::NAN, 0.0], Metric::Cosine, 1).is_err());
+    }
+}
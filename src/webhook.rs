@@ -0,0 +1,294 @@
+use log::*;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::prelude::*;
+use tokio::time::{delay_for, timeout};
+
+/// Number of times delivery of an event is attempted before it is dropped
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled after each failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Time allowed to connect, send an event, or read the response before an
+/// attempt fails
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum length of the status line read from a response
+const MAX_STATUS_LINE: u64 = 1024;
+
+/// Maximum number of events that are delivered at once. Further events wait
+/// until a delivery finishes.
+pub const MAX_CONCURRENT_DELIVERIES: usize = 16;
+
+/// A change to a key, reported to webhooks
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Key that was changed
+    pub key: String,
+    /// Name of the command that changed the key (e.g. "set"), or "expired"
+    /// if the key was removed because its time to live ran out
+    pub event: String,
+    /// Namespace containing the key
+    pub db: String,
+    /// Time of the change in milliseconds since the Unix epoch
+    pub timestamp: i64,
+}
+
+impl Event {
+    /// Serialize the event as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"key\":{},\"event\":{},\"db\":{},\"timestamp\":{}}}",
+            json_str(&self.key),
+            json_str(&self.event),
+            json_str(&self.db),
+            self.timestamp
+        )
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// An HTTP endpoint that receives events matching its filters as POST requests.
+///
+/// Only plain `http://` URLs are supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Webhook {
+    host: String,
+    port: u16,
+    path: String,
+    events: Vec<String>,
+}
+
+impl Webhook {
+    /// Create a webhook for a URL of the form `http://host[:port][/path]`,
+    /// where an IPv6 host is enclosed in brackets. Events are a list of command names or "expired" to report, or "*" for
+    /// all events.
+    pub fn new(url: &str, events: Vec<String>) -> Result<Self, String> {
+        let rest = match url.strip_prefix("http://") {
+            Some(rest) => rest,
+            None => return Err(format!("Unsupported webhook URL \"{}\"", url)),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(rest) => match rest.split_once(']') {
+                Some((host, "")) => (host, None),
+                Some((host, port)) if port.starts_with(':') => (host, Some(&port[1..])),
+                _ => return Err(format!("Invalid host in webhook URL \"{}\"", url)),
+            },
+            None => match authority.rfind(':') {
+                Some(i) => (&authority[..i], Some(&authority[i + 1..])),
+                None => (authority, None),
+            },
+        };
+        let port = match port.map(|port| port.parse::<u16>()) {
+            Some(Ok(port)) => port,
+            Some(Err(_)) => return Err(format!("Invalid port in webhook URL \"{}\"", url)),
+            None => 80,
+        };
+        if host.is_empty() {
+            return Err(format!("Missing host in webhook URL \"{}\"", url));
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            events: events.into_iter().map(|e| e.to_lowercase()).collect(),
+        })
+    }
+
+    /// Return if the event passes this webhook's filters.
+    pub fn matches(&self, event: &Event) -> bool {
+        self.events.iter().any(|e| e == "*" || *e == event.event)
+    }
+
+    /// POST the event to the webhook, retrying with exponential backoff if
+    /// the endpoint cannot be reached or does not respond with a 2xx status.
+    /// Return an error after the final attempt fails.
+    pub async fn deliver(&self, event: &Event) -> Result<(), String> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            match self.post(&event.to_json()).await {
+                Ok(_) => return Ok(()),
+                Err(e) if attempt >= MAX_ATTEMPTS => return Err(e),
+                Err(e) => {
+                    debug!(
+                        "Webhook delivery attempt {} to {}:{} failed: {}",
+                        attempt, self.host, self.port, e
+                    );
+                    delay_for(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn post(&self, body: &str) -> Result<(), String> {
+        let connect = TcpStream::connect((self.host.as_str(), self.port));
+        let mut stream = timeout(ATTEMPT_TIMEOUT, connect)
+            .await
+            .map_err(|_| "Timed out connecting".to_string())?
+            .map_err(|e| e.to_string())?;
+        let host = match self.host.contains(':') {
+            true => format!("[{}]", self.host),
+            false => self.host.clone(),
+        };
+        let req = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            host,
+            body.len(),
+            body
+        );
+        timeout(ATTEMPT_TIMEOUT, stream.write_all(req.as_bytes()))
+            .await
+            .map_err(|_| "Timed out sending event".to_string())?
+            .map_err(|e| e.to_string())?;
+
+        // Only the status line is read, since endpoints may keep the
+        // connection open after responding
+        let mut status = Vec::new();
+        let mut reader = BufReader::new(stream.take(MAX_STATUS_LINE));
+        timeout(ATTEMPT_TIMEOUT, reader.read_until(b'\n', &mut status))
+            .await
+            .map_err(|_| "Timed out reading response".to_string())?
+            .map_err(|e| e.to_string())?;
+        let status = String::from_utf8_lossy(&status);
+        let status = status.trim_end();
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(format!("Unexpected response \"{}\"", status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn event(name: &str) -> Event {
+        Event {
+            key: "fo\"o".to_string(),
+            event: name.to_string(),
+            db: "default".to_string(),
+            timestamp: 1000,
+        }
+    }
+
+    #[test]
+    fn test_webhook_new() {
+        let hook = Webhook::new("http://localhost:8080/hooks/kiba", vec!["SET".to_string()]);
+        assert_eq!(
+            hook.unwrap(),
+            Webhook {
+                host: "localhost".to_string(),
+                port: 8080,
+                path: "/hooks/kiba".to_string(),
+                events: vec!["set".to_string()],
+            }
+        );
+        let hook = Webhook::new("http://example.com", vec![]).unwrap();
+        assert_eq!((hook.port, hook.path.as_str()), (80, "/"));
+        assert!(Webhook::new("https://example.com", vec![]).is_err());
+        assert!(Webhook::new("http://example.com:abc/", vec![]).is_err());
+        assert!(Webhook::new("http://", vec![]).is_err());
+
+        let hook = Webhook::new("http://[::1]:8080/events", vec![]).unwrap();
+        assert_eq!((hook.host.as_str(), hook.port), ("::1", 8080));
+        let hook = Webhook::new("http://[::1]", vec![]).unwrap();
+        assert_eq!((hook.host.as_str(), hook.port), ("::1", 80));
+        assert!(Webhook::new("http://[::1", vec![]).is_err());
+        assert!(Webhook::new("http://[::1]8080", vec![]).is_err());
+        assert!(Webhook::new("http://[]:8080", vec![]).is_err());
+    }
+
+    #[test]
+    fn test_webhook_matches() {
+        let hook = Webhook::new("http://localhost", vec!["set".to_string()]).unwrap();
+        assert!(hook.matches(&event("set")));
+        assert!(!hook.matches(&event("lpush")));
+        let hook = Webhook::new("http://localhost", vec!["*".to_string()]).unwrap();
+        assert!(hook.matches(&event("lpush")));
+        assert!(hook.matches(&event("expired")));
+        assert_eq!(
+            event("set").to_json(),
+            "{\"key\":\"fo\\\"o\",\"event\":\"set\",\"db\":\"default\",\"timestamp\":1000}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_deliver() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let url = format!("http://127.0.0.1:{}/events", port);
+        let hook = Webhook::new(&url, vec!["*".to_string()]).unwrap();
+        assert!(hook.deliver(&event("set")).await.is_ok());
+
+        let req = server.await.unwrap();
+        assert!(req.starts_with("POST /events HTTP/1.1\r\n"));
+        assert!(req.ends_with(&event("set").to_json()));
+    }
+    #[tokio::test]
+    async fn test_webhook_keep_alive() {
+        // Delivery finishes once the status is read, even if the endpoint
+        // keeps the connection open
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = done_rx.await;
+        });
+
+        let url = format!("http://127.0.0.1:{}/events", port);
+        let hook = Webhook::new(&url, vec!["*".to_string()]).unwrap();
+        let delivered = timeout(Duration::from_secs(1), hook.deliver(&event("set"))).await;
+        assert_eq!(delivered, Ok(Ok(())));
+
+        done_tx.send(()).unwrap();
+        server.await.unwrap();
+    }
+}